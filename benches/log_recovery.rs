@@ -0,0 +1,71 @@
+//! Recovery time for a synthetic log of full page images, replayed into a file-backed
+//! DiskManager with 1 redo worker versus several.
+//! Run with `cargo bench --bench log_recovery`; GRIMOIRE_BENCH_LOG_MB sets the log size
+//! (default 1024).
+
+use std::io::{BufWriter, Write};
+use std::sync::Arc;
+use std::time::Instant;
+
+use sqlite_rust::backend::storage::disk_manager::{DiskManager, GRIMOIRE_PAGE_SIZE};
+use sqlite_rust::backend::storage::log_frame::encode_frame;
+use sqlite_rust::backend::storage::redo::redo_parallel;
+use sqlite_rust::backend::storage::storage_backend::{DurabilityMode, FileBackend};
+use sqlite_rust::common::types::PageId;
+
+const PAGES: usize = 4096;
+
+fn page_of(record: &[u8]) -> Option<PageId> {
+    Some(PageId::from_le_bytes(record.get(..4)?.try_into().ok()?))
+}
+
+#[tokio::main]
+async fn main() {
+    let log_mb: usize = std::env::var("GRIMOIRE_BENCH_LOG_MB")
+        .ok()
+        .and_then(|mb| mb.parse().ok())
+        .unwrap_or(1024);
+    let dir = tempfile::tempdir().unwrap();
+    let log_path = dir.path().join("recovery.log");
+
+    let records = log_mb * 1024 * 1024 / (GRIMOIRE_PAGE_SIZE + 12);
+    let mut out = BufWriter::new(std::fs::File::create(&log_path).unwrap());
+    let mut record = vec![0u8; 4 + GRIMOIRE_PAGE_SIZE];
+    for i in 0..records {
+        let page_id = (i * 2654435761 % PAGES) as PageId;
+        record[..4].copy_from_slice(&page_id.to_le_bytes());
+        record[4..12].copy_from_slice(&(i as u64).to_le_bytes());
+        out.write_all(&encode_frame(&record)).unwrap();
+    }
+    out.into_inner().unwrap().sync_all().unwrap();
+    println!("log: {} MiB, {} records over {} pages", log_mb, records, PAGES);
+
+    for workers in [1, 4, 16] {
+        let db_path = dir.path().join(format!("recovered-{}.db", workers));
+        let dm = Arc::new(
+            DiskManager::new(&db_path)
+                .await
+                .unwrap()
+                .with_durability(DurabilityMode::None),
+        );
+        let log = FileBackend::open(&log_path).await.unwrap();
+
+        let start = Instant::now();
+        let target = Arc::clone(&dm);
+        let stats = redo_parallel(&log, workers, page_of, move |page_id, record| {
+            let dm = Arc::clone(&target);
+            async move { dm.write_page(page_id, &record[4..]).await }
+        })
+        .await
+        .unwrap();
+        let elapsed = start.elapsed();
+
+        println!(
+            "{:>2} worker(s): {:>8.2?} ({:.0} MiB/s, {} records)",
+            workers,
+            elapsed,
+            stats.log_bytes as f64 / (1024.0 * 1024.0) / elapsed.as_secs_f64(),
+            stats.applied
+        );
+    }
+}
@@ -0,0 +1,37 @@
+//! Compares inserting timestamp-like keys in order, which takes the append path, with
+//! inserting the same keys shuffled, which searches for every one.
+//! Run with `cargo bench --bench skiplist_append`.
+
+use std::time::{Duration, Instant};
+
+use rand::{SeedableRng, rngs::StdRng, seq::SliceRandom};
+use sqlite_rust::skiplist::SkipList;
+
+const KEYS: u64 = 200_000;
+
+fn ingest_time(keys: &[u64]) -> (Duration, usize) {
+    let mut list = SkipList::new(50).with_rng(StdRng::seed_from_u64(1));
+    let start = Instant::now();
+    for &key in keys {
+        list.insert(key, "");
+    }
+    (start.elapsed(), list.appends())
+}
+
+fn main() {
+    // microsecond timestamps, one every 250us
+    let sorted: Vec<u64> = (0..KEYS).map(|i| 1_700_000_000_000_000 + i * 250).collect();
+    let mut shuffled = sorted.clone();
+    shuffled.shuffle(&mut StdRng::seed_from_u64(42));
+
+    let (in_order, appends) = ingest_time(&sorted);
+    let (random, _) = ingest_time(&shuffled);
+    println!(
+        "{} keys: in order {:>7.2?}/insert ({} appended), shuffled {:>7.2?}/insert ({:.2}x)",
+        KEYS,
+        in_order / KEYS as u32,
+        appends,
+        random / KEYS as u32,
+        random.as_secs_f64() / in_order.as_secs_f64()
+    );
+}
@@ -0,0 +1,45 @@
+//! Compares copying reads (SkipList::search) with shared reads (SkipList::get) for
+//! large payloads. Run with `cargo bench --bench skiplist_read`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+use sqlite_rust::skiplist::SkipList;
+
+const KEYS: i32 = 256;
+const READS: usize = 20_000;
+
+fn time(mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    f();
+    start.elapsed()
+}
+
+fn main() {
+    for size in [4 * 1024, 64 * 1024, 256 * 1024] {
+        let mut list = SkipList::new(50);
+        for key in 0..KEYS {
+            list.insert_bytes(key, Bytes::from(vec![b'x'; size]));
+        }
+
+        let copied = time(|| {
+            for i in 0..READS {
+                black_box(list.search(&(i as i32 % KEYS)));
+            }
+        });
+        let shared = time(|| {
+            for i in 0..READS {
+                black_box(list.get(&(i as i32 % KEYS)));
+            }
+        });
+
+        println!(
+            "{:>4} KB values: search {:>8.2?}/read, get {:>8.2?}/read ({:.1}x)",
+            size / 1024,
+            copied / READS as u32,
+            shared / READS as u32,
+            copied.as_secs_f64() / shared.as_secs_f64()
+        );
+    }
+}
@@ -0,0 +1,45 @@
+//! Compares searches on long byte-string keys with and without the inline key prefix
+//! (Bytewise vs. a plain closure comparator that has none).
+//! Run with `cargo bench --bench skiplist_search`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use sqlite_rust::skiplist::{Bytewise, Comparator, SkipList};
+
+const KEYS: usize = 10_000;
+const SEARCHES: usize = 100_000;
+
+fn search_time<C: Comparator<Vec<u8>>>(cmp: C, keys: &[Vec<u8>]) -> Duration {
+    let mut list = SkipList::with_comparator(50, cmp).with_rng(StdRng::seed_from_u64(1));
+    for key in keys {
+        list.insert(key.clone(), "");
+    }
+
+    let start = Instant::now();
+    for i in 0..SEARCHES {
+        black_box(list.get(&keys[i * 7919 % keys.len()]));
+    }
+    start.elapsed()
+}
+
+fn main() {
+    let mut rng = StdRng::seed_from_u64(42);
+    for len in [16, 64, 256] {
+        let keys: Vec<Vec<u8>> = (0..KEYS)
+            .map(|_| (0..len).map(|_| rng.random()).collect())
+            .collect();
+
+        let plain = search_time(|a: &Vec<u8>, b: &Vec<u8>| a.cmp(b), &keys);
+        let prefixed = search_time(Bytewise, &keys);
+
+        println!(
+            "{:>3} B keys: full compare {:>8.2?}/search, prefix {:>8.2?}/search ({:.2}x)",
+            len,
+            plain / SEARCHES as u32,
+            prefixed / SEARCHES as u32,
+            plain.as_secs_f64() / prefixed.as_secs_f64()
+        );
+    }
+}
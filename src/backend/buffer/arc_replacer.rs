@@ -5,12 +5,18 @@
 //! Translated from BusTub C++ skeleton into Rust.
 //! Implements the ARC eviction policy used in the buffer pool manager.
 //! See https://github.com/cmu-db/bustub/blob/master/src/buffer/arc_replacer.cpp
+//!
+//! Alive frames live in the MRU (seen once) or MFU (seen at least twice) list.
+//! Evicted pages are remembered by page id in the matching ghost list; a hit on a
+//! ghost adapts the target size of the MRU list towards the list that would have kept it.
+//! Front of every list is the most recent entry, eviction takes from the back.
+//!
+//! Frames loaded for a one-shot read (see ReadOptions::fill_cache) sit at the back of
+//! the MRU list outside the ARC bookkeeping: they are evicted before anything else and
+//! leave no ghost behind.
 
-/*
-use std::collections::{HashMap, VecDeque};
-use std::sync::Mutex;
+use std::collections::{HashMap, HashSet, VecDeque};
 use anyhow::{Result};
-//use log::{error, info};
 use crate::common::types::{FrameId, PageId};
 
 /// Access type (needed for leaderboard tests).
@@ -19,18 +25,25 @@ pub enum AccessType {
     Scan,
     Lookup,
     Index,
-    Unknown, 
+    Unknown,
 }
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ArcStatus{
     //most recently used
     MRU,
     //most frequently used
     MFU,
-    //evicted MRU
-    MRUGhost,
-    //evicted MFU
-    MFUGhost,
+}
+
+/// What record_access() found for the accessed page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessOutcome {
+    /// The frame was already tracked.
+    Resident,
+    /// The page was remembered in a ghost list, i.e. evicted too early.
+    GhostHit(ArcStatus),
+    /// The page was not known at all.
+    Miss,
 }
 
 /// Metadata for a frame tracked by the replacer.
@@ -44,14 +57,16 @@ pub struct FrameStatus {
 
 /// Adaptive Replacement Cache (ARC) Replacer.
 /// Keeps track of MRU, MFU, and their ghost lists.
+/// Not internally synchronized: the buffer pool wraps it in its own latch.
 pub struct ArcReplacer {
     replacer_size: usize,
+    mru_target_size: usize,
     mru_list: VecDeque<FrameId>,
     mfu_list: VecDeque<FrameId>,
-    mru_ghost_list: VecDeque<FrameId>,
-    mfu_ghost_list: VecDeque<FrameId>,
+    mru_ghost_list: VecDeque<PageId>,
+    mfu_ghost_list: VecDeque<PageId>,
     pin_table: HashMap<FrameId, FrameStatus>,
-    latch : Mutex<()>,
+    one_shot: HashSet<FrameId>,
 }
 
 impl ArcReplacer {
@@ -59,88 +74,169 @@ impl ArcReplacer {
     pub fn new(num_frames: usize) -> Self {
         Self {
             replacer_size: num_frames,
+            mru_target_size: 0,
             mru_list: VecDeque::new(),
             mfu_list: VecDeque::new(),
             mru_ghost_list: VecDeque::new(),
             mfu_ghost_list: VecDeque::new(),
             pin_table: HashMap::new(),
-            latch: Mutex::new(()),
+            one_shot: HashSet::new(),
         }
     }
 
+    /// Evict the least recently used evictable frame, preferring the MRU list while it
+    /// is at or above its target size. The evicted page moves to the matching ghost list.
     pub fn evict(&mut self) -> Option<FrameId> {
-        let _guard = self.latch.lock().unwrap();
-
-        // Scan MRU list first
-        let mut idx = 0;
-        while idx < self.mru_list.len() {
-            let candt = self.mru_list[idx];
-            if let Some(status) = self.pin_table.get(&candt) {
-                if status.evictable {
-                    let candt = self.mru_list.remove(idx).unwrap();
-                    self.mru_ghost_list.push_back(candt);
-                    if let Some(entry) = self.pin_table.get_mut(&candt) {
-                        entry.arc_status = ArcStatus::MRUGhost;
-                    }
-                    return Some(candt);
-                }
-            }
-            idx += 1;
+        if let Some(frame_id) = self.evict_one_shot() {
+            return Some(frame_id);
         }
+        let prefer_mru = self.mru_list.len() >= self.mru_target_size.max(1);
+        let order = if prefer_mru {
+            [ArcStatus::MRU, ArcStatus::MFU]
+        } else {
+            [ArcStatus::MFU, ArcStatus::MRU]
+        };
 
-        // Then scan MFU list
-        let mut idx = 0;
-        while idx < self.mfu_list.len() {
-            let candt = self.mfu_list[idx];
-            if let Some(status) = self.pin_table.get(&candt) {
-                if status.evictable {
-                    let candt = self.mfu_list.remove(idx).unwrap();
-                    self.mfu_ghost_list.push_back(candt);
-                    if let Some(entry) = self.pin_table.get_mut(&candt) {
-                        entry.arc_status = ArcStatus::MFUGhost;
-                    }
-                    return Some(candt);
-                }
+        for status in order {
+            if let Some(frame_id) = self.evict_from(status) {
+                return Some(frame_id);
             }
-            idx += 1;
         }
         log::error!("No evictable frame found");
         None
     }
 
+    fn evict_from(&mut self, status: ArcStatus) -> Option<FrameId> {
+        let list = match status {
+            ArcStatus::MRU => &mut self.mru_list,
+            ArcStatus::MFU => &mut self.mfu_list,
+        };
+        let pin_table = &self.pin_table;
+        let idx = list
+            .iter()
+            .rposition(|frame_id| pin_table.get(frame_id).is_some_and(|s| s.evictable))?;
+        let frame_id = list.remove(idx).unwrap();
+
+        let entry = self.pin_table.remove(&frame_id).unwrap();
+        match status {
+            ArcStatus::MRU => self.mru_ghost_list.push_front(entry.page_id),
+            ArcStatus::MFU => self.mfu_ghost_list.push_front(entry.page_id),
+        }
+        self.delete_ghost();
+        Some(frame_id)
+    }
+
+    fn evict_one_shot(&mut self) -> Option<FrameId> {
+        if self.one_shot.is_empty() {
+            return None;
+        }
+        let (one_shot, pin_table) = (&self.one_shot, &self.pin_table);
+        let idx = self.mru_list.iter().rposition(|frame_id| {
+            one_shot.contains(frame_id) && pin_table.get(frame_id).is_some_and(|s| s.evictable)
+        })?;
+        let frame_id = self.mru_list.remove(idx).unwrap();
+        self.pin_table.remove(&frame_id);
+        self.one_shot.remove(&frame_id);
+        Some(frame_id)
+    }
+
+    /// Track a frame loaded for a one-shot read as the next eviction victim, without
+    /// touching the ghost lists or the MRU target. An already tracked frame keeps its place.
+    pub fn record_one_shot(&mut self, frame_id: FrameId, page_id: PageId) -> AccessOutcome {
+        if self.pin_table.contains_key(&frame_id) {
+            return AccessOutcome::Resident;
+        }
+        self.mru_list.push_back(frame_id);
+        self.pin_table.insert(frame_id, FrameStatus {
+            page_id,
+            frame_id,
+            evictable: false,
+            arc_status: ArcStatus::MRU,
+        });
+        self.one_shot.insert(frame_id);
+        AccessOutcome::Miss
+    }
+
 
     /// Record access to a frame and update ARC bookkeeping.
-    /// {TODO: after buffer pool manager}
     /// Four cases:
     /// 1. Frame exists in MRU/MFU
     /// 2. Frame exists in MRU ghost
     /// 3. Frame exists in MFU ghost
     /// 4. Miss everywhere
-    pub fn record_access(&mut self, frame_id: FrameId, page_id: PageId, _access_type: AccessType) {
-
-    }
+    ///
+    /// New frames start out non-evictable, since the buffer pool pins them right away.
+    pub fn record_access(&mut self, frame_id: FrameId, page_id: PageId, _access_type: AccessType) -> AccessOutcome {
+        // A regular access to a one-shot frame counts as its first one
+        if self.one_shot.remove(&frame_id) {
+            Self::remove_from(&mut self.mru_list, &frame_id);
+            self.mru_list.push_front(frame_id);
+            return AccessOutcome::Resident;
+        }
 
-    /// Toggle whether a frame is evictable.
-    /// Updates replacer size accordingly.
-    pub fn set_keep(&mut self, frame_id: FrameId) -> Result<()> {
+        // 1. Hit on an alive frame: promote to the front of MFU
         if let Some(status) = self.pin_table.get_mut(&frame_id) {
-            status.evictable = false;
+            let arc_status = status.arc_status;
+            status.arc_status = ArcStatus::MFU;
+            Self::remove_from(match arc_status {
+                ArcStatus::MRU => &mut self.mru_list,
+                ArcStatus::MFU => &mut self.mfu_list,
+            }, &frame_id);
+            self.mfu_list.push_front(frame_id);
+            return AccessOutcome::Resident;
+        }
+
+        let (arc_status, outcome) = if let Some(pos) = self.mru_ghost_list.iter().position(|&id| id == page_id) {
+            // 2. MRU ghost hit: MRU was too small, grow its target
+            let delta = (self.mfu_ghost_list.len() / self.mru_ghost_list.len()).max(1);
+            self.mru_target_size = (self.mru_target_size + delta).min(self.replacer_size);
+            self.mru_ghost_list.remove(pos);
+            (ArcStatus::MFU, AccessOutcome::GhostHit(ArcStatus::MRU))
+        } else if let Some(pos) = self.mfu_ghost_list.iter().position(|&id| id == page_id) {
+            // 3. MFU ghost hit: MFU was too small, shrink the MRU target
+            let delta = (self.mru_ghost_list.len() / self.mfu_ghost_list.len()).max(1);
+            self.mru_target_size = self.mru_target_size.saturating_sub(delta);
+            self.mfu_ghost_list.remove(pos);
+            (ArcStatus::MFU, AccessOutcome::GhostHit(ArcStatus::MFU))
+        } else {
+            // 4. Miss: make room in the ghost lists so that they never outgrow the cache
+            if self.mru_list.len() + self.mru_ghost_list.len() >= self.replacer_size {
+                self.mru_ghost_list.pop_back();
+            } else if self.total_size() >= 2 * self.replacer_size {
+                self.mfu_ghost_list.pop_back();
+            }
+            (ArcStatus::MRU, AccessOutcome::Miss)
+        };
+
+        match arc_status {
+            ArcStatus::MRU => self.mru_list.push_front(frame_id),
+            ArcStatus::MFU => self.mfu_list.push_front(frame_id),
         }
-        Ok(())
+        self.pin_table.insert(frame_id, FrameStatus {
+            page_id,
+            frame_id,
+            evictable: false,
+            arc_status,
+        });
+        outcome
     }
 
-    pub fn set_evicted(&mut self, frame_id: FrameId) -> Result<()> {
-        if let Some(status) = self.pin_table.get_mut(&frame_id) {
-            status.evictable = true;
+    /// Toggle whether a frame is evictable.
+    /// Updates replacer size accordingly.
+    pub fn set_evictable(&mut self, frame_id: FrameId, evictable: bool) -> Result<()> {
+        match self.pin_table.get_mut(&frame_id) {
+            Some(status) => {
+                status.evictable = evictable;
+                Ok(())
+            }
+            None => Err(anyhow::anyhow!("Frame {} not found in replacer", frame_id)),
         }
-        Ok(())
     }
 
     /// Remove an evictable frame from the replacer.
     /// If frame is not evictable → error.
+    /// Unlike evict(), the page is not remembered in a ghost list.
     pub fn remove(&mut self, frame_id: FrameId) -> Result<()> {
-        let _guard = self.latch.lock().unwrap();
-
         // 1. Lookup frame
         if let Some(status) = self.pin_table.get(&frame_id) {
             // 2. Check evictable
@@ -149,29 +245,12 @@ impl ArcReplacer {
             }
             // 3. Remove from the correct list
             match status.arc_status {
-                ArcStatus::MRU => {
-                    if let Some(pos) = self.mru_list.iter().position(|&id| id == frame_id) {
-                        self.mru_list.remove(pos);
-                    }
-                }
-                ArcStatus::MFU => {
-                    if let Some(pos) = self.mfu_list.iter().position(|&id| id == frame_id) {
-                        self.mfu_list.remove(pos);
-                    }
-                }
-                ArcStatus::MRUGhost => {
-                    if let Some(pos) = self.mru_ghost_list.iter().position(|&id| id == frame_id) {
-                        self.mru_ghost_list.remove(pos);
-                    }
-                }
-                ArcStatus::MFUGhost => {
-                    if let Some(pos) = self.mfu_ghost_list.iter().position(|&id| id == frame_id) {
-                        self.mfu_ghost_list.remove(pos);
-                    }
-                }
+                ArcStatus::MRU => Self::remove_from(&mut self.mru_list, &frame_id),
+                ArcStatus::MFU => Self::remove_from(&mut self.mfu_list, &frame_id),
             }
             //remove from table
             self.pin_table.remove(&frame_id);
+            self.one_shot.remove(&frame_id);
             Ok(())
         } else {
             // Frame not tracked
@@ -181,77 +260,154 @@ impl ArcReplacer {
 
     /// Return the number of evictable frames.
     pub fn size(&self) -> usize {
-        let _guard = self.latch.lock().unwrap();
-        return self.pin_table.values().filter(|status| status.evictable).count()
+        self.pin_table.values().filter(|status| status.evictable).count()
+    }
+
+    /// Current target size of the MRU list (ARC's `p`).
+    pub fn mru_target_size(&self) -> usize {
+        self.mru_target_size
+    }
+
+    /// Follow a change in the number of frames. Frames past the new size must have been
+    /// removed already; the MRU target and the ghost lists are clamped to the new size.
+    pub fn set_capacity(&mut self, num_frames: usize) {
+        self.replacer_size = num_frames;
+        self.mru_target_size = self.mru_target_size.min(num_frames);
+        self.mru_ghost_list.truncate(num_frames);
+        self.mfu_ghost_list.truncate(num_frames);
+    }
+
+    fn total_size(&self) -> usize {
+        self.mru_list.len() + self.mfu_list.len() + self.mru_ghost_list.len() + self.mfu_ghost_list.len()
+    }
+
+    fn remove_from<T: PartialEq>(list: &mut VecDeque<T>, item: &T) {
+        if let Some(pos) = list.iter().position(|x| x == item) {
+            list.remove(pos);
+        }
     }
 
    //delete from ghost deques if they exceed set replacer size
     fn delete_ghost(&mut self){
         if self.mru_ghost_list.len() > self.replacer_size {
-            self.mru_ghost_list.pop_front();
+            self.mru_ghost_list.pop_back();
         } else if self.mfu_ghost_list.len() > self.replacer_size {
-            self.mfu_ghost_list.pop_front();
+            self.mfu_ghost_list.pop_back();
         }
     }
 
 }
-/*
+
 #[cfg(test)]
 mod tests {
-    use crate::backend::buffer::arc_replacer::{ArcReplacer, ArcStatus};
-    //use crate::common::types::FrameId;
+    use crate::backend::buffer::arc_replacer::{AccessOutcome, AccessType, ArcReplacer, ArcStatus};
+
+    fn insert(replacer: &mut ArcReplacer, frame_id: usize, page_id: i32) {
+        replacer.record_access(frame_id, page_id, AccessType::Unknown);
+        replacer.set_evictable(frame_id, true).unwrap();
+    }
 
     #[test]
     fn test_insert_and_evict() {
         let mut replacer = ArcReplacer::new(3); // capacity 3
 
         // Insert frames
-        replacer.insert(1);
-        replacer.insert(2);
-        replacer.insert(3);
-
-        // Initially, all frames are evictable
-        assert!(replacer.pin_table.get(&1).unwrap().evictable);
-        assert!(replacer.pin_table.get(&2).unwrap().evictable);
-        assert!(replacer.pin_table.get(&3).unwrap().evictable);
-
-        // Evict one frame
-        let victim = replacer.evict();
-        assert!(victim.is_some());
-        let victim_id = victim.unwrap();
-
-        // The victim should now be in the ghost list
-        let ghost_status = replacer.pin_table.get(&victim_id).unwrap();
-        match ghost_status.arc_status {
-            ArcStatus::MRUGhost | ArcStatus::MFUGhost => {}
-            _ => panic!("Evicted frame not in ghost list"),
-        }
+        insert(&mut replacer, 1, 101);
+        insert(&mut replacer, 2, 102);
+        insert(&mut replacer, 3, 103);
+        assert_eq!(replacer.size(), 3);
+
+        // Oldest MRU entry goes first
+        assert_eq!(replacer.evict(), Some(1));
+        assert_eq!(replacer.size(), 2);
+
+        // The victim's page should now be in the ghost list
+        assert!(replacer.mru_ghost_list.contains(&101));
+        assert!(!replacer.pin_table.contains_key(&1));
     }
 
     #[test]
     fn test_set_evictable() {
         let mut replacer = ArcReplacer::new(2);
-        replacer.insert(10);
-        replacer.insert(20);
+        insert(&mut replacer, 10, 1);
+        insert(&mut replacer, 20, 2);
 
         // Pin frame 10 (set evictable = false)
         replacer.set_evictable(10, false).unwrap();
 
-        // Trying to evict should skip frame 10 if it is MRU/MFU head
+        // Eviction skips frame 10 even though it is the oldest
         let victim = replacer.evict().unwrap();
         assert_ne!(victim, 10);
+        assert_eq!(replacer.evict(), None);
+        assert!(replacer.set_evictable(99, true).is_err());
     }
 
     #[test]
     fn test_remove() {
         let mut replacer = ArcReplacer::new(2);
-        replacer.insert(100);
-        replacer.insert(200);
+        insert(&mut replacer, 100, 1);
+        insert(&mut replacer, 200, 2);
 
         // Remove a frame
         replacer.remove(100).unwrap();
-        assert!(replacer.table.get(&100).is_none());
+        assert!(!replacer.pin_table.contains_key(&100));
+        assert!(replacer.mru_ghost_list.is_empty());
+
+        // Pinned frames cannot be removed
+        replacer.set_evictable(200, false).unwrap();
+        assert!(replacer.remove(200).is_err());
+    }
+
+    #[test]
+    fn test_second_access_moves_to_mfu() {
+        let mut replacer = ArcReplacer::new(3);
+        insert(&mut replacer, 1, 101);
+        insert(&mut replacer, 2, 102);
+        replacer.record_access(1, 101, AccessType::Lookup);
+
+        assert_eq!(replacer.pin_table[&1].arc_status, ArcStatus::MFU);
+        // MRU is above its target, so the once-seen frame is evicted first
+        assert_eq!(replacer.evict(), Some(2));
+        assert_eq!(replacer.evict(), Some(1));
+        assert!(replacer.mfu_ghost_list.contains(&101));
+    }
+
+    #[test]
+    fn test_one_shot_frames_go_first_without_ghosts() {
+        let mut replacer = ArcReplacer::new(3);
+        insert(&mut replacer, 1, 101);
+        replacer.record_access(1, 101, AccessType::Lookup);
+        insert(&mut replacer, 2, 102);
+        assert_eq!(replacer.record_one_shot(3, 103), AccessOutcome::Miss);
+        replacer.set_evictable(3, true).unwrap();
+        assert_eq!(replacer.record_one_shot(1, 101), AccessOutcome::Resident);
+
+        assert_eq!(replacer.evict(), Some(3));
+        assert!(!replacer.mru_ghost_list.contains(&103));
+
+        // Read again normally, a one-shot frame becomes an ordinary MRU entry
+        replacer.record_one_shot(3, 103);
+        replacer.record_access(3, 103, AccessType::Unknown);
+        replacer.set_evictable(3, true).unwrap();
+        assert_eq!(replacer.evict(), Some(2));
+        assert_eq!(replacer.evict(), Some(3));
+    }
+
+    #[test]
+    fn test_ghost_hit_adapts_target() {
+        let mut replacer = ArcReplacer::new(2);
+        insert(&mut replacer, 1, 101);
+        insert(&mut replacer, 2, 102);
+        assert_eq!(replacer.evict(), Some(1));
+
+        // Page 101 comes back while remembered in the MRU ghost list
+        assert_eq!(
+            replacer.record_access(1, 101, AccessType::Unknown),
+            AccessOutcome::GhostHit(ArcStatus::MRU)
+        );
+        replacer.set_evictable(1, true).unwrap();
+        assert_eq!(replacer.mru_target_size(), 1);
+        assert_eq!(replacer.pin_table[&1].arc_status, ArcStatus::MFU);
+        assert!(replacer.mru_ghost_list.is_empty());
     }
 }
-    */ 
-*/
@@ -1,7 +1,1510 @@
 // src/buffer/buffer_pool_manager.rs
 
-//! ArcReplacer (Adaptive Replacement Cache)
+//! BufferPoolManager
 //!
 //! Translated from BusTub C++ skeleton into Rust.
-//! Implements the ARC eviction policy used in the buffer pool manager.
-//! See https://github.com/cmu-db/bustub/blob/master/src/buffer/arc_replacer.cpp
\ No newline at end of file
+//! Caches disk pages in a set of frames and hands out page guards. The number of
+//! frames can change at runtime (see set_pool_size).
+//! Frames are chosen for eviction by the ArcReplacer.
+//! See https://github.com/cmu-db/bustub/blob/master/src/buffer/buffer_pool_manager.cpp
+//!
+//! Locking:
+//! - `page_table` (async mutex) maps page ids to frames. It is held across the disk I/O
+//!   of a miss, so a page is never loaded twice and an evicted page is fully written
+//!   back before anyone can read it again.
+//! - `frame_table` (sync mutex, in PoolShared) holds the replacer and pin counts. Pinning
+//!   and unpinning happen under it, so "evictable" always means "pin count is zero".
+//! - page latches are taken only after `page_table` is released.
+//! - `frames` (sync rwlock) only changes in set_pool_size, under `page_table`.
+//!
+//! Observers (see BufferPoolObserver) are told about evictions, flushes and ghost hits.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, RwLock as SyncRwLock,
+        atomic::{AtomicI32, AtomicU64, AtomicUsize, Ordering},
+    },
+};
+
+use serde::Serialize;
+use tokio::sync::Mutex;
+
+use crate::backend::buffer::arc_replacer::{AccessOutcome, AccessType, ArcReplacer};
+use crate::backend::buffer::latch_tracker::{LatchMode, LatchTracker};
+use crate::backend::buffer::observer::BufferPoolObserver;
+use crate::backend::buffer::page::{FrameHeader, INVALID_PAGE_ID, PageData};
+use crate::backend::buffer::pin_tracker::PinTracker;
+use crate::backend::buffer::page_guard::{ReadPageGuard, WritePageGuard};
+use crate::backend::buffer::trace_recorder::TraceRecorder;
+use crate::backend::storage::disk_manager::DiskManager;
+use crate::backend::storage::io_stats::IoSource;
+use crate::common::{
+    cancellation::CancellationToken,
+    errors::DiskError,
+    options::ReadOptions,
+    progress::ProgressTracker,
+    sync::{Mutex as FrameMutex, MutexGuard as FrameGuard},
+    types::{FrameId, PageId},
+};
+
+/// Pages a checkpoint writes at once unless changed with set_flush_batch().
+pub const DEFAULT_FLUSH_BATCH: usize = 1;
+
+struct PageTable {
+    pages: HashMap<PageId, FrameId>,
+    free_frames: Vec<FrameId>,
+}
+
+// A page read_pages() has pinned: already resident, or loading into a frame it took.
+// The latch of a loading frame is None while the read is in flight or after it failed.
+enum Claim {
+    Resident(PageId, Arc<FrameHeader>),
+    Loading(PageId, Arc<FrameHeader>, Option<tokio::sync::OwnedRwLockWriteGuard<PageData>>),
+}
+
+struct FrameTable {
+    replacer: ArcReplacer,
+    pin_counts: Vec<usize>,
+}
+
+/// Point-in-time occupancy of the pool, plus hit/miss counts since it was created.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct BufferPoolStats {
+    pub num_frames: usize,
+    pub resident_pages: usize,
+    pub pinned_frames: usize,
+    pub dirty_pages: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl BufferPoolStats {
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 { 0.0 } else { self.hits as f64 / total as f64 }
+    }
+}
+
+/// State shared between the pool and its outstanding page guards.
+pub(crate) struct PoolShared {
+    // Modeled by loom in the loom tests (see common::sync)
+    frame_table: FrameMutex<FrameTable>,
+    latch_tracker: LatchTracker,
+    pin_tracker: PinTracker,
+}
+
+impl PoolShared {
+    fn new(num_frames: usize) -> Self {
+        Self {
+            frame_table: FrameMutex::new(FrameTable {
+                replacer: ArcReplacer::new(num_frames),
+                pin_counts: vec![0; num_frames],
+            }),
+            latch_tracker: LatchTracker::new(),
+            pin_tracker: PinTracker::new(),
+        }
+    }
+
+    fn lock_frames(&self) -> FrameGuard<'_, FrameTable> {
+        // A panic in one page access must not fail every later one
+        self.frame_table.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn pin(&self, frame_id: FrameId, page_id: PageId, access_type: AccessType) -> AccessOutcome {
+        let mut frames = self.lock_frames();
+        frames.pin_counts[frame_id] += 1;
+        let outcome = frames.replacer.record_access(frame_id, page_id, access_type);
+        let _ = frames.replacer.set_evictable(frame_id, false);
+        outcome
+    }
+
+    /// Pin for a read that should not stay in the pool (see ArcReplacer::record_one_shot).
+    fn pin_one_shot(&self, frame_id: FrameId, page_id: PageId) {
+        let mut frames = self.lock_frames();
+        frames.pin_counts[frame_id] += 1;
+        frames.replacer.record_one_shot(frame_id, page_id);
+        let _ = frames.replacer.set_evictable(frame_id, false);
+    }
+
+    fn unpin(&self, frame_id: FrameId) {
+        let mut frames = self.lock_frames();
+        let pin_count = &mut frames.pin_counts[frame_id];
+        *pin_count = pin_count.saturating_sub(1);
+        if *pin_count == 0 {
+            let _ = frames.replacer.set_evictable(frame_id, true);
+        }
+    }
+
+    /// Unpin a frame left holding no page. On its last pin the frame is dropped from the
+    /// replacer and true is returned: it then belongs on the free list.
+    fn unpin_vacated(&self, frame_id: FrameId) -> bool {
+        let mut frames = self.lock_frames();
+        let pin_count = &mut frames.pin_counts[frame_id];
+        *pin_count = pin_count.saturating_sub(1);
+        if *pin_count > 0 {
+            return false;
+        }
+        let _ = frames.replacer.set_evictable(frame_id, true);
+        let _ = frames.replacer.remove(frame_id);
+        true
+    }
+
+    /// Called by a page guard once it has dropped its latch.
+    pub(crate) fn release(
+        &self,
+        page_id: PageId,
+        frame_id: FrameId,
+        latch_token: Option<u64>,
+        pin_token: Option<u64>,
+    ) {
+        if let Some(token) = latch_token {
+            self.latch_tracker.released(page_id, token);
+        }
+        if let Some(token) = pin_token {
+            self.pin_tracker.unpinned(token);
+        }
+        self.unpin(frame_id);
+    }
+}
+
+pub struct BufferPoolManager {
+    frames: SyncRwLock<Vec<Arc<FrameHeader>>>,
+    page_table: Mutex<PageTable>,
+    shared: Arc<PoolShared>,
+    next_page_id: AtomicI32,
+    disk_manager: Arc<DiskManager>,
+    observers: std::sync::RwLock<Vec<Arc<dyn BufferPoolObserver>>>,
+    trace_recorder: TraceRecorder,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    flush_batch: AtomicUsize,
+}
+
+impl BufferPoolManager {
+    pub fn new(num_frames: usize, disk_manager: Arc<DiskManager>) -> Self {
+        Self {
+            frames: SyncRwLock::new(FrameHeader::allocate(0, num_frames)),
+            page_table: Mutex::new(PageTable {
+                pages: HashMap::new(),
+                free_frames: (0..num_frames).rev().collect(),
+            }),
+            shared: Arc::new(PoolShared::new(num_frames)),
+            next_page_id: AtomicI32::new(0),
+            disk_manager,
+            observers: std::sync::RwLock::new(Vec::new()),
+            trace_recorder: TraceRecorder::new(),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            flush_batch: AtomicUsize::new(DEFAULT_FLUSH_BATCH),
+        }
+    }
+
+    /// Number of frames in the pool.
+    pub fn size(&self) -> usize {
+        self.frame_headers().len()
+    }
+
+    /// Grow or shrink the pool to `num_frames` frames. Growing adds empty frames.
+    /// Shrinking drops the highest-numbered frames, writing back and evicting the pages
+    /// in them; it fails with NoFreeFrame, changing nothing, while any of those frames
+    /// is pinned.
+    pub async fn set_pool_size(&self, num_frames: usize) -> Result<(), DiskError> {
+        let mut table = self.page_table.lock().await;
+        let old_frames = self.size();
+        if num_frames > old_frames {
+            self.frames
+                .write()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .extend(FrameHeader::allocate(old_frames, num_frames - old_frames));
+            let mut frames = self.shared.lock_frames();
+            frames.pin_counts.resize(num_frames, 0);
+            frames.replacer.set_capacity(num_frames);
+            drop(frames);
+            // Keep handing out low frames first
+            table.free_frames.splice(0..0, (old_frames..num_frames).rev());
+            return Ok(());
+        }
+
+        let removed: Vec<Arc<FrameHeader>> = self.frame_headers()[num_frames..].to_vec();
+        if self.shared.lock_frames().pin_counts[num_frames..].iter().any(|&pins| pins > 0) {
+            return Err(DiskError::NoFreeFrame);
+        }
+        let was_dirty: Vec<bool> = removed.iter().map(|frame| frame.is_dirty()).collect();
+        // Write everything back first, so a failed write leaves the pool as it was
+        for frame in removed.iter().filter(|frame| frame.is_dirty()) {
+            let latch = frame
+                .latch()
+                .try_read_owned()
+                .expect("an unpinned frame must not be latched");
+            let page_id = frame.page_id();
+            self.disk_manager.write_page(page_id, &latch).await?;
+            frame.set_dirty(false);
+            self.notify(|observer| observer.on_flush(page_id));
+        }
+
+        let mut frames = self.shared.lock_frames();
+        for (frame, dirty) in removed.iter().zip(was_dirty) {
+            // Including frames left holding no page, or the replacer could evict them later
+            let _ = frames.replacer.remove(frame.frame_id());
+            let page_id = frame.page_id();
+            if page_id != INVALID_PAGE_ID {
+                table.pages.remove(&page_id);
+                self.notify(|observer| observer.on_evict(page_id, frame.frame_id(), dirty));
+            }
+        }
+        frames.pin_counts.truncate(num_frames);
+        frames.replacer.set_capacity(num_frames);
+        drop(frames);
+        table.free_frames.retain(|&frame_id| frame_id < num_frames);
+        self.frames
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .truncate(num_frames);
+        Ok(())
+    }
+
+    fn frame_headers(&self) -> std::sync::RwLockReadGuard<'_, Vec<Arc<FrameHeader>>> {
+        self.frames.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn frame(&self, frame_id: FrameId) -> Arc<FrameHeader> {
+        Arc::clone(&self.frame_headers()[frame_id])
+    }
+
+    /// Allocate a fresh page id. The page reads as zeroes until it is first written.
+    pub fn new_page(&self) -> PageId {
+        // Skip the ids the DiskManager already maps, e.g. the pages of a reopened database
+        self.next_page_id.fetch_max(self.disk_manager.next_page_id(), Ordering::SeqCst);
+        self.next_page_id.fetch_add(1, Ordering::SeqCst)
+    }
+
+    /// Debug-mode latch tracking, see LatchTracker.
+    pub fn latch_tracker(&self) -> &LatchTracker {
+        &self.shared.latch_tracker
+    }
+
+    /// Debug-mode pin leak tracking, see PinTracker.
+    pub fn pin_tracker(&self) -> &PinTracker {
+        &self.shared.pin_tracker
+    }
+
+    /// Access trace recording, see TraceRecorder.
+    pub fn trace_recorder(&self) -> &TraceRecorder {
+        &self.trace_recorder
+    }
+
+    /// Register an observer for eviction, flush and ghost-hit events.
+    pub fn add_observer(&self, observer: Arc<dyn BufferPoolObserver>) {
+        self.observers
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .push(observer);
+    }
+
+    fn notify(&self, event: impl Fn(&dyn BufferPoolObserver)) {
+        let observers = self.observers.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        for observer in observers.iter() {
+            event(observer.as_ref());
+        }
+    }
+
+    /// Pin `page_id` and take its latch in shared mode.
+    pub async fn read_page(&self, page_id: PageId) -> Result<ReadPageGuard, DiskError> {
+        self.read_page_with(page_id, AccessType::Unknown).await
+    }
+
+    pub async fn read_page_with(
+        &self,
+        page_id: PageId,
+        access_type: AccessType,
+    ) -> Result<ReadPageGuard, DiskError> {
+        self.read_page_inner(page_id, access_type, ReadOptions::default(), None).await
+    }
+
+    /// Like read_page, with `options` deciding how the read affects the pool.
+    pub async fn read_page_with_options(
+        &self,
+        page_id: PageId,
+        options: &ReadOptions,
+    ) -> Result<ReadPageGuard, DiskError> {
+        self.read_page_inner(page_id, AccessType::Unknown, *options, None).await
+    }
+
+    /// Like read_page, but gives up with DiskError::Cancelled (and unpins the frame)
+    /// if `cancel` fires while waiting for the latch.
+    pub async fn read_page_cancellable(
+        &self,
+        page_id: PageId,
+        cancel: &CancellationToken,
+    ) -> Result<ReadPageGuard, DiskError> {
+        self.read_page_inner(page_id, AccessType::Unknown, ReadOptions::default(), Some(cancel))
+            .await
+    }
+
+    /// Pin and read-latch every page in `page_ids`, returning the guards in the same
+    /// order. Resident pages are pinned right away and all misses are then read from disk
+    /// concurrently, rather than one after another as a loop over read_page() would.
+    /// Every distinct missing page needs a frame at once; if the pool cannot supply them
+    /// this fails with NoFreeFrame and holds nothing.
+    pub async fn read_pages(&self, page_ids: &[PageId]) -> Result<Vec<ReadPageGuard>, DiskError> {
+        let mut claims = Vec::with_capacity(page_ids.len());
+        let mut table = self.page_table.lock().await;
+        for &page_id in page_ids {
+            if let Some(&frame_id) = table.pages.get(&page_id) {
+                self.shared.pin(frame_id, page_id, AccessType::Unknown);
+                claims.push(Claim::Resident(page_id, self.frame(frame_id)));
+                continue;
+            }
+            let (frame, latch) = match self.take_frame(&mut table).await {
+                Ok(taken) => taken,
+                Err(e) => {
+                    // Nothing was loaded yet
+                    for claim in &mut claims {
+                        if let Claim::Loading(_, _, latch) = claim {
+                            latch.take();
+                        }
+                    }
+                    self.release_claims(&mut table, claims);
+                    return Err(e);
+                }
+            };
+            // Visible to other readers from here on; they wait on the latch until it is loaded
+            frame.set_page_id(page_id);
+            frame.set_dirty(false);
+            table.pages.insert(page_id, frame.frame_id());
+            if let AccessOutcome::GhostHit(list) = self.shared.pin(frame.frame_id(), page_id, AccessType::Unknown) {
+                self.notify(|observer| observer.on_ghost_hit(page_id, list));
+            }
+            claims.push(Claim::Loading(page_id, frame, Some(latch)));
+        }
+        drop(table);
+
+        let mut loads = tokio::task::JoinSet::new();
+        for (index, claim) in claims.iter_mut().enumerate() {
+            if let Claim::Loading(page_id, _, latch) = claim {
+                let (page_id, mut latch) = (*page_id, latch.take().expect("latch held until loaded"));
+                let disk_manager = Arc::clone(&self.disk_manager);
+                loads.spawn(async move {
+                    let result = match disk_manager.read_page(page_id, &mut latch).await {
+                        Err(DiskError::PageNotFound(_)) => {
+                            latch.fill(0);
+                            Ok(())
+                        }
+                        result => result,
+                    };
+                    (index, latch, result)
+                });
+            }
+        }
+        let mut first_error = None;
+        while let Some(loaded) = loads.join_next().await {
+            let (index, latch, result) = match loaded {
+                Ok(loaded) => loaded,
+                // Its claim is left without a latch, like a failed load
+                Err(e) => {
+                    first_error.get_or_insert(DiskError::IoError(std::io::Error::other(e)));
+                    continue;
+                }
+            };
+            match result {
+                Ok(()) => {
+                    if let Claim::Loading(_, _, slot) = &mut claims[index] {
+                        *slot = Some(latch);
+                    }
+                }
+                // Left without its latch, the claim is unmapped by release_claims()
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        if let Some(e) = first_error {
+            let mut table = self.page_table.lock().await;
+            self.release_claims(&mut table, claims);
+            return Err(e);
+        }
+
+        // Loaded pages first, so no write latch of ours is held while waiting on others
+        let tracker = &self.shared.latch_tracker;
+        let mut guards: Vec<Option<ReadPageGuard>> = Vec::with_capacity(claims.len());
+        let mut resident = Vec::new();
+        for (index, claim) in claims.into_iter().enumerate() {
+            match claim {
+                Claim::Loading(page_id, frame, latch) => {
+                    let latch = latch.expect("loaded page keeps its latch").downgrade();
+                    let pin_token = self.shared.pin_tracker.pinned(page_id, frame.frame_id());
+                    let token = tracker.acquired(page_id, LatchMode::Shared);
+                    guards.push(Some(ReadPageGuard::new(
+                        page_id,
+                        frame,
+                        latch,
+                        Arc::clone(&self.shared),
+                        token,
+                        pin_token,
+                    )));
+                    self.record_access(page_id, AccessType::Unknown, false).await;
+                }
+                Claim::Resident(page_id, frame) => {
+                    guards.push(None);
+                    resident.push((index, page_id, frame));
+                }
+            }
+        }
+        let mut resident = resident.into_iter();
+        while let Some((index, page_id, frame)) = resident.next() {
+            let pin_token = self.shared.pin_tracker.pinned(page_id, frame.frame_id());
+            tracker.begin_wait(page_id, LatchMode::Shared);
+            let latch = frame.latch().read_owned().await;
+            let token = tracker.acquired(page_id, LatchMode::Shared);
+            let guard = ReadPageGuard::new(
+                page_id,
+                Arc::clone(&frame),
+                latch,
+                Arc::clone(&self.shared),
+                token,
+                pin_token,
+            );
+            if let Err(e) = Self::check_resident(&frame, page_id) {
+                // The guards made so far unpin their pages when dropped; the rest have none
+                for (_, _, frame) in resident {
+                    self.shared.unpin(frame.frame_id());
+                }
+                return Err(e);
+            }
+            guards[index] = Some(guard);
+            self.record_access(page_id, AccessType::Unknown, true).await;
+        }
+        Ok(guards.into_iter().map(|guard| guard.expect("every page has a guard")).collect())
+    }
+
+    // Unpin everything read_pages() claimed. Pages it did not finish loading are unmapped
+    // again, and their frames go back on the free list once no waiter has them pinned.
+    fn release_claims(&self, table: &mut PageTable, claims: Vec<Claim>) {
+        for claim in claims {
+            match claim {
+                Claim::Resident(_, frame) | Claim::Loading(_, frame, Some(_)) => {
+                    self.shared.unpin(frame.frame_id());
+                }
+                Claim::Loading(page_id, frame, None) => {
+                    if table.pages.get(&page_id) == Some(&frame.frame_id()) {
+                        table.pages.remove(&page_id);
+                    }
+                    frame.set_page_id(INVALID_PAGE_ID);
+                    if self.shared.unpin_vacated(frame.frame_id()) {
+                        table.free_frames.push(frame.frame_id());
+                    }
+                }
+            }
+        }
+    }
+
+    async fn read_page_inner(
+        &self,
+        page_id: PageId,
+        access_type: AccessType,
+        options: ReadOptions,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<ReadPageGuard, DiskError> {
+        if cancel.is_some_and(|token| token.is_cancelled()) {
+            return Err(DiskError::Cancelled);
+        }
+        let (frame, loaded) = self.pin_frame(page_id, access_type, options.fill_cache).await?;
+        let pin_token = self.shared.pin_tracker.pinned(page_id, frame.frame_id());
+        let tracker = &self.shared.latch_tracker;
+
+        let latch = match loaded {
+            Some(write_latch) => write_latch.downgrade(),
+            None => {
+                tracker.begin_wait(page_id, LatchMode::Shared);
+                let latch = frame.latch().read_owned();
+                self.wait_for_latch(latch, &frame, cancel, pin_token).await?
+            }
+        };
+        let token = tracker.acquired(page_id, LatchMode::Shared);
+        let guard = ReadPageGuard::new(
+            page_id,
+            Arc::clone(&frame),
+            latch,
+            Arc::clone(&self.shared),
+            token,
+            pin_token,
+        );
+        Self::check_resident(&frame, page_id)?;
+        Ok(guard)
+    }
+
+    /// Pin `page_id` and take its latch in exclusive mode.
+    pub async fn write_page(&self, page_id: PageId) -> Result<WritePageGuard, DiskError> {
+        self.write_page_with(page_id, AccessType::Unknown).await
+    }
+
+    pub async fn write_page_with(
+        &self,
+        page_id: PageId,
+        access_type: AccessType,
+    ) -> Result<WritePageGuard, DiskError> {
+        self.write_page_inner(page_id, access_type, None).await
+    }
+
+    /// Like write_page, but gives up with DiskError::Cancelled (and unpins the frame)
+    /// if `cancel` fires while waiting for the latch.
+    pub async fn write_page_cancellable(
+        &self,
+        page_id: PageId,
+        cancel: &CancellationToken,
+    ) -> Result<WritePageGuard, DiskError> {
+        self.write_page_inner(page_id, AccessType::Unknown, Some(cancel)).await
+    }
+
+    async fn write_page_inner(
+        &self,
+        page_id: PageId,
+        access_type: AccessType,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<WritePageGuard, DiskError> {
+        if cancel.is_some_and(|token| token.is_cancelled()) {
+            return Err(DiskError::Cancelled);
+        }
+        let (frame, loaded) = self.pin_frame(page_id, access_type, true).await?;
+        let pin_token = self.shared.pin_tracker.pinned(page_id, frame.frame_id());
+        let tracker = &self.shared.latch_tracker;
+
+        let latch = match loaded {
+            Some(write_latch) => write_latch,
+            None => {
+                tracker.begin_wait(page_id, LatchMode::Exclusive);
+                let latch = frame.latch().write_owned();
+                self.wait_for_latch(latch, &frame, cancel, pin_token).await?
+            }
+        };
+        let token = tracker.acquired(page_id, LatchMode::Exclusive);
+        let guard = WritePageGuard::new(
+            page_id,
+            Arc::clone(&frame),
+            latch,
+            Arc::clone(&self.shared),
+            token,
+            pin_token,
+        );
+        Self::check_resident(&frame, page_id)?;
+        Ok(guard)
+    }
+
+    /// Await a latch on an already pinned frame. On cancellation the pin is dropped.
+    async fn wait_for_latch<L>(
+        &self,
+        latch: impl Future<Output = L>,
+        frame: &FrameHeader,
+        cancel: Option<&CancellationToken>,
+        pin_token: Option<u64>,
+    ) -> Result<L, DiskError> {
+        let Some(cancel) = cancel else {
+            return Ok(latch.await);
+        };
+        tokio::select! {
+            latch = latch => Ok(latch),
+            _ = cancel.cancelled() => {
+                self.shared.latch_tracker.cancel_wait();
+                if let Some(token) = pin_token {
+                    self.shared.pin_tracker.unpinned(token);
+                }
+                self.shared.unpin(frame.frame_id());
+                Err(DiskError::Cancelled)
+            }
+        }
+    }
+
+    /// Write `page_id` back to disk if it is resident. Returns false if it is not.
+    pub async fn flush_page(&self, page_id: PageId) -> Result<bool, DiskError> {
+        self.flush_page_for(IoSource::DataPage, page_id).await
+    }
+
+    async fn flush_page_for(&self, source: IoSource, page_id: PageId) -> Result<bool, DiskError> {
+        if !self.page_table.lock().await.pages.contains_key(&page_id) {
+            return Ok(false);
+        }
+        let guard = self.read_page(page_id).await?;
+        self.disk_manager.write_page_for(source, page_id, guard.data()).await?;
+        guard.frame().set_dirty(false);
+        self.notify(|observer| observer.on_flush(page_id));
+        Ok(true)
+    }
+
+    /// Write every resident page back to disk.
+    pub async fn flush_all_pages(&self) -> Result<(), DiskError> {
+        let resident: Vec<PageId> = self.page_table.lock().await.pages.keys().copied().collect();
+        for page_id in resident {
+            self.flush_page(page_id).await?;
+        }
+        Ok(())
+    }
+
+    /// Pages a checkpoint writes at once.
+    pub fn flush_batch(&self) -> usize {
+        self.flush_batch.load(Ordering::Relaxed)
+    }
+
+    /// Let checkpoints write up to `pages` pages at once (at least 1). Takes effect
+    /// from the next checkpoint on.
+    pub fn set_flush_batch(&self, pages: usize) {
+        self.flush_batch.store(pages.max(1), Ordering::Relaxed);
+    }
+
+    // Write back the resident pages among `page_ids` concurrently, latching them in the
+    // order given. Returns the number written; on failure, the pages whose writes did
+    // complete are still marked clean.
+    async fn flush_pages_for(&self, source: IoSource, page_ids: &[PageId]) -> Result<usize, DiskError> {
+        if let [page_id] = page_ids {
+            return Ok(self.flush_page_for(source, *page_id).await? as usize);
+        }
+        let mut guards = Vec::with_capacity(page_ids.len());
+        for &page_id in page_ids {
+            if self.page_table.lock().await.pages.contains_key(&page_id) {
+                guards.push(self.read_page(page_id).await?);
+            }
+        }
+
+        let mut writes = tokio::task::JoinSet::new();
+        for guard in guards {
+            let disk_manager = Arc::clone(&self.disk_manager);
+            writes.spawn(async move {
+                let written = disk_manager.write_page_for(source, guard.page_id(), guard.data()).await;
+                (guard, written)
+            });
+        }
+        let mut flushed = 0;
+        let mut first_error = None;
+        while let Some(joined) = writes.join_next().await {
+            let (guard, written) = joined.map_err(|e| DiskError::IoError(e.into()))?;
+            match written {
+                Ok(()) => {
+                    guard.frame().set_dirty(false);
+                    self.notify(|observer| observer.on_flush(guard.page_id()));
+                    flushed += 1;
+                }
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        first_error.map_or(Ok(flushed), Err)
+    }
+
+    /// Write every dirty page back to disk, attributing the I/O to checkpoints.
+    /// Returns the number of pages written.
+    pub async fn checkpoint(&self) -> Result<usize, DiskError> {
+        self.checkpoint_with(&CancellationToken::new(), &ProgressTracker::new()).await
+    }
+
+    /// checkpoint(), reporting each written page to `progress`. Pages are written
+    /// flush_batch() at a time, in page id order. Cancelling `cancel` stops it between
+    /// batches with DiskError::Cancelled; the pages not reached stay dirty.
+    pub async fn checkpoint_with(
+        &self,
+        cancel: &CancellationToken,
+        progress: &ProgressTracker,
+    ) -> Result<usize, DiskError> {
+        let mut dirty: Vec<PageId> = {
+            let table = self.page_table.lock().await;
+            let frames = self.frame_headers();
+            table
+                .pages
+                .iter()
+                .filter(|&(_, &frame_id)| frames[frame_id].is_dirty())
+                .map(|(&page_id, _)| page_id)
+                .collect()
+        };
+        // A consistent latch order for the pages of a batch
+        dirty.sort_unstable();
+        progress.set_total(dirty.len() as u64);
+        let mut written = 0;
+        for batch in dirty.chunks(self.flush_batch()) {
+            if cancel.is_cancelled() {
+                return Err(DiskError::Cancelled);
+            }
+            written += self.flush_pages_for(IoSource::Checkpoint, batch).await?;
+            progress.advance(batch.len() as u64);
+        }
+        Ok(written)
+    }
+
+    /// Drop `page_id` from the pool and from disk. Returns false if the page is pinned.
+    pub async fn delete_page(&self, page_id: PageId) -> Result<bool, DiskError> {
+        let mut table = self.page_table.lock().await;
+        if let Some(&frame_id) = table.pages.get(&page_id) {
+            {
+                let mut frames = self.shared.lock_frames();
+                if frames.pin_counts[frame_id] > 0 {
+                    return Ok(false);
+                }
+                let _ = frames.replacer.remove(frame_id);
+            }
+            table.pages.remove(&page_id);
+            table.free_frames.push(frame_id);
+            let frame = self.frame(frame_id);
+            frame.set_page_id(INVALID_PAGE_ID);
+            frame.set_dirty(false);
+        }
+        drop(table);
+
+        match self.disk_manager.delete_page(page_id).await {
+            Ok(()) | Err(DiskError::PageNotFound(_)) => Ok(true),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Pin count of a resident page, or None if the page is not in the pool.
+    pub async fn get_pin_count(&self, page_id: PageId) -> Option<usize> {
+        let table = self.page_table.lock().await;
+        let frame_id = *table.pages.get(&page_id)?;
+        Some(self.shared.lock_frames().pin_counts[frame_id])
+    }
+
+    /// Ids of all pages currently in the pool.
+    pub async fn resident_pages(&self) -> Vec<PageId> {
+        self.page_table.lock().await.pages.keys().copied().collect()
+    }
+
+    /// Occupancy and hit/miss counts of the pool.
+    pub async fn stats(&self) -> BufferPoolStats {
+        let table = self.page_table.lock().await;
+        let pinned_frames = self.shared.lock_frames().pin_counts.iter().filter(|&&c| c > 0).count();
+        let frames = self.frame_headers();
+        let dirty_pages = table
+            .pages
+            .values()
+            .filter(|&&frame_id| frames[frame_id].is_dirty())
+            .count();
+        BufferPoolStats {
+            num_frames: frames.len(),
+            resident_pages: table.pages.len(),
+            pinned_frames,
+            dirty_pages,
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Load `page_id` into a free frame without pinning it, e.g. to warm the cache.
+    /// Never evicts: returns false if the page is already resident, no frame is free,
+    /// or the page does not exist on disk.
+    pub async fn prefetch_page(&self, page_id: PageId) -> Result<bool, DiskError> {
+        let mut table = self.page_table.lock().await;
+        if table.pages.contains_key(&page_id) {
+            return Ok(false);
+        }
+        let Some(frame_id) = table.free_frames.pop() else {
+            return Ok(false);
+        };
+        let frame = self.frame(frame_id);
+        let mut latch = frame
+            .latch()
+            .try_write_owned()
+            .expect("a free frame must not be latched");
+
+        match self.disk_manager.read_page(page_id, &mut latch).await {
+            Ok(()) => {}
+            Err(e) => {
+                table.free_frames.push(frame_id);
+                return match e {
+                    DiskError::PageNotFound(_) => Ok(false),
+                    e => Err(e),
+                };
+            }
+        }
+        frame.set_page_id(page_id);
+        frame.set_dirty(false);
+        table.pages.insert(page_id, frame_id);
+        self.shared.pin(frame_id, page_id, AccessType::Scan);
+        self.shared.unpin(frame_id);
+        Ok(true)
+    }
+
+    /// A frame whose load failed is released before its waiters get the latch.
+    fn check_resident(frame: &FrameHeader, page_id: PageId) -> Result<(), DiskError> {
+        if frame.page_id() == page_id {
+            Ok(())
+        } else {
+            Err(DiskError::PageNotFound(page_id))
+        }
+    }
+
+    /// Pin the frame holding `page_id`, loading it from disk on a miss.
+    /// On a miss the frame's write latch is returned already held, with the page loaded.
+    /// Without `fill_cache` the access neither promotes a resident page nor keeps a
+    /// loaded one around.
+    async fn pin_frame(
+        &self,
+        page_id: PageId,
+        access_type: AccessType,
+        fill_cache: bool,
+    ) -> Result<(Arc<FrameHeader>, Option<tokio::sync::OwnedRwLockWriteGuard<PageData>>), DiskError> {
+        let mut table = self.page_table.lock().await;
+
+        if let Some(&frame_id) = table.pages.get(&page_id) {
+            if fill_cache {
+                self.shared.pin(frame_id, page_id, access_type);
+            } else {
+                self.shared.pin_one_shot(frame_id, page_id);
+            }
+            drop(table);
+            self.record_access(page_id, access_type, true).await;
+            return Ok((self.frame(frame_id), None));
+        }
+
+        let (frame, mut latch) = self.take_frame(&mut table).await?;
+        let frame_id = frame.frame_id();
+
+        match self.disk_manager.read_page(page_id, &mut latch).await {
+            Ok(()) => {}
+            Err(DiskError::PageNotFound(_)) => latch.fill(0),
+            Err(e) => {
+                frame.set_page_id(INVALID_PAGE_ID);
+                frame.set_dirty(false);
+                table.free_frames.push(frame_id);
+                return Err(e);
+            }
+        }
+        frame.set_page_id(page_id);
+        frame.set_dirty(false);
+        table.pages.insert(page_id, frame_id);
+        if !fill_cache {
+            self.shared.pin_one_shot(frame_id, page_id);
+        } else if let AccessOutcome::GhostHit(list) = self.shared.pin(frame_id, page_id, access_type) {
+            self.notify(|observer| observer.on_ghost_hit(page_id, list));
+        }
+        drop(table);
+        self.record_access(page_id, access_type, false).await;
+
+        Ok((frame, Some(latch)))
+    }
+
+    /// A free frame, or else an evicted one with its page written back and unmapped.
+    /// Its write latch is returned held.
+    async fn take_frame(
+        &self,
+        table: &mut PageTable,
+    ) -> Result<(Arc<FrameHeader>, tokio::sync::OwnedRwLockWriteGuard<PageData>), DiskError> {
+        let frame_id = match table.free_frames.pop() {
+            Some(frame_id) => frame_id,
+            None => self
+                .shared
+                .lock_frames()
+                .replacer
+                .evict()
+                .ok_or(DiskError::NoFreeFrame)?,
+        };
+        let frame = self.frame(frame_id);
+        let latch = frame
+            .latch()
+            .try_write_owned()
+            .expect("an unpinned frame must not be latched");
+
+        // Write back the victim before its page can be looked up again
+        let old_page_id = frame.page_id();
+        if old_page_id != INVALID_PAGE_ID {
+            let dirty = frame.is_dirty();
+            if dirty {
+                if let Err(e) = self.disk_manager.write_page(old_page_id, &latch).await {
+                    // Keep the victim resident; it is still the only copy of its changes
+                    let mut frames = self.shared.lock_frames();
+                    frames.replacer.record_access(frame_id, old_page_id, AccessType::Unknown);
+                    let _ = frames.replacer.set_evictable(frame_id, true);
+                    return Err(e);
+                }
+                self.notify(|observer| observer.on_flush(old_page_id));
+            }
+            table.pages.remove(&old_page_id);
+            self.notify(|observer| observer.on_evict(old_page_id, frame_id, dirty));
+        }
+        Ok((frame, latch))
+    }
+
+    async fn record_access(&self, page_id: PageId, access_type: AccessType, hit: bool) {
+        let counter = if hit { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        if self.trace_recorder.record(page_id, access_type, hit)
+            && let Err(e) = self.trace_recorder.flush().await
+        {
+            log::warn!("access trace flush failed: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::storage::disk_manager::GRIMOIRE_PAGE_SIZE;
+    use crate::backend::storage::storage_backend::{BackendFuture, MemoryBackend, StorageBackend};
+    use std::time::Duration;
+
+    async fn make_pool(num_frames: usize) -> BufferPoolManager {
+        let dm = Arc::new(DiskManager::in_memory().await.unwrap());
+        BufferPoolManager::new(num_frames, dm)
+    }
+
+    // Memory storage whose reads wait while the gate is closed
+    struct GatedBackend {
+        inner: MemoryBackend,
+        gate: tokio::sync::Semaphore,
+    }
+
+    impl GatedBackend {
+        fn new() -> Self {
+            Self { inner: MemoryBackend::new(), gate: tokio::sync::Semaphore::new(1) }
+        }
+
+        async fn close(&self) {
+            self.gate.acquire().await.unwrap().forget();
+        }
+
+        fn open(&self) {
+            self.gate.add_permits(1);
+        }
+    }
+
+    impl StorageBackend for GatedBackend {
+        fn read_at<'a>(&'a self, offset: u64, buf: &'a mut [u8]) -> BackendFuture<'a, ()> {
+            Box::pin(async move {
+                drop(self.gate.acquire().await);
+                self.inner.read_at(offset, buf).await
+            })
+        }
+
+        fn write_at<'a>(&'a self, offset: u64, data: &'a [u8]) -> BackendFuture<'a, ()> {
+            self.inner.write_at(offset, data)
+        }
+
+        fn append<'a>(&'a self, data: &'a [u8]) -> BackendFuture<'a, ()> {
+            self.inner.append(data)
+        }
+
+        fn sync(&self) -> BackendFuture<'_, ()> {
+            self.inner.sync()
+        }
+
+        fn size(&self) -> BackendFuture<'_, u64> {
+            self.inner.size()
+        }
+
+        fn set_len(&self, len: u64) -> BackendFuture<'_, ()> {
+            self.inner.set_len(len)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_write_evict_and_read_back() {
+        let bpm = make_pool(2).await;
+        let pages: Vec<PageId> = (0..4).map(|_| bpm.new_page()).collect();
+
+        for &page_id in &pages {
+            let mut guard = bpm.write_page(page_id).await.unwrap();
+            guard.data_mut()[0] = page_id as u8 + 1;
+        }
+
+        // Only two frames: the first pages must have been evicted and written back
+        for &page_id in &pages {
+            let guard = bpm.read_page(page_id).await.unwrap();
+            assert_eq!(guard.data()[0], page_id as u8 + 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pinned_pages_are_not_evicted() {
+        let bpm = make_pool(1).await;
+        let p0 = bpm.new_page();
+        let p1 = bpm.new_page();
+
+        let guard = bpm.read_page(p0).await.unwrap();
+        assert_eq!(bpm.get_pin_count(p0).await, Some(1));
+        assert!(matches!(bpm.read_page(p1).await, Err(DiskError::NoFreeFrame)));
+        assert!(!bpm.delete_page(p0).await.unwrap());
+
+        drop(guard);
+        assert_eq!(bpm.get_pin_count(p0).await, Some(0));
+        assert!(bpm.read_page(p1).await.is_ok());
+        assert_eq!(bpm.get_pin_count(p0).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_flush_clears_dirty() {
+        let bpm = make_pool(2).await;
+        let page_id = bpm.new_page();
+        {
+            let mut guard = bpm.write_page(page_id).await.unwrap();
+            guard.data_mut()[10] = 42;
+            assert!(guard.is_dirty());
+        }
+        assert!(bpm.flush_page(page_id).await.unwrap());
+        assert!(!bpm.read_page(page_id).await.unwrap().is_dirty());
+        assert!(!bpm.flush_page(bpm.new_page()).await.unwrap());
+    }
+
+    #[cfg(feature = "native")]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_latch_tracker_reports_deadlock() {
+        let bpm = Arc::new(make_pool(4).await);
+        bpm.latch_tracker().set_enabled(true);
+        let p0 = bpm.new_page();
+        let p1 = bpm.new_page();
+
+        // Two tasks lock the same pair of pages in opposite order
+        let barrier = Arc::new(tokio::sync::Barrier::new(2));
+        let spawn_locker = |first: PageId, second: PageId| {
+            let bpm = Arc::clone(&bpm);
+            let barrier = Arc::clone(&barrier);
+            tokio::spawn(async move {
+                let _first = bpm.write_page(first).await.unwrap();
+                barrier.wait().await;
+                let _second = bpm.write_page(second).await.unwrap();
+            })
+        };
+        let a = spawn_locker(p0, p1);
+        let b = spawn_locker(p1, p0);
+
+        let mut reports = vec![];
+        for _ in 0..100 {
+            reports = bpm.latch_tracker().reports();
+            if !reports.is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        a.abort();
+        b.abort();
+
+        assert_eq!(reports.len(), 1);
+        let mut pages = reports[0].page_ids();
+        pages.sort();
+        assert_eq!(pages, vec![p0, p1]);
+        assert!(reports[0].cycle.iter().all(|wait| wait.holding.len() == 1));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_latch_wait_unpins() {
+        let bpm = make_pool(2).await;
+        let page_id = bpm.new_page();
+        let writer = bpm.write_page(page_id).await.unwrap();
+
+        let token = CancellationToken::new();
+        let canceller = token.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            canceller.cancel();
+        });
+        let result = bpm.read_page_cancellable(page_id, &token).await;
+        assert!(matches!(result, Err(DiskError::Cancelled)));
+        assert_eq!(bpm.get_pin_count(page_id).await, Some(1));
+
+        drop(writer);
+        assert_eq!(bpm.get_pin_count(page_id).await, Some(0));
+        assert!(matches!(
+            bpm.write_page_cancellable(page_id, &token).await,
+            Err(DiskError::Cancelled)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_pin_tracker_reports_long_pins() {
+        let bpm = make_pool(2).await;
+        bpm.pin_tracker().set_enabled(true);
+        let p0 = bpm.new_page();
+        let p1 = bpm.new_page();
+
+        let leaked = bpm.read_page(p0).await.unwrap();
+        drop(bpm.write_page(p1).await.unwrap());
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let leaks = bpm.pin_tracker().report_leaks(Duration::from_millis(10));
+        assert_eq!(leaks.len(), 1);
+        assert_eq!(leaks[0].page_id, p0);
+        assert!(bpm.pin_tracker().report_leaks(Duration::from_millis(10)).is_empty());
+        assert_eq!(bpm.pin_tracker().long_pins(Duration::from_millis(10)).len(), 1);
+
+        drop(leaked);
+        assert!(bpm.pin_tracker().long_pins(Duration::ZERO).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_observer_sees_evict_flush_and_ghost_hit() {
+        use crate::backend::buffer::arc_replacer::ArcStatus;
+
+        #[derive(Default)]
+        struct Recorder(std::sync::Mutex<Vec<String>>);
+
+        impl BufferPoolObserver for Recorder {
+            fn on_evict(&self, page_id: PageId, _frame_id: FrameId, dirty: bool) {
+                self.0.lock().unwrap().push(format!("evict {} dirty={}", page_id, dirty));
+            }
+            fn on_flush(&self, page_id: PageId) {
+                self.0.lock().unwrap().push(format!("flush {}", page_id));
+            }
+            fn on_ghost_hit(&self, page_id: PageId, list: ArcStatus) {
+                self.0.lock().unwrap().push(format!("ghost {} {:?}", page_id, list));
+            }
+        }
+
+        let bpm = make_pool(2).await;
+        let recorder = Arc::new(Recorder::default());
+        bpm.add_observer(recorder.clone());
+        let pages: Vec<PageId> = (0..3).map(|_| bpm.new_page()).collect();
+
+        bpm.write_page(pages[0]).await.unwrap().data_mut()[0] = 1;
+        // Seen twice, so pages[1] moves to MFU and pages[0] is the victim below
+        drop(bpm.read_page(pages[1]).await.unwrap());
+        drop(bpm.read_page(pages[1]).await.unwrap());
+        drop(bpm.read_page(pages[2]).await.unwrap());
+        drop(bpm.read_page(pages[0]).await.unwrap());
+
+        assert_eq!(*recorder.0.lock().unwrap(), vec![
+            format!("flush {}", pages[0]),
+            format!("evict {} dirty=true", pages[0]),
+            format!("evict {} dirty=false", pages[2]),
+            format!("ghost {} MRU", pages[0]),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_trace_recorder_logs_hits_and_misses() {
+        use crate::backend::buffer::trace_recorder::decode_trace;
+        use crate::backend::storage::storage_backend::{MemoryBackend, StorageBackend};
+
+        let bpm = make_pool(2).await;
+        let trace = Arc::new(MemoryBackend::new());
+        let p0 = bpm.new_page();
+
+        drop(bpm.read_page(p0).await.unwrap());
+        bpm.trace_recorder().start(trace.clone()).await.unwrap();
+        drop(bpm.read_page_with(p0, AccessType::Lookup).await.unwrap());
+        drop(bpm.write_page(bpm.new_page()).await.unwrap());
+        bpm.trace_recorder().stop().await.unwrap();
+        drop(bpm.read_page(p0).await.unwrap());
+
+        let mut bytes = vec![0u8; trace.size().await.unwrap() as usize];
+        trace.read_at(0, &mut bytes).await.unwrap();
+        let records = decode_trace(&bytes).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            (records[0].page_id, records[0].access_type, records[0].hit),
+            (p0, AccessType::Lookup, true)
+        );
+        assert!(!records[1].hit);
+        assert!(records[0].timestamp_us <= records[1].timestamp_us);
+    }
+
+    #[tokio::test]
+    async fn test_frames_share_one_region() {
+        let bpm = make_pool(2).await;
+        let (p0, p1) = (bpm.new_page(), bpm.new_page());
+        let first = bpm.read_page(p0).await.unwrap();
+        let second = bpm.read_page(p1).await.unwrap();
+        // Free frames are handed out lowest first, so these are frames 0 and 1
+        assert_eq!(
+            second.data().as_ptr() as usize - first.data().as_ptr() as usize,
+            GRIMOIRE_PAGE_SIZE
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resize_pool() {
+        let bpm = make_pool(2).await;
+        let pages: Vec<PageId> = (0..4).map(|_| bpm.new_page()).collect();
+
+        bpm.set_pool_size(4).await.unwrap();
+        for &page_id in &pages {
+            bpm.write_page(page_id).await.unwrap().data_mut()[0] = page_id as u8 + 1;
+        }
+        assert_eq!(bpm.stats().await.resident_pages, 4);
+
+        // A pinned page in a frame that would go blocks the shrink
+        let pinned = bpm.read_page(pages[3]).await.unwrap();
+        assert!(matches!(bpm.set_pool_size(2).await, Err(DiskError::NoFreeFrame)));
+        assert_eq!(bpm.size(), 4);
+        drop(pinned);
+
+        bpm.set_pool_size(2).await.unwrap();
+        let stats = bpm.stats().await;
+        assert_eq!((stats.num_frames, stats.resident_pages, stats.dirty_pages), (2, 2, 2));
+        for &page_id in &pages {
+            assert_eq!(bpm.read_page(page_id).await.unwrap().data()[0], page_id as u8 + 1);
+        }
+        assert_eq!(bpm.stats().await.num_frames, 2);
+    }
+
+    #[tokio::test]
+    async fn test_stats_count_hits_and_misses() {
+        let bpm = make_pool(4).await;
+        let p0 = bpm.new_page();
+        let p1 = bpm.new_page();
+
+        bpm.write_page(p0).await.unwrap().data_mut()[0] = 1;
+        drop(bpm.read_page(p0).await.unwrap());
+        let _pinned = bpm.read_page(p1).await.unwrap();
+
+        let stats = bpm.stats().await;
+        assert_eq!(stats.num_frames, 4);
+        assert_eq!(stats.resident_pages, 2);
+        assert_eq!(stats.pinned_frames, 1);
+        assert_eq!(stats.dirty_pages, 1);
+        assert_eq!((stats.hits, stats.misses), (1, 2));
+        assert!((stats.hit_rate() - 1.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_read_pages_batches_misses() {
+        let dm = Arc::new(DiskManager::in_memory().await.unwrap());
+        for page_id in 0..6 {
+            dm.write_page(page_id, &vec![page_id as u8 + 1; GRIMOIRE_PAGE_SIZE]).await.unwrap();
+        }
+        let bpm = BufferPoolManager::new(4, Arc::clone(&dm));
+        drop(bpm.read_page(5).await.unwrap());
+
+        // 5 is resident, 3 repeats, 9 was never written and reads as zeroes
+        let guards = bpm.read_pages(&[3, 5, 0, 3, 9]).await.unwrap();
+        let firsts: Vec<u8> = guards.iter().map(|guard| guard.data()[0]).collect();
+        assert_eq!(firsts, [4, 6, 1, 4, 0]);
+        assert_eq!(guards.iter().map(|guard| guard.page_id()).collect::<Vec<_>>(), [3, 5, 0, 3, 9]);
+        let stats = bpm.stats().await;
+        assert_eq!((stats.hits, stats.misses, stats.pinned_frames), (2, 4, 4));
+
+        // No frame left for another miss: fails without holding anything extra
+        assert!(matches!(bpm.read_pages(&[0, 1]).await, Err(DiskError::NoFreeFrame)));
+        assert_eq!(bpm.stats().await.pinned_frames, 4);
+        drop(guards);
+        assert_eq!(bpm.stats().await.pinned_frames, 0);
+        let guards = bpm.read_pages(&[1, 2]).await.unwrap();
+        assert_eq!((guards[0].data()[0], guards[1].data()[0]), (2, 3));
+    }
+
+    #[tokio::test]
+    async fn test_read_pages_unpins_after_a_concurrent_load_fails() {
+        let db = Arc::new(GatedBackend::new());
+        let dm = DiskManager::with_backends(db.clone(), Arc::new(MemoryBackend::new()))
+            .await
+            .unwrap()
+            .with_page_checksums(Arc::new(MemoryBackend::new()))
+            .await
+            .unwrap();
+        let dm = Arc::new(dm);
+        for page_id in [1, 2] {
+            dm.write_page(page_id, &vec![page_id as u8; GRIMOIRE_PAGE_SIZE]).await.unwrap();
+        }
+        let bpm = Arc::new(BufferPoolManager::new(4, Arc::clone(&dm)));
+        drop(bpm.read_page(2).await.unwrap());
+        // Rot a bit in page 1 on disk, so loading it fails its checksum
+        let mut slot = vec![0u8; GRIMOIRE_PAGE_SIZE];
+        for offset in (0..db.size().await.unwrap()).step_by(GRIMOIRE_PAGE_SIZE) {
+            db.read_at(offset, &mut slot).await.unwrap();
+            if slot.iter().all(|&byte| byte == 1) {
+                db.write_at(offset, &[0]).await.unwrap();
+            }
+        }
+
+        // Hold the load of page 1 until the second batch waits on it
+        db.close().await;
+        let loading = tokio::spawn({
+            let bpm = Arc::clone(&bpm);
+            async move { bpm.read_pages(&[1]).await.map(drop) }
+        });
+        while bpm.get_pin_count(1).await.is_none() {
+            tokio::task::yield_now().await;
+        }
+        let waiting = tokio::spawn({
+            let bpm = Arc::clone(&bpm);
+            async move { bpm.read_pages(&[1, 2]).await.map(drop) }
+        });
+        while bpm.get_pin_count(2).await != Some(1) {
+            tokio::task::yield_now().await;
+        }
+        db.open();
+
+        assert!(matches!(loading.await.unwrap(), Err(DiskError::ChecksumMismatch { page_id: 1, .. })));
+        assert!(matches!(waiting.await.unwrap(), Err(DiskError::PageNotFound(1))));
+        assert_eq!(bpm.get_pin_count(2).await, Some(0));
+        assert_eq!(bpm.stats().await.pinned_frames, 0);
+    }
+
+    #[tokio::test]
+    async fn test_shrink_after_a_failed_read_pages_load() {
+        let db = Arc::new(MemoryBackend::new());
+        let dm = DiskManager::with_backends(db.clone(), Arc::new(MemoryBackend::new()))
+            .await
+            .unwrap()
+            .with_page_checksums(Arc::new(MemoryBackend::new()))
+            .await
+            .unwrap();
+        let dm = Arc::new(dm);
+        for page_id in [1, 2, 3] {
+            dm.write_page(page_id, &vec![page_id as u8; GRIMOIRE_PAGE_SIZE]).await.unwrap();
+        }
+        let mut slot = vec![0u8; GRIMOIRE_PAGE_SIZE];
+        for offset in (0..db.size().await.unwrap()).step_by(GRIMOIRE_PAGE_SIZE) {
+            db.read_at(offset, &mut slot).await.unwrap();
+            if slot.iter().all(|&byte| byte == 1) {
+                db.write_at(offset, &[0]).await.unwrap();
+            }
+        }
+        let bpm = BufferPoolManager::new(3, Arc::clone(&dm));
+        drop(bpm.read_pages(&[2, 3]).await.unwrap());
+        // Page 1 fails to load into frame 2, the last one
+        assert!(matches!(bpm.read_pages(&[1]).await, Err(DiskError::ChecksumMismatch { page_id: 1, .. })));
+        // Touched twice, pages 2 and 3 are less evictable than frame 2 would be
+        drop(bpm.read_pages(&[2, 3]).await.unwrap());
+
+        bpm.set_pool_size(2).await.unwrap();
+        let page_id = bpm.new_page();
+        bpm.write_page(page_id).await.unwrap().data_mut()[0] = 9;
+        assert_eq!(bpm.read_page(page_id).await.unwrap().data()[0], 9);
+        assert_eq!(bpm.size(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint_writes_in_batches() {
+        let dm = Arc::new(DiskManager::in_memory().await.unwrap());
+        let bpm = BufferPoolManager::new(8, Arc::clone(&dm));
+        bpm.set_flush_batch(3);
+        let pages: Vec<_> = (0..7).map(|_| bpm.new_page()).collect();
+        for &page_id in &pages {
+            bpm.write_page(page_id).await.unwrap().data_mut()[0] = page_id as u8 + 1;
+        }
+
+        assert_eq!(bpm.checkpoint().await.unwrap(), 7);
+        let stats = bpm.stats().await;
+        assert_eq!((stats.dirty_pages, stats.pinned_frames), (0, 0));
+        let mut page_data = vec![0u8; GRIMOIRE_PAGE_SIZE];
+        for &page_id in &pages {
+            dm.read_page(page_id, &mut page_data).await.unwrap();
+            assert_eq!(page_data[0], page_id as u8 + 1);
+        }
+        bpm.set_flush_batch(0);
+        assert_eq!((bpm.flush_batch(), bpm.checkpoint().await.unwrap()), (1, 0));
+    }
+
+    #[tokio::test]
+    async fn test_scan_without_fill_cache_keeps_working_set() {
+        let bpm = make_pool(3).await;
+        let hot: Vec<_> = (0..2).map(|_| bpm.new_page()).collect();
+        for &page_id in &hot {
+            bpm.write_page(page_id).await.unwrap().data_mut()[0] = 1;
+        }
+
+        let scan = ReadOptions { fill_cache: false };
+        for _ in 0..5 {
+            let page_id = bpm.new_page();
+            drop(bpm.read_page_with_options(page_id, &scan).await.unwrap());
+        }
+        let resident = bpm.resident_pages().await;
+        assert!(hot.iter().all(|page_id| resident.contains(page_id)));
+        assert_eq!(bpm.stats().await.misses, 7);
+    }
+
+    #[tokio::test]
+    async fn test_latch_tracker_quiet_without_deadlock() {
+        let bpm = make_pool(2).await;
+        bpm.latch_tracker().set_enabled(true);
+        let page_id = bpm.new_page();
+
+        let r1 = bpm.read_page(page_id).await.unwrap();
+        let r2 = bpm.read_page(page_id).await.unwrap();
+        drop((r1, r2));
+        drop(bpm.write_page(page_id).await.unwrap());
+
+        assert!(bpm.latch_tracker().reports().is_empty());
+    }
+}
+
+// Exhaustive interleavings of the frame table, run with `--cfg grimoire_loom` (see common::sync)
+#[cfg(all(test, grimoire_loom))]
+mod loom_tests {
+    use super::*;
+    use loom::{sync::Arc, thread};
+
+    /// Mirrors pin_frame(): hits and evictions both happen under the page table lock,
+    /// unpins (page guard drops) do not.
+    struct Pool {
+        shared: PoolShared,
+        resident: loom::sync::Mutex<Vec<bool>>,
+    }
+
+    impl Pool {
+        fn new(num_frames: usize) -> Self {
+            let pool = Self {
+                shared: PoolShared::new(num_frames),
+                resident: loom::sync::Mutex::new(vec![true; num_frames]),
+            };
+            for frame_id in 0..num_frames {
+                pool.shared.pin(frame_id, frame_id as PageId, AccessType::Unknown);
+            }
+            pool
+        }
+
+        fn pin_if_resident(&self, frame_id: FrameId) -> bool {
+            let resident = self.resident.lock().unwrap();
+            if resident[frame_id] {
+                self.shared.pin(frame_id, frame_id as PageId, AccessType::Unknown);
+            }
+            resident[frame_id]
+        }
+
+        fn evict(&self) -> Option<FrameId> {
+            let mut resident = self.resident.lock().unwrap();
+            let mut frames = self.shared.lock_frames();
+            let victim = frames.replacer.evict()?;
+            assert_eq!(frames.pin_counts[victim], 0, "evicted pinned frame {}", victim);
+            resident[victim] = false;
+            Some(victim)
+        }
+    }
+
+    #[test]
+    fn loom_unpin_races_pin_and_evict() {
+        loom::model(|| {
+            let pool = Arc::new(Pool::new(2));
+            let unpinner = {
+                let pool = Arc::clone(&pool);
+                thread::spawn(move || pool.shared.release(0, 0, None, None))
+            };
+            let reader = {
+                let pool = Arc::clone(&pool);
+                thread::spawn(move || {
+                    if pool.pin_if_resident(0) {
+                        pool.shared.release(0, 0, None, None);
+                    }
+                })
+            };
+            let evicted = pool.evict();
+            unpinner.join().unwrap();
+            reader.join().unwrap();
+
+            // Frame 1 stays pinned throughout, so only frame 0 can ever go
+            assert!(matches!(evicted, None | Some(0)));
+            let frames = pool.shared.lock_frames();
+            assert_eq!(frames.pin_counts, vec![0, 1]);
+            assert_eq!(frames.replacer.size(), usize::from(evicted.is_none()));
+        });
+    }
+
+    #[test]
+    fn loom_one_shot_and_regular_pins_settle() {
+        loom::model(|| {
+            let pool = Arc::new(Pool::new(1));
+            pool.shared.release(0, 0, None, None);
+            let scanner = {
+                let pool = Arc::clone(&pool);
+                thread::spawn(move || {
+                    pool.shared.pin_one_shot(0, 0);
+                    pool.shared.release(0, 0, None, None);
+                })
+            };
+            pool.shared.pin(0, 0, AccessType::Lookup);
+            pool.shared.release(0, 0, None, None);
+            scanner.join().unwrap();
+
+            // Whatever the order, the frame ends up unpinned and evictable exactly once
+            assert_eq!(pool.shared.lock_frames().pin_counts[0], 0);
+            assert_eq!(pool.evict(), Some(0));
+            assert_eq!(pool.evict(), None);
+        });
+    }
+}
@@ -0,0 +1,181 @@
+// src/buffer/hot_pages.rs
+
+//! Hot page dump/restore
+//!
+//! A restarted instance starts with an empty buffer pool and pays a miss for every page
+//! its workload touches. To shorten that cold period, the ids of the resident pages are
+//! saved periodically (dump_hot_pages / spawn_hot_page_dumper) and read back in the
+//! background after a restart (prewarm). Prewarming only fills free frames, so pages
+//! the live workload has already pulled in are never evicted for it.
+//!
+//! Dump layout (little endian):
+//! | magic: u32 | count: u32 | page ids: count * i32 | crc: u32 |
+//!
+//! A missing, torn or corrupt dump just means nothing is prewarmed.
+
+use std::{
+    sync::{Arc, Weak},
+    time::Duration,
+};
+
+use crate::backend::buffer::buffer_pool_manager::BufferPoolManager;
+use crate::backend::storage::storage_backend::StorageBackend;
+use crate::common::{checksum::crc32, errors::DiskError, types::PageId};
+
+const MAGIC: u32 = 0x4854_5047; // "GPTH"
+
+/// Save the ids of all resident pages to `backend`, replacing any previous dump.
+/// Returns the number of page ids written.
+pub async fn dump_hot_pages(
+    pool: &BufferPoolManager,
+    backend: &dyn StorageBackend,
+) -> Result<usize, DiskError> {
+    let pages = pool.resident_pages().await;
+    let bytes = encode(&pages);
+    backend.set_len(0).await?;
+    backend.write_at(0, &bytes).await?;
+    backend.sync_data().await?;
+    Ok(pages.len())
+}
+
+/// Page ids saved by dump_hot_pages, or an empty list if there is no valid dump.
+pub async fn load_hot_pages(backend: &dyn StorageBackend) -> Result<Vec<PageId>, DiskError> {
+    let size = backend.size().await? as usize;
+    if size == 0 {
+        return Ok(Vec::new());
+    }
+    let mut bytes = vec![0u8; size];
+    backend.read_at(0, &mut bytes).await?;
+    Ok(decode(&bytes).unwrap_or_else(|| {
+        log::warn!("ignoring corrupt hot page dump ({} bytes)", size);
+        Vec::new()
+    }))
+}
+
+/// Load the dumped pages into free frames in the background. The task resolves to the
+/// number of pages actually loaded.
+pub fn prewarm(
+    pool: Arc<BufferPoolManager>,
+    backend: Arc<dyn StorageBackend>,
+) -> tokio::task::JoinHandle<Result<usize, DiskError>> {
+    tokio::spawn(async move {
+        let mut loaded = 0;
+        for page_id in load_hot_pages(backend.as_ref()).await? {
+            if pool.prefetch_page(page_id).await? {
+                loaded += 1;
+            }
+        }
+        log::info!("prewarmed {} page(s) into the buffer pool", loaded);
+        Ok(loaded)
+    })
+}
+
+/// Dump the hot page set every `interval` until the pool is dropped.
+pub fn spawn_hot_page_dumper(
+    pool: &Arc<BufferPoolManager>,
+    backend: Arc<dyn StorageBackend>,
+    interval: Duration,
+) -> tokio::task::JoinHandle<()> {
+    let pool: Weak<BufferPoolManager> = Arc::downgrade(pool);
+    tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(interval);
+        ticks.tick().await; // the first tick fires immediately
+        loop {
+            ticks.tick().await;
+            let Some(pool) = pool.upgrade() else {
+                break;
+            };
+            if let Err(e) = dump_hot_pages(&pool, backend.as_ref()).await {
+                log::warn!("hot page dump failed: {}", e);
+            }
+        }
+    })
+}
+
+fn encode(pages: &[PageId]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(12 + 4 * pages.len());
+    bytes.extend_from_slice(&MAGIC.to_le_bytes());
+    bytes.extend_from_slice(&(pages.len() as u32).to_le_bytes());
+    for page_id in pages {
+        bytes.extend_from_slice(&page_id.to_le_bytes());
+    }
+    let crc = crc32(&bytes);
+    bytes.extend_from_slice(&crc.to_le_bytes());
+    bytes
+}
+
+fn decode(bytes: &[u8]) -> Option<Vec<PageId>> {
+    let (body, crc) = bytes.split_at_checked(bytes.len().checked_sub(4)?)?;
+    if crc32(body) != u32::from_le_bytes(crc.try_into().ok()?) {
+        return None;
+    }
+    let (header, ids) = body.split_at_checked(8)?;
+    if u32::from_le_bytes(header[..4].try_into().ok()?) != MAGIC {
+        return None;
+    }
+    let count = u32::from_le_bytes(header[4..].try_into().ok()?) as usize;
+    if ids.len() != count * 4 {
+        return None;
+    }
+    Some(
+        ids.chunks_exact(4)
+            .map(|id| PageId::from_le_bytes(id.try_into().unwrap()))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::storage::disk_manager::{DiskManager, GRIMOIRE_PAGE_SIZE};
+    use crate::backend::storage::storage_backend::MemoryBackend;
+
+    #[tokio::test]
+    async fn test_dump_and_prewarm() {
+        let dm = Arc::new(DiskManager::in_memory().await.unwrap());
+        for page_id in 0..6 {
+            dm.write_page(page_id, &vec![page_id as u8; GRIMOIRE_PAGE_SIZE]).await.unwrap();
+        }
+        let dump: Arc<dyn StorageBackend> = Arc::new(MemoryBackend::new());
+
+        let before = BufferPoolManager::new(4, Arc::clone(&dm));
+        for page_id in [1, 3, 5] {
+            before.read_page(page_id).await.unwrap();
+        }
+        assert_eq!(dump_hot_pages(&before, dump.as_ref()).await.unwrap(), 3);
+
+        // "Restart": a new pool over the same disk warms up from the dump
+        let after = Arc::new(BufferPoolManager::new(4, Arc::clone(&dm)));
+        after.read_page(0).await.unwrap();
+        assert_eq!(prewarm(Arc::clone(&after), Arc::clone(&dump)).await.unwrap().unwrap(), 3);
+
+        let mut resident = after.resident_pages().await;
+        resident.sort();
+        assert_eq!(resident, vec![0, 1, 3, 5]);
+        assert_eq!(after.get_pin_count(3).await, Some(0));
+        assert_eq!(after.read_page(5).await.unwrap().data()[0], 5);
+    }
+
+    #[tokio::test]
+    async fn test_prewarm_never_evicts() {
+        let dm = Arc::new(DiskManager::in_memory().await.unwrap());
+        for page_id in 0..3 {
+            dm.write_page(page_id, &vec![0u8; GRIMOIRE_PAGE_SIZE]).await.unwrap();
+        }
+        let pool = BufferPoolManager::new(1, dm);
+        let _held = pool.read_page(0).await.unwrap();
+        assert!(!pool.prefetch_page(1).await.unwrap());
+        assert!(!pool.prefetch_page(0).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_dump_is_ignored() {
+        let dump = MemoryBackend::new();
+        let mut bytes = encode(&[1, 2, 3]);
+        assert_eq!(decode(&bytes), Some(vec![1, 2, 3]));
+        bytes[9] ^= 0xFF;
+        dump.write_at(0, &bytes).await.unwrap();
+        assert!(load_hot_pages(&dump).await.unwrap().is_empty());
+        assert!(decode(&[]).is_none());
+    }
+}
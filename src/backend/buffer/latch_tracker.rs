@@ -0,0 +1,294 @@
+// src/buffer/latch_tracker.rs
+
+//! LatchTracker
+//!
+//! Debug mode for page latches. When enabled, every page guard records which task holds
+//! or waits for which page latch. Each time a task starts waiting, the wait-for graph is
+//! searched for a cycle back to that task; a cycle is a deadlock that will never resolve
+//! on its own, so it is logged and kept as a DeadlockReport with page ids and backtraces.
+//!
+//! Disabled by default: the only cost then is an atomic load per latch acquisition.
+
+use std::{
+    backtrace::Backtrace,
+    collections::{HashMap, HashSet},
+    fmt,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+};
+
+use crate::common::types::PageId;
+
+/// Who holds or waits for a latch: the current tokio task, or the thread outside of one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LatchOwner {
+    Task(tokio::task::Id),
+    Thread(std::thread::ThreadId),
+}
+
+impl LatchOwner {
+    pub fn current() -> Self {
+        match tokio::task::try_id() {
+            Some(id) => LatchOwner::Task(id),
+            None => LatchOwner::Thread(std::thread::current().id()),
+        }
+    }
+}
+
+impl fmt::Display for LatchOwner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LatchOwner::Task(id) => write!(f, "task {}", id),
+            LatchOwner::Thread(id) => write!(f, "thread {:?}", id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LatchMode {
+    Shared,
+    Exclusive,
+}
+
+impl LatchMode {
+    fn conflicts_with(self, other: LatchMode) -> bool {
+        !(self == LatchMode::Shared && other == LatchMode::Shared)
+    }
+}
+
+/// A latch held by a task in a reported cycle.
+#[derive(Debug, Clone)]
+pub struct HeldLatch {
+    pub page_id: PageId,
+    pub mode: LatchMode,
+    pub backtrace: String,
+}
+
+/// One task in a deadlock cycle: what it waits for and what it holds.
+#[derive(Debug, Clone)]
+pub struct LatchWait {
+    pub owner: LatchOwner,
+    pub waiting_for: PageId,
+    pub mode: LatchMode,
+    pub backtrace: String,
+    pub holding: Vec<HeldLatch>,
+}
+
+/// A cycle in the wait-for graph. Each task waits for a page held by the next one,
+/// and the last waits for a page held by the first.
+#[derive(Debug, Clone)]
+pub struct DeadlockReport {
+    pub cycle: Vec<LatchWait>,
+}
+
+impl DeadlockReport {
+    /// Page ids waited on around the cycle, in order.
+    pub fn page_ids(&self) -> Vec<PageId> {
+        self.cycle.iter().map(|wait| wait.waiting_for).collect()
+    }
+}
+
+impl fmt::Display for DeadlockReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "page latch deadlock between {} task(s):", self.cycle.len())?;
+        for wait in &self.cycle {
+            let held: Vec<String> = wait.holding.iter().map(|h| h.page_id.to_string()).collect();
+            writeln!(
+                f,
+                "  {} holds pages [{}] and waits for page {} ({:?})",
+                wait.owner,
+                held.join(", "),
+                wait.waiting_for,
+                wait.mode
+            )?;
+            writeln!(f, "    waiting at:\n{}", wait.backtrace)?;
+            for h in &wait.holding {
+                writeln!(f, "    acquired page {} at:\n{}", h.page_id, h.backtrace)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Hold {
+    token: u64,
+    owner: LatchOwner,
+    mode: LatchMode,
+    backtrace: Arc<Backtrace>,
+}
+
+struct Wait {
+    page_id: PageId,
+    mode: LatchMode,
+    backtrace: Arc<Backtrace>,
+}
+
+#[derive(Default)]
+struct TrackerState {
+    holders: HashMap<PageId, Vec<Hold>>,
+    waiters: HashMap<LatchOwner, Wait>,
+    reports: Vec<DeadlockReport>,
+}
+
+#[derive(Default)]
+pub struct LatchTracker {
+    enabled: AtomicBool,
+    next_token: AtomicU64,
+    state: Mutex<TrackerState>,
+}
+
+impl LatchTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Turn tracking on or off. Latches taken while disabled are not tracked.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::SeqCst);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::SeqCst)
+    }
+
+    /// Deadlocks detected so far.
+    pub fn reports(&self) -> Vec<DeadlockReport> {
+        self.lock_state().reports.clone()
+    }
+
+    /// Record that the current task is about to wait for `page_id`, and check whether
+    /// that wait closes a cycle.
+    pub(crate) fn begin_wait(&self, page_id: PageId, mode: LatchMode) {
+        if !self.is_enabled() {
+            return;
+        }
+        let owner = LatchOwner::current();
+        let mut state = self.lock_state();
+        state.waiters.insert(owner, Wait {
+            page_id,
+            mode,
+            backtrace: Arc::new(Backtrace::force_capture()),
+        });
+
+        if let Some(cycle) = Self::find_cycle(&state, owner) {
+            let report = Self::build_report(&state, &cycle);
+            log::error!("{}", report);
+            state.reports.push(report);
+        }
+    }
+
+    /// Record that the current task now holds `page_id`. Returns a token to pass to
+    /// released(), or None when tracking is disabled.
+    pub(crate) fn acquired(&self, page_id: PageId, mode: LatchMode) -> Option<u64> {
+        if !self.is_enabled() {
+            return None;
+        }
+        let owner = LatchOwner::current();
+        let token = self.next_token.fetch_add(1, Ordering::SeqCst);
+        let mut state = self.lock_state();
+        state.waiters.remove(&owner);
+        state.holders.entry(page_id).or_default().push(Hold {
+            token,
+            owner,
+            mode,
+            backtrace: Arc::new(Backtrace::force_capture()),
+        });
+        Some(token)
+    }
+
+    /// Record that the current task stopped waiting without getting the latch.
+    pub(crate) fn cancel_wait(&self) {
+        if !self.is_enabled() {
+            return;
+        }
+        self.lock_state().waiters.remove(&LatchOwner::current());
+    }
+
+    pub(crate) fn released(&self, page_id: PageId, token: u64) {
+        let mut state = self.lock_state();
+        if let Some(holds) = state.holders.get_mut(&page_id) {
+            holds.retain(|hold| hold.token != token);
+            if holds.is_empty() {
+                state.holders.remove(&page_id);
+            }
+        }
+    }
+
+    fn lock_state(&self) -> std::sync::MutexGuard<'_, TrackerState> {
+        // A panic while tracking must not take the debug tool down with it
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Depth-first search of the wait-for graph starting at `start`'s pending wait.
+    /// Returns the owners around the cycle, starting with `start`.
+    fn find_cycle(state: &TrackerState, start: LatchOwner) -> Option<Vec<LatchOwner>> {
+        let mut path = vec![start];
+        let mut visited = HashSet::new();
+        visited.insert(start);
+        if Self::visit(state, start, start, &mut path, &mut visited) {
+            Some(path)
+        } else {
+            None
+        }
+    }
+
+    fn visit(
+        state: &TrackerState,
+        start: LatchOwner,
+        current: LatchOwner,
+        path: &mut Vec<LatchOwner>,
+        visited: &mut HashSet<LatchOwner>,
+    ) -> bool {
+        let Some(wait) = state.waiters.get(&current) else {
+            return false;
+        };
+        let Some(holds) = state.holders.get(&wait.page_id) else {
+            return false;
+        };
+
+        for hold in holds.iter().filter(|hold| hold.mode.conflicts_with(wait.mode)) {
+            if hold.owner == start {
+                return true;
+            }
+            if !visited.insert(hold.owner) {
+                continue;
+            }
+            path.push(hold.owner);
+            if Self::visit(state, start, hold.owner, path, visited) {
+                return true;
+            }
+            path.pop();
+        }
+        false
+    }
+
+    fn build_report(state: &TrackerState, cycle: &[LatchOwner]) -> DeadlockReport {
+        let cycle = cycle
+            .iter()
+            .filter_map(|owner| {
+                let wait = state.waiters.get(owner)?;
+                let holding = state
+                    .holders
+                    .iter()
+                    .flat_map(|(page_id, holds)| {
+                        holds.iter().filter(|hold| hold.owner == *owner).map(|hold| HeldLatch {
+                            page_id: *page_id,
+                            mode: hold.mode,
+                            backtrace: hold.backtrace.to_string(),
+                        })
+                    })
+                    .collect();
+                Some(LatchWait {
+                    owner: *owner,
+                    waiting_for: wait.page_id,
+                    mode: wait.mode,
+                    backtrace: wait.backtrace.to_string(),
+                    holding,
+                })
+            })
+            .collect();
+        DeadlockReport { cycle }
+    }
+}
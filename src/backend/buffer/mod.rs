@@ -1,4 +1,13 @@
 pub mod arc_replacer;
+pub mod clock_replacer;
+pub mod lru_k_replacer;
+pub mod replacer;
+pub mod replacer_sim;
 pub mod page_guard;
 pub mod buffer_pool_manager;
-pub mod page;
\ No newline at end of file
+pub mod latch_tracker;
+pub mod observer;
+pub mod pin_tracker;
+pub mod page;
+pub mod hot_pages;
+pub mod trace_recorder;
@@ -0,0 +1,25 @@
+// src/buffer/observer.rs
+
+//! Buffer pool observers
+//!
+//! Hooks for telemetry or warmth tracking layered on top of the buffer pool, without
+//! forking its internals. Observers registered with BufferPoolManager::add_observer are
+//! called synchronously, in registration order, while the pool holds its page table
+//! lock: callbacks must be quick and must not call back into the pool.
+
+use crate::backend::buffer::arc_replacer::ArcStatus;
+use crate::common::types::{FrameId, PageId};
+
+/// Callbacks for buffer pool events. Every method defaults to doing nothing.
+pub trait BufferPoolObserver: Send + Sync {
+    /// `page_id` was evicted from `frame_id` to make room for another page.
+    /// `dirty` pages have already been written back (and reported through on_flush).
+    fn on_evict(&self, _page_id: PageId, _frame_id: FrameId, _dirty: bool) {}
+
+    /// `page_id` was written back to disk, by flush_page or before eviction.
+    fn on_flush(&self, _page_id: PageId) {}
+
+    /// `page_id` was loaded again while the replacer still remembered it in the ghost
+    /// list of `list`, i.e. it was evicted too early.
+    fn on_ghost_hit(&self, _page_id: PageId, _list: ArcStatus) {}
+}
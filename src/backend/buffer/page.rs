@@ -1 +1,81 @@
+// src/buffer/page.rs
 
+//! FrameHeader
+//!
+//! A frame is one page-sized slot of buffer pool memory. The header tracks which page
+//! currently lives in the frame and whether it has been modified since it was read.
+//! The page bytes sit behind a RwLock, which doubles as the page latch. Frames are
+//! allocated in batches whose pages share one contiguous region, and a frame keeps its
+//! buffer for life: loading a new page into it overwrites the bytes in place.
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, AtomicI32, Ordering},
+};
+
+use bytes::BytesMut;
+use tokio::sync::RwLock;
+
+use crate::backend::storage::disk_manager::GRIMOIRE_PAGE_SIZE;
+use crate::common::types::{FrameId, PageId};
+
+pub const INVALID_PAGE_ID: PageId = -1;
+
+/// A frame's slice of its batch's page memory.
+pub(crate) type PageData = BytesMut;
+
+pub struct FrameHeader {
+    frame_id: FrameId,
+    page_id: AtomicI32,
+    is_dirty: AtomicBool,
+    data: Arc<RwLock<PageData>>,
+}
+
+impl FrameHeader {
+    pub fn new(frame_id: FrameId) -> Self {
+        Self::with_data(frame_id, BytesMut::zeroed(GRIMOIRE_PAGE_SIZE))
+    }
+
+    /// `count` frames numbered from `first_id`, with their pages carved out of a single
+    /// allocation.
+    pub fn allocate(first_id: FrameId, count: usize) -> Vec<Arc<FrameHeader>> {
+        let mut region = BytesMut::zeroed(count * GRIMOIRE_PAGE_SIZE);
+        (first_id..first_id + count)
+            .map(|frame_id| Arc::new(Self::with_data(frame_id, region.split_to(GRIMOIRE_PAGE_SIZE))))
+            .collect()
+    }
+
+    fn with_data(frame_id: FrameId, data: PageData) -> Self {
+        Self {
+            frame_id,
+            page_id: AtomicI32::new(INVALID_PAGE_ID),
+            is_dirty: AtomicBool::new(false),
+            data: Arc::new(RwLock::new(data)),
+        }
+    }
+
+    pub fn frame_id(&self) -> FrameId {
+        self.frame_id
+    }
+
+    pub fn page_id(&self) -> PageId {
+        self.page_id.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn set_page_id(&self, page_id: PageId) {
+        self.page_id.store(page_id, Ordering::SeqCst);
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.is_dirty.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn set_dirty(&self, dirty: bool) {
+        self.is_dirty.store(dirty, Ordering::SeqCst);
+    }
+
+    /// The page latch guarding the frame's bytes.
+    pub(crate) fn latch(&self) -> Arc<RwLock<PageData>> {
+        Arc::clone(&self.data)
+    }
+}
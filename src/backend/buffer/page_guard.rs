@@ -1 +1,125 @@
+// src/buffer/page_guard.rs
 
+//! Page guards
+//!
+//! RAII handles returned by the BufferPoolManager. A guard keeps its frame pinned and
+//! holds the page latch (shared for ReadPageGuard, exclusive for WritePageGuard) for as
+//! long as it lives. Dropping a guard releases the latch first and then unpins the frame,
+//! so an evictable frame is never latched.
+
+use std::sync::Arc;
+
+use tokio::sync::{OwnedRwLockReadGuard, OwnedRwLockWriteGuard};
+
+use crate::backend::buffer::buffer_pool_manager::PoolShared;
+use crate::backend::buffer::page::{FrameHeader, PageData};
+use crate::common::types::PageId;
+
+/// Shared (read) access to a page.
+pub struct ReadPageGuard {
+    page_id: PageId,
+    frame: Arc<FrameHeader>,
+    latch: Option<OwnedRwLockReadGuard<PageData>>,
+    pool: Arc<PoolShared>,
+    latch_token: Option<u64>,
+    pin_token: Option<u64>,
+}
+
+/// Exclusive (write) access to a page. Mutable access marks the page dirty.
+pub struct WritePageGuard {
+    page_id: PageId,
+    frame: Arc<FrameHeader>,
+    latch: Option<OwnedRwLockWriteGuard<PageData>>,
+    pool: Arc<PoolShared>,
+    latch_token: Option<u64>,
+    pin_token: Option<u64>,
+}
+
+impl ReadPageGuard {
+    pub(crate) fn new(
+        page_id: PageId,
+        frame: Arc<FrameHeader>,
+        latch: OwnedRwLockReadGuard<PageData>,
+        pool: Arc<PoolShared>,
+        latch_token: Option<u64>,
+        pin_token: Option<u64>,
+    ) -> Self {
+        Self {
+            page_id,
+            frame,
+            latch: Some(latch),
+            pool,
+            latch_token,
+            pin_token,
+        }
+    }
+
+    pub fn page_id(&self) -> PageId {
+        self.page_id
+    }
+
+    pub fn data(&self) -> &[u8] {
+        self.latch.as_ref().expect("guard already released")
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.frame.is_dirty()
+    }
+
+    pub(crate) fn frame(&self) -> &FrameHeader {
+        &self.frame
+    }
+}
+
+impl WritePageGuard {
+    pub(crate) fn new(
+        page_id: PageId,
+        frame: Arc<FrameHeader>,
+        latch: OwnedRwLockWriteGuard<PageData>,
+        pool: Arc<PoolShared>,
+        latch_token: Option<u64>,
+        pin_token: Option<u64>,
+    ) -> Self {
+        Self {
+            page_id,
+            frame,
+            latch: Some(latch),
+            pool,
+            latch_token,
+            pin_token,
+        }
+    }
+
+    pub fn page_id(&self) -> PageId {
+        self.page_id
+    }
+
+    pub fn data(&self) -> &[u8] {
+        self.latch.as_ref().expect("guard already released")
+    }
+
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        self.frame.set_dirty(true);
+        self.latch.as_mut().expect("guard already released")
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.frame.is_dirty()
+    }
+}
+
+impl Drop for ReadPageGuard {
+    fn drop(&mut self) {
+        self.latch.take();
+        self.pool
+            .release(self.page_id, self.frame.frame_id(), self.latch_token, self.pin_token);
+    }
+}
+
+impl Drop for WritePageGuard {
+    fn drop(&mut self) {
+        self.latch.take();
+        self.pool
+            .release(self.page_id, self.frame.frame_id(), self.latch_token, self.pin_token);
+    }
+}
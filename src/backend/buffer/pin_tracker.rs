@@ -0,0 +1,152 @@
+// src/buffer/pin_tracker.rs
+
+//! PinTracker
+//!
+//! Debug mode for page pins. When enabled, every page guard records where its pin was
+//! taken (a backtrace) and when. A guard that is leaked, or kept far longer than it
+//! should be, keeps its frame pinned; enough of them exhaust the pool and every later
+//! miss fails with NoFreeFrame. long_pins() lists pins older than a threshold, and
+//! spawn_pin_leak_reporter logs each one once, well before the pool runs dry.
+//!
+//! Disabled by default: the only cost then is an atomic load per guard.
+
+use std::{
+    backtrace::Backtrace,
+    collections::HashMap,
+    fmt,
+    sync::{
+        Arc, Mutex, Weak,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use crate::backend::buffer::buffer_pool_manager::BufferPoolManager;
+use crate::common::types::{FrameId, PageId};
+
+/// A pin held for longer than the threshold it was checked against.
+#[derive(Debug, Clone)]
+pub struct PinLeak {
+    pub page_id: PageId,
+    pub frame_id: FrameId,
+    pub pinned_for: Duration,
+    pub backtrace: String,
+}
+
+impl fmt::Display for PinLeak {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "page {} (frame {}) pinned for {:?}, pinned at:\n{}",
+            self.page_id, self.frame_id, self.pinned_for, self.backtrace
+        )
+    }
+}
+
+struct PinRecord {
+    page_id: PageId,
+    frame_id: FrameId,
+    since: Instant,
+    backtrace: Arc<Backtrace>,
+    reported: bool,
+}
+
+#[derive(Default)]
+pub struct PinTracker {
+    enabled: AtomicBool,
+    next_token: AtomicU64,
+    pins: Mutex<HashMap<u64, PinRecord>>,
+}
+
+impl PinTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Turn tracking on or off. Pins taken while disabled are not tracked.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::SeqCst);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::SeqCst)
+    }
+
+    /// Tracked pins held for at least `threshold`, oldest first.
+    pub fn long_pins(&self, threshold: Duration) -> Vec<PinLeak> {
+        self.collect(threshold, false)
+    }
+
+    /// Like long_pins(), but each pin is returned only the first time it crosses the threshold.
+    pub fn report_leaks(&self, threshold: Duration) -> Vec<PinLeak> {
+        self.collect(threshold, true)
+    }
+
+    /// Record a pin of `frame_id` by a page guard. Returns a token to pass to unpinned(),
+    /// or None when tracking is disabled.
+    pub(crate) fn pinned(&self, page_id: PageId, frame_id: FrameId) -> Option<u64> {
+        if !self.is_enabled() {
+            return None;
+        }
+        let token = self.next_token.fetch_add(1, Ordering::SeqCst);
+        self.lock_pins().insert(token, PinRecord {
+            page_id,
+            frame_id,
+            since: Instant::now(),
+            backtrace: Arc::new(Backtrace::force_capture()),
+            reported: false,
+        });
+        Some(token)
+    }
+
+    pub(crate) fn unpinned(&self, token: u64) {
+        self.lock_pins().remove(&token);
+    }
+
+    fn collect(&self, threshold: Duration, only_new: bool) -> Vec<PinLeak> {
+        let now = Instant::now();
+        let mut pins = self.lock_pins();
+        let mut leaks: Vec<PinLeak> = pins
+            .values_mut()
+            .filter(|pin| now - pin.since >= threshold && !(only_new && pin.reported))
+            .map(|pin| {
+                pin.reported |= only_new;
+                PinLeak {
+                    page_id: pin.page_id,
+                    frame_id: pin.frame_id,
+                    pinned_for: now - pin.since,
+                    backtrace: pin.backtrace.to_string(),
+                }
+            })
+            .collect();
+        leaks.sort_by_key(|leak| std::cmp::Reverse(leak.pinned_for));
+        leaks
+    }
+
+    fn lock_pins(&self) -> std::sync::MutexGuard<'_, HashMap<u64, PinRecord>> {
+        // A panic while tracking must not take the debug tool down with it
+        self.pins.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Every `interval`, log pins of `pool` held longer than `threshold` (each one once),
+/// until the pool is dropped. Only sees pins taken while the tracker is enabled.
+pub fn spawn_pin_leak_reporter(
+    pool: &Arc<BufferPoolManager>,
+    threshold: Duration,
+    interval: Duration,
+) -> tokio::task::JoinHandle<()> {
+    let pool: Weak<BufferPoolManager> = Arc::downgrade(pool);
+    tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(interval);
+        loop {
+            ticks.tick().await;
+            let Some(pool) = pool.upgrade() else {
+                break;
+            };
+            for leak in pool.pin_tracker().report_leaks(threshold) {
+                log::warn!("possible leaked page guard: {}", leak);
+            }
+        }
+    })
+}
@@ -0,0 +1,52 @@
+// src/buffer/replacer.rs
+
+//! Replacer trait
+//!
+//! The interface the buffer pool uses to choose eviction victims, so that policies can
+//! be compared (see replacer_sim) and swapped. Frames enter a replacer through
+//! record_access() as non-evictable, because the pool pins them right away, and become
+//! candidates once set_evictable(frame, true) is called.
+
+use anyhow::Result;
+
+use crate::backend::buffer::arc_replacer::{AccessOutcome, AccessType, ArcReplacer};
+use crate::common::types::{FrameId, PageId};
+
+pub trait Replacer: Send {
+    /// Record that `frame_id`, holding `page_id`, was accessed.
+    fn record_access(&mut self, frame_id: FrameId, page_id: PageId, access_type: AccessType) -> AccessOutcome;
+
+    /// Toggle whether a tracked frame may be evicted. Errors if the frame is not tracked.
+    fn set_evictable(&mut self, frame_id: FrameId, evictable: bool) -> Result<()>;
+
+    /// Choose and stop tracking a victim among the evictable frames.
+    fn evict(&mut self) -> Option<FrameId>;
+
+    /// Stop tracking an evictable frame. Errors if it is pinned or not tracked.
+    fn remove(&mut self, frame_id: FrameId) -> Result<()>;
+
+    /// Number of evictable frames.
+    fn size(&self) -> usize;
+}
+
+impl Replacer for ArcReplacer {
+    fn record_access(&mut self, frame_id: FrameId, page_id: PageId, access_type: AccessType) -> AccessOutcome {
+        ArcReplacer::record_access(self, frame_id, page_id, access_type)
+    }
+
+    fn set_evictable(&mut self, frame_id: FrameId, evictable: bool) -> Result<()> {
+        ArcReplacer::set_evictable(self, frame_id, evictable)
+    }
+
+    fn evict(&mut self) -> Option<FrameId> {
+        ArcReplacer::evict(self)
+    }
+
+    fn remove(&mut self, frame_id: FrameId) -> Result<()> {
+        ArcReplacer::remove(self, frame_id)
+    }
+
+    fn size(&self) -> usize {
+        ArcReplacer::size(self)
+    }
+}
@@ -0,0 +1,258 @@
+// src/buffer/replacer_sim.rs
+
+//! Replacer simulation
+//!
+//! Replays a recorded page-access trace through a replacement policy offline and counts
+//! hits and misses, to pick a policy and a pool size for a workload without touching a
+//! running engine. The simulated pool pins and unpins each page around its access, the
+//! way a short-lived page guard would, and drives the policy through the same Replacer
+//! interface the buffer pool uses.
+//!
+//! Traces are either binary files written by the buffer pool's TraceRecorder, or text
+//! with one access per line: a page id, optionally followed by an access type (scan,
+//! lookup, index). Blank lines and lines starting with `#` are skipped.
+
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use crate::backend::buffer::arc_replacer::{AccessType, ArcReplacer};
+use crate::backend::buffer::clock_replacer::ClockReplacer;
+use crate::backend::buffer::lru_k_replacer::LruKReplacer;
+use crate::backend::buffer::replacer::Replacer;
+use crate::backend::buffer::trace_recorder::{TRACE_MAGIC, decode_trace};
+use crate::common::types::{FrameId, PageId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceAccess {
+    pub page_id: PageId,
+    pub access_type: AccessType,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Policy {
+    Arc,
+    LruK(usize),
+    Clock,
+}
+
+impl Policy {
+    pub fn build(self, num_frames: usize) -> Box<dyn Replacer> {
+        match self {
+            Policy::Arc => Box::new(ArcReplacer::new(num_frames)),
+            Policy::LruK(k) => Box::new(LruKReplacer::new(k)),
+            Policy::Clock => Box::new(ClockReplacer::new(num_frames)),
+        }
+    }
+}
+
+impl fmt::Display for Policy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Policy::Arc => write!(f, "arc"),
+            Policy::LruK(k) => write!(f, "lru-{}", k),
+            Policy::Clock => write!(f, "clock"),
+        }
+    }
+}
+
+impl FromStr for Policy {
+    type Err = String;
+
+    /// `arc`, `clock`, or `lru-<k>` (plain `lru` means LRU-2).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "arc" => Ok(Policy::Arc),
+            "clock" => Ok(Policy::Clock),
+            "lru" => Ok(Policy::LruK(2)),
+            _ => s
+                .strip_prefix("lru-")
+                .and_then(|k| k.parse().ok())
+                .filter(|&k| k > 0)
+                .map(Policy::LruK)
+                .ok_or_else(|| format!("unknown policy {:?}, expected arc, clock or lru-<k>", s)),
+        }
+    }
+}
+
+/// Outcome of replaying one trace through one policy at one pool size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SimResult {
+    pub policy: Policy,
+    pub num_frames: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl SimResult {
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 { 0.0 } else { self.hits as f64 / total as f64 }
+    }
+}
+
+impl fmt::Display for SimResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:<8} {:>8} frames  {:>10} hits  {:>10} misses  {:>6.2}%",
+            self.policy.to_string(),
+            self.num_frames,
+            self.hits,
+            self.misses,
+            100.0 * self.hit_rate()
+        )
+    }
+}
+
+/// Replay `trace` through `policy` with a pool of `num_frames` frames.
+pub fn simulate(policy: Policy, num_frames: usize, trace: &[TraceAccess]) -> SimResult {
+    let mut replacer = policy.build(num_frames);
+    let mut page_table: HashMap<PageId, FrameId> = HashMap::new();
+    let mut frame_pages: Vec<Option<PageId>> = vec![None; num_frames];
+    let mut free_frames: Vec<FrameId> = (0..num_frames).rev().collect();
+    let mut result = SimResult {
+        policy,
+        num_frames,
+        hits: 0,
+        misses: 0,
+    };
+
+    for access in trace {
+        let frame_id = match page_table.get(&access.page_id) {
+            Some(&frame_id) => {
+                result.hits += 1;
+                frame_id
+            }
+            None => {
+                result.misses += 1;
+                let Some(frame_id) = free_frames.pop().or_else(|| replacer.evict()) else {
+                    continue; // zero frames: every access misses
+                };
+                if let Some(old_page) = frame_pages[frame_id].replace(access.page_id) {
+                    page_table.remove(&old_page);
+                }
+                page_table.insert(access.page_id, frame_id);
+                frame_id
+            }
+        };
+        replacer.record_access(frame_id, access.page_id, access.access_type);
+        let _ = replacer.set_evictable(frame_id, true);
+    }
+    result
+}
+
+/// Replay `trace` for every combination of `policies` and `sizes`.
+pub fn compare(policies: &[Policy], sizes: &[usize], trace: &[TraceAccess]) -> Vec<SimResult> {
+    sizes
+        .iter()
+        .flat_map(|&size| policies.iter().map(move |&policy| simulate(policy, size, trace)))
+        .collect()
+}
+
+/// Parse a recorded binary trace or a text trace, whichever `bytes` holds.
+pub fn parse_trace(bytes: &[u8]) -> Result<Vec<TraceAccess>, String> {
+    if bytes.starts_with(&TRACE_MAGIC.to_le_bytes()) {
+        let records = decode_trace(bytes)?;
+        return Ok(records
+            .into_iter()
+            .map(|record| TraceAccess {
+                page_id: record.page_id,
+                access_type: record.access_type,
+            })
+            .collect());
+    }
+    let text = std::str::from_utf8(bytes).map_err(|_| "trace is neither a recorded trace nor text".to_string())?;
+    parse_text_trace(text)
+}
+
+/// Parse a text trace (see the module docs). Errors name the offending line.
+pub fn parse_text_trace(text: &str) -> Result<Vec<TraceAccess>, String> {
+    let mut trace = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.split_whitespace();
+        let page_id = fields
+            .next()
+            .and_then(|id| id.parse().ok())
+            .ok_or_else(|| format!("line {}: expected a page id, got {:?}", number + 1, line))?;
+        let access_type = match fields.next() {
+            None => AccessType::Unknown,
+            Some("scan") => AccessType::Scan,
+            Some("lookup") => AccessType::Lookup,
+            Some("index") => AccessType::Index,
+            Some(other) => return Err(format!("line {}: unknown access type {:?}", number + 1, other)),
+        };
+        trace.push(TraceAccess { page_id, access_type });
+    }
+    Ok(trace)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trace(pages: &[PageId]) -> Vec<TraceAccess> {
+        pages
+            .iter()
+            .map(|&page_id| TraceAccess {
+                page_id,
+                access_type: AccessType::Unknown,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_simulate_counts_hits() {
+        let pages = trace(&[1, 2, 1, 2, 3, 1]);
+        for policy in [Policy::Arc, Policy::LruK(2), Policy::Clock] {
+            let result = simulate(policy, 2, &pages);
+            assert_eq!(result.hits + result.misses, 6, "{}", policy);
+            assert!(result.hits >= 2, "{}: {:?}", policy, result);
+        }
+        // Everything fits: only the cold misses remain
+        let fits = simulate(Policy::Clock, 3, &pages);
+        assert_eq!((fits.hits, fits.misses), (3, 3));
+    }
+
+    #[test]
+    fn test_scan_resistance() {
+        // A hot set of 4 pages interleaved with a long one-off scan
+        let mut pages = vec![];
+        for i in 0..400 {
+            pages.push(i % 4);
+            pages.push(1000 + i);
+        }
+        let results = compare(&[Policy::Arc, Policy::LruK(2)], &[8], &trace(&pages));
+        for result in results {
+            assert!(result.hit_rate() > 0.45, "{}", result);
+        }
+    }
+
+    #[test]
+    fn test_parse_text_trace_and_policy() {
+        let parsed = parse_text_trace("# header\n5\n\n7 scan\n").unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[1].access_type, AccessType::Scan);
+        assert!(parse_text_trace("x").unwrap_err().contains("line 1"));
+
+        let mut recorded = [TRACE_MAGIC.to_le_bytes(), 1u32.to_le_bytes()].concat();
+        crate::backend::buffer::trace_recorder::TraceRecord {
+            timestamp_us: 1,
+            page_id: 9,
+            access_type: AccessType::Index,
+            hit: true,
+        }
+        .encode(&mut recorded);
+        assert_eq!(parse_trace(&recorded).unwrap(), vec![TraceAccess {
+            page_id: 9,
+            access_type: AccessType::Index,
+        }]);
+        assert_eq!(parse_trace(b"3\n4\n").unwrap().len(), 2);
+
+        assert_eq!("lru-3".parse::<Policy>(), Ok(Policy::LruK(3)));
+        assert_eq!("lru".parse::<Policy>(), Ok(Policy::LruK(2)));
+        assert!("lru-0".parse::<Policy>().is_err());
+    }
+}
@@ -0,0 +1,165 @@
+// src/buffer/trace_recorder.rs
+
+//! TraceRecorder
+//!
+//! Records every page access the buffer pool serves, so a production workload can be
+//! replayed offline through replacer_sim. Off by default; start() and stop() toggle it at
+//! runtime. Records are buffered in memory and appended to the trace backend in batches,
+//! so the cost on the access path is an atomic load plus, while recording, a short
+//! mutex-protected push.
+//!
+//! File layout (little endian): an 8-byte header `| magic: u32 | version: u32 |`, then
+//! one 13-byte record per access:
+//! | timestamp_us: u64 | page_id: i32 | flags: u8 |
+//! where timestamp_us is microseconds since the unix epoch, flags bits 0-1 hold the
+//! access type (0 unknown, 1 scan, 2 lookup, 3 index) and bit 2 is set on a hit.
+
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::backend::buffer::arc_replacer::AccessType;
+use crate::backend::storage::storage_backend::StorageBackend;
+use crate::common::{errors::DiskError, types::PageId};
+
+pub const TRACE_MAGIC: u32 = 0x4352_5447; // "GTRC"
+pub const TRACE_VERSION: u32 = 1;
+pub const TRACE_HEADER_SIZE: usize = 8;
+pub const TRACE_RECORD_SIZE: usize = 13;
+
+// Buffered bytes that trigger an append to the backend
+const FLUSH_THRESHOLD: usize = 64 * 1024;
+
+/// One decoded trace record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceRecord {
+    pub timestamp_us: u64,
+    pub page_id: PageId,
+    pub access_type: AccessType,
+    pub hit: bool,
+}
+
+impl TraceRecord {
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        let access_bits = match self.access_type {
+            AccessType::Unknown => 0,
+            AccessType::Scan => 1,
+            AccessType::Lookup => 2,
+            AccessType::Index => 3,
+        };
+        out.extend_from_slice(&self.timestamp_us.to_le_bytes());
+        out.extend_from_slice(&self.page_id.to_le_bytes());
+        out.push(access_bits | ((self.hit as u8) << 2));
+    }
+
+    pub fn decode(bytes: &[u8; TRACE_RECORD_SIZE]) -> Self {
+        let access_type = match bytes[12] & 0b11 {
+            1 => AccessType::Scan,
+            2 => AccessType::Lookup,
+            3 => AccessType::Index,
+            _ => AccessType::Unknown,
+        };
+        TraceRecord {
+            timestamp_us: u64::from_le_bytes(bytes[..8].try_into().unwrap()),
+            page_id: PageId::from_le_bytes(bytes[8..12].try_into().unwrap()),
+            access_type,
+            hit: bytes[12] & 0b100 != 0,
+        }
+    }
+}
+
+/// Decode a whole trace file. Errors if the header is wrong; a torn final record is dropped.
+pub fn decode_trace(bytes: &[u8]) -> Result<Vec<TraceRecord>, String> {
+    let (header, records) = bytes
+        .split_at_checked(TRACE_HEADER_SIZE)
+        .ok_or("trace is shorter than its header")?;
+    if u32::from_le_bytes(header[..4].try_into().unwrap()) != TRACE_MAGIC {
+        return Err("not a grimoire access trace".to_string());
+    }
+    let version = u32::from_le_bytes(header[4..].try_into().unwrap());
+    if version != TRACE_VERSION {
+        return Err(format!("unsupported trace version {}", version));
+    }
+    Ok(records
+        .chunks_exact(TRACE_RECORD_SIZE)
+        .map(|record| TraceRecord::decode(record.try_into().unwrap()))
+        .collect())
+}
+
+#[derive(Default)]
+pub struct TraceRecorder {
+    enabled: AtomicBool,
+    buffer: Mutex<Vec<u8>>,
+    // Also serializes flushes, so batches reach the backend in order
+    backend: tokio::sync::Mutex<Option<Arc<dyn StorageBackend>>>,
+}
+
+impl TraceRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.enabled.load(Ordering::SeqCst)
+    }
+
+    /// Start appending accesses to `backend`, writing the header if it is empty.
+    /// Replaces (and flushes) any trace already being recorded.
+    pub async fn start(&self, backend: Arc<dyn StorageBackend>) -> Result<(), DiskError> {
+        self.stop().await?;
+        if backend.size().await? == 0 {
+            let mut header = Vec::with_capacity(TRACE_HEADER_SIZE);
+            header.extend_from_slice(&TRACE_MAGIC.to_le_bytes());
+            header.extend_from_slice(&TRACE_VERSION.to_le_bytes());
+            backend.append(&header).await?;
+        }
+        *self.backend.lock().await = Some(backend);
+        self.enabled.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Stop recording and write out everything buffered so far.
+    pub async fn stop(&self) -> Result<(), DiskError> {
+        self.enabled.store(false, Ordering::SeqCst);
+        self.flush().await?;
+        *self.backend.lock().await = None;
+        Ok(())
+    }
+
+    /// Append buffered records to the backend.
+    pub async fn flush(&self) -> Result<(), DiskError> {
+        let backend = self.backend.lock().await;
+        let pending = std::mem::take(&mut *self.lock_buffer());
+        match backend.as_ref() {
+            Some(backend) if !pending.is_empty() => backend.append(&pending).await,
+            _ => Ok(()),
+        }
+    }
+
+    /// Buffer one access. Returns true once enough is buffered that the caller should flush().
+    pub(crate) fn record(&self, page_id: PageId, access_type: AccessType, hit: bool) -> bool {
+        if !self.is_recording() {
+            return false;
+        }
+        let timestamp_us = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_micros() as u64);
+        let mut buffer = self.lock_buffer();
+        TraceRecord {
+            timestamp_us,
+            page_id,
+            access_type,
+            hit,
+        }
+        .encode(&mut buffer);
+        buffer.len() >= FLUSH_THRESHOLD
+    }
+
+    fn lock_buffer(&self) -> std::sync::MutexGuard<'_, Vec<u8>> {
+        self.buffer.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
@@ -0,0 +1,445 @@
+// src/index/hash_index.rs
+
+//! Extendible hash index over the buffer pool, for equality-only lookups.
+//!
+//! A directory page maps the low `global_depth` bits of a key's hash to bucket pages.
+//! Several directory slots may share a bucket; its `local_depth` says how many hash bits
+//! its keys actually agree on. When a bucket overflows it splits in two on the next bit,
+//! and when that bit is past the global depth the directory doubles first. Buckets are
+//! not merged when they empty out.
+//!
+//! Keys are fixed-size byte strings (e.g. OrderedKey bytes of an integer or a
+//! BinaryKey) and map to one u64 value each, such as a record location. The hash must
+//! stay the same across processes, so it is FNV-1a rather than std's RandomState.
+//!
+//! Directory page (little endian):
+//! | magic: u32 | global_depth: u32 | key_len: u32 | 0: u32 |
+//! | bucket page_id: i32 x 2^MAX_GLOBAL_DEPTH | local_depth: u8 x 2^MAX_GLOBAL_DEPTH |
+//!
+//! Bucket page:
+//! | magic: u32 | count: u32 | local_depth: u32 | (key, value: u64) x count |
+//!
+//! Inserts and removes hold the directory's write latch for their whole duration,
+//! lookups its read latch; bucket latches are always taken after the directory's.
+
+use std::sync::Arc;
+
+use crate::backend::buffer::buffer_pool_manager::BufferPoolManager;
+use crate::backend::storage::disk_manager::GRIMOIRE_PAGE_SIZE;
+use crate::common::{errors::DiskError, types::PageId};
+
+const DIRECTORY_MAGIC: u32 = 0x4844_4952; // "HDIR"
+const BUCKET_MAGIC: u32 = 0x4842_4b54; // "HBKT"
+
+/// Deepest the directory can get: 2^9 slots fill most of a 4 KiB directory page.
+pub const MAX_GLOBAL_DEPTH: u32 = 9;
+const DIRECTORY_SLOTS: usize = 1 << MAX_GLOBAL_DEPTH;
+const DIRECTORY_HEADER_SIZE: usize = 16;
+const LOCAL_DEPTHS_OFFSET: usize = DIRECTORY_HEADER_SIZE + DIRECTORY_SLOTS * 4;
+const BUCKET_HEADER_SIZE: usize = 12;
+
+const _: () = assert!(LOCAL_DEPTHS_OFFSET + DIRECTORY_SLOTS <= GRIMOIRE_PAGE_SIZE);
+
+pub struct HashIndex {
+    bpm: Arc<BufferPoolManager>,
+    directory_page_id: PageId,
+    key_len: usize,
+}
+
+impl HashIndex {
+    /// Create an empty index for keys of `key_len` bytes. Its directory page id is what
+    /// open() needs later.
+    pub async fn create(bpm: Arc<BufferPoolManager>, key_len: usize) -> Result<Self, DiskError> {
+        assert!(
+            key_len > 0 && BUCKET_HEADER_SIZE + 2 * (key_len + 8) <= GRIMOIRE_PAGE_SIZE,
+            "hash index keys must fit at least two to a bucket page"
+        );
+        let directory_page_id = bpm.new_page();
+        let bucket_page_id = bpm.new_page();
+
+        let mut bucket = bpm.write_page(bucket_page_id).await?;
+        Bucket::init(bucket.data_mut(), 0);
+        drop(bucket);
+
+        let mut directory = bpm.write_page(directory_page_id).await?;
+        let data = directory.data_mut();
+        put_u32(data, 0, DIRECTORY_MAGIC);
+        put_u32(data, 4, 0);
+        put_u32(data, 8, key_len as u32);
+        set_bucket_page(data, 0, bucket_page_id);
+        drop(directory);
+
+        Ok(Self {
+            bpm,
+            directory_page_id,
+            key_len,
+        })
+    }
+
+    /// Open the index whose directory is `directory_page_id`.
+    pub async fn open(bpm: Arc<BufferPoolManager>, directory_page_id: PageId) -> Result<Self, DiskError> {
+        let directory = bpm.read_page(directory_page_id).await?;
+        if get_u32(directory.data(), 0) != DIRECTORY_MAGIC {
+            return Err(corrupt_page(directory_page_id));
+        }
+        let key_len = get_u32(directory.data(), 8) as usize;
+        drop(directory);
+        Ok(Self {
+            bpm,
+            directory_page_id,
+            key_len,
+        })
+    }
+
+    pub fn directory_page_id(&self) -> PageId {
+        self.directory_page_id
+    }
+
+    pub fn key_len(&self) -> usize {
+        self.key_len
+    }
+
+    pub async fn get(&self, key: &[u8]) -> Result<Option<u64>, DiskError> {
+        self.check_key(key);
+        let hash = hash_key(key);
+        let directory = self.bpm.read_page(self.directory_page_id).await?;
+        let bucket_page_id = bucket_page(directory.data(), slot_of(directory.data(), hash));
+        let bucket = self.bpm.read_page(bucket_page_id).await?;
+        drop(directory);
+        Ok(Bucket::new(bucket.data(), self.key_len).find(key).map(|(_, value)| value))
+    }
+
+    /// Insert `key`, unless it is already present; returns whether it was inserted.
+    /// Fails once the bucket for `key` would need a directory deeper than MAX_GLOBAL_DEPTH.
+    pub async fn insert(&self, key: &[u8], value: u64) -> Result<bool, DiskError> {
+        self.check_key(key);
+        let hash = hash_key(key);
+        let mut directory = self.bpm.write_page(self.directory_page_id).await?;
+        loop {
+            let slot = slot_of(directory.data(), hash);
+            let bucket_page_id = bucket_page(directory.data(), slot);
+            let mut bucket = self.bpm.write_page(bucket_page_id).await?;
+            let view = Bucket::new(bucket.data(), self.key_len);
+            if view.find(key).is_some() {
+                return Ok(false);
+            }
+            if view.count() < view.capacity() {
+                BucketMut::new(bucket.data_mut(), self.key_len).push(key, value);
+                return Ok(true);
+            }
+            drop(bucket);
+            self.split(directory.data_mut(), slot).await?;
+        }
+    }
+
+    /// Remove `key`; returns its value if it was present.
+    pub async fn remove(&self, key: &[u8]) -> Result<Option<u64>, DiskError> {
+        self.check_key(key);
+        let hash = hash_key(key);
+        let directory = self.bpm.write_page(self.directory_page_id).await?;
+        let bucket_page_id = bucket_page(directory.data(), slot_of(directory.data(), hash));
+        let mut bucket = self.bpm.write_page(bucket_page_id).await?;
+        let Some((index, value)) = Bucket::new(bucket.data(), self.key_len).find(key) else {
+            return Ok(None);
+        };
+        BucketMut::new(bucket.data_mut(), self.key_len).swap_remove(index);
+        Ok(Some(value))
+    }
+
+    /// Directory depth, and the number of distinct bucket pages.
+    pub async fn depth_and_buckets(&self) -> Result<(u32, usize), DiskError> {
+        let directory = self.bpm.read_page(self.directory_page_id).await?;
+        let data = directory.data();
+        let global_depth = get_u32(data, 4);
+        let mut buckets: Vec<PageId> = (0..1 << global_depth).map(|slot| bucket_page(data, slot)).collect();
+        buckets.sort_unstable();
+        buckets.dedup();
+        Ok((global_depth, buckets.len()))
+    }
+
+    // Split the bucket behind directory `slot` on its next hash bit, doubling the
+    // directory first if that bit is not covered yet.
+    async fn split(&self, directory: &mut [u8], slot: usize) -> Result<(), DiskError> {
+        let global_depth = get_u32(directory, 4);
+        let depth = local_depth(directory, slot);
+        if depth == global_depth {
+            if global_depth == MAX_GLOBAL_DEPTH {
+                return Err(DiskError::IndexFull(self.directory_page_id));
+            }
+            let half = 1usize << global_depth;
+            for low in 0..half {
+                set_bucket_page(directory, low + half, bucket_page(directory, low));
+                set_local_depth(directory, low + half, local_depth(directory, low));
+            }
+            put_u32(directory, 4, global_depth + 1);
+        }
+
+        let old_page_id = bucket_page(directory, slot);
+        let new_page_id = self.bpm.new_page();
+        let split_bit = 1u64 << depth;
+
+        let mut old = self.bpm.write_page(old_page_id).await?;
+        let mut new = self.bpm.write_page(new_page_id).await?;
+        Bucket::init(new.data_mut(), depth + 1);
+        let entries: Vec<(Vec<u8>, u64)> = Bucket::new(old.data(), self.key_len).entries().collect();
+        Bucket::init(old.data_mut(), depth + 1);
+        {
+            let mut old_entries = BucketMut::new(old.data_mut(), self.key_len);
+            let mut new_entries = BucketMut::new(new.data_mut(), self.key_len);
+            for (key, value) in entries {
+                if hash_key(&key) & split_bit == 0 {
+                    old_entries.push(&key, value);
+                } else {
+                    new_entries.push(&key, value);
+                }
+            }
+        }
+
+        // Every slot that pointed at the old bucket now agrees on one more bit
+        let global_depth = get_u32(directory, 4);
+        let low_bits = slot as u64 & (split_bit - 1);
+        for other in 0..1usize << global_depth {
+            if other as u64 & (split_bit - 1) == low_bits {
+                if other as u64 & split_bit != 0 {
+                    set_bucket_page(directory, other, new_page_id);
+                }
+                set_local_depth(directory, other, depth + 1);
+            }
+        }
+        Ok(())
+    }
+
+    fn check_key(&self, key: &[u8]) {
+        assert_eq!(key.len(), self.key_len, "hash index key must be exactly {} bytes", self.key_len);
+    }
+}
+
+/// 64-bit FNV-1a with a final avalanche, stable across processes and releases.
+fn hash_key(key: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &b in key {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    hash ^ (hash >> 33)
+}
+
+fn slot_of(directory: &[u8], hash: u64) -> usize {
+    (hash & ((1u64 << get_u32(directory, 4)) - 1)) as usize
+}
+
+fn bucket_page(directory: &[u8], slot: usize) -> PageId {
+    PageId::from_le_bytes(directory[DIRECTORY_HEADER_SIZE + slot * 4..][..4].try_into().unwrap())
+}
+
+fn set_bucket_page(directory: &mut [u8], slot: usize, page_id: PageId) {
+    directory[DIRECTORY_HEADER_SIZE + slot * 4..][..4].copy_from_slice(&page_id.to_le_bytes());
+}
+
+fn local_depth(directory: &[u8], slot: usize) -> u32 {
+    directory[LOCAL_DEPTHS_OFFSET + slot] as u32
+}
+
+fn set_local_depth(directory: &mut [u8], slot: usize, depth: u32) {
+    directory[LOCAL_DEPTHS_OFFSET + slot] = u8::try_from(depth).expect("local depth fits in u8");
+}
+
+fn get_u32(data: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(data[at..at + 4].try_into().unwrap())
+}
+
+fn put_u32(data: &mut [u8], at: usize, value: u32) {
+    data[at..at + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+fn corrupt_page(page_id: PageId) -> DiskError {
+    DiskError::IoError(std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!("page {} is not a hash index page", page_id),
+    ))
+}
+
+struct Bucket<'a> {
+    data: &'a [u8],
+    entry_len: usize,
+}
+
+impl<'a> Bucket<'a> {
+    fn init(data: &mut [u8], local_depth: u32) {
+        put_u32(data, 0, BUCKET_MAGIC);
+        put_u32(data, 4, 0);
+        put_u32(data, 8, local_depth);
+    }
+
+    fn new(data: &'a [u8], key_len: usize) -> Self {
+        Self {
+            data,
+            entry_len: key_len + 8,
+        }
+    }
+
+    fn count(&self) -> usize {
+        get_u32(self.data, 4) as usize
+    }
+
+    fn capacity(&self) -> usize {
+        (self.data.len() - BUCKET_HEADER_SIZE) / self.entry_len
+    }
+
+    fn entry(&self, index: usize) -> (&'a [u8], u64) {
+        let entry = &self.data[BUCKET_HEADER_SIZE + index * self.entry_len..][..self.entry_len];
+        let (key, value) = entry.split_at(self.entry_len - 8);
+        (key, u64::from_le_bytes(value.try_into().unwrap()))
+    }
+
+    fn find(&self, key: &[u8]) -> Option<(usize, u64)> {
+        (0..self.count())
+            .map(|index| (index, self.entry(index)))
+            .find(|(_, (entry_key, _))| *entry_key == key)
+            .map(|(index, (_, value))| (index, value))
+    }
+
+    fn entries(&self) -> impl Iterator<Item = (Vec<u8>, u64)> + '_ {
+        (0..self.count()).map(|index| {
+            let (key, value) = self.entry(index);
+            (key.to_vec(), value)
+        })
+    }
+}
+
+struct BucketMut<'a> {
+    data: &'a mut [u8],
+    entry_len: usize,
+}
+
+impl<'a> BucketMut<'a> {
+    fn new(data: &'a mut [u8], key_len: usize) -> Self {
+        Self {
+            data,
+            entry_len: key_len + 8,
+        }
+    }
+
+    fn push(&mut self, key: &[u8], value: u64) {
+        let count = get_u32(self.data, 4) as usize;
+        let entry = &mut self.data[BUCKET_HEADER_SIZE + count * self.entry_len..][..self.entry_len];
+        entry[..key.len()].copy_from_slice(key);
+        entry[key.len()..].copy_from_slice(&value.to_le_bytes());
+        put_u32(self.data, 4, count as u32 + 1);
+    }
+
+    // Move the last entry into `index`
+    fn swap_remove(&mut self, index: usize) {
+        let last = get_u32(self.data, 4) as usize - 1;
+        let start = BUCKET_HEADER_SIZE;
+        self.data.copy_within(
+            start + last * self.entry_len..start + (last + 1) * self.entry_len,
+            start + index * self.entry_len,
+        );
+        put_u32(self.data, 4, last as u32);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::storage::disk_manager::DiskManager;
+    use crate::backend::storage::storage_backend::{MemoryBackend, StorageBackend};
+    use crate::common::codec::OrderedKey;
+
+    async fn pool(num_frames: usize) -> Arc<BufferPoolManager> {
+        let dm = Arc::new(DiskManager::in_memory().await.unwrap());
+        Arc::new(BufferPoolManager::new(num_frames, dm))
+    }
+
+    #[tokio::test]
+    async fn test_insert_get_remove_with_splits() {
+        let bpm = pool(16).await;
+        let index = HashIndex::create(Arc::clone(&bpm), 8).await.unwrap();
+
+        for i in 0..2000u64 {
+            assert!(index.insert(&i.to_key_bytes(), i * 10).await.unwrap());
+        }
+        assert!(!index.insert(&7u64.to_key_bytes(), 0).await.unwrap());
+        let (depth, buckets) = index.depth_and_buckets().await.unwrap();
+        // 2000 entries at 255 per bucket
+        assert!(buckets >= 8 && depth >= 3, "depth {} with {} buckets", depth, buckets);
+
+        for i in (0..2000u64).step_by(2) {
+            assert_eq!(index.remove(&i.to_key_bytes()).await.unwrap(), Some(i * 10));
+        }
+        assert_eq!(index.remove(&0u64.to_key_bytes()).await.unwrap(), None);
+        for i in 0..2000u64 {
+            let expected = (i % 2 == 1).then_some(i * 10);
+            assert_eq!(index.get(&i.to_key_bytes()).await.unwrap(), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reopen_over_a_new_pool_keeps_splitting() {
+        let db: Arc<dyn StorageBackend> = Arc::new(MemoryBackend::new());
+        let dir0: Arc<dyn StorageBackend> = Arc::new(MemoryBackend::new());
+        let dir1: Arc<dyn StorageBackend> = Arc::new(MemoryBackend::new());
+        let open = || async {
+            let dm = DiskManager::with_backends(db.clone(), Arc::new(MemoryBackend::new()))
+                .await
+                .unwrap()
+                .with_page_directory(dir0.clone(), dir1.clone())
+                .await
+                .unwrap();
+            Arc::new(BufferPoolManager::new(16, Arc::new(dm)))
+        };
+
+        let bpm = open().await;
+        let index = HashIndex::create(Arc::clone(&bpm), 8).await.unwrap();
+        for i in 0..1000u64 {
+            index.insert(&i.to_key_bytes(), i * 10).await.unwrap();
+        }
+        let directory_page_id = index.directory_page_id();
+        bpm.flush_all_pages().await.unwrap();
+        drop((index, bpm));
+
+        // Everything lives in pool pages, so the index can be reopened from its directory
+        let bpm = open().await;
+        let reopened = HashIndex::open(Arc::clone(&bpm), directory_page_id).await.unwrap();
+        assert_eq!(reopened.key_len(), 8);
+        let buckets_before = reopened.depth_and_buckets().await.unwrap().1;
+        for i in 1000..3000u64 {
+            reopened.insert(&i.to_key_bytes(), i * 10).await.unwrap();
+        }
+        // The new buckets got page ids of their own rather than the index's existing pages
+        assert!(reopened.depth_and_buckets().await.unwrap().1 > buckets_before);
+        for i in 0..3000u64 {
+            assert_eq!(reopened.get(&i.to_key_bytes()).await.unwrap(), Some(i * 10));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_directory_depth_is_bounded() {
+        let bpm = pool(64).await;
+        // Large keys leave room for only a few per bucket
+        let key_len = (GRIMOIRE_PAGE_SIZE - BUCKET_HEADER_SIZE) / 2 - 8;
+        let index = HashIndex::create(bpm, key_len).await.unwrap();
+
+        let mut inserted = 0u64;
+        let result = loop {
+            let mut key = vec![0u8; key_len];
+            key[..8].copy_from_slice(&inserted.to_le_bytes());
+            match index.insert(&key, inserted).await {
+                Ok(_) => inserted += 1,
+                Err(e) => break e,
+            }
+        };
+        assert!(matches!(result, DiskError::IndexFull(page_id) if page_id == index.directory_page_id()));
+        assert_eq!(result.code(), crate::common::errors::ErrorCode::ResourceExhausted);
+        assert_eq!(index.depth_and_buckets().await.unwrap().0, MAX_GLOBAL_DEPTH);
+        // The failed insert left everything before it in place
+        for i in 0..inserted {
+            let mut key = vec![0u8; key_len];
+            key[..8].copy_from_slice(&i.to_le_bytes());
+            assert_eq!(index.get(&key).await.unwrap(), Some(i));
+        }
+    }
+}
@@ -0,0 +1,385 @@
+// src/storage/commit_pipeline.rs
+
+//! Pipelined write path: commits wait for the log, not for page application.
+//!
+//! commit() queues a record for the log writer task and returns once the record is
+//! durable in the log. The writer takes every commit queued while the previous sync ran
+//! and writes them with one write_log_batch call, so concurrent commits share a single
+//! sync (group commit). Each durable record is then handed to the applier task, which
+//! applies records one at a time in log order. Applying happens after the commit has
+//! returned and does not hold it up.
+//!
+//! Why this is safe across a crash:
+//! - A commit is acknowledged only after its group is durable, and groups are written in
+//!   the order their sequence numbers were assigned. So the acknowledged commits are
+//!   always a prefix of the intact log.
+//! - The applier applies records in that same order. So the applied records are always a
+//!   prefix of the committed ones, and the only thing a crash can lose is a suffix of
+//!   committed records that were not applied yet.
+//! - Recovery replays the log (see redo) and re-applies that suffix. It also re-applies
+//!   records that were applied already, so `apply` must be idempotent. Writing a value,
+//!   or skipping a record older than the page's LSN, both qualify.
+//!
+//! The apply queue is bounded. An applier that falls far enough behind slows the writer,
+//! and through it new commits, instead of letting unapplied records pile up in memory.
+//! If `apply` fails, the applier stops. Later commits are still made durable, and their
+//! records are left for recovery.
+//!
+//! With a WriteController (see with_write_controller), commit() reports the apply backlog
+//! to it and goes through its admission first, so commits slow down well before the
+//! queues fill and fail with WriteStalled once the backlog reaches the stop threshold.
+
+use std::future::Future;
+use std::io;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use tokio::sync::{mpsc, oneshot, watch};
+use tokio::task::JoinHandle;
+
+use crate::backend::storage::disk_manager::DiskManager;
+use crate::common::{
+    clock::Clock,
+    errors::DiskError,
+    histogram::{LatencyHistogram, LatencySummary},
+    options::WriteOptions,
+    write_stall::WriteController,
+};
+
+/// Commits written with one log write at most.
+const MAX_GROUP_RECORDS: usize = 256;
+/// Commits waiting for the writer before commit() itself waits.
+const COMMIT_QUEUE_DEPTH: usize = 1024;
+/// Durable records waiting for the applier before the writer waits.
+const APPLY_QUEUE_DEPTH: usize = 1024;
+
+struct CommitRequest {
+    record: Vec<u8>,
+    done: oneshot::Sender<Result<u64, DiskError>>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CommitStats {
+    /// Records made durable.
+    pub committed: u64,
+    /// Log writes those records took.
+    pub groups: u64,
+    /// Sequence number of the last record applied.
+    pub applied: u64,
+    /// commit() calls that succeeded, from queueing the record to its being durable.
+    pub commit_latency: LatencySummary,
+}
+
+#[derive(Default)]
+struct Counters {
+    committed: AtomicU64,
+    groups: AtomicU64,
+}
+
+pub struct CommitPipeline {
+    requests: mpsc::Sender<CommitRequest>,
+    applied: watch::Receiver<u64>,
+    counters: Arc<Counters>,
+    commit_latency: LatencyHistogram,
+    clock: Arc<dyn Clock>,
+    writer: JoinHandle<()>,
+    applier: JoinHandle<Result<(), DiskError>>,
+    write_controller: Option<Arc<WriteController>>,
+}
+
+impl CommitPipeline {
+    /// Start the writer and applier tasks. Committed records go to `disk_manager`'s log and
+    /// are then passed to `apply`. Sequence numbers start at 1.
+    pub fn start<A, F>(disk_manager: Arc<DiskManager>, apply: A) -> Self
+    where
+        A: Fn(Vec<u8>) -> F + Send + 'static,
+        F: Future<Output = Result<(), DiskError>> + Send + 'static,
+    {
+        let (requests, request_rx) = mpsc::channel(COMMIT_QUEUE_DEPTH);
+        let (apply_tx, apply_rx) = mpsc::channel(APPLY_QUEUE_DEPTH);
+        let (applied_tx, applied) = watch::channel(0);
+        let counters = Arc::new(Counters::default());
+        let clock = Arc::clone(disk_manager.clock());
+
+        let writer = tokio::spawn(write_groups(disk_manager, request_rx, apply_tx, Arc::clone(&counters)));
+        let applier = tokio::spawn(apply_in_order(apply_rx, applied_tx, apply));
+        Self {
+            requests,
+            applied,
+            counters,
+            commit_latency: LatencyHistogram::new(),
+            clock,
+            writer,
+            applier,
+            write_controller: None,
+        }
+    }
+
+    /// Hold commits back through `controller` as unapplied records pile up.
+    pub fn with_write_controller(mut self, controller: Arc<WriteController>) -> Self {
+        self.write_controller = Some(controller);
+        self
+    }
+
+    /// Append `record` to the log and return its sequence number once it is durable.
+    /// The record may not be applied yet; see wait_applied().
+    pub async fn commit(&self, record: Vec<u8>) -> Result<u64, DiskError> {
+        if let Some(controller) = &self.write_controller {
+            let committed = self.counters.committed.load(Ordering::SeqCst);
+            controller.set_apply_backlog(committed.saturating_sub(*self.applied.borrow()));
+            controller.admit().await?;
+        }
+        let started = self.clock.now();
+        let (done, result) = oneshot::channel();
+        self.requests
+            .send(CommitRequest { record, done })
+            .await
+            .map_err(|_| stopped("commit pipeline is shut down"))?;
+        let seq = result.await.map_err(|_| stopped("commit pipeline is shut down"))??;
+        self.commit_latency.record(self.clock.now().duration_since(started));
+        Ok(seq)
+    }
+
+    /// Wait until every record up to sequence number `seq` has been applied, e.g. to read
+    /// back one's own commit. Fails if the applier stopped before getting there.
+    pub async fn wait_applied(&self, seq: u64) -> Result<(), DiskError> {
+        let mut applied = self.applied.clone();
+        applied
+            .wait_for(|&applied| applied >= seq)
+            .await
+            .map(|_| ())
+            .map_err(|_| stopped("commit pipeline stopped applying; the rest is replayed on recovery"))
+    }
+
+    pub fn stats(&self) -> CommitStats {
+        CommitStats {
+            committed: self.counters.committed.load(Ordering::SeqCst),
+            groups: self.counters.groups.load(Ordering::SeqCst),
+            applied: *self.applied.borrow(),
+            commit_latency: self.commit_latency.summary(),
+        }
+    }
+
+    /// Stop taking commits, finish writing and applying the queued ones, and return the
+    /// error the applier stopped on, if any.
+    pub async fn close(self) -> Result<(), DiskError> {
+        drop(self.requests);
+        self.writer.await.map_err(|e| DiskError::IoError(e.into()))?;
+        self.applier.await.map_err(|e| DiskError::IoError(e.into()))?
+    }
+}
+
+async fn write_groups(
+    disk_manager: Arc<DiskManager>,
+    mut requests: mpsc::Receiver<CommitRequest>,
+    apply: mpsc::Sender<(u64, Vec<u8>)>,
+    counters: Arc<Counters>,
+) {
+    let mut next_seq = 1;
+    let mut group = Vec::with_capacity(MAX_GROUP_RECORDS);
+    while let Some(first) = requests.recv().await {
+        // Everything that queued up during the previous sync goes into this group
+        group.push(first);
+        while group.len() < MAX_GROUP_RECORDS {
+            match requests.try_recv() {
+                Ok(request) => group.push(request),
+                Err(_) => break,
+            }
+        }
+
+        let records: Vec<&[u8]> = group.iter().map(|request| request.record.as_slice()).collect();
+        let written = disk_manager.write_log_batch(&records, &WriteOptions::default()).await;
+        drop(records);
+        if let Err(e) = written {
+            // Nothing of the group is acknowledged; sequence numbers are not used up
+            for request in group.drain(..) {
+                let _ = request.done.send(Err(DiskError::IoError(shared_error(&e))));
+            }
+            continue;
+        }
+
+        counters.committed.fetch_add(group.len() as u64, Ordering::SeqCst);
+        counters.groups.fetch_add(1, Ordering::SeqCst);
+        for request in group.drain(..) {
+            let seq = next_seq;
+            next_seq += 1;
+            let _ = request.done.send(Ok(seq));
+            // A stopped applier leaves the record to recovery
+            let _ = apply.send((seq, request.record)).await;
+        }
+    }
+}
+
+async fn apply_in_order<A, F>(
+    mut records: mpsc::Receiver<(u64, Vec<u8>)>,
+    applied: watch::Sender<u64>,
+    apply: A,
+) -> Result<(), DiskError>
+where
+    A: Fn(Vec<u8>) -> F,
+    F: Future<Output = Result<(), DiskError>>,
+{
+    while let Some((seq, record)) = records.recv().await {
+        if let Err(e) = apply(record).await {
+            log::error!("commit pipeline stopped applying at record {}: {}", seq, e);
+            return Err(e);
+        }
+        applied.send_replace(seq);
+    }
+    Ok(())
+}
+
+/// An io::Error carrying `e`, for every waiter of a failed group.
+fn shared_error(e: &DiskError) -> io::Error {
+    let kind = match e {
+        DiskError::IoError(e) => e.kind(),
+        _ => io::ErrorKind::Other,
+    };
+    io::Error::new(kind, e.to_string())
+}
+
+fn stopped(message: &'static str) -> DiskError {
+    DiskError::IoError(io::Error::new(io::ErrorKind::BrokenPipe, message))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::backend::storage::redo::redo_parallel;
+    use crate::backend::storage::storage_backend::{MemoryBackend, StorageBackend};
+
+    // | key: i32 | value: u32 |
+    fn record(key: i32, value: u32) -> Vec<u8> {
+        let mut record = key.to_le_bytes().to_vec();
+        record.extend_from_slice(&value.to_le_bytes());
+        record
+    }
+
+    fn decode(record: &[u8]) -> (i32, u32) {
+        (
+            i32::from_le_bytes(record[..4].try_into().unwrap()),
+            u32::from_le_bytes(record[4..8].try_into().unwrap()),
+        )
+    }
+
+    type Table = Arc<Mutex<HashMap<i32, u32>>>;
+
+    fn apply_to(table: &Table) -> impl Fn(Vec<u8>) -> std::future::Ready<Result<(), DiskError>> + Clone + use<> {
+        let table = Arc::clone(table);
+        move |record| {
+            let (key, value) = decode(&record);
+            table.lock().unwrap().insert(key, value);
+            std::future::ready(Ok(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_commits_share_log_writes() {
+        let dm = Arc::new(DiskManager::in_memory().await.unwrap());
+        let table = Table::default();
+        let pipeline = Arc::new(CommitPipeline::start(Arc::clone(&dm), apply_to(&table)));
+
+        let mut commits = tokio::task::JoinSet::new();
+        for key in 0..64 {
+            let pipeline = Arc::clone(&pipeline);
+            commits.spawn(async move { pipeline.commit(record(key, key as u32 * 10)).await });
+        }
+        let mut seqs: Vec<u64> = commits.join_all().await.into_iter().map(Result::unwrap).collect();
+        seqs.sort_unstable();
+        assert_eq!(seqs, (1..=64).collect::<Vec<_>>());
+
+        pipeline.wait_applied(64).await.unwrap();
+        let stats = pipeline.stats();
+        assert_eq!((stats.committed, stats.applied, stats.commit_latency.count), (64, 64, 64));
+        assert!(stats.groups < 64, "every commit took its own log write");
+        assert_eq!(table.lock().unwrap()[&7], 70);
+        assert_eq!(dm.read_log().await.unwrap().len(), 64);
+    }
+
+    #[tokio::test]
+    async fn test_commits_stall_on_apply_backlog() {
+        use crate::common::write_stall::{StallReason, WriteStallConfig};
+
+        let dm = Arc::new(DiskManager::in_memory().await.unwrap());
+        // The applier gets through one record per permit
+        let gate = Arc::new(tokio::sync::Semaphore::new(0));
+        let apply = {
+            let gate = Arc::clone(&gate);
+            move |_record| {
+                let gate = Arc::clone(&gate);
+                async move {
+                    gate.acquire().await.unwrap().forget();
+                    Ok(())
+                }
+            }
+        };
+        let controller = Arc::new(WriteController::new(WriteStallConfig {
+            slowdown_apply_backlog: 2,
+            stop_apply_backlog: 4,
+            max_delay: std::time::Duration::ZERO,
+            ..WriteStallConfig::default()
+        }));
+        let pipeline = CommitPipeline::start(dm, apply).with_write_controller(Arc::clone(&controller));
+
+        for key in 0..4 {
+            pipeline.commit(record(key, 0)).await.unwrap();
+        }
+        assert!(matches!(
+            pipeline.commit(record(4, 0)).await,
+            Err(DiskError::WriteStalled(StallReason::ApplyBacklog))
+        ));
+        assert_eq!(controller.status().delayed_writes, 2);
+
+        gate.add_permits(4);
+        pipeline.wait_applied(4).await.unwrap();
+        assert_eq!(pipeline.commit(record(4, 0)).await.unwrap(), 5);
+        gate.add_permits(1);
+        pipeline.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_recovery_applies_committed_but_unapplied_records() {
+        let db: Arc<dyn StorageBackend> = Arc::new(MemoryBackend::new());
+        let log: Arc<dyn StorageBackend> = Arc::new(MemoryBackend::new());
+        let dm = Arc::new(DiskManager::with_backends(Arc::clone(&db), Arc::clone(&log)).await.unwrap());
+
+        // The applier gets through two records and then hangs, as if the process died
+        let table = Table::default();
+        let applied_so_far = Arc::clone(&table);
+        let pipeline = CommitPipeline::start(dm, move |record| {
+            let table = Arc::clone(&applied_so_far);
+            async move {
+                if table.lock().unwrap().len() == 2 {
+                    std::future::pending::<()>().await;
+                }
+                let (key, value) = decode(&record);
+                table.lock().unwrap().insert(key, value);
+                Ok(())
+            }
+        });
+        for key in 0..5 {
+            pipeline.commit(record(key, key as u32 + 100)).await.unwrap();
+        }
+        pipeline.commit(record(0, 7)).await.unwrap();
+        pipeline.wait_applied(2).await.unwrap();
+        assert_eq!(table.lock().unwrap().len(), 2);
+        drop(pipeline);
+
+        // Reopen and redo the log into what survived the crash
+        let dm = DiskManager::with_backends(db, Arc::clone(&log)).await.unwrap();
+        assert_eq!(dm.read_log().await.unwrap().len(), 6);
+        let stats = redo_parallel(log.as_ref(), 2, |record| Some(decode(record).0), {
+            let apply = apply_to(&table);
+            move |_, record| apply(record)
+        })
+        .await
+        .unwrap();
+        assert_eq!(stats.applied, 6);
+
+        let expected = HashMap::from([(0, 7), (1, 101), (2, 102), (3, 103), (4, 104)]);
+        assert_eq!(*table.lock().unwrap(), expected);
+    }
+}
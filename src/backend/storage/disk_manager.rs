@@ -3,264 +3,1394 @@
 //! - Read
 //! - Write
 //! - Delete
+//!
 //! this module are triggered and organized by disk_scheduler
-
+//!
 //! Async Disk Manager using Tokio
-//! Provides non-blocking I/O operations for page management
+//! Provides non-blocking I/O operations for page management.
+//! The bytes themselves live behind a StorageBackend (a file by default).
 
 use std::{
-    collections::HashMap,
-    path::{Path, PathBuf},
-    sync::Arc,
-};
-use tokio::{
-    fs::{File, OpenOptions},
-    io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt},
-    sync::{RwLock, Semaphore},
+    collections::{BTreeMap, HashMap},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicI32, AtomicUsize, Ordering},
+    },
 };
+#[cfg(feature = "native")]
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+use tokio::sync::{Mutex, RwLock, Semaphore};
 
-use crate::common::{errors::DiskError, types::PageId};
+use crate::common::{
+    buffer_recycler::{BufferRecycler, BufferStats},
+    cancellation::CancellationToken,
+    clock::{Clock, system_clock},
+    cpu_pool::CpuPool,
+    errors::{DiskError, ErrorCode},
+    histogram::{LatencyHistogram, LatencySummary},
+    options::WriteOptions,
+    progress::ProgressTracker,
+    slow_log::{PageIoKind, SlowLog},
+    types::{PageId, SegmentId},
+};
+use crate::backend::storage::double_write::{
+    DEFAULT_DOUBLE_WRITE_SLOTS, DoubleWriteBuffer, DoubleWriteSlot,
+};
+use crate::backend::storage::free_space::{DEFAULT_EXTENT_PAGES, FreeSpaceMap, FreeSpaceStats};
+use crate::backend::storage::io_stats::{IoBreakdown, IoSource};
+use crate::backend::storage::log_frame::{DEFAULT_LOG_READ_CHUNK, LogReader, encode_frame, scan_log};
+use crate::backend::storage::page_checksums::PageChecksums;
+use crate::backend::storage::page_directory::{DirectoryDelta, DirectoryStats, PageDirectory};
+use crate::backend::storage::page_repair::PageSource;
+#[cfg(feature = "native")]
+use crate::backend::storage::storage_backend::FileBackend;
+use crate::backend::storage::storage_backend::{DurabilityMode, MemoryBackend, StorageBackend};
+use crate::backend::storage::tiering::{ColdTier, TierStats, TieringPolicy};
 
 pub const GRIMOIRE_PAGE_SIZE: usize = 4096;
 
+// Pages the file has room for before it first grows
+const INITIAL_PAGE_CAPACITY: usize = 128;
+
+/// Page/log I/O operations allowed in flight at once, unless changed at runtime.
+pub const DEFAULT_IO_CONCURRENCY: usize = 10;
+
 #[derive(Default)]
 struct DiskStats {
     num_writes: u64,
     num_reads: u64,
     num_deletes: u64,
     num_flushes: u64,
+    num_repairs: u64,
+    io: IoBreakdown,
+}
+
+#[derive(Default)]
+struct DiskLatencies {
+    page_read: LatencyHistogram,
+    page_write: LatencyHistogram,
+    wal_fsync: LatencyHistogram,
+    io_queue_wait: LatencyHistogram,
+}
+
+/// A page salvage mode gave up on, and why.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct QuarantinedPage {
+    pub page_id: PageId,
+    pub reason: String,
+}
+
+/// What a scan_pages() run visited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScanSummary {
+    pub pages_read: usize,
+    /// Quarantined pages passed over (salvage mode only).
+    pub pages_skipped: usize,
+}
+
+/// Latency percentiles of page I/O and log syncs since the DiskManager was opened.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct DiskLatencyStats {
+    pub page_read: LatencySummary,
+    pub page_write: LatencySummary,
+    /// Log writes that synced, timed from the start of the sync.
+    pub wal_fsync: LatencySummary,
+    /// Time page reads and writes waited for an I/O permit before starting.
+    pub io_queue_wait: LatencySummary,
 }
 
 pub struct DiskManager {
-    db_file_path: PathBuf,
-    log_file_path: PathBuf,
+    // Storage for pages and for the log
+    db_backend: Arc<dyn StorageBackend>,
+    log_backend: Arc<dyn StorageBackend>,
+
+    // End of the last intact log record; the log file may extend past it with
+    // preallocated zeros. Held across a log write, so it also serializes them.
+    log_end: Mutex<u64>,
+
+    // Bytes the log file grows by at a time when it runs out of preallocated space;
+    // 0 grows it with every write
+    log_preallocation: u64,
+
+    // Bytes the data file grows by at least, allocated up front; 0 only doubles it
+    data_preallocation: u64,
+
+    // Free bytes to keep on the volume; writes that would eat into them fail with
+    // DiskFull, and `low_space` stays set until a later check finds room again. 0 is off.
+    min_free_space: u64,
+    low_space: AtomicBool,
     
     // Page mapping: page_id -> offset
     pages: Arc<RwLock<HashMap<PageId, u64>>>,
+
+    // One past the highest page id ever mapped, where fresh page ids start
+    next_page_id: AtomicI32,
     
-    // Free slots for reuse
-    free_slots: Arc<RwLock<Vec<u64>>>,
+    // Free slots and extents for reuse
+    free_space: Arc<RwLock<FreeSpaceMap>>,
     
     // Capacity tracking
     page_capacity: Arc<RwLock<usize>>,
     
     // Statistics
     stats: Arc<RwLock<DiskStats>>,
+    latencies: DiskLatencies,
     
-    // Semaphore to limit concurrent I/O operations
+    // Semaphore to limit concurrent I/O operations, and its target size. Resizes and the
+    // passes that take every permit hold io_resize, so readers of the size never wait
     io_semaphore: Arc<Semaphore>,
+    io_concurrency: AtomicUsize,
+    io_resize: Mutex<()>,
+
+    // Checksumming and other CPU-heavy transforms run here, off the async workers
+    cpu_pool: CpuPool,
+
+    // Scratch page buffers for tiering and compaction, reused across calls
+    page_buffers: BufferRecycler,
+
+    // How page and log writes are synced
+    durability: DurabilityMode,
+
+    // Torn-write protection, if enabled
+    double_write: Option<DoubleWriteBuffer>,
+
+    // Checksums of page contents, verified on every read, if enabled
+    checksums: Option<PageChecksums>,
+
+    // Where changes to `pages` are persisted, if anywhere. Appended to while `pages`
+    // is write-locked, so its deltas are in the same order as the changes.
+    page_directory: Option<PageDirectory>,
+
+    // Reject every write (see OpenOptions::read_only)
+    read_only: bool,
+
+    // Where page I/O and log syncs over their threshold are reported, if anywhere
+    slow_log: Option<Arc<SlowLog>>,
+
+    // Where migrate_cold() moves pages that are rarely accessed, if anywhere.
+    // Its page lock is taken before `pages`.
+    cold_tier: Option<ColdTier>,
+
+    // Where clean copies of corrupt pages come from, if anywhere
+    repair_source: Option<Arc<dyn PageSource>>,
+
+    // Quarantine pages that read as corrupt instead of failing (see with_salvage), and
+    // the pages quarantined so far with the reason
+    salvage: bool,
+    quarantine: std::sync::Mutex<BTreeMap<PageId, String>>,
+
+    // Source of time for I/O timings and page recency
+    clock: Arc<dyn Clock>,
 }
 
-impl DiskManager {
-    pub async fn new(db_file: &Path) -> Result<Self, DiskError> {
+/// How DiskManager opens a file-backed database, in the style of embedded databases.
+/// The defaults (create if missing, existing files are fine, writable) match DiskManager::new.
+#[cfg(feature = "native")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpenOptions {
+    create_if_missing: bool,
+    error_if_exists: bool,
+    read_only: bool,
+}
+
+#[cfg(feature = "native")]
+impl Default for OpenOptions {
+    fn default() -> Self {
+        Self {
+            create_if_missing: true,
+            error_if_exists: false,
+            read_only: false,
+        }
+    }
+}
+
+#[cfg(feature = "native")]
+impl OpenOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create the database if it does not exist; otherwise fail with DatabaseNotFound.
+    pub fn create_if_missing(mut self, create_if_missing: bool) -> Self {
+        self.create_if_missing = create_if_missing;
+        self
+    }
+
+    /// Fail with AlreadyExists if the database exists.
+    pub fn error_if_exists(mut self, error_if_exists: bool) -> Self {
+        self.error_if_exists = error_if_exists;
+        self
+    }
+
+    /// Open without write access. The database must exist, and every write fails with ReadOnly.
+    /// Takes no lock, so it can inspect a database another process is writing; what it
+    /// reads is then only as consistent as the pages on disk at that moment.
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Open the database at `db_file`, with its log next to it.
+    pub async fn open(&self, db_file: &Path) -> Result<DiskManager, DiskError> {
         let db_file_path = db_file.to_path_buf();
-        let log_file_path = db_file_path
-            .file_stem()
-            .map(|stem| PathBuf::from(format!("{}.log", stem.to_string_lossy())))
-            .unwrap_or_else(|| PathBuf::from("grimoire.log"));
-
-        // Create db file
-        let db_file = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create(true)
-            .open(&db_file_path)
+        let exists = tokio::fs::try_exists(&db_file_path)
             .await
             .map_err(DiskError::IoError)?;
+        if exists && self.error_if_exists {
+            return Err(DiskError::AlreadyExists(db_file_path));
+        }
+        if !exists && (self.read_only || !self.create_if_missing) {
+            return Err(DiskError::DatabaseNotFound(db_file_path));
+        }
 
-        // Create log file
-        OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create(true)
-            .open(&log_file_path)
-            .await
-            .map_err(DiskError::IoError)?;
+        let log_file_path = DiskManager::log_path(&db_file_path);
+        if self.read_only {
+            // The log is only ever appended to, so a read-only database does not need it
+            let db_backend = FileBackend::open_read_only(&db_file_path).await?;
+            return DiskManager::from_backends(Arc::new(db_backend), Arc::new(MemoryBackend::new()), true).await;
+        }
 
-        let initial_capacity = 128;
-        db_file
-            .set_len(((initial_capacity + 1) * GRIMOIRE_PAGE_SIZE) as u64)
-            .await
-            .map_err(DiskError::IoError)?;
+        // One writer per database: two would each trust their own page directory and
+        // free list and overwrite each other's pages
+        let db_backend = FileBackend::open(&db_file_path).await?.lock_exclusive().await?;
+        let log_backend = FileBackend::open(&log_file_path).await?;
+        DiskManager::from_backends(Arc::new(db_backend), Arc::new(log_backend), false).await
+    }
+}
+
+impl DiskManager {
+    /// Open (or create) a file-backed database at `db_file`, with its log next to it.
+    /// See OpenOptions for more control.
+    #[cfg(feature = "native")]
+    pub async fn new(db_file: &Path) -> Result<Self, DiskError> {
+        OpenOptions::new().open(db_file).await
+    }
+
+    // Keep the log next to the db file rather than in the working directory
+    #[cfg(feature = "native")]
+    fn log_path(db_file_path: &Path) -> PathBuf {
+        if db_file_path.file_stem().is_some() {
+            db_file_path.with_extension("log")
+        } else {
+            db_file_path.with_file_name("grimoire.log")
+        }
+    }
+
+    /// Create a database that lives entirely in memory.
+    pub async fn in_memory() -> Result<Self, DiskError> {
+        Self::with_backends(Arc::new(MemoryBackend::new()), Arc::new(MemoryBackend::new())).await
+    }
+
+    /// Create a DiskManager over arbitrary storage backends.
+    pub async fn with_backends(
+        db_backend: Arc<dyn StorageBackend>,
+        log_backend: Arc<dyn StorageBackend>,
+    ) -> Result<Self, DiskError> {
+        Self::from_backends(db_backend, log_backend, false).await
+    }
+
+    async fn from_backends(
+        db_backend: Arc<dyn StorageBackend>,
+        log_backend: Arc<dyn StorageBackend>,
+        read_only: bool,
+    ) -> Result<Self, DiskError> {
+        let initial_capacity = INITIAL_PAGE_CAPACITY;
+        let initial_size = ((initial_capacity + 1) * GRIMOIRE_PAGE_SIZE) as u64;
+        if !read_only && db_backend.size().await? < initial_size {
+            db_backend.set_len(initial_size).await?;
+        }
+        let log_end = if read_only {
+            log_backend.size().await?
+        } else {
+            Self::truncate_torn_log(log_backend.as_ref()).await?
+        };
 
         Ok(Self {
-            db_file_path,
-            log_file_path,
+            db_backend,
+            log_backend,
+            log_end: Mutex::new(log_end),
+            log_preallocation: 0,
+            data_preallocation: 0,
+            min_free_space: 0,
+            low_space: AtomicBool::new(false),
             pages: Arc::new(RwLock::new(HashMap::new())),
-            free_slots: Arc::new(RwLock::new(Vec::new())),
+            next_page_id: AtomicI32::new(0),
+            free_space: Arc::new(RwLock::new(FreeSpaceMap::new(GRIMOIRE_PAGE_SIZE, DEFAULT_EXTENT_PAGES))),
             page_capacity: Arc::new(RwLock::new(initial_capacity)),
             stats: Arc::new(RwLock::new(DiskStats::default())),
-            io_semaphore: Arc::new(Semaphore::new(10)), // Limit to 10 concurrent I/O ops
+            latencies: DiskLatencies::default(),
+            io_semaphore: Arc::new(Semaphore::new(DEFAULT_IO_CONCURRENCY)),
+            io_concurrency: AtomicUsize::new(DEFAULT_IO_CONCURRENCY),
+            io_resize: Mutex::new(()),
+            cpu_pool: CpuPool::default(),
+            page_buffers: BufferRecycler::new(GRIMOIRE_PAGE_SIZE, DEFAULT_IO_CONCURRENCY),
+            durability: DurabilityMode::default(),
+            double_write: None,
+            checksums: None,
+            page_directory: None,
+            read_only,
+            slow_log: None,
+            cold_tier: None,
+            repair_source: None,
+            salvage: false,
+            quarantine: std::sync::Mutex::new(BTreeMap::new()),
+            clock: system_clock(),
         })
     }
 
+    // Drop whatever follows the last intact log record; a crash mid-append leaves it there.
+    // A zero-filled tail is preallocated space and stays. Returns where the next record goes.
+    async fn truncate_torn_log(log_backend: &dyn StorageBackend) -> Result<u64, DiskError> {
+        let size = log_backend.size().await?;
+        let mut reader = LogReader::open(log_backend, DEFAULT_LOG_READ_CHUNK).await?;
+        while reader.next_record().await?.is_some() {}
+        let valid_len = reader.valid_len();
+        if !reader.zero_tail().await? {
+            log::warn!(
+                "truncating {} byte(s) of torn log tail at offset {}",
+                size - valid_len,
+                valid_len
+            );
+            log_backend.set_len(valid_len).await?;
+            log_backend.sync().await?;
+        }
+        Ok(valid_len)
+    }
+
+    /// Protect page writes against torn writes with a double-write buffer stored in
+    /// `dwb_backend` (e.g. a `<db>.dwb` file next to the database).
+    /// Any pages left in the buffer by a crash are restored before this returns, so call
+    /// with_page_checksums() first to have their checksums recorded too.
+    pub async fn with_double_write(
+        mut self,
+        dwb_backend: Arc<dyn StorageBackend>,
+    ) -> Result<Self, DiskError> {
+        if self.read_only {
+            return Err(DiskError::ReadOnly);
+        }
+        let dwb = DoubleWriteBuffer::open(dwb_backend, DEFAULT_DOUBLE_WRITE_SLOTS).await?;
+        let restored = dwb.recover(self.db_backend.as_ref()).await?;
+        if !restored.is_empty() {
+            log::warn!("restored {} torn page(s) from the double-write buffer", restored.len());
+        }
+        // A crash may have come between a page's checksum and its slot copy
+        if let Some(checksums) = &self.checksums {
+            let mut page_data = vec![0u8; GRIMOIRE_PAGE_SIZE];
+            for offset in restored {
+                self.db_backend.read_at(offset, &mut page_data).await?;
+                checksums.record(offset, &page_data).await?;
+            }
+            checksums.backend().sync().await?;
+        }
+        self.double_write = Some(dwb);
+        Ok(self)
+    }
+
+    /// Checksum every page written from now on, storing the checksums in `crc_backend`
+    /// (e.g. a `<db>.crc` file next to the database), and verify them on every read. A
+    /// page failing its checksum reads as ChecksumMismatch (ErrorCode::Corruption), which
+    /// with_page_repair() and with_salvage() act on. See page_checksums.
+    pub async fn with_page_checksums(mut self, crc_backend: Arc<dyn StorageBackend>) -> Result<Self, DiskError> {
+        self.checksums = Some(PageChecksums::open(crc_backend).await?);
+        Ok(self)
+    }
+
+    // Record the checksum of `page_data` before it is written at `offset`
+    async fn record_checksum(
+        &self,
+        offset: u64,
+        page_data: &[u8],
+        durability: DurabilityMode,
+    ) -> Result<(), DiskError> {
+        let Some(checksums) = &self.checksums else {
+            return Ok(());
+        };
+        let (entry_offset, entry_len) = checksums.record(offset, page_data).await?;
+        Self::sync_backend_as(durability, checksums.backend(), entry_offset, entry_len).await?;
+        Ok(())
+    }
+
+    fn verify_checksum(&self, page_id: PageId, offset: u64, page_data: &[u8]) -> Result<(), DiskError> {
+        match &self.checksums {
+            Some(checksums) => checksums
+                .verify(offset, page_data)
+                .map_err(|(expected, actual)| DiskError::ChecksumMismatch { page_id, expected, actual }),
+            None => Ok(()),
+        }
+    }
+
+    /// Hand out slots to segments `extent_pages` at a time (see free_space). Call before
+    /// any page is written and before with_page_directory().
+    pub fn with_extent_pages(mut self, extent_pages: usize) -> Self {
+        self.free_space = Arc::new(RwLock::new(FreeSpaceMap::new(GRIMOIRE_PAGE_SIZE, extent_pages)));
+        self
+    }
+
+    /// Persist the page directory (which offset each page lives at) as delta records in
+    /// `first` and `second` (e.g. `<db>.dir0` and `<db>.dir1` next to the database), and
+    /// restore the mappings already there. Cold-tier locations are not persisted.
+    pub async fn with_page_directory(
+        mut self,
+        first: Arc<dyn StorageBackend>,
+        second: Arc<dyn StorageBackend>,
+    ) -> Result<Self, DiskError> {
+        if self.read_only {
+            return Err(DiskError::ReadOnly);
+        }
+        let (directory, restored) = PageDirectory::open(first, second).await?;
+
+        // Every slot below the highest one in use that no page occupies is free
+        let mut free_space = self.free_space.write().await;
+        free_space.rebuild(restored.values().copied());
+        let capacity = (free_space.end_pages() as usize).max(INITIAL_PAGE_CAPACITY);
+        drop(free_space);
+        let size = ((capacity + 1) * GRIMOIRE_PAGE_SIZE) as u64;
+        if self.db_backend.size().await? < size {
+            self.db_backend.set_len(size).await?;
+        }
+        self.next_page_id = AtomicI32::new(restored.keys().max().map_or(0, |&page_id| page_id.saturating_add(1)));
+        *self.pages.write().await = restored;
+        *self.page_capacity.write().await = capacity;
+        self.page_directory = Some(directory);
+        Ok(self)
+    }
+
+    /// Record changes made to `pages` in the page directory, if there is one.
+    async fn persist_directory(
+        &self,
+        deltas: &[DirectoryDelta],
+        pages: &HashMap<PageId, u64>,
+    ) -> Result<(), DiskError> {
+        match &self.page_directory {
+            Some(directory) if !deltas.is_empty() => directory.append(deltas, pages, self.durability).await,
+            _ => Ok(()),
+        }
+    }
+
+    /// One past the highest page id mapped so far, including pages restored from the page
+    /// directory: page ids from here on are not in use.
+    pub fn next_page_id(&self) -> PageId {
+        self.next_page_id.load(Ordering::SeqCst)
+    }
+
+    /// Generation, pending deltas and size of the page directory, or None without one.
+    pub async fn directory_stats(&self) -> Option<DirectoryStats> {
+        Some(self.page_directory.as_ref()?.stats().await)
+    }
+
+    /// Set how page and log writes are synced (see DurabilityMode for the guarantees)
+    pub fn with_durability(mut self, durability: DurabilityMode) -> Self {
+        self.durability = durability;
+        self
+    }
+
+    /// Report page reads/writes and log syncs that exceed their threshold to `slow_log`
+    pub fn with_slow_log(mut self, slow_log: Arc<SlowLog>) -> Self {
+        self.slow_log = Some(slow_log);
+        self
+    }
+
+    /// Grow the log file `bytes` at a time (fallocate on Linux) instead of with every
+    /// write, so a log sync does not also have to persist a new file size. 0 turns it off.
+    pub fn with_log_preallocation(mut self, bytes: u64) -> Self {
+        self.log_preallocation = bytes;
+        self
+    }
+
+    /// Grow the data file by at least `bytes` at a time, with the space allocated up front
+    /// (fallocate on Linux) rather than left sparse, so writes into it cannot run out of
+    /// space halfway. 0 turns it off.
+    pub fn with_data_preallocation(mut self, bytes: u64) -> Self {
+        self.data_preallocation = bytes;
+        self
+    }
+
+    /// Keep `bytes` free on the volumes of the data and log files: a write that needs to
+    /// grow a file into that reserve fails with DiskFull, and while space is low every
+    /// write is rejected until a check finds room again. Only backends that report their
+    /// available_space() are checked. 0 turns it off.
+    pub fn with_min_free_space(mut self, bytes: u64) -> Self {
+        self.min_free_space = bytes;
+        self
+    }
+
+    /// Whether the last space check found the volume below min_free_space.
+    pub fn is_low_on_space(&self) -> bool {
+        self.low_space.load(Ordering::SeqCst)
+    }
+
+    /// Free bytes on the data file's volume, if the backend can tell. Also updates
+    /// is_low_on_space(), so a health check can clear the condition once space is freed.
+    pub async fn available_space(&self) -> Result<Option<u64>, DiskError> {
+        let available = self.db_backend.available_space().await?;
+        if let (Some(available), true) = (available, self.min_free_space > 0) {
+            self.set_low_space(available < self.min_free_space, available);
+        }
+        Ok(available)
+    }
+
+    // Fail with DiskFull if growing `backend` by `growth` bytes would leave less than
+    // min_free_space on its volume. Cheap unless growing or already low on space.
+    async fn ensure_space(&self, backend: &dyn StorageBackend, growth: u64) -> Result<(), DiskError> {
+        if self.min_free_space == 0 || (growth == 0 && !self.is_low_on_space()) {
+            return Ok(());
+        }
+        let Some(available) = backend.available_space().await? else {
+            return Ok(());
+        };
+        let required = self.min_free_space.saturating_add(growth);
+        self.set_low_space(available < required, available);
+        if available < required {
+            return Err(DiskError::DiskFull { available, required });
+        }
+        Ok(())
+    }
+
+    fn set_low_space(&self, low: bool, available: u64) {
+        match (self.low_space.swap(low, Ordering::SeqCst), low) {
+            (false, true) => log::warn!(
+                "disk space low: {} bytes free, keeping {} in reserve; rejecting writes",
+                available,
+                self.min_free_space
+            ),
+            (true, false) => log::info!("disk space recovered: {} bytes free; accepting writes", available),
+            _ => {}
+        }
+    }
+
+    pub fn slow_log(&self) -> Option<&Arc<SlowLog>> {
+        self.slow_log.as_ref()
+    }
+
+    /// Attach `cold_backend` as the tier migrate_cold() moves rarely accessed pages to
+    /// (see tiering). It starts out empty; anything already in it is overwritten.
+    pub fn with_cold_tier(mut self, cold_backend: Arc<dyn StorageBackend>) -> Self {
+        self.cold_tier = Some(ColdTier::new(cold_backend, Arc::clone(&self.clock)));
+        self
+    }
+
+    /// Repair pages that read as corrupt from `source`, e.g. a replica or a restored backup.
+    pub fn with_page_repair(mut self, source: Arc<dyn PageSource>) -> Self {
+        self.repair_source = Some(source);
+        self
+    }
+
+    /// Salvage mode, for getting what can be got out of a damaged database: a page that
+    /// reads as corrupt (fails its checksum, see with_page_checksums, or comes up short)
+    /// and cannot be repaired is quarantined. Reads of it then fail with PageQuarantined
+    /// without touching the disk, scan_pages() skips it with a warning, and
+    /// quarantined_pages() lists it. Writing the page lifts the quarantine.
+    pub fn with_salvage(mut self, salvage: bool) -> Self {
+        self.salvage = salvage;
+        self
+    }
+
+    pub fn is_salvaging(&self) -> bool {
+        self.salvage
+    }
+
+    /// Measure I/O latency and page recency with `clock` instead of the system clock.
+    /// Call before with_cold_tier(), which picks up the clock set at that point.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn durability(&self) -> DurabilityMode {
+        self.durability
+    }
+
+    pub fn clock(&self) -> &Arc<dyn Clock> {
+        &self.clock
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    pub async fn io_concurrency(&self) -> usize {
+        self.io_concurrency.load(Ordering::SeqCst)
+    }
+
+    /// Change how many I/O operations may run at once, at least one. Shrinking waits for
+    /// enough in-flight operations to finish; io_concurrency() reports the new limit
+    /// right away.
+    pub async fn set_io_concurrency(&self, limit: usize) {
+        // No permits at all would stall every read and write
+        let limit = limit.max(1);
+        let _resize = self.io_resize.lock().await;
+        let current = self.io_concurrency.swap(limit, Ordering::SeqCst);
+        if limit > current {
+            self.io_semaphore.add_permits(limit - current);
+        } else if limit < current {
+            let excess = u32::try_from(current - limit).expect("I/O concurrency fits in u32");
+            // Closed only on shutdown, when there is nothing left to limit
+            if let Ok(permits) = self.io_semaphore.acquire_many(excess).await {
+                permits.forget();
+            }
+        }
+    }
+
+    /// Pool that checksums double-write slots and log records.
+    pub fn cpu_pool(&self) -> &CpuPool {
+        &self.cpu_pool
+    }
+
+    /// Sync `offset..offset + len` of `backend` according to the durability mode.
+    /// Returns whether anything was actually flushed.
+    async fn sync_backend(
+        &self,
+        backend: &dyn StorageBackend,
+        offset: u64,
+        len: u64,
+    ) -> Result<bool, DiskError> {
+        Self::sync_backend_as(self.durability, backend, offset, len).await
+    }
+
+    /// Like sync_backend, with `durability` instead of the manager's mode.
+    pub(crate) async fn sync_backend_as(
+        durability: DurabilityMode,
+        backend: &dyn StorageBackend,
+        offset: u64,
+        len: u64,
+    ) -> Result<bool, DiskError> {
+        match durability {
+            DurabilityMode::SyncAll => backend.sync().await?,
+            DurabilityMode::DataSync => backend.sync_data().await?,
+            DurabilityMode::RangeSync => backend.sync_range(offset, len).await?,
+            DurabilityMode::None => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Bytes in the page file.
+    pub async fn db_size(&self) -> Result<u64, DiskError> {
+        self.db_backend.size().await
+    }
+
+    /// Bytes of log records, not counting preallocated space.
+    pub async fn log_size(&self) -> Result<u64, DiskError> {
+        Ok(*self.log_end.lock().await)
+    }
+
+    /// Page/log I/O operations currently holding an I/O slot.
+    pub async fn io_in_flight(&self) -> usize {
+        let limit = self.io_concurrency.load(Ordering::SeqCst);
+        limit.saturating_sub(self.io_semaphore.available_permits())
+    }
+
     /// Write a page to disk asynchronously
     pub async fn write_page(&self, page_id: PageId, page_data: &[u8]) -> Result<(), DiskError> {
+        self.write_page_for(IoSource::DataPage, page_id, page_data).await
+    }
+
+    /// Write a page with per-call `options` (e.g. a different durability mode).
+    pub async fn write_page_with_options(
+        &self,
+        page_id: PageId,
+        page_data: &[u8],
+        options: &WriteOptions,
+    ) -> Result<(), DiskError> {
+        let durability = options.effective_durability(self.durability);
+        self.write_page_inner(IoSource::DataPage, page_id, page_data, durability).await
+    }
+
+    /// Write a page on behalf of `source`, so the bytes are attributed to it in io_breakdown()
+    pub async fn write_page_for(
+        &self,
+        source: IoSource,
+        page_id: PageId,
+        page_data: &[u8],
+    ) -> Result<(), DiskError> {
+        self.write_page_inner(source, page_id, page_data, self.durability).await
+    }
+
+    async fn write_page_inner(
+        &self,
+        source: IoSource,
+        page_id: PageId,
+        page_data: &[u8],
+        durability: DurabilityMode,
+    ) -> Result<(), DiskError> {
         if page_data.len() != GRIMOIRE_PAGE_SIZE {
             panic!("page_data must be exactly {} bytes", GRIMOIRE_PAGE_SIZE);
         }
+        if self.read_only {
+            return Err(DiskError::ReadOnly);
+        }
+        self.ensure_space(self.db_backend.as_ref(), 0).await?;
 
-        let _permit = self.io_semaphore.acquire().await.unwrap();
+        let queued = self.clock.now();
+        let _permit = self.io_semaphore.acquire().await?;
+        let started = self.clock.now();
+        self.latencies.io_queue_wait.record(started.duration_since(queued));
 
         // Ensure the page_id is allocated first
-        let offset = {
-            let mut pages = self.pages.write().await;
-            if let Some(&offset) = pages.get(&page_id) {
-                offset
-            } else {
-                // Allocate new page atomically while holding write lock
-                let mut free_slots = self.free_slots.write().await;
-                if let Some(offset) = free_slots.pop() {
-                    pages.insert(page_id, offset);
-                    offset
-                } else {
-                    let offset = pages.len() as u64 * GRIMOIRE_PAGE_SIZE as u64;
-                    pages.insert(page_id, offset);
-                    offset
+        let offset = self.allocate_page(page_id, None).await?;
+
+        // Now perform I/O safely, going through the double-write buffer when it is enabled.
+        // Without syncs there is no ordering to rely on, so the buffer is skipped.
+        let flushed = match &self.double_write {
+            Some(dwb) if durability != DurabilityMode::None => {
+                let slot = dwb.acquire().await?;
+                let result = self
+                    .write_through_double_write(dwb, &slot, page_id, offset, page_data, durability)
+                    .await;
+                dwb.release(slot).await;
+                result?
+            }
+            _ => {
+                self.record_checksum(offset, page_data, durability).await?;
+                self.db_backend.write_at(offset, page_data).await?;
+                Self::sync_backend_as(durability, self.db_backend.as_ref(), offset, page_data.len() as u64)
+                    .await?
+            }
+        };
+        let elapsed = self.clock.now().duration_since(started);
+        self.latencies.page_write.record(elapsed);
+        if let Some(slow_log) = &self.slow_log {
+            slow_log.page_io(PageIoKind::Write, page_id, elapsed);
+        }
+        if let Some(cold) = &self.cold_tier {
+            // The new hot copy supersedes a cold one
+            cold.touch(page_id);
+            cold.lock_pages().await.remove(page_id);
+        }
+
+        // The new contents replace whatever was corrupt
+        self.lock_quarantine().remove(&page_id);
+
+        let mut stats = self.stats.write().await;
+        stats.num_writes += 1;
+        stats.num_flushes += flushed as u64;
+        stats.io.record_write(source, page_data.len());
+
+        Ok(())
+    }
+
+    /// Write the page copy into its double-write slot and sync it, then write and sync
+    /// the home copy and clear the slot. Returns whether anything was flushed.
+    async fn write_through_double_write(
+        &self,
+        dwb: &DoubleWriteBuffer,
+        slot: &DoubleWriteSlot,
+        page_id: PageId,
+        offset: u64,
+        page_data: &[u8],
+        durability: DurabilityMode,
+    ) -> Result<bool, DiskError> {
+        let (slot_offset, slot_len) = dwb
+            .write(slot, page_id, offset, page_data, &self.cpu_pool)
+            .await?;
+        Self::sync_backend_as(durability, dwb.backend(), slot_offset, slot_len).await?;
+
+        self.record_checksum(offset, page_data, durability).await?;
+        self.db_backend.write_at(offset, page_data).await?;
+        let flushed =
+            Self::sync_backend_as(durability, self.db_backend.as_ref(), offset, page_data.len() as u64)
+                .await?;
+
+        // Home is durable; a stale copy must not be replayed over later writes
+        let (header_offset, header_len) = dwb.clear(slot).await?;
+        Self::sync_backend_as(durability, dwb.backend(), header_offset, header_len).await?;
+        Ok(flushed)
+    }
+
+    /// Read a page from disk asynchronously
+    pub async fn read_page(&self, page_id: PageId, page_data: &mut [u8]) -> Result<(), DiskError> {
+        self.read_page_for(IoSource::DataPage, page_id, page_data).await
+    }
+
+    /// Read a page on behalf of `source`, so the bytes are attributed to it in io_breakdown()
+    pub async fn read_page_for(
+        &self,
+        source: IoSource,
+        page_id: PageId,
+        page_data: &mut [u8],
+    ) -> Result<(), DiskError> {
+        if page_data.len() != GRIMOIRE_PAGE_SIZE {
+            panic!("page_data must be exactly {} bytes", GRIMOIRE_PAGE_SIZE);
+        }
+        if self.lock_quarantine().contains_key(&page_id) {
+            return Err(DiskError::PageQuarantined(page_id));
+        }
+
+        let queued = self.clock.now();
+        let _permit = self.io_semaphore.acquire().await?;
+        let started = self.clock.now();
+        self.latencies.io_queue_wait.record(started.duration_since(queued));
+
+        // Get offset, bringing the page back from the cold tier if it is there
+        let offset = self.pages.read().await.get(&page_id).copied();
+        let offset = match offset {
+            Some(offset) => offset,
+            None => self.promote(page_id).await?,
+        };
+        if let Some(cold) = &self.cold_tier {
+            cold.touch(page_id);
+        }
+
+        let read = match self.db_backend.read_at(offset, page_data).await {
+            Ok(()) => self.verify_checksum(page_id, offset, page_data),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = read
+            && let Err(e) = self.repair_page(page_id, offset, page_data, e).await
+        {
+            return Err(self.quarantine_if_salvaging(page_id, e));
+        }
+        let elapsed = self.clock.now().duration_since(started);
+        self.latencies.page_read.record(elapsed);
+        if let Some(slow_log) = &self.slow_log {
+            slow_log.page_io(PageIoKind::Read, page_id, elapsed);
+        }
+
+        // Update stats
+        let mut stats = self.stats.write().await;
+        stats.num_reads += 1;
+        stats.io.record_read(source, page_data.len());
+
+        Ok(())
+    }
+
+    // Replace a page whose read failed with `error` by the repair source's copy, and
+    // write that back over the damaged one. Fails with `error` unless it reported
+    // corruption and the source has a copy.
+    async fn repair_page(
+        &self,
+        page_id: PageId,
+        offset: u64,
+        page_data: &mut [u8],
+        error: DiskError,
+    ) -> Result<(), DiskError> {
+        let Some(source) = &self.repair_source else {
+            return Err(error);
+        };
+        if error.code() != ErrorCode::Corruption {
+            return Err(error);
+        }
+        match source.fetch_page(page_id, page_data).await {
+            Ok(true) => {}
+            Ok(false) => {
+                log::error!("page {} is corrupt ({}) and the repair source has no copy", page_id, error);
+                return Err(error);
+            }
+            Err(e) => {
+                log::error!("page {} is corrupt ({}) and fetching a copy failed: {}", page_id, error, e);
+                return Err(error);
+            }
+        }
+
+        // The reader gets the clean copy even if it cannot be written back; the next
+        // read then repairs it again
+        if !self.read_only {
+            let len = page_data.len() as u64;
+            let written = async {
+                self.record_checksum(offset, page_data, self.durability).await?;
+                self.db_backend.write_at(offset, page_data).await?;
+                Self::sync_backend_as(self.durability, self.db_backend.as_ref(), offset, len).await
+            }
+            .await;
+            if let Err(e) = written {
+                log::error!("writing back repaired page {} at offset {} failed: {}", page_id, offset, e);
+            }
+        }
+        log::warn!("page {} at offset {} was corrupt ({}); repaired from the repair source", page_id, offset, error);
+        self.stats.write().await.num_repairs += 1;
+        Ok(())
+    }
+
+    // In salvage mode, quarantine a page whose read failed as corrupt, a checksum
+    // mismatch included
+    fn quarantine_if_salvaging(&self, page_id: PageId, error: DiskError) -> DiskError {
+        if !self.salvage || error.code() != ErrorCode::Corruption {
+            return error;
+        }
+        log::warn!("quarantining page {}: {}", page_id, error);
+        self.lock_quarantine().insert(page_id, error.to_string());
+        DiskError::PageQuarantined(page_id)
+    }
+
+    fn lock_quarantine(&self) -> std::sync::MutexGuard<'_, BTreeMap<PageId, String>> {
+        self.quarantine.lock().unwrap_or_else(|p| p.into_inner())
+    }
+
+    /// Pages quarantined as corrupt so far, in page id order (see with_salvage).
+    pub fn quarantined_pages(&self) -> Vec<QuarantinedPage> {
+        self.lock_quarantine()
+            .iter()
+            .map(|(&page_id, reason)| QuarantinedPage {
+                page_id,
+                reason: reason.clone(),
+            })
+            .collect()
+    }
+
+    /// Read every page in the main backend in page id order and hand it to `visit`. In
+    /// salvage mode quarantined pages, including ones found corrupt along the way, are
+    /// skipped with a warning; otherwise the first failed read ends the scan. Pages in
+    /// the cold tier are not visited.
+    pub async fn scan_pages<F>(&self, mut visit: F) -> Result<ScanSummary, DiskError>
+    where
+        F: FnMut(PageId, &[u8]),
+    {
+        let mut page_ids: Vec<PageId> = self.pages.read().await.keys().copied().collect();
+        page_ids.sort_unstable();
+
+        let mut summary = ScanSummary::default();
+        let mut page_data = self.page_buffers.take();
+        for page_id in page_ids {
+            match self.read_page(page_id, &mut page_data).await {
+                Ok(()) => {
+                    visit(page_id, &page_data);
+                    summary.pages_read += 1;
+                }
+                Err(DiskError::PageQuarantined(_)) if self.salvage => {
+                    log::warn!("scan: skipping quarantined page {}", page_id);
+                    summary.pages_skipped += 1;
+                }
+                // Deleted since the scan started
+                Err(DiskError::PageNotFound(_)) => {}
+                Err(e) => {
+                    self.page_buffers.give_back(page_data);
+                    return Err(e);
                 }
             }
+        }
+        self.page_buffers.give_back(page_data);
+        Ok(summary)
+    }
+
+    /// Delete a page (mark slot as free)
+    pub async fn delete_page(&self, page_id: PageId) -> Result<(), DiskError> {
+        if self.read_only {
+            return Err(DiskError::ReadOnly);
+        }
+        let mut pages = self.pages.write().await;
+        
+        if let Some(offset) = pages.remove(&page_id) {
+            if let Err(e) = self.persist_directory(&[DirectoryDelta::Unmap { page_id }], &pages).await {
+                pages.insert(page_id, offset);
+                return Err(e);
+            }
+            drop(pages); // Release write lock before acquiring next lock
+            self.lock_quarantine().remove(&page_id);
+            
+            self.free_space.write().await.free(offset);
+
+            let mut stats = self.stats.write().await;
+            stats.num_deletes += 1;
+
+            Ok(())
+        } else {
+            drop(pages);
+            let Some(cold) = &self.cold_tier else {
+                return Err(DiskError::PageNotFound(page_id));
+            };
+            if !cold.lock_pages().await.remove(page_id) {
+                return Err(DiskError::PageNotFound(page_id));
+            }
+            cold.forget(page_id);
+            self.stats.write().await.num_deletes += 1;
+            Ok(())
+        }
+    }
+
+    /// Move pages that the cold tier's `policy` considers cold out of the main backend.
+    /// Their slots become free for new pages, and compact() can shrink the file. Blocks
+    /// all other page/log I/O while it runs. Returns the number of pages moved, which is
+    /// always 0 without a cold tier.
+    pub async fn migrate_cold(&self, policy: &TieringPolicy) -> Result<usize, DiskError> {
+        let Some(cold) = &self.cold_tier else {
+            return Ok(0);
         };
+        if self.read_only {
+            return Err(DiskError::ReadOnly);
+        }
+        let _resize = self.io_resize.lock().await;
+        let limit = self.io_concurrency.load(Ordering::SeqCst);
+        let permits = u32::try_from(limit).expect("I/O concurrency fits in u32");
+        let _all_io = self.io_semaphore.acquire_many(permits).await?;
 
-        // Now perform I/O safely
-        let mut file = OpenOptions::new()
-            .write(true)
-            .open(&self.db_file_path)
-            .await
-            .map_err(DiskError::IoError)?;
+        let mut cold_pages = cold.lock_pages().await;
+        let mut pages = self.pages.write().await;
+        let mut free_space = self.free_space.write().await;
 
-        file.seek(std::io::SeekFrom::Start(offset))
-            .await
-            .map_err(DiskError::IoError)?;
-        file.write_all(page_data)
-            .await
-            .map_err(DiskError::IoError)?;
-        file.sync_all()
-            .await
-            .map_err(DiskError::IoError)?;
+        // Copy everything first and commit the new locations only once the copies are durable
+        let mut moved = Vec::new();
+        let mut page_data = self.page_buffers.take();
+        let mut result = Ok(());
+        for page_id in cold.coldest(pages.keys().copied(), policy) {
+            let offset = pages[&page_id];
+            let cold_offset = cold_pages.allocate();
+            result = async {
+                self.db_backend.read_at(offset, &mut page_data).await?;
+                cold.backend.write_at(cold_offset, &page_data).await
+            }
+            .await;
+            moved.push((page_id, offset, cold_offset));
+            if result.is_err() {
+                break;
+            }
+        }
+        self.page_buffers.give_back(page_data);
+        if result.is_ok() && !moved.is_empty() {
+            result = cold.backend.sync().await;
+        }
+        if let Err(e) = result {
+            for (_, _, cold_offset) in moved {
+                cold_pages.release(cold_offset);
+            }
+            return Err(e);
+        }
+
+        for &(page_id, _, _) in &moved {
+            pages.remove(&page_id);
+        }
+        let unmapped: Vec<DirectoryDelta> = moved
+            .iter()
+            .map(|&(page_id, _, _)| DirectoryDelta::Unmap { page_id })
+            .collect();
+        if let Err(e) = self.persist_directory(&unmapped, &pages).await {
+            for (page_id, offset, cold_offset) in moved {
+                pages.insert(page_id, offset);
+                cold_pages.release(cold_offset);
+            }
+            return Err(e);
+        }
+
+        let mut stats = self.stats.write().await;
+        for &(page_id, offset, cold_offset) in &moved {
+            free_space.free(offset);
+            cold_pages.offsets.insert(page_id, cold_offset);
+            cold.record_demoted();
+            stats.io.record_read(IoSource::Tiering, GRIMOIRE_PAGE_SIZE);
+            stats.io.record_write(IoSource::Tiering, GRIMOIRE_PAGE_SIZE);
+        }
+        if !moved.is_empty() {
+            log::info!("moved {} cold page(s) to the cold tier", moved.len());
+        }
+        Ok(moved.len())
+    }
+
+    /// How often scratch buffers (cold-tier copies, compaction moves, double-write
+    /// slots) were allocated versus reused.
+    pub fn buffer_stats(&self) -> BufferStats {
+        let page_buffers = self.page_buffers.stats();
+        match &self.double_write {
+            Some(double_write) => page_buffers.combine(double_write.buffer_stats()),
+            None => page_buffers,
+        }
+    }
+
+    /// Pages in each tier and the traffic between them, or None without a cold tier.
+    pub async fn tier_stats(&self) -> Option<TierStats> {
+        let cold = self.cold_tier.as_ref()?;
+        let cold_pages = cold.lock_pages().await.offsets.len();
+        let hot_pages = self.pages.read().await.len();
+        Some(TierStats {
+            hot_pages,
+            cold_pages,
+            ..cold.stats()
+        })
+    }
+
+    /// Copy a cold page back into the main backend and return its new offset.
+    async fn promote(&self, page_id: PageId) -> Result<u64, DiskError> {
+        let Some(cold) = &self.cold_tier else {
+            return Err(DiskError::PageNotFound(page_id));
+        };
+        let mut cold_pages = cold.lock_pages().await;
+        // Another reader may have promoted it while this one waited
+        if let Some(&offset) = self.pages.read().await.get(&page_id) {
+            return Ok(offset);
+        }
+        let cold_offset = *cold_pages
+            .offsets
+            .get(&page_id)
+            .ok_or(DiskError::PageNotFound(page_id))?;
+
+        let mut page_data = self.page_buffers.take();
+        let read = cold.backend.read_at(cold_offset, &mut page_data).await;
+        if let Err(e) = read {
+            self.page_buffers.give_back(page_data);
+            return Err(e);
+        }
+        let offset = match self.allocate_page(page_id, None).await {
+            Ok(offset) => offset,
+            Err(e) => {
+                self.page_buffers.give_back(page_data);
+                return Err(e);
+            }
+        };
+        let written = async {
+            self.record_checksum(offset, &page_data, self.durability).await?;
+            self.db_backend.write_at(offset, &page_data).await?;
+            self.sync_backend(self.db_backend.as_ref(), offset, GRIMOIRE_PAGE_SIZE as u64)
+                .await
+        }
+        .await;
+        self.page_buffers.give_back(page_data);
+        if let Err(e) = written {
+            // Leave the page where it was
+            let mut pages = self.pages.write().await;
+            pages.remove(&page_id);
+            if let Err(unmap_err) = self.persist_directory(&[DirectoryDelta::Unmap { page_id }], &pages).await {
+                log::warn!("page {} stays mapped to an unwritten slot: {}", page_id, unmap_err);
+                return Err(e);
+            }
+            drop(pages);
+            self.free_space.write().await.free(offset);
+            return Err(e);
+        }
+        cold_pages.remove(page_id);
+        cold.record_promoted();
+
+        let mut stats = self.stats.write().await;
+        stats.io.record_read(IoSource::Tiering, GRIMOIRE_PAGE_SIZE);
+        stats.io.record_write(IoSource::Tiering, GRIMOIRE_PAGE_SIZE);
+        Ok(offset)
+    }
+
+    /// Append one record to the log asynchronously, framed so read_log can find it again
+    pub async fn write_log(&self, log_data: &[u8]) -> Result<(), DiskError> {
+        self.write_log_batch(&[log_data], &WriteOptions::default()).await
+    }
+
+    /// Append `records` to the log in order with a single vectored write and one sync.
+    pub async fn write_log_batch(&self, records: &[&[u8]], options: &WriteOptions) -> Result<(), DiskError> {
+        if self.read_only {
+            return Err(DiskError::ReadOnly);
+        }
+        let payloads: Vec<Vec<u8>> = records.iter().map(|record| record.to_vec()).collect();
+        let frames = self
+            .cpu_pool
+            .run(move || payloads.iter().map(|payload| encode_frame(payload)).collect::<Vec<_>>())
+            .await?;
+        let len: u64 = frames.iter().map(|frame| frame.len() as u64).sum();
+
+        let mut log_end = self.log_end.lock().await;
+        let offset = *log_end;
+        if self.log_preallocation > 0 {
+            let size = self.log_backend.size().await?;
+            if size < offset + len {
+                let target = (offset + len).div_ceil(self.log_preallocation) * self.log_preallocation;
+                self.ensure_space(self.log_backend.as_ref(), target - size).await?;
+                self.log_backend.preallocate(target).await?;
+            } else {
+                self.ensure_space(self.log_backend.as_ref(), 0).await?;
+            }
+        } else {
+            self.ensure_space(self.log_backend.as_ref(), len).await?;
+        }
+        self.log_backend.write_vectored_at(offset, frames).await?;
+        let started = self.clock.now();
+        let durability = options.effective_durability(self.durability);
+        let flushed = Self::sync_backend_as(durability, self.log_backend.as_ref(), offset, len).await?;
+        if flushed {
+            let elapsed = self.clock.now().duration_since(started);
+            self.latencies.wal_fsync.record(elapsed);
+            if let Some(slow_log) = &self.slow_log {
+                slow_log.wal_fsync(len as usize, elapsed);
+            }
+        }
+        *log_end = offset + len;
+        drop(log_end);
 
         let mut stats = self.stats.write().await;
-        stats.num_writes += 1;
+        stats.num_flushes += flushed as u64;
+        stats.io.record_write(IoSource::Wal, len as usize);
 
         Ok(())
     }
 
+    /// Every intact record in the log, oldest first
+    pub async fn read_log(&self) -> Result<Vec<Vec<u8>>, DiskError> {
+        let scan = scan_log(self.log_backend.as_ref()).await?;
+        let mut stats = self.stats.write().await;
+        stats.io.record_read(IoSource::Wal, scan.valid_len as usize);
+        Ok(scan.records)
+    }
 
-    /// Read a page from disk asynchronously
-    pub async fn read_page(&self, page_id: PageId, page_data: &mut [u8]) -> Result<(), DiskError> {
-        if page_data.len() != GRIMOIRE_PAGE_SIZE {
-            panic!("page_data must be exactly {} bytes", GRIMOIRE_PAGE_SIZE);
-        }
+    /// Move the pages at the end of the file into the slots freed by deleted pages, then
+    /// shrink the file. Blocks all other page/log I/O while it runs. Returns the number
+    /// of pages moved.
+    pub async fn compact(&self) -> Result<usize, DiskError> {
+        self.compact_with(&CancellationToken::new(), &ProgressTracker::new()).await
+    }
 
-        let _permit = self.io_semaphore.acquire().await.unwrap();
+    /// compact(), reporting each moved page to `progress`. Cancelling `cancel` stops it
+    /// between pages with DiskError::Cancelled; pages moved so far stay moved, and the
+    /// file keeps its size.
+    pub async fn compact_with(
+        &self,
+        cancel: &CancellationToken,
+        progress: &ProgressTracker,
+    ) -> Result<usize, DiskError> {
+        if self.read_only {
+            return Err(DiskError::ReadOnly);
+        }
+        let _resize = self.io_resize.lock().await;
+        let limit = self.io_concurrency.load(Ordering::SeqCst);
+        let permits = u32::try_from(limit).expect("I/O concurrency fits in u32");
+        let _all_io = self.io_semaphore.acquire_many(permits).await?;
 
-        // Get offset
-        let offset = {
-            let pages = self.pages.read().await;
-            pages
-                .get(&page_id)
-                .copied()
-                .ok_or(DiskError::PageNotFound(page_id))?
-        };
+        let mut pages = self.pages.write().await;
+        let mut free_space = self.free_space.write().await;
+        let mut capacity = self.page_capacity.write().await;
 
-        // Open file and read
-        let mut file = File::open(&self.db_file_path)
-            .await
-            .map_err(DiskError::IoError)?;
+        // Lowest free slots first, filled from the highest occupied offsets. Slots
+        // reserved in open extents are filled too; segments start new extents after this.
+        let mut free_slots = free_space.take_all_free();
+        free_slots.reverse();
+        let mut by_offset: Vec<(u64, PageId)> = pages.iter().map(|(&id, &offset)| (offset, id)).collect();
+        by_offset.sort_unstable();
+        let planned = free_slots
+            .iter()
+            .rev()
+            .zip(by_offset.iter().rev())
+            .take_while(|&(&slot, &(offset, _))| slot < offset)
+            .count();
+        progress.set_total(planned as u64);
 
-        file.seek(std::io::SeekFrom::Start(offset))
-            .await
-            .map_err(DiskError::IoError)?;
-        
-        file.read_exact(page_data)
-            .await
-            .map_err(DiskError::IoError)?;
+        let mut vacated = Vec::new();
+        let mut page_data = self.page_buffers.take();
+        while let (Some(&slot), Some(&(offset, page_id))) = (free_slots.last(), by_offset.last()) {
+            if slot > offset {
+                break;
+            }
+            let result = if cancel.is_cancelled() {
+                Err(DiskError::Cancelled)
+            } else {
+                self.move_page(offset, slot, &mut page_data).await
+            };
+            // The old copy must stay mapped on disk until the new location is recorded
+            let result = match result {
+                Ok(()) => {
+                    pages.insert(page_id, slot);
+                    let persisted = self
+                        .persist_directory(&[DirectoryDelta::Map { page_id, offset: slot }], &pages)
+                        .await;
+                    if persisted.is_err() {
+                        pages.insert(page_id, offset);
+                    }
+                    persisted
+                }
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                // Stop with the file as it is; the slots emptied so far are free now
+                free_space.rebuild(pages.values().copied());
+                self.page_buffers.give_back(page_data);
+                return Err(e);
+            }
+            free_slots.pop();
+            by_offset.pop();
+            vacated.push(offset);
+            progress.advance(1);
+        }
 
-        // Update stats
-        let mut stats = self.stats.write().await;
-        stats.num_reads += 1;
+        self.page_buffers.give_back(page_data);
 
-        Ok(())
+        // Every occupied slot is now below pages.len(), so the rest of the file is free
+        free_space.rebuild(pages.values().copied());
+        if let Some(directory) = &self.page_directory {
+            directory.compact(&pages).await?;
+        }
+        *capacity = pages.len().max(INITIAL_PAGE_CAPACITY);
+        self.db_backend
+            .set_len((*capacity + 1) as u64 * GRIMOIRE_PAGE_SIZE as u64)
+            .await?;
+        Ok(vacated.len())
     }
 
-    /// Delete a page (mark slot as free)
-    pub async fn delete_page(&self, page_id: PageId) -> Result<(), DiskError> {
-        let mut pages = self.pages.write().await;
-        
-        if let Some(offset) = pages.remove(&page_id) {
-            drop(pages); // Release write lock before acquiring next lock
-            
-            let mut free_slots = self.free_slots.write().await;
-            free_slots.push(offset);
-            drop(free_slots);
+    // Copy the page at `from` to `to` and make the copy durable
+    async fn move_page(&self, from: u64, to: u64, page_data: &mut [u8]) -> Result<(), DiskError> {
+        self.db_backend.read_at(from, page_data).await?;
+        self.record_checksum(to, page_data, self.durability).await?;
+        self.db_backend.write_at(to, page_data).await?;
+        self.sync_backend(self.db_backend.as_ref(), to, GRIMOIRE_PAGE_SIZE as u64).await?;
 
-            let mut stats = self.stats.write().await;
-            stats.num_deletes += 1;
+        let mut stats = self.stats.write().await;
+        stats.io.record_read(IoSource::Compaction, GRIMOIRE_PAGE_SIZE);
+        stats.io.record_write(IoSource::Compaction, GRIMOIRE_PAGE_SIZE);
+        Ok(())
+    }
 
-            Ok(())
-        } else {
-            Err(DiskError::PageNotFound(page_id))
+    /// Place `page_id` in `segment`'s current extent, so the segment's pages sit in
+    /// contiguous runs. Call before the page is first written; a page that is already
+    /// placed keeps its slot.
+    pub async fn allocate_page_in(&self, segment: SegmentId, page_id: PageId) -> Result<(), DiskError> {
+        if self.read_only {
+            return Err(DiskError::ReadOnly);
         }
+        self.allocate_page(page_id, Some(segment)).await.map(|_| ())
     }
 
-    /// Write log data asynchronously
-    pub async fn write_log(&self, log_data: &[u8]) -> Result<(), DiskError> {
-        let mut file = OpenOptions::new()
-            .append(true)
-            .open(&self.log_file_path)
-            .await
-            .map_err(DiskError::IoError)?;
-
-        file.write_all(log_data)
-            .await
-            .map_err(DiskError::IoError)?;
-        
-        file.sync_all()
-            .await
-            .map_err(DiskError::IoError)?;
-
-        Ok(())
+    /// Free single slots and extents, and slots reserved by segments.
+    pub async fn free_space_stats(&self) -> FreeSpaceStats {
+        self.free_space.read().await.stats()
     }
 
-    /// Allocate a new page offset
-    async fn allocate_page(&self, page_id:PageId) -> u64 {
-        // Check free slots first
-        {
-            let mut free_slots = self.free_slots.write().await;
-            if let Some(offset) = free_slots.pop() {
-                let mut pages = self.pages.write().await;
-                pages.insert(page_id, offset);
-                return offset;
-            }
+    /// Allocate a new page offset, or return the existing one if the page is already mapped.
+    /// Pages in a segment come from its extent, others from single free slots.
+    /// Locks are always taken in the order pages -> free_space -> page_capacity.
+    async fn allocate_page(&self, page_id:PageId, segment: Option<SegmentId>) -> Result<u64, DiskError> {
+        let mut pages = self.pages.write().await;
+        if let Some(&offset) = pages.get(&page_id) {
+            return Ok(offset);
         }
 
-        // Need to allocate new page
-        let mut pages = self.pages.write().await;
-        let mut capacity = self.page_capacity.write().await;
+        let mut free_space = self.free_space.write().await;
+        let offset = match segment {
+            Some(segment) => free_space.allocate_in(segment),
+            None => free_space.allocate(),
+        };
 
-        // Check if expansion needed
-        if pages.len() >= *capacity {
-            let new_capacity = *capacity * 2;
-            *capacity = new_capacity;
+        // Check if expansion needed; a new extent may reach well past the last page
+        let mut capacity = self.page_capacity.write().await;
+        let needed = free_space.end_pages() as usize;
+        if needed > *capacity {
+            let mut grown = *capacity;
+            while grown < needed {
+                grown *= 2;
+            }
+            let page_size = GRIMOIRE_PAGE_SIZE as u64;
+            let old_size = (*capacity + 1) as u64 * page_size;
+            let mut new_size = (grown + 1) as u64 * page_size;
+            if self.data_preallocation > 0 {
+                let chunk = self.data_preallocation.div_ceil(page_size) * page_size;
+                new_size = new_size.max(old_size + chunk);
+            }
 
-            // Expand file (do this after releasing locks would be better,
-            // but for simplicity we keep it here)
-            let new_size = (new_capacity + 1) as u64 * GRIMOIRE_PAGE_SIZE as u64;
-            
-            // Open temporarily to resize
-            if let Ok(file) = OpenOptions::new()
-                .write(true)
-                .open(&self.db_file_path)
-                .await
-            {
-                let _ = file.set_len(new_size).await;
+            // A file that cannot grow leaves the page unallocated rather than pointing
+            // past the end of the file
+            let grow = async {
+                self.ensure_space(self.db_backend.as_ref(), new_size - old_size).await?;
+                if self.data_preallocation > 0 {
+                    self.db_backend.preallocate(new_size).await
+                } else {
+                    self.db_backend.set_len(new_size).await
+                }
+            };
+            if let Err(e) = grow.await {
+                free_space.free(offset);
+                return Err(e);
             }
+            *capacity = (new_size / page_size) as usize - 1;
         }
+        drop(capacity);
 
-        // Calculate new offset
-        let offset = pages.len() as u64 * GRIMOIRE_PAGE_SIZE as u64;
         pages.insert(page_id, offset);
+        if let Err(e) = self.persist_directory(&[DirectoryDelta::Map { page_id, offset }], &pages).await {
+            pages.remove(&page_id);
+            free_space.free(offset);
+            return Err(e);
+        }
+        self.next_page_id.fetch_max(page_id.saturating_add(1), Ordering::SeqCst);
 
-        offset
+        Ok(offset)
     }
 
     // Statistics methods
@@ -275,14 +1405,40 @@ impl DiskManager {
     pub async fn get_num_deletes(&self) -> u64 {
         self.stats.read().await.num_deletes
     }
+
+    pub async fn get_num_flushes(&self) -> u64 {
+        self.stats.read().await.num_flushes
+    }
+
+    /// Pages repaired from the repair source (see with_page_repair)
+    pub async fn get_num_repairs(&self) -> u64 {
+        self.stats.read().await.num_repairs
+    }
+
+    /// Bytes read/written so far, broken down by IoSource
+    pub async fn io_breakdown(&self) -> IoBreakdown {
+        self.stats.read().await.io.clone()
+    }
+
+    pub fn latency_stats(&self) -> DiskLatencyStats {
+        DiskLatencyStats {
+            page_read: self.latencies.page_read.summary(),
+            page_write: self.latencies.page_write.summary(),
+            wal_fsync: self.latencies.wal_fsync.summary(),
+            io_queue_wait: self.latencies.io_queue_wait.summary(),
+        }
+    }
 }
 
 // Example usage and tests
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::backend::storage::log_frame::LOG_FRAME_HEADER_SIZE;
+    #[cfg(feature = "native")]
     use tempfile::tempdir;
 
+    #[cfg(feature = "native")]
     #[tokio::test]
     async fn test_write_and_read_page() {
         let dir = tempdir().unwrap();
@@ -305,6 +1461,7 @@ mod tests {
         assert_eq!(dm.get_num_reads().await, 1);
     }
 
+    #[cfg(feature = "native")]
     #[tokio::test]
     async fn test_concurrent_writes() {
         let dir = tempdir().unwrap();
@@ -333,6 +1490,7 @@ mod tests {
         assert_eq!(dm.get_num_writes().await, 100);
     }
 
+    #[cfg(feature = "native")]
     #[tokio::test]
     async fn test_delete_and_reuse() {
         let dir = tempdir().unwrap();
@@ -352,4 +1510,665 @@ mod tests {
 
         assert_eq!(dm.get_num_deletes().await, 1);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_in_memory_manager() {
+        let dm = DiskManager::in_memory().await.unwrap();
+
+        let page_data = vec![9u8; GRIMOIRE_PAGE_SIZE];
+        dm.write_page(5, &page_data).await.unwrap();
+        dm.write_log(b"in-memory log").await.unwrap();
+
+        let mut read_buf = vec![0u8; GRIMOIRE_PAGE_SIZE];
+        dm.read_page(5, &mut read_buf).await.unwrap();
+        assert_eq!(read_buf, page_data);
+    }
+
+    #[cfg(feature = "native")]
+    #[tokio::test]
+    async fn test_durability_modes() {
+        let dir = tempdir().unwrap();
+        let page_data = vec![3u8; GRIMOIRE_PAGE_SIZE];
+
+        for (i, mode) in [
+            DurabilityMode::SyncAll,
+            DurabilityMode::DataSync,
+            DurabilityMode::RangeSync,
+            DurabilityMode::None,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let db_path = dir.path().join(format!("durability_{}.db", i));
+            let dm = DiskManager::new(&db_path).await.unwrap().with_durability(mode);
+            assert_eq!(dm.durability(), mode);
+
+            dm.write_page(1, &page_data).await.unwrap();
+            dm.write_log(b"record").await.unwrap();
+
+            let mut read_buf = vec![0u8; GRIMOIRE_PAGE_SIZE];
+            dm.read_page(1, &mut read_buf).await.unwrap();
+            assert_eq!(read_buf, page_data);
+
+            let expected_flushes = if mode == DurabilityMode::None { 0 } else { 2 };
+            assert_eq!(dm.get_num_flushes().await, expected_flushes);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_write_options_override_durability() {
+        let dm = DiskManager::in_memory().await.unwrap().with_durability(DurabilityMode::None);
+        let page_data = vec![5u8; GRIMOIRE_PAGE_SIZE];
+        let synced = WriteOptions {
+            durability: Some(DurabilityMode::DataSync),
+            ..WriteOptions::default()
+        };
+        dm.write_page_with_options(1, &page_data, &synced).await.unwrap();
+        dm.write_log_batch(&[b"a", b"b"], &synced).await.unwrap();
+        assert_eq!(dm.get_num_flushes().await, 2);
+
+        let dm = DiskManager::in_memory().await.unwrap();
+        let unsynced = WriteOptions { sync: false, ..WriteOptions::default() };
+        dm.write_page_with_options(1, &page_data, &unsynced).await.unwrap();
+        dm.write_log_batch(&[b"a"], &unsynced).await.unwrap();
+        assert_eq!(dm.get_num_flushes().await, 0);
+        assert_eq!(dm.read_log().await.unwrap(), vec![b"a".to_vec()]);
+    }
+
+    #[tokio::test]
+    async fn test_double_write_restores_torn_page() {
+        let db: Arc<dyn StorageBackend> = Arc::new(MemoryBackend::new());
+        let log: Arc<dyn StorageBackend> = Arc::new(MemoryBackend::new());
+        let dwb: Arc<dyn StorageBackend> = Arc::new(MemoryBackend::new());
+
+        let dm = DiskManager::with_backends(db.clone(), log.clone())
+            .await
+            .unwrap()
+            .with_double_write(dwb.clone())
+            .await
+            .unwrap();
+        dm.write_page(1, &vec![0x11u8; GRIMOIRE_PAGE_SIZE]).await.unwrap();
+        drop(dm);
+
+        // Simulate a crash during the next write of the page: its copy is in a slot, and
+        // the home copy was torn halfway through
+        let page_data = vec![0xABu8; GRIMOIRE_PAGE_SIZE];
+        let buffer = DoubleWriteBuffer::open(dwb.clone(), DEFAULT_DOUBLE_WRITE_SLOTS).await.unwrap();
+        let slot = buffer.acquire().await.unwrap();
+        buffer.write(&slot, 1, 0, &page_data, &CpuPool::default()).await.unwrap();
+        db.write_at(0, &page_data[..GRIMOIRE_PAGE_SIZE / 2]).await.unwrap();
+
+        let _dm = DiskManager::with_backends(db.clone(), log)
+            .await
+            .unwrap()
+            .with_double_write(dwb)
+            .await
+            .unwrap();
+        let mut home = vec![0u8; GRIMOIRE_PAGE_SIZE];
+        db.read_at(0, &mut home).await.unwrap();
+        assert_eq!(home, page_data);
+    }
+
+    #[tokio::test]
+    async fn test_clean_reopen_keeps_unsynced_newer_write() {
+        let db: Arc<dyn StorageBackend> = Arc::new(MemoryBackend::new());
+        let log: Arc<dyn StorageBackend> = Arc::new(MemoryBackend::new());
+        let dwb: Arc<dyn StorageBackend> = Arc::new(MemoryBackend::new());
+
+        let dm = DiskManager::with_backends(db.clone(), log.clone())
+            .await
+            .unwrap()
+            .with_double_write(dwb.clone())
+            .await
+            .unwrap();
+        dm.write_page(1, &vec![0x01u8; GRIMOIRE_PAGE_SIZE]).await.unwrap();
+        // Skips the double-write buffer
+        let unsynced = WriteOptions { sync: false, ..WriteOptions::default() };
+        dm.write_page_with_options(1, &vec![0x02u8; GRIMOIRE_PAGE_SIZE], &unsynced).await.unwrap();
+        drop(dm);
+
+        let _dm = DiskManager::with_backends(db.clone(), log)
+            .await
+            .unwrap()
+            .with_double_write(dwb)
+            .await
+            .unwrap();
+        let mut home = vec![0u8; GRIMOIRE_PAGE_SIZE];
+        db.read_at(0, &mut home).await.unwrap();
+        assert_eq!(home, vec![0x02u8; GRIMOIRE_PAGE_SIZE]);
+    }
+
+    #[tokio::test]
+    async fn test_double_write_skips_torn_slot() {
+        let db: Arc<dyn StorageBackend> = Arc::new(MemoryBackend::new());
+        let log: Arc<dyn StorageBackend> = Arc::new(MemoryBackend::new());
+        let dwb: Arc<dyn StorageBackend> = Arc::new(MemoryBackend::new());
+
+        let dm = DiskManager::with_backends(db.clone(), log.clone())
+            .await
+            .unwrap()
+            .with_double_write(dwb.clone())
+            .await
+            .unwrap();
+        dm.write_page(1, &vec![0x11u8; GRIMOIRE_PAGE_SIZE]).await.unwrap();
+        drop(dm);
+
+        // A crash during the double-write itself: the slot is torn, home is the old copy
+        let buffer = DoubleWriteBuffer::open(dwb.clone(), DEFAULT_DOUBLE_WRITE_SLOTS).await.unwrap();
+        let copy = buffer.acquire().await.unwrap();
+        buffer
+            .write(&copy, 1, 0, &vec![0x33u8; GRIMOIRE_PAGE_SIZE], &CpuPool::default())
+            .await
+            .unwrap();
+        let mut slot = vec![0u8; 64];
+        dwb.read_at(0, &mut slot).await.unwrap();
+        slot[40] ^= 0xFF;
+        dwb.write_at(0, &slot).await.unwrap();
+        db.write_at(0, &[0x22u8; GRIMOIRE_PAGE_SIZE]).await.unwrap();
+
+        let _dm = DiskManager::with_backends(db.clone(), log)
+            .await
+            .unwrap()
+            .with_double_write(dwb)
+            .await
+            .unwrap();
+        let mut home = vec![0u8; GRIMOIRE_PAGE_SIZE];
+        db.read_at(0, &mut home).await.unwrap();
+        assert_eq!(home, vec![0x22u8; GRIMOIRE_PAGE_SIZE]);
+    }
+
+    #[tokio::test]
+    async fn test_io_breakdown_by_source() {
+        let dm = DiskManager::in_memory().await.unwrap();
+        let page_data = vec![1u8; GRIMOIRE_PAGE_SIZE];
+
+        dm.write_page(1, &page_data).await.unwrap();
+        dm.write_page_for(IoSource::Checkpoint, 2, &page_data).await.unwrap();
+        dm.write_log(b"0123456789").await.unwrap();
+        let mut read_buf = vec![0u8; GRIMOIRE_PAGE_SIZE];
+        dm.read_page_for(IoSource::Compaction, 1, &mut read_buf).await.unwrap();
+
+        let io = dm.io_breakdown().await;
+        assert_eq!(io.bytes_written(IoSource::DataPage), GRIMOIRE_PAGE_SIZE as u64);
+        assert_eq!(io.bytes_written(IoSource::Checkpoint), GRIMOIRE_PAGE_SIZE as u64);
+        assert_eq!(io.bytes_written(IoSource::Wal), (10 + LOG_FRAME_HEADER_SIZE) as u64);
+        assert_eq!(io.bytes_read(IoSource::Compaction), GRIMOIRE_PAGE_SIZE as u64);
+        assert_eq!(io.bytes_read(IoSource::DataPage), 0);
+    }
+
+    #[tokio::test]
+    async fn test_low_disk_space_rejects_writes() {
+        let db = Arc::new(MemoryBackend::new());
+        let log = Arc::new(MemoryBackend::new());
+        db.set_available_space(Some(900_000));
+        log.set_available_space(Some(900_000));
+        let dm = DiskManager::with_backends(db.clone(), log.clone())
+            .await
+            .unwrap()
+            .with_data_preallocation(2 << 20)
+            .with_min_free_space(512 << 10);
+        let page = vec![1u8; GRIMOIRE_PAGE_SIZE];
+        dm.write_page(0, &page).await.unwrap();
+
+        // Growing past the initial 128 pages would eat into the reserve
+        for page_id in 1..128 {
+            dm.write_page(page_id, &page).await.unwrap();
+        }
+        let result = dm.write_page(128, &page).await;
+        assert!(matches!(result, Err(DiskError::DiskFull { available: 900_000, .. })));
+        let mut page_data = vec![0u8; GRIMOIRE_PAGE_SIZE];
+        assert!(matches!(dm.read_page(128, &mut page_data).await, Err(DiskError::PageNotFound(128))));
+        // Writes that need no new space still go through
+        dm.write_page(0, &page).await.unwrap();
+        assert!(!dm.is_low_on_space());
+
+        // Below the reserve, every write is rejected until space comes back
+        db.set_available_space(Some(100_000));
+        log.set_available_space(Some(100_000));
+        assert_eq!(dm.available_space().await.unwrap(), Some(100_000));
+        assert!(dm.is_low_on_space());
+        assert!(matches!(dm.write_page(0, &page).await, Err(DiskError::DiskFull { .. })));
+        assert!(matches!(dm.write_log(b"record").await, Err(DiskError::DiskFull { .. })));
+
+        db.set_available_space(Some(64 << 20));
+        log.set_available_space(Some(64 << 20));
+        dm.write_page(128, &page).await.unwrap();
+        assert!(!dm.is_low_on_space());
+        // Grown by the preallocation chunk rather than just doubled
+        assert_eq!(db.size().await.unwrap(), (129 * GRIMOIRE_PAGE_SIZE) as u64 + (2 << 20));
+    }
+
+    #[tokio::test]
+    async fn test_log_torn_tail_is_truncated() {
+        let db: Arc<dyn StorageBackend> = Arc::new(MemoryBackend::new());
+        let log: Arc<dyn StorageBackend> = Arc::new(MemoryBackend::new());
+
+        let dm = DiskManager::with_backends(db.clone(), log.clone()).await.unwrap();
+        dm.write_log(b"first").await.unwrap();
+        dm.write_log(b"second").await.unwrap();
+        let intact = log.size().await.unwrap();
+        drop(dm);
+
+        // Simulate a crash halfway through appending a third record
+        log.append(&encode_frame(b"third")[..6]).await.unwrap();
+
+        let dm = DiskManager::with_backends(db, log.clone()).await.unwrap();
+        assert_eq!(log.size().await.unwrap(), intact);
+        dm.write_log(b"after").await.unwrap();
+        assert_eq!(dm.read_log().await.unwrap(), vec![
+            b"first".to_vec(),
+            b"second".to_vec(),
+            b"after".to_vec(),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_preallocated_log_survives_reopen() {
+        let db: Arc<dyn StorageBackend> = Arc::new(MemoryBackend::new());
+        let log: Arc<dyn StorageBackend> = Arc::new(MemoryBackend::new());
+
+        let dm = DiskManager::with_backends(db.clone(), log.clone())
+            .await
+            .unwrap()
+            .with_log_preallocation(4096);
+        dm.write_log_batch(&[b"first", b"second"], &WriteOptions::default()).await.unwrap();
+        dm.write_log(b"third").await.unwrap();
+        let records = dm.log_size().await.unwrap();
+        assert_eq!(log.size().await.unwrap(), 4096);
+        assert_eq!(dm.io_breakdown().await.bytes_written(IoSource::Wal), records);
+        drop(dm);
+
+        // The zero tail is kept, and new records go right after the old ones
+        let dm = DiskManager::with_backends(db.clone(), log.clone()).await.unwrap();
+        assert_eq!(log.size().await.unwrap(), 4096);
+        assert_eq!(dm.log_size().await.unwrap(), records);
+        dm.write_log(b"fourth").await.unwrap();
+        assert_eq!(dm.read_log().await.unwrap().len(), 4);
+        drop(dm);
+
+        // A record torn inside the preallocated space is cut off with the rest of the tail
+        log.write_at(records + 6 + LOG_FRAME_HEADER_SIZE as u64, &encode_frame(b"torn")[..6])
+            .await
+            .unwrap();
+        let dm = DiskManager::with_backends(db, log.clone()).await.unwrap();
+        assert_eq!(log.size().await.unwrap(), records + 6 + LOG_FRAME_HEADER_SIZE as u64);
+        assert_eq!(dm.read_log().await.unwrap(), vec![
+            b"first".to_vec(),
+            b"second".to_vec(),
+            b"third".to_vec(),
+            b"fourth".to_vec(),
+        ]);
+    }
+
+    #[cfg(feature = "native")]
+    #[tokio::test]
+    async fn test_slow_log_reports_page_io_and_log_sync() {
+        use crate::common::slow_log::SlowLogThresholds;
+        use std::time::Duration;
+
+        let dir = tempdir().unwrap();
+        let slow_path = dir.path().join("diagnostics.log");
+        // A zero threshold makes every operation slow
+        let slow_log = SlowLog::to_file(
+            SlowLogThresholds {
+                page_io: Some(Duration::ZERO),
+                wal_fsync: Some(Duration::ZERO),
+                query: None,
+            },
+            &slow_path,
+        )
+        .unwrap();
+        let dm = DiskManager::new(&dir.path().join("slow.db"))
+            .await
+            .unwrap()
+            .with_slow_log(Arc::new(slow_log));
+
+        let mut page_data = vec![5u8; GRIMOIRE_PAGE_SIZE];
+        dm.write_page(3, &page_data).await.unwrap();
+        dm.read_page(3, &mut page_data).await.unwrap();
+        dm.write_log(b"record").await.unwrap();
+
+        let text = std::fs::read_to_string(&slow_path).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].contains("slow page write: page 3 took"));
+        assert!(lines[1].contains("slow page read: page 3 took"));
+        assert!(lines[2].contains("slow wal fsync: 14 bytes took"));
+    }
+
+    #[tokio::test]
+    async fn test_compact_fills_freed_slots() {
+        let dm = DiskManager::in_memory().await.unwrap();
+        for page_id in 0..200 {
+            dm.write_page(page_id, &vec![page_id as u8; GRIMOIRE_PAGE_SIZE]).await.unwrap();
+        }
+        for page_id in 0..100 {
+            dm.delete_page(page_id).await.unwrap();
+        }
+        let grown = dm.db_size().await.unwrap();
+
+        assert_eq!(dm.compact().await.unwrap(), 100);
+        assert!(dm.db_size().await.unwrap() < grown);
+        assert_eq!(dm.compact().await.unwrap(), 0);
+
+        let mut read_buf = vec![0u8; GRIMOIRE_PAGE_SIZE];
+        for page_id in 100..200 {
+            dm.read_page(page_id, &mut read_buf).await.unwrap();
+            assert_eq!(read_buf, vec![page_id as u8; GRIMOIRE_PAGE_SIZE]);
+        }
+        // New pages still get slots of their own
+        dm.write_page(500, &vec![1u8; GRIMOIRE_PAGE_SIZE]).await.unwrap();
+        dm.read_page(199, &mut read_buf).await.unwrap();
+        assert_eq!(read_buf, vec![199u8; GRIMOIRE_PAGE_SIZE]);
+        assert_eq!(
+            dm.io_breakdown().await.bytes_written(IoSource::Compaction),
+            100 * GRIMOIRE_PAGE_SIZE as u64
+        );
+    }
+
+    #[tokio::test]
+    async fn test_segments_allocate_in_extents() {
+        let dm = DiskManager::in_memory().await.unwrap().with_extent_pages(8);
+        // Two segments growing in lockstep, as two tables loaded side by side would
+        for i in 0..12 {
+            dm.allocate_page_in(1, i).await.unwrap();
+            dm.allocate_page_in(2, 100 + i).await.unwrap();
+        }
+        let slot = |offset: u64| offset / GRIMOIRE_PAGE_SIZE as u64;
+        let pages = dm.pages.read().await.clone();
+        let first: Vec<u64> = (0..12).map(|i| slot(pages[&i])).collect();
+        let second: Vec<u64> = (0..12).map(|i| slot(pages[&(100 + i)])).collect();
+        assert_eq!(first, [0, 1, 2, 3, 4, 5, 6, 7, 16, 17, 18, 19]);
+        assert_eq!(second, [8, 9, 10, 11, 12, 13, 14, 15, 24, 25, 26, 27]);
+
+        for i in 0..8 {
+            dm.write_page(i, &vec![i as u8; GRIMOIRE_PAGE_SIZE]).await.unwrap();
+        }
+        let mut read_buf = vec![0u8; GRIMOIRE_PAGE_SIZE];
+        dm.read_page(5, &mut read_buf).await.unwrap();
+        assert_eq!(read_buf, vec![5u8; GRIMOIRE_PAGE_SIZE]);
+
+        // Dropping segment 1's first extent frees it whole, and a new segment takes it
+        for i in 0..8 {
+            dm.delete_page(i).await.unwrap();
+        }
+        assert_eq!(dm.free_space_stats().await.free_extents, 1);
+        dm.allocate_page_in(3, 200).await.unwrap();
+        assert_eq!(slot(dm.pages.read().await[&200]), 0);
+
+        // Pages outside a segment do not take slots reserved by one
+        dm.write_page(300, &vec![3u8; GRIMOIRE_PAGE_SIZE]).await.unwrap();
+        assert_eq!(slot(dm.pages.read().await[&300]), 32);
+    }
+
+    #[tokio::test]
+    async fn test_page_directory_survives_reopen() {
+        let db: Arc<dyn StorageBackend> = Arc::new(MemoryBackend::new());
+        let dir0: Arc<dyn StorageBackend> = Arc::new(MemoryBackend::new());
+        let dir1: Arc<dyn StorageBackend> = Arc::new(MemoryBackend::new());
+        let open = || async {
+            DiskManager::with_backends(db.clone(), Arc::new(MemoryBackend::new()))
+                .await
+                .unwrap()
+                .with_page_directory(dir0.clone(), dir1.clone())
+                .await
+                .unwrap()
+        };
+
+        let dm = open().await;
+        for page_id in 0..10 {
+            dm.write_page(page_id, &vec![page_id as u8; GRIMOIRE_PAGE_SIZE]).await.unwrap();
+        }
+        dm.delete_page(4).await.unwrap();
+        assert_eq!(dm.directory_stats().await.unwrap().deltas, 11);
+        drop(dm);
+
+        let dm = open().await;
+        let mut read_buf = vec![0u8; GRIMOIRE_PAGE_SIZE];
+        dm.read_page(9, &mut read_buf).await.unwrap();
+        assert_eq!(read_buf, vec![9u8; GRIMOIRE_PAGE_SIZE]);
+        assert!(matches!(dm.read_page(4, &mut read_buf).await, Err(DiskError::PageNotFound(4))));
+
+        // The freed slot is found again and reused rather than growing the file
+        dm.write_page(20, &vec![20u8; GRIMOIRE_PAGE_SIZE]).await.unwrap();
+        assert_eq!(dm.pages.read().await[&20], 4 * GRIMOIRE_PAGE_SIZE as u64);
+
+        // Compacting the file snapshots the directory
+        dm.delete_page(0).await.unwrap();
+        assert_eq!(dm.compact().await.unwrap(), 1);
+        let stats = dm.directory_stats().await.unwrap();
+        assert_eq!((stats.generation, stats.deltas), (2, 0));
+        drop(dm);
+
+        let dm = open().await;
+        for page_id in (1..10).filter(|&id| id != 4).chain([20]) {
+            dm.read_page(page_id, &mut read_buf).await.unwrap();
+            assert_eq!(read_buf, vec![page_id as u8; GRIMOIRE_PAGE_SIZE]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cold_pages_migrate_and_promote() {
+        use std::time::Duration;
+
+        let cold: Arc<dyn StorageBackend> = Arc::new(MemoryBackend::new());
+        let dm = DiskManager::in_memory().await.unwrap().with_cold_tier(cold.clone());
+        for page_id in 0..4 {
+            dm.write_page(page_id, &vec![page_id as u8; GRIMOIRE_PAGE_SIZE]).await.unwrap();
+        }
+
+        // Nothing has been idle for an hour
+        let hourly = TieringPolicy {
+            cold_after: Duration::from_secs(3600),
+            max_pages_per_run: 10,
+        };
+        assert_eq!(dm.migrate_cold(&hourly).await.unwrap(), 0);
+
+        // Touch page 3 last, so it stays hot when only three pages may move
+        let mut read_buf = vec![0u8; GRIMOIRE_PAGE_SIZE];
+        dm.read_page(3, &mut read_buf).await.unwrap();
+        let eager = TieringPolicy {
+            cold_after: Duration::ZERO,
+            max_pages_per_run: 3,
+        };
+        assert_eq!(dm.migrate_cold(&eager).await.unwrap(), 3);
+        let stats = dm.tier_stats().await.unwrap();
+        assert_eq!((stats.hot_pages, stats.cold_pages, stats.pages_demoted), (1, 3, 3));
+        assert_eq!(cold.size().await.unwrap(), 3 * GRIMOIRE_PAGE_SIZE as u64);
+
+        // Reading a cold page promotes it; writing one drops the cold copy; both delete
+        dm.read_page(1, &mut read_buf).await.unwrap();
+        assert_eq!(read_buf, vec![1u8; GRIMOIRE_PAGE_SIZE]);
+        dm.write_page(2, &vec![9u8; GRIMOIRE_PAGE_SIZE]).await.unwrap();
+        dm.read_page(2, &mut read_buf).await.unwrap();
+        assert_eq!(read_buf, vec![9u8; GRIMOIRE_PAGE_SIZE]);
+        dm.delete_page(0).await.unwrap();
+        assert!(matches!(dm.delete_page(0).await, Err(DiskError::PageNotFound(0))));
+
+        let stats = dm.tier_stats().await.unwrap();
+        assert_eq!((stats.hot_pages, stats.cold_pages), (3, 0));
+        assert_eq!(stats.pages_promoted, 1);
+        assert_eq!(stats.bytes_demoted, 3 * GRIMOIRE_PAGE_SIZE as u64);
+        let io = dm.io_breakdown().await;
+        assert_eq!(io.bytes_written(IoSource::Tiering), 4 * GRIMOIRE_PAGE_SIZE as u64);
+        // Both migration runs and the promotion shared one scratch buffer
+        let buffers = dm.buffer_stats();
+        assert_eq!((buffers.allocated, buffers.reused), (1, 2));
+    }
+
+    #[tokio::test]
+    async fn test_cold_tier_follows_injected_clock() {
+        use crate::common::clock::ManualClock;
+        use std::time::Duration;
+
+        let clock = Arc::new(ManualClock::new(Duration::ZERO));
+        let dm = DiskManager::in_memory()
+            .await
+            .unwrap()
+            .with_clock(clock.clone())
+            .with_cold_tier(Arc::new(MemoryBackend::new()));
+        for page_id in 0..2 {
+            dm.write_page(page_id, &vec![page_id as u8; GRIMOIRE_PAGE_SIZE]).await.unwrap();
+        }
+
+        let hourly = TieringPolicy {
+            cold_after: Duration::from_secs(3600),
+            max_pages_per_run: 10,
+        };
+        clock.advance(Duration::from_secs(1800));
+        dm.write_page(1, &vec![1u8; GRIMOIRE_PAGE_SIZE]).await.unwrap();
+        clock.advance(Duration::from_secs(1800));
+        // Only page 0 has been idle for the full hour
+        assert_eq!(dm.migrate_cold(&hourly).await.unwrap(), 1);
+        assert_eq!(dm.tier_stats().await.unwrap().cold_pages, 1);
+    }
+
+    #[cfg(feature = "native")]
+    #[tokio::test]
+    async fn test_open_options() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("opts.db");
+
+        assert!(matches!(
+            OpenOptions::new().create_if_missing(false).open(&path).await,
+            Err(DiskError::DatabaseNotFound(_))
+        ));
+        assert!(matches!(
+            OpenOptions::new().read_only(true).open(&path).await,
+            Err(DiskError::DatabaseNotFound(_))
+        ));
+
+        let dm = OpenOptions::new().error_if_exists(true).open(&path).await.unwrap();
+        dm.write_page(1, &vec![1u8; GRIMOIRE_PAGE_SIZE]).await.unwrap();
+        drop(dm);
+
+        assert!(matches!(
+            OpenOptions::new().error_if_exists(true).open(&path).await,
+            Err(DiskError::AlreadyExists(_))
+        ));
+        OpenOptions::new().create_if_missing(false).open(&path).await.unwrap();
+
+        let ro = OpenOptions::new().read_only(true).open(&path).await.unwrap();
+        assert!(ro.is_read_only());
+        let page = vec![2u8; GRIMOIRE_PAGE_SIZE];
+        assert!(matches!(ro.write_page(1, &page).await, Err(DiskError::ReadOnly)));
+        assert!(matches!(ro.delete_page(1).await, Err(DiskError::ReadOnly)));
+        assert!(matches!(ro.write_log(b"x").await, Err(DiskError::ReadOnly)));
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_page_is_repaired_from_source() {
+        let db = Arc::new(MemoryBackend::new());
+        let dm = DiskManager::with_backends(db.clone(), Arc::new(MemoryBackend::new()))
+            .await
+            .unwrap()
+            .with_page_checksums(Arc::new(MemoryBackend::new()))
+            .await
+            .unwrap();
+        let replica = Arc::new(DiskManager::in_memory().await.unwrap());
+        for page_id in [1, 2] {
+            dm.write_page(page_id, &vec![page_id as u8; GRIMOIRE_PAGE_SIZE]).await.unwrap();
+        }
+        replica.write_page(1, &vec![1u8; GRIMOIRE_PAGE_SIZE]).await.unwrap();
+
+        // Rot one bit in each page; the reads still return a full page
+        for page_id in [1, 2] {
+            let offset = dm.pages.read().await[&page_id];
+            let mut byte = [0u8; 1];
+            db.read_at(offset + 1000, &mut byte).await.unwrap();
+            db.write_at(offset + 1000, &[byte[0] ^ 0x10]).await.unwrap();
+        }
+        let mut page = vec![0u8; GRIMOIRE_PAGE_SIZE];
+        let err = dm.read_page(1, &mut page).await.unwrap_err();
+        assert!(matches!(err, DiskError::ChecksumMismatch { page_id: 1, .. }));
+        assert_eq!(err.code(), ErrorCode::Corruption);
+
+        let dm = dm.with_page_repair(replica);
+        dm.read_page(1, &mut page).await.unwrap();
+        assert_eq!(page, vec![1u8; GRIMOIRE_PAGE_SIZE]);
+        assert_eq!(dm.get_num_repairs().await, 1);
+        // Written back: the next read needs no repair
+        dm.read_page(1, &mut page).await.unwrap();
+        assert_eq!(dm.get_num_repairs().await, 1);
+
+        // The replica has no page 2, and a missing page is not corruption
+        assert_eq!(dm.read_page(2, &mut page).await.unwrap_err().code(), ErrorCode::Corruption);
+        assert!(matches!(dm.read_page(9, &mut page).await, Err(DiskError::PageNotFound(9))));
+        assert_eq!(dm.get_num_repairs().await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_salvage_quarantines_corrupt_pages() {
+        let db = Arc::new(MemoryBackend::new());
+        let dm = DiskManager::with_backends(db.clone(), Arc::new(MemoryBackend::new()))
+            .await
+            .unwrap()
+            .with_page_checksums(Arc::new(MemoryBackend::new()))
+            .await
+            .unwrap()
+            .with_salvage(true);
+        for page_id in 1..=4 {
+            dm.write_page(page_id, &vec![page_id as u8; GRIMOIRE_PAGE_SIZE]).await.unwrap();
+        }
+        // Rot a bit in page 2, and cut the file off in the middle of page 4
+        let offset = dm.pages.read().await[&2];
+        db.write_at(offset + 10, &[2 ^ 0x01]).await.unwrap();
+        let offset = dm.pages.read().await[&4];
+        db.set_len(offset + 100).await.unwrap();
+
+        let mut visited = Vec::new();
+        let summary = dm.scan_pages(|page_id, data| visited.push((page_id, data[0]))).await.unwrap();
+        assert_eq!(summary, ScanSummary { pages_read: 2, pages_skipped: 2 });
+        assert_eq!(visited, vec![(1, 1), (3, 3)]);
+        let quarantined = dm.quarantined_pages();
+        let page_ids: Vec<PageId> = quarantined.iter().map(|q| q.page_id).collect();
+        assert_eq!(page_ids, vec![2, 4]);
+        assert!(quarantined[0].reason.contains("checksum"), "{}", quarantined[0].reason);
+
+        let mut page = vec![0u8; GRIMOIRE_PAGE_SIZE];
+        assert!(matches!(dm.read_page(2, &mut page).await, Err(DiskError::PageQuarantined(2))));
+        // Rewriting a page lifts its quarantine
+        dm.write_page(2, &vec![9u8; GRIMOIRE_PAGE_SIZE]).await.unwrap();
+        dm.read_page(2, &mut page).await.unwrap();
+        assert_eq!(page[0], 9);
+        assert_eq!(dm.quarantined_pages().len(), 1);
+    }
+
+    #[cfg(feature = "native")]
+    #[tokio::test]
+    async fn test_second_writer_is_locked_out() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("locked.db");
+
+        let dm = DiskManager::new(&path).await.unwrap();
+        assert!(matches!(DiskManager::new(&path).await, Err(DiskError::DatabaseLocked(_))));
+        // Readers take no lock
+        OpenOptions::new().read_only(true).open(&path).await.unwrap();
+        drop(dm);
+        DiskManager::new(&path).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_io_concurrency_stays_positive_and_readable_while_shrinking() {
+        let dm = Arc::new(DiskManager::in_memory().await.unwrap());
+        dm.set_io_concurrency(0).await;
+        assert_eq!(dm.io_concurrency().await, 1);
+        dm.write_page(1, &vec![1u8; GRIMOIRE_PAGE_SIZE]).await.unwrap();
+
+        // With both slots held, shrinking waits, but the limit and status read straight away
+        dm.set_io_concurrency(2).await;
+        let held = dm.io_semaphore.acquire_many(2).await.unwrap();
+        let shrink = tokio::spawn({
+            let dm = Arc::clone(&dm);
+            async move { dm.set_io_concurrency(1).await }
+        });
+        while dm.io_concurrency().await != 1 {
+            tokio::task::yield_now().await;
+        }
+        assert!(!shrink.is_finished());
+        drop(held);
+        shrink.await.unwrap();
+        assert_eq!(dm.io_semaphore.available_permits(), 1);
+        assert_eq!(dm.io_in_flight().await, 0);
+    }
+}
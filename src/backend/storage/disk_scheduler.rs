@@ -13,17 +13,18 @@
 
 use std::{
     collections::VecDeque,
-    path::{Path},
     sync::Arc,
 };
+#[cfg(feature = "native")]
+use std::time::Duration;
 
 use tokio::{
     sync::{RwLock, Semaphore, oneshot},
 };
 
-use tokio::time::sleep;
-
-use crate::common::{errors::DiskError, types::PageId};
+use crate::common::{cancellation::CancellationToken, errors::DiskError, types::PageId};
+#[cfg(feature = "native")]
+use crate::common::supervisor::spawn_supervised;
 use crate::backend::storage::disk_manager::DiskManager;
 
 /// A request to read or write a page from disk.
@@ -32,6 +33,9 @@ pub struct DiskRequest {
     pub data: Vec<u8>,
     pub page_id: PageId,
     pub callback: oneshot::Sender<Result<Vec<u8>, DiskError>>,
+    /// If cancelled before the I/O starts, the request completes with DiskError::Cancelled.
+    /// I/O already in flight is never interrupted.
+    pub cancel: Option<CancellationToken>,
 }
 
 /// The DiskScheduler queues DiskRequests and executes them in order.
@@ -56,26 +60,38 @@ impl DiskScheduler {
         queue.push_back(req);
     }
 
-    /// Worker loop (background thread).
+    /// Worker loop (background thread). The loop is supervised: if it panics it is logged
+    /// and restarted rather than leaving the queue unserved.
+    /// Without threads (no `native` feature), drive the queue by calling schedule instead.
+    #[cfg(feature = "native")]
     pub fn start_worker_thread(self: Arc<Self>, thread_num: usize, count_load: usize) {
         std::thread::spawn(move || {
-            let runtime = tokio::runtime::Builder::new_multi_thread()
+            let runtime = match tokio::runtime::Builder::new_multi_thread()
                 .worker_threads(thread_num)
                 .enable_all()
                 .build()
-                .expect("Failed to build Tokio runtime");
-
-            runtime.block_on(async move {
-                loop {
-                    // Schedule a batch of work
-                    if let Err(e) = self.schedule(count_load).await {
-                        eprintln!("DiskScheduler error: {:?}", e);
+            {
+                Ok(runtime) => runtime,
+                Err(e) => {
+                    log::error!("DiskScheduler cannot start its runtime: {}", e);
+                    return;
+                }
+            };
+
+            let _ = runtime.block_on(spawn_supervised("disk scheduler", Duration::from_millis(100), move || {
+                let scheduler = Arc::clone(&self);
+                async move {
+                    loop {
+                        // Schedule a batch of work
+                        if let Err(e) = scheduler.schedule(count_load).await {
+                            log::error!("DiskScheduler error: {}", e);
+                        }
+
+                        // Small delay to avoid busy looping if queue is empty
+                        tokio::time::sleep(Duration::from_millis(50)).await;
                     }
-
-                    // Small delay to avoid busy looping if queue is empty
-                    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
                 }
-            });
+            }));
         });
     }
 
@@ -86,38 +102,75 @@ impl DiskScheduler {
         let reqs: Vec<DiskRequest> = queue.drain(0..count).collect();
         drop(queue);
 
+        // Group requests by page so that requests for the same page run in
+        // arrival order (a read queued after a write must observe it), while
+        // different pages are still processed concurrently.
+        let mut per_page: Vec<(PageId, Vec<DiskRequest>)> = Vec::new();
+        for req in reqs {
+            match per_page.iter_mut().find(|(page_id, _)| *page_id == req.page_id) {
+                Some((_, group)) => group.push(req),
+                None => per_page.push((req.page_id, vec![req])),
+            }
+        }
+
         // Spawn tasks concurrently with semaphore limiting concurrent I/O
         let mut handles = vec![];
 
-        for mut req in reqs {
+        for (_, group) in per_page {
             let manager = self.manager.clone();
             let semaphore = self.io_semaphore.clone();
-            
+
             let handle = tokio::spawn(async move {
-                // Acquire semaphore permit for I/O operation
-                let _permit = semaphore.acquire().await.expect("Semaphore closed");
-                
-                let result = if req.is_write {
-                    match manager.write_page(req.page_id, &req.data).await {
-                        Ok(_) => Ok(req.data),
-                        Err(e) => Err(e),
-                    }
-                } else {
-                    match manager.read_page(req.page_id, &mut req.data).await {
-                        Ok(_) => Ok(req.data),
-                        Err(e) => Err(e),
+                for mut req in group {
+                    if req.cancel.as_ref().is_some_and(|token| token.is_cancelled()) {
+                        let _ = req.callback.send(Err(DiskError::Cancelled));
+                        continue;
                     }
-                };
-                
-                let _ = req.callback.send(result);
+
+                    // Acquire semaphore permit for I/O operation, unless cancelled while waiting
+                    let permit = match &req.cancel {
+                        Some(token) => tokio::select! {
+                            permit = semaphore.acquire() => permit,
+                            _ = token.cancelled() => {
+                                let _ = req.callback.send(Err(DiskError::Cancelled));
+                                continue;
+                            }
+                        },
+                        None => semaphore.acquire().await,
+                    };
+                    let _permit = match permit {
+                        Ok(permit) => permit,
+                        Err(e) => {
+                            let _ = req.callback.send(Err(e.into()));
+                            continue;
+                        }
+                    };
+
+                    let result = if req.is_write {
+                        match manager.write_page(req.page_id, &req.data).await {
+                            Ok(_) => Ok(req.data),
+                            Err(e) => Err(e),
+                        }
+                    } else {
+                        match manager.read_page(req.page_id, &mut req.data).await {
+                            Ok(_) => Ok(req.data),
+                            Err(e) => Err(e),
+                        }
+                    };
+
+                    let _ = req.callback.send(result);
+                }
             });
 
             handles.push(handle);
         }
 
-        // Wait for all tasks to complete
+        // Wait for all tasks to complete. A task that panicked drops its callbacks, so its
+        // callers see the channel close instead of waiting forever.
         for handle in handles {
-            let _ = handle.await;
+            if let Err(e) = handle.await {
+                log::error!("DiskScheduler request task failed: {}", e);
+            }
         }
 
         Ok(())
@@ -128,9 +181,11 @@ impl DiskScheduler {
     }
 }
 
-#[cfg(test)]
+// Every test runs over files
+#[cfg(all(test, feature = "native"))]
 mod tests {
     use super::*;
+    use std::path::Path;
     use tempfile::tempdir;
     use tokio::sync::oneshot;
     use std::sync::Arc;
@@ -167,6 +222,7 @@ mod tests {
             data: data_write_1.clone(),
             page_id: page_id_1,
             callback: tx1,
+            cancel: None,
         }).await;
 
         let (tx2, rx2) = oneshot::channel();
@@ -175,6 +231,7 @@ mod tests {
             data: data_write_2.clone(),
             page_id: page_id_2,
             callback: tx2,
+            cancel: None,
         }).await;
 
         // --- Read requests ---
@@ -184,6 +241,7 @@ mod tests {
             data: data_read_1.clone(),
             page_id: page_id_1,
             callback: tx3,
+            cancel: None,
         }).await;
 
         let (tx4, rx4) = oneshot::channel();
@@ -192,6 +250,7 @@ mod tests {
             data: data_read_2.clone(),
             page_id: page_id_2,
             callback: tx4,
+            cancel: None,
         }).await;
 
         // Spawn background worker
@@ -201,8 +260,8 @@ mod tests {
         });
 
         // Wait for all callbacks to confirm completion
-        let result1 = rx1.await.unwrap().unwrap();
-        let result2 = rx2.await.unwrap().unwrap();
+        let _result1 = rx1.await.unwrap().unwrap();
+        let _result2 = rx2.await.unwrap().unwrap();
         let result3 = rx3.await.unwrap().unwrap();
         let result4 = rx4.await.unwrap().unwrap();
 
@@ -217,4 +276,27 @@ mod tests {
         manager.read_page(page_id_2, &mut buf).await.unwrap();
         assert_eq!(buf, data_write_2);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_cancelled_request_is_skipped() {
+        let dir = tempdir().unwrap();
+        let manager = make_disk_manager(&dir.path().join("cancel.db")).await;
+        let scheduler = DiskScheduler::new(manager.clone()).unwrap();
+
+        let token = CancellationToken::new();
+        let (tx, rx) = oneshot::channel();
+        scheduler.enqueue(DiskRequest {
+            is_write: true,
+            data: vec![1u8; 4096],
+            page_id: 7,
+            callback: tx,
+            cancel: Some(token.clone()),
+        }).await;
+
+        token.cancel();
+        scheduler.schedule(10).await.unwrap();
+
+        assert!(matches!(rx.await.unwrap(), Err(DiskError::Cancelled)));
+        assert_eq!(manager.get_num_writes().await, 0);
+    }
+}
@@ -0,0 +1,206 @@
+// src/storage/double_write.rs
+
+//! Double-write buffer for torn-write protection.
+//!
+//! Before a page is written to its home offset, DiskManager first writes a copy into a
+//! slot of the double-write buffer and syncs it. A crash in the middle of the home write
+//! can then tear only the home copy; on the next open, recover() copies every intact slot
+//! back to its home offset. Once the home write is durable the slot is cleared, so a slot
+//! only survives for writes that may not have reached home. Otherwise a clean reopen would
+//! roll back newer writes that skipped the buffer (unsynced writes, repairs, promotions).
+//!
+//! Slot layout (little endian):
+//! | seq: u64 | offset: u64 | page_id: i32 | crc: u32 | page data (GRIMOIRE_PAGE_SIZE) |
+//!
+//! The crc covers seq, offset, page_id and the data, so a slot torn by a crash during the
+//! double-write itself is skipped (the home copy was not touched yet). Slots are replayed
+//! in seq order so that the newest copy of an offset wins.
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicU64, Ordering},
+};
+
+use tokio::sync::{Mutex, Semaphore};
+
+use crate::backend::storage::disk_manager::GRIMOIRE_PAGE_SIZE;
+use crate::backend::storage::storage_backend::StorageBackend;
+use crate::common::{
+    buffer_recycler::{BufferRecycler, BufferStats},
+    checksum::Crc32,
+    cpu_pool::CpuPool,
+    errors::DiskError,
+    types::PageId,
+};
+
+const SLOT_HEADER_SIZE: usize = 24;
+const SLOT_SIZE: usize = SLOT_HEADER_SIZE + GRIMOIRE_PAGE_SIZE;
+
+/// Default number of slots, matching the DiskManager's concurrent I/O limit.
+pub const DEFAULT_DOUBLE_WRITE_SLOTS: usize = 10;
+
+pub struct DoubleWriteBuffer {
+    backend: Arc<dyn StorageBackend>,
+    num_slots: usize,
+
+    // Slots not currently holding an in-flight page write
+    free_slots: Mutex<Vec<usize>>,
+    slot_permits: Semaphore,
+
+    next_seq: AtomicU64,
+
+    // Staging buffers for slot writes, one per slot at most
+    slot_buffers: BufferRecycler,
+}
+
+/// A slot reserved for one page write. Must be handed back through release().
+pub struct DoubleWriteSlot {
+    index: usize,
+}
+
+impl DoubleWriteBuffer {
+    /// Open a double-write buffer with `num_slots` slots over `backend`.
+    /// Call recover() before issuing any writes.
+    pub async fn open(backend: Arc<dyn StorageBackend>, num_slots: usize) -> Result<Self, DiskError> {
+        let size = (num_slots * SLOT_SIZE) as u64;
+        if backend.size().await? < size {
+            backend.set_len(size).await?;
+        }
+
+        Ok(Self {
+            backend,
+            num_slots,
+            free_slots: Mutex::new((0..num_slots).rev().collect()),
+            slot_permits: Semaphore::new(num_slots),
+            next_seq: AtomicU64::new(1),
+            slot_buffers: BufferRecycler::new(SLOT_SIZE, num_slots),
+        })
+    }
+
+    /// Copy every intact slot back to its home offset in `db_backend`, oldest first,
+    /// then clear the buffer. Returns the home offsets restored.
+    pub async fn recover(&self, db_backend: &dyn StorageBackend) -> Result<Vec<u64>, DiskError> {
+        let mut intact = Vec::new();
+        let mut slot = vec![0u8; SLOT_SIZE];
+        for index in 0..self.num_slots {
+            self.backend.read_at((index * SLOT_SIZE) as u64, &mut slot).await?;
+            if let Some((seq, offset, page_id)) = decode_header(&slot) {
+                intact.push((seq, offset, page_id, slot[SLOT_HEADER_SIZE..].to_vec()));
+            }
+        }
+        if intact.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        intact.sort_by_key(|(seq, _, _, _)| *seq);
+        for (_, offset, page_id, data) in &intact {
+            log::warn!("double-write recovery: restoring page {} at offset {}", page_id, offset);
+            db_backend.write_at(*offset, data).await?;
+        }
+        db_backend.sync().await?;
+
+        // Restored pages are durable at home now; forget the copies
+        let max_seq = intact.last().map(|(seq, _, _, _)| *seq).unwrap_or(0);
+        self.next_seq.fetch_max(max_seq + 1, Ordering::SeqCst);
+        self.backend.set_len(0).await?;
+        self.backend.set_len((self.num_slots * SLOT_SIZE) as u64).await?;
+        self.backend.sync().await?;
+
+        Ok(intact.iter().map(|(_, offset, _, _)| *offset).collect())
+    }
+
+    /// Reserve a slot, waiting if all of them hold in-flight writes.
+    pub async fn acquire(&self) -> Result<DoubleWriteSlot, DiskError> {
+        let permit = self.slot_permits.acquire().await?;
+        permit.forget();
+        let index = self
+            .free_slots
+            .lock()
+            .await
+            .pop()
+            .expect("double-write slot permits out of sync with free slots");
+        Ok(DoubleWriteSlot { index })
+    }
+
+    /// Write the copy of a page into `slot`, checksumming it on `cpu_pool`.
+    /// Returns the slot's byte range for syncing.
+    pub async fn write(
+        &self,
+        slot: &DoubleWriteSlot,
+        page_id: PageId,
+        offset: u64,
+        page_data: &[u8],
+        cpu_pool: &CpuPool,
+    ) -> Result<(u64, u64), DiskError> {
+        let seq = self.next_seq.fetch_add(1, Ordering::SeqCst);
+
+        let mut buf = self.slot_buffers.take();
+        buf[0..8].copy_from_slice(&seq.to_le_bytes());
+        buf[8..16].copy_from_slice(&offset.to_le_bytes());
+        buf[16..20].copy_from_slice(&page_id.to_le_bytes());
+        // crc at 20..24 is filled in below
+        buf[SLOT_HEADER_SIZE..].copy_from_slice(page_data);
+        let buf = cpu_pool
+            .run(move || {
+                let mut buf = buf;
+                let crc = slot_crc(seq, offset, page_id, &buf[SLOT_HEADER_SIZE..]);
+                buf[20..24].copy_from_slice(&crc.to_le_bytes());
+                buf
+            })
+            .await?;
+
+        let slot_offset = (slot.index * SLOT_SIZE) as u64;
+        let written = self.backend.write_at(slot_offset, &buf).await;
+        self.slot_buffers.give_back(buf);
+        written?;
+        Ok((slot_offset, SLOT_SIZE as u64))
+    }
+
+    /// Invalidate the copy in `slot` once its home write is durable, so recover() does not
+    /// replay it. Returns the slot header's byte range for syncing.
+    pub async fn clear(&self, slot: &DoubleWriteSlot) -> Result<(u64, u64), DiskError> {
+        let slot_offset = (slot.index * SLOT_SIZE) as u64;
+        self.backend.write_at(slot_offset, &[0u8; SLOT_HEADER_SIZE]).await?;
+        Ok((slot_offset, SLOT_HEADER_SIZE as u64))
+    }
+
+    /// Hand a slot back once the home write is durable.
+    pub async fn release(&self, slot: DoubleWriteSlot) {
+        self.free_slots.lock().await.push(slot.index);
+        self.slot_permits.add_permits(1);
+    }
+
+    pub fn buffer_stats(&self) -> BufferStats {
+        self.slot_buffers.stats()
+    }
+
+    pub fn backend(&self) -> &dyn StorageBackend {
+        self.backend.as_ref()
+    }
+}
+
+fn slot_crc(seq: u64, offset: u64, page_id: PageId, page_data: &[u8]) -> u32 {
+    let mut crc = Crc32::new();
+    crc.update(&seq.to_le_bytes());
+    crc.update(&offset.to_le_bytes());
+    crc.update(&page_id.to_le_bytes());
+    crc.update(page_data);
+    crc.finish()
+}
+
+/// Returns (seq, offset, page_id) if the slot holds an intact page copy.
+fn decode_header(slot: &[u8]) -> Option<(u64, u64, PageId)> {
+    let seq = u64::from_le_bytes(slot[0..8].try_into().unwrap());
+    if seq == 0 {
+        return None; // never written
+    }
+    let offset = u64::from_le_bytes(slot[8..16].try_into().unwrap());
+    let page_id = PageId::from_le_bytes(slot[16..20].try_into().unwrap());
+    let crc = u32::from_le_bytes(slot[20..24].try_into().unwrap());
+
+    if slot_crc(seq, offset, page_id, &slot[SLOT_HEADER_SIZE..]) == crc {
+        Some((seq, offset, page_id))
+    } else {
+        None
+    }
+}
@@ -0,0 +1,248 @@
+// src/storage/free_space.rs
+
+//! Free-space map for the page file, with extent-based allocation.
+//!
+//! The file is divided into extents of `extent_pages` consecutive slots, aligned to
+//! multiples of the extent size. A segment (a table or an index) takes a whole extent
+//! at a time and hands out its slots in order, so the pages of one segment sit in
+//! contiguous runs and a sequential scan reads mostly contiguous ranges. Pages that do
+//! not belong to a segment take single slots.
+//!
+//! Freed slots return as single free slots, unless that leaves their extent with nothing
+//! in use or reserved; then the whole extent becomes free for the next segment that
+//! needs one. Offsets in and out are byte offsets of slots.
+
+use std::collections::{BTreeSet, HashMap};
+
+use serde::Serialize;
+
+use crate::common::types::SegmentId;
+
+/// Slots per extent unless configured otherwise.
+pub const DEFAULT_EXTENT_PAGES: usize = 64;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct FreeSpaceStats {
+    /// Free slots outside of free extents.
+    pub free_pages: usize,
+    /// Extents with no slot in use or reserved.
+    pub free_extents: usize,
+    /// Slots reserved by segments but not handed out yet.
+    pub reserved_pages: usize,
+    /// Slots up to the highest one handed out or reserved.
+    pub end_pages: u64,
+}
+
+#[derive(Debug)]
+pub struct FreeSpaceMap {
+    page_size: u64,
+    extent_pages: u64,
+    // Slots below this have been handed out or reserved at some point
+    end: u64,
+    free_slots: BTreeSet<u64>,
+    free_extents: BTreeSet<u64>,
+    // Unused slots of each segment's current extent, next one last
+    open: HashMap<SegmentId, Vec<u64>>,
+    // Slots in use or reserved, per extent
+    in_use: HashMap<u64, u64>,
+}
+
+impl FreeSpaceMap {
+    pub fn new(page_size: usize, extent_pages: usize) -> Self {
+        Self {
+            page_size: page_size as u64,
+            extent_pages: extent_pages.max(1) as u64,
+            end: 0,
+            free_slots: BTreeSet::new(),
+            free_extents: BTreeSet::new(),
+            open: HashMap::new(),
+            in_use: HashMap::new(),
+        }
+    }
+
+    /// Rebuild the map for a file whose slots at `offsets` are in use; every other slot
+    /// below the highest of them is free. Open extents are forgotten.
+    pub fn rebuild(&mut self, offsets: impl IntoIterator<Item = u64>) {
+        self.free_slots.clear();
+        self.free_extents.clear();
+        self.open.clear();
+        self.in_use.clear();
+        let mut used = BTreeSet::new();
+        for offset in offsets {
+            let slot = offset / self.page_size;
+            used.insert(slot);
+            *self.in_use.entry(slot / self.extent_pages).or_default() += 1;
+        }
+        self.end = used.last().map_or(0, |&slot| slot + 1);
+        for slot in (0..self.end).filter(|slot| !used.contains(slot)) {
+            let extent = slot / self.extent_pages;
+            let whole = (extent + 1) * self.extent_pages <= self.end;
+            if whole && !self.in_use.contains_key(&extent) {
+                self.free_extents.insert(extent);
+            } else {
+                self.free_slots.insert(slot);
+            }
+        }
+    }
+
+    /// A slot for a page outside any segment: the lowest free single slot, else one
+    /// carved out of a free extent, else a new slot at the end of the file.
+    pub fn allocate(&mut self) -> u64 {
+        let slot = match self.free_slots.pop_first() {
+            Some(slot) => slot,
+            None => match self.free_extents.pop_first() {
+                Some(extent) => {
+                    let first = extent * self.extent_pages;
+                    self.free_slots.extend(first + 1..first + self.extent_pages);
+                    first
+                }
+                None => {
+                    self.end += 1;
+                    self.end - 1
+                }
+            },
+        };
+        *self.in_use.entry(slot / self.extent_pages).or_default() += 1;
+        slot * self.page_size
+    }
+
+    /// The next slot of `segment`'s current extent, taking a new extent once it is used up.
+    pub fn allocate_in(&mut self, segment: SegmentId) -> u64 {
+        if let Some(slot) = self.open.get_mut(&segment).and_then(Vec::pop) {
+            return slot * self.page_size;
+        }
+        let extent = match self.free_extents.pop_first() {
+            Some(extent) => extent,
+            None => {
+                // Skip to the next extent boundary; what is skipped is free
+                let extent = self.end.div_ceil(self.extent_pages);
+                let first = extent * self.extent_pages;
+                for slot in self.end..first {
+                    self.free_slots.insert(slot);
+                }
+                self.end = first + self.extent_pages;
+                extent
+            }
+        };
+        let first = extent * self.extent_pages;
+        self.in_use.insert(extent, self.extent_pages);
+        let slots = self.open.entry(segment).or_default();
+        *slots = (first + 1..first + self.extent_pages).rev().collect();
+        first * self.page_size
+    }
+
+    /// Give back a slot from allocate() or allocate_in().
+    pub fn free(&mut self, offset: u64) {
+        self.release_slot(offset / self.page_size);
+    }
+
+    /// Take every free and reserved slot out of the map, lowest first, e.g. for
+    /// compaction to fill. Follow up with rebuild().
+    pub fn take_all_free(&mut self) -> Vec<u64> {
+        let mut slots: Vec<u64> = self.free_slots.iter().copied().collect();
+        for extent in &self.free_extents {
+            let first = extent * self.extent_pages;
+            slots.extend(first..first + self.extent_pages);
+        }
+        for reserved in self.open.values() {
+            slots.extend(reserved);
+        }
+        self.free_slots.clear();
+        self.free_extents.clear();
+        self.open.clear();
+        slots.sort_unstable();
+        slots.into_iter().map(|slot| slot * self.page_size).collect()
+    }
+
+    /// Slots up to the highest one handed out or reserved.
+    pub fn end_pages(&self) -> u64 {
+        self.end
+    }
+
+    pub fn stats(&self) -> FreeSpaceStats {
+        FreeSpaceStats {
+            free_pages: self.free_slots.len(),
+            free_extents: self.free_extents.len(),
+            reserved_pages: self.open.values().map(Vec::len).sum(),
+            end_pages: self.end,
+        }
+    }
+
+    fn release_slot(&mut self, slot: u64) {
+        let extent = slot / self.extent_pages;
+        let count = self.in_use.entry(extent).or_default();
+        *count = count.saturating_sub(1);
+        if *count > 0 {
+            self.free_slots.insert(slot);
+            return;
+        }
+        self.in_use.remove(&extent);
+        let first = extent * self.extent_pages;
+        if first + self.extent_pages > self.end {
+            // The extent runs past the end of the file; keep its slots single
+            self.free_slots.insert(slot);
+            return;
+        }
+        for other in first..first + self.extent_pages {
+            self.free_slots.remove(&other);
+        }
+        self.free_extents.insert(extent);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: usize = 4096;
+
+    fn slot(offset: u64) -> u64 {
+        offset / PAGE as u64
+    }
+
+    #[test]
+    fn test_segments_get_contiguous_extents() {
+        let mut map = FreeSpaceMap::new(PAGE, 4);
+        let loose = map.allocate();
+        let a: Vec<u64> = (0..6).map(|_| slot(map.allocate_in(1))).collect();
+        let b: Vec<u64> = (0..2).map(|_| slot(map.allocate_in(2))).collect();
+
+        // Segment 1 starts at the first extent boundary after the loose page
+        assert_eq!(slot(loose), 0);
+        assert_eq!(a, vec![4, 5, 6, 7, 8, 9]);
+        assert_eq!(b, vec![12, 13]);
+        // Slots skipped to reach the boundary are free for loose pages
+        assert_eq!(slot(map.allocate()), 1);
+        assert_eq!(map.stats().reserved_pages, 2 + 2);
+    }
+
+    #[test]
+    fn test_freed_extent_is_reused_whole() {
+        let mut map = FreeSpaceMap::new(PAGE, 4);
+        let first: Vec<u64> = (0..4).map(|_| map.allocate_in(1)).collect();
+        let second = map.allocate_in(2);
+        for &offset in &first[..3] {
+            map.free(offset);
+        }
+        assert_eq!(map.stats().free_extents, 0);
+        map.free(first[3]);
+        assert_eq!((map.stats().free_extents, map.stats().free_pages), (1, 0));
+
+        // The next segment extent is the freed one, not a new one at the end
+        assert_eq!(slot(map.allocate_in(3)), 0);
+        assert_eq!(slot(second), 4);
+        assert_eq!(map.end_pages(), 8);
+    }
+
+    #[test]
+    fn test_rebuild_from_used_slots() {
+        let mut map = FreeSpaceMap::new(PAGE, 4);
+        let used = [0u64, 2, 9].map(|s| s * PAGE as u64);
+        map.rebuild(used);
+        let stats = map.stats();
+        // Slots 1 and 3 are single free slots; extent 1 (slots 4..8) is free as a whole
+        assert_eq!((stats.end_pages, stats.free_pages, stats.free_extents), (10, 3, 1));
+        assert_eq!(slot(map.allocate()), 1);
+        assert_eq!(slot(map.allocate_in(7)), 4);
+    }
+}
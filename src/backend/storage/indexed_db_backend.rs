@@ -0,0 +1,249 @@
+// src/storage/indexed_db_backend.rs
+
+//! StorageBackend over a browser's IndexedDB, for wasm32 builds (no `native` feature)
+//! where there is no filesystem.
+//!
+//! IndexedDB stores whole values under keys rather than byte ranges, so the storage is
+//! cut into page-sized blocks, one record per block, plus a record holding the length.
+//! A block never written reads as zeroes, and bytes past the length are always zero,
+//! so growing the storage only has to update the length.
+//!
+//! The crate does not depend on wasm-bindgen: the embedder implements IdbObjectStore
+//! over an IDBObjectStore (one get, put or delete request per call). Every put is its
+//! own transaction, which the browser makes durable when it completes, so sync() has
+//! nothing left to do; a write spanning several blocks is not atomic, like a torn write
+//! on a file, which the double-write buffer already covers. The browser runs wasm on one
+//! thread, so glue whose JS futures are not Send can wrap them (e.g. in
+//! send_wrapper::SendWrapper) to meet BackendFuture's bound.
+
+use std::sync::Arc;
+
+use tokio::sync::Mutex;
+
+use crate::backend::storage::disk_manager::GRIMOIRE_PAGE_SIZE;
+use crate::backend::storage::storage_backend::{BackendFuture, StorageBackend};
+use crate::common::errors::DiskError;
+
+/// Bytes per block record.
+pub const IDB_BLOCK_SIZE: usize = GRIMOIRE_PAGE_SIZE;
+
+/// Key of a record in the object store.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IdbKey {
+    /// The storage's length in bytes, as a little-endian u64.
+    Len,
+    /// Bytes `n * IDB_BLOCK_SIZE..(n + 1) * IDB_BLOCK_SIZE`.
+    Block(u64),
+}
+
+/// The object store records live in, implemented by the embedder's JS glue.
+pub trait IdbObjectStore: Send + Sync {
+    /// The record under `key`, or None if there is none.
+    fn get(&self, key: IdbKey) -> BackendFuture<'_, Option<Vec<u8>>>;
+
+    /// Store `value` under `key`, replacing any previous record.
+    fn put(&self, key: IdbKey, value: Vec<u8>) -> BackendFuture<'_, ()>;
+
+    /// Remove the record under `key`, if any.
+    fn delete(&self, key: IdbKey) -> BackendFuture<'_, ()>;
+}
+
+pub struct IndexedDbBackend {
+    store: Arc<dyn IdbObjectStore>,
+    // Length in bytes, held for the whole of each operation so writes do not interleave
+    len: Mutex<u64>,
+}
+
+impl IndexedDbBackend {
+    /// Back onto the records in `store`. An empty store is empty storage.
+    pub async fn open(store: Arc<dyn IdbObjectStore>) -> Result<Self, DiskError> {
+        let len = match store.get(IdbKey::Len).await? {
+            Some(bytes) => {
+                let bytes = bytes.try_into().map_err(|_| {
+                    DiskError::IoError(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "IndexedDB length record is not 8 bytes",
+                    ))
+                })?;
+                u64::from_le_bytes(bytes)
+            }
+            None => 0,
+        };
+        Ok(Self {
+            store,
+            len: Mutex::new(len),
+        })
+    }
+
+    // Block `n`, padded with zeroes to a full block
+    async fn block(&self, n: u64) -> Result<Vec<u8>, DiskError> {
+        let mut block = self.store.get(IdbKey::Block(n)).await?.unwrap_or_default();
+        block.resize(IDB_BLOCK_SIZE, 0);
+        Ok(block)
+    }
+
+    async fn write_locked(&self, len: &mut u64, offset: u64, data: &[u8]) -> Result<(), DiskError> {
+        let end = offset + data.len() as u64;
+        let mut pos = offset;
+        while pos < end {
+            let n = pos / IDB_BLOCK_SIZE as u64;
+            let start = (pos % IDB_BLOCK_SIZE as u64) as usize;
+            let take = (IDB_BLOCK_SIZE - start).min((end - pos) as usize);
+            let src = &data[(pos - offset) as usize..][..take];
+            let block = if take == IDB_BLOCK_SIZE {
+                src.to_vec()
+            } else {
+                let mut block = self.block(n).await?;
+                block[start..start + take].copy_from_slice(src);
+                block
+            };
+            self.store.put(IdbKey::Block(n), block).await?;
+            pos += take as u64;
+        }
+        if end > *len {
+            self.store.put(IdbKey::Len, end.to_le_bytes().to_vec()).await?;
+            *len = end;
+        }
+        Ok(())
+    }
+}
+
+impl StorageBackend for IndexedDbBackend {
+    fn read_at<'a>(&'a self, offset: u64, buf: &'a mut [u8]) -> BackendFuture<'a, ()> {
+        Box::pin(async move {
+            let len = self.len.lock().await;
+            let end = offset + buf.len() as u64;
+            if end > *len {
+                return Err(DiskError::IoError(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "read past end of IndexedDB storage",
+                )));
+            }
+            let mut pos = offset;
+            while pos < end {
+                let start = (pos % IDB_BLOCK_SIZE as u64) as usize;
+                let take = (IDB_BLOCK_SIZE - start).min((end - pos) as usize);
+                let block = self.block(pos / IDB_BLOCK_SIZE as u64).await?;
+                buf[(pos - offset) as usize..][..take].copy_from_slice(&block[start..start + take]);
+                pos += take as u64;
+            }
+            Ok(())
+        })
+    }
+
+    fn write_at<'a>(&'a self, offset: u64, data: &'a [u8]) -> BackendFuture<'a, ()> {
+        Box::pin(async move {
+            let mut len = self.len.lock().await;
+            self.write_locked(&mut len, offset, data).await
+        })
+    }
+
+    fn append<'a>(&'a self, data: &'a [u8]) -> BackendFuture<'a, ()> {
+        Box::pin(async move {
+            let mut len = self.len.lock().await;
+            let offset = *len;
+            self.write_locked(&mut len, offset, data).await
+        })
+    }
+
+    fn sync(&self) -> BackendFuture<'_, ()> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn size(&self) -> BackendFuture<'_, u64> {
+        Box::pin(async move { Ok(*self.len.lock().await) })
+    }
+
+    fn set_len(&self, new_len: u64) -> BackendFuture<'_, ()> {
+        Box::pin(async move {
+            let mut len = self.len.lock().await;
+            if new_len < *len {
+                // Drop the blocks past the new end and zero the tail of the last one, so
+                // growing again reads zeroes
+                let block_size = IDB_BLOCK_SIZE as u64;
+                for n in new_len.div_ceil(block_size)..len.div_ceil(block_size) {
+                    self.store.delete(IdbKey::Block(n)).await?;
+                }
+                let tail = (new_len % block_size) as usize;
+                if tail != 0 {
+                    let n = new_len / block_size;
+                    let mut block = self.block(n).await?;
+                    block[tail..].fill(0);
+                    self.store.put(IdbKey::Block(n), block).await?;
+                }
+            }
+            self.store.put(IdbKey::Len, new_len.to_le_bytes().to_vec()).await?;
+            *len = new_len;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    use crate::backend::storage::disk_manager::DiskManager;
+    use crate::backend::storage::storage_backend::MemoryBackend;
+
+    // Stands in for the JS glue
+    #[derive(Default)]
+    struct MapStore(std::sync::Mutex<HashMap<IdbKey, Vec<u8>>>);
+
+    impl IdbObjectStore for MapStore {
+        fn get(&self, key: IdbKey) -> BackendFuture<'_, Option<Vec<u8>>> {
+            let value = self.0.lock().unwrap().get(&key).cloned();
+            Box::pin(async move { Ok(value) })
+        }
+
+        fn put(&self, key: IdbKey, value: Vec<u8>) -> BackendFuture<'_, ()> {
+            self.0.lock().unwrap().insert(key, value);
+            Box::pin(async { Ok(()) })
+        }
+
+        fn delete(&self, key: IdbKey) -> BackendFuture<'_, ()> {
+            self.0.lock().unwrap().remove(&key);
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_blocks_survive_reopen() {
+        let store = Arc::new(MapStore::default());
+        let backend = IndexedDbBackend::open(store.clone()).await.unwrap();
+        let straddle = IDB_BLOCK_SIZE as u64 - 2;
+        backend.write_at(straddle, b"abcd").await.unwrap();
+        backend.append(b"ef").await.unwrap();
+        assert_eq!(backend.size().await.unwrap(), straddle + 6);
+        drop(backend);
+
+        let backend = IndexedDbBackend::open(store.clone()).await.unwrap();
+        let mut buf = [0xFFu8; 8];
+        backend.read_at(straddle - 2, &mut buf).await.unwrap();
+        assert_eq!(&buf, b"\0\0abcdef");
+        assert!(backend.read_at(straddle, &mut buf).await.is_err());
+
+        // Shrinking drops whole blocks and zeroes the cut-off tail
+        backend.set_len(straddle + 1).await.unwrap();
+        assert!(!store.0.lock().unwrap().contains_key(&IdbKey::Block(1)));
+        backend.set_len(straddle + 4).await.unwrap();
+        let mut buf = [0xFFu8; 4];
+        backend.read_at(straddle, &mut buf).await.unwrap();
+        assert_eq!(&buf, b"a\0\0\0");
+    }
+
+    #[tokio::test]
+    async fn test_disk_manager_over_indexed_db() {
+        let store = Arc::new(MapStore::default());
+        let db = IndexedDbBackend::open(store.clone()).await.unwrap();
+        let dm = DiskManager::with_backends(Arc::new(db), Arc::new(MemoryBackend::new())).await.unwrap();
+        let page = vec![0x5Au8; GRIMOIRE_PAGE_SIZE];
+        dm.write_page(3, &page).await.unwrap();
+
+        let mut read = vec![0u8; GRIMOIRE_PAGE_SIZE];
+        dm.read_page(3, &mut read).await.unwrap();
+        assert_eq!(read, page);
+        assert!(store.0.lock().unwrap().values().any(|block| *block == page));
+    }
+}
@@ -0,0 +1,104 @@
+// src/storage/io_stats.rs
+
+//! I/O accounting per logical source.
+//! DiskManager attributes every byte it reads or writes to an IoSource, so callers can
+//! compute write amplification and find which subsystem is hammering the disk.
+
+/// The logical subsystem an I/O is issued on behalf of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IoSource {
+    Wal,
+    DataPage,
+    Compaction,
+    Checkpoint,
+    TempSpill,
+    Tiering,
+}
+
+impl IoSource {
+    pub const ALL: [IoSource; 6] = [
+        IoSource::Wal,
+        IoSource::DataPage,
+        IoSource::Compaction,
+        IoSource::Checkpoint,
+        IoSource::TempSpill,
+        IoSource::Tiering,
+    ];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            IoSource::Wal => "wal",
+            IoSource::DataPage => "data_page",
+            IoSource::Compaction => "compaction",
+            IoSource::Checkpoint => "checkpoint",
+            IoSource::TempSpill => "temp_spill",
+            IoSource::Tiering => "tiering",
+        }
+    }
+}
+
+/// Bytes read and written per IoSource.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IoBreakdown {
+    bytes_read: [u64; IoSource::ALL.len()],
+    bytes_written: [u64; IoSource::ALL.len()],
+}
+
+impl IoBreakdown {
+    pub(crate) fn record_read(&mut self, source: IoSource, bytes: usize) {
+        self.bytes_read[source.index()] += bytes as u64;
+    }
+
+    pub(crate) fn record_write(&mut self, source: IoSource, bytes: usize) {
+        self.bytes_written[source.index()] += bytes as u64;
+    }
+
+    pub fn bytes_read(&self, source: IoSource) -> u64 {
+        self.bytes_read[source.index()]
+    }
+
+    pub fn bytes_written(&self, source: IoSource) -> u64 {
+        self.bytes_written[source.index()]
+    }
+
+    pub fn total_bytes_read(&self) -> u64 {
+        self.bytes_read.iter().sum()
+    }
+
+    pub fn total_bytes_written(&self) -> u64 {
+        self.bytes_written.iter().sum()
+    }
+
+    /// Physical bytes written per logical byte the user asked to store.
+    /// Returns 0.0 when nothing logical has been written yet.
+    pub fn write_amplification(&self, logical_bytes: u64) -> f64 {
+        if logical_bytes == 0 {
+            return 0.0;
+        }
+        self.total_bytes_written() as f64 / logical_bytes as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breakdown_totals_and_amplification() {
+        let mut breakdown = IoBreakdown::default();
+        breakdown.record_write(IoSource::Wal, 100);
+        breakdown.record_write(IoSource::DataPage, 300);
+        breakdown.record_read(IoSource::Compaction, 50);
+
+        assert_eq!(breakdown.bytes_written(IoSource::Wal), 100);
+        assert_eq!(breakdown.bytes_written(IoSource::Checkpoint), 0);
+        assert_eq!(breakdown.total_bytes_written(), 400);
+        assert_eq!(breakdown.total_bytes_read(), 50);
+        assert_eq!(breakdown.write_amplification(100), 4.0);
+        assert_eq!(breakdown.write_amplification(0), 0.0);
+    }
+}
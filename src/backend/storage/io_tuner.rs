@@ -0,0 +1,204 @@
+// src/storage/io_tuner.rs
+
+//! Adaptive I/O tuning.
+//!
+//! IoTuner looks at how page I/O behaved since its last observation and picks the I/O
+//! concurrency limit and checkpoint flush batch for the next interval. So the same
+//! defaults work on a disk that wants a couple of requests in flight and on an NVMe
+//! drive that wants dozens.
+//!
+//! The controller is additive-increase, multiplicative-decrease, on the mean latencies
+//! of the interval:
+//! - Page I/O slower than the target means the device is saturated, and concurrency
+//!   drops by a quarter.
+//! - Otherwise, if I/O spent a good part of its time waiting for a permit, the limit is
+//!   what holds it back, and concurrency grows by one.
+//! - Page writes well under the target double the flush batch; writes over it halve it.
+//!   A flush batch above the concurrency limit would only queue for permits, so it is
+//!   capped there.
+//!
+//! Intervals with too little I/O to judge leave everything as it is. Applying the
+//! settings is up to the caller (see Instance::with_io_tuning).
+
+use std::time::Duration;
+
+use crate::backend::storage::disk_manager::DiskLatencyStats;
+use crate::common::histogram::LatencySummary;
+
+/// Page I/Os an interval needs before it is acted on.
+const MIN_SAMPLES: u64 = 32;
+
+/// Bounds and target the tuner works within.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IoTunerConfig {
+    pub min_io_concurrency: usize,
+    pub max_io_concurrency: usize,
+    pub min_flush_batch: usize,
+    pub max_flush_batch: usize,
+    /// Mean page I/O latency above which the device counts as saturated.
+    pub target_latency: Duration,
+}
+
+impl Default for IoTunerConfig {
+    fn default() -> Self {
+        Self {
+            min_io_concurrency: 1,
+            max_io_concurrency: 64,
+            min_flush_batch: 1,
+            max_flush_batch: 64,
+            target_latency: Duration::from_millis(10),
+        }
+    }
+}
+
+/// The settings for the next interval.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IoTuning {
+    pub io_concurrency: usize,
+    pub flush_batch: usize,
+}
+
+pub struct IoTuner {
+    config: IoTunerConfig,
+    current: IoTuning,
+    last: DiskLatencyStats,
+}
+
+// Operations and total microseconds between two summaries of the same histogram
+fn window(now: &LatencySummary, last: &LatencySummary) -> (u64, f64) {
+    let total = |s: &LatencySummary| s.mean_us * s.count as f64;
+    (now.count.saturating_sub(last.count), (total(now) - total(last)).max(0.0))
+}
+
+fn mean((count, total): (u64, f64)) -> f64 {
+    if count == 0 { 0.0 } else { total / count as f64 }
+}
+
+impl IoTuner {
+    /// Start from `current`, pulled into the configured bounds, with `stats` as the
+    /// baseline of the first interval.
+    pub fn new(config: IoTunerConfig, current: IoTuning, stats: DiskLatencyStats) -> Self {
+        let mut tuner = Self {
+            config,
+            current,
+            last: stats,
+        };
+        tuner.current = tuner.clamp(current);
+        tuner
+    }
+
+    pub fn current(&self) -> IoTuning {
+        self.current
+    }
+
+    /// Feed the DiskManager's latency_stats() at the end of an interval and get the
+    /// settings for the next one.
+    pub fn observe(&mut self, stats: &DiskLatencyStats) -> IoTuning {
+        let reads = window(&stats.page_read, &self.last.page_read);
+        let writes = window(&stats.page_write, &self.last.page_write);
+        let waits = window(&stats.io_queue_wait, &self.last.io_queue_wait);
+        self.last = *stats;
+
+        let ops = reads.0 + writes.0;
+        if ops < MIN_SAMPLES {
+            return self.current;
+        }
+        let io_us = mean((ops, reads.1 + writes.1));
+        let write_us = mean(writes);
+        let wait_us = mean(waits);
+        let target_us = self.config.target_latency.as_micros() as f64;
+
+        let IoTuning { mut io_concurrency, mut flush_batch } = self.current;
+        if io_us > target_us {
+            io_concurrency -= (io_concurrency / 4).max(1);
+        } else if wait_us > io_us / 2.0 {
+            io_concurrency += 1;
+        }
+        if writes.0 > 0 && write_us < target_us / 2.0 {
+            flush_batch *= 2;
+        } else if write_us > target_us {
+            flush_batch /= 2;
+        }
+
+        let next = self.clamp(IoTuning { io_concurrency, flush_batch });
+        if next != self.current {
+            log::debug!(
+                "io tuning: mean I/O {:.0}us, permit wait {:.0}us -> concurrency {}, flush batch {}",
+                io_us,
+                wait_us,
+                next.io_concurrency,
+                next.flush_batch
+            );
+        }
+        self.current = next;
+        next
+    }
+
+    fn clamp(&self, tuning: IoTuning) -> IoTuning {
+        let config = &self.config;
+        let (min, max) = (config.min_io_concurrency.max(1), config.max_io_concurrency);
+        let io_concurrency = tuning.io_concurrency.clamp(min, max.max(min));
+        let (min, max) = (config.min_flush_batch.max(1), config.max_flush_batch);
+        let flush_batch = tuning.flush_batch.min(io_concurrency).clamp(min, max.max(min));
+        IoTuning { io_concurrency, flush_batch }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Cumulative stats after `ops` more page reads and writes of `io_us` each, which
+    // waited `wait_us` each for a permit
+    fn advance(stats: &mut DiskLatencyStats, ops: u64, io_us: f64, wait_us: f64) {
+        for (summary, latency) in [
+            (&mut stats.page_read, io_us),
+            (&mut stats.page_write, io_us),
+            (&mut stats.io_queue_wait, wait_us),
+        ] {
+            let total = summary.mean_us * summary.count as f64 + latency * ops as f64;
+            summary.count += ops;
+            summary.mean_us = total / summary.count as f64;
+        }
+    }
+
+    fn tuner(io_concurrency: usize, flush_batch: usize) -> IoTuner {
+        let config = IoTunerConfig {
+            max_io_concurrency: 16,
+            max_flush_batch: 8,
+            ..IoTunerConfig::default()
+        };
+        IoTuner::new(config, IoTuning { io_concurrency, flush_batch }, DiskLatencyStats::default())
+    }
+
+    #[test]
+    fn test_grows_while_io_queues_and_stays_fast() {
+        let mut tuner = tuner(4, 1);
+        let mut stats = DiskLatencyStats::default();
+        let mut seen = Vec::new();
+        for _ in 0..20 {
+            advance(&mut stats, 100, 200.0, 500.0);
+            seen.push(tuner.observe(&stats));
+        }
+        assert_eq!(seen[0], IoTuning { io_concurrency: 5, flush_batch: 2 });
+        // Both settle at their configured maximum
+        assert_eq!(tuner.current(), IoTuning { io_concurrency: 16, flush_batch: 8 });
+    }
+
+    #[test]
+    fn test_backs_off_when_saturated() {
+        let mut tuner = tuner(16, 8);
+        let mut stats = DiskLatencyStats::default();
+        advance(&mut stats, 100, 40_000.0, 90_000.0);
+        assert_eq!(tuner.observe(&stats), IoTuning { io_concurrency: 12, flush_batch: 4 });
+        for _ in 0..20 {
+            advance(&mut stats, 100, 40_000.0, 90_000.0);
+            tuner.observe(&stats);
+        }
+        assert_eq!(tuner.current(), IoTuning { io_concurrency: 1, flush_batch: 1 });
+
+        // Too few operations to judge: nothing changes
+        advance(&mut stats, 5, 10.0, 1000.0);
+        assert_eq!(tuner.observe(&stats), IoTuning { io_concurrency: 1, flush_batch: 1 });
+    }
+}
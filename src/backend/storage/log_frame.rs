@@ -0,0 +1,272 @@
+// src/storage/log_frame.rs
+
+//! Log record framing.
+//!
+//! write_log wraps every record in a frame so the log can be read back record by record
+//! and a record torn by a crash is recognised instead of being glued to the next one.
+//!
+//! Frame layout (little endian):
+//! | len: u32 | crc: u32 | payload (len bytes) |
+//!
+//! The crc covers len and the payload. A reader stops at the first frame that is cut
+//! short or fails its crc: everything from there on is the torn tail of the last write
+//! that did not finish, and recovery truncates it away. A zero-filled tail is log space
+//! preallocated ahead of the writes; its zero header never passes the crc check.
+//!
+//! LogReader streams the log in large sequential reads, so recovery of a big log holds
+//! one chunk in memory rather than the whole file.
+
+use crate::backend::storage::storage_backend::StorageBackend;
+use crate::common::{checksum::Crc32, errors::DiskError};
+
+pub const LOG_FRAME_HEADER_SIZE: usize = 8;
+
+/// Largest payload a single frame may carry. A larger length can only come from a torn
+/// or corrupted header.
+pub const MAX_LOG_RECORD_SIZE: usize = 64 * 1024 * 1024;
+
+/// Bytes LogReader reads at a time unless told otherwise.
+pub const DEFAULT_LOG_READ_CHUNK: usize = 4 * 1024 * 1024;
+
+/// The records of a log, and how many bytes of it are intact.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LogScan {
+    pub records: Vec<Vec<u8>>,
+    pub valid_len: u64,
+    /// Whether every byte past valid_len is zero, i.e. preallocated space rather than a
+    /// torn record.
+    pub zero_tail: bool,
+}
+
+/// Wrap `payload` in a frame.
+pub fn encode_frame(payload: &[u8]) -> Vec<u8> {
+    assert!(
+        payload.len() <= MAX_LOG_RECORD_SIZE,
+        "log record must be at most {} bytes",
+        MAX_LOG_RECORD_SIZE
+    );
+    let len = (payload.len() as u32).to_le_bytes();
+    let mut frame = Vec::with_capacity(LOG_FRAME_HEADER_SIZE + payload.len());
+    frame.extend_from_slice(&len);
+    frame.extend_from_slice(&frame_crc(&len, payload).to_le_bytes());
+    frame.extend_from_slice(payload);
+    frame
+}
+
+enum Decoded {
+    /// An intact frame with a payload of this many bytes.
+    Frame(usize),
+    /// The bytes end before the frame does.
+    Incomplete,
+    /// Torn or corrupted.
+    Invalid,
+}
+
+fn decode_frame(bytes: &[u8]) -> Decoded {
+    let Some((header, body)) = bytes.split_at_checked(LOG_FRAME_HEADER_SIZE) else {
+        return Decoded::Incomplete;
+    };
+    let len = u32::from_le_bytes(header[..4].try_into().unwrap()) as usize;
+    let crc = u32::from_le_bytes(header[4..].try_into().unwrap());
+    if len > MAX_LOG_RECORD_SIZE {
+        return Decoded::Invalid;
+    }
+    let Some(payload) = body.get(..len) else {
+        return Decoded::Incomplete;
+    };
+    if frame_crc(&header[..4], payload) == crc {
+        Decoded::Frame(len)
+    } else {
+        Decoded::Invalid
+    }
+}
+
+/// Decode frames from the start of `bytes`, stopping at the first invalid one.
+pub fn decode_frames(bytes: &[u8]) -> LogScan {
+    let mut scan = LogScan::default();
+    let mut rest = bytes;
+    while let Decoded::Frame(len) = decode_frame(rest) {
+        let end = LOG_FRAME_HEADER_SIZE + len;
+        scan.records.push(rest[LOG_FRAME_HEADER_SIZE..end].to_vec());
+        scan.valid_len += end as u64;
+        rest = &rest[end..];
+    }
+    scan.zero_tail = rest.iter().all(|&b| b == 0);
+    scan
+}
+
+/// Read and decode the whole log in `backend`.
+pub async fn scan_log(backend: &dyn StorageBackend) -> Result<LogScan, DiskError> {
+    let mut reader = LogReader::open(backend, DEFAULT_LOG_READ_CHUNK).await?;
+    let mut records = Vec::new();
+    while let Some(record) = reader.next_record().await? {
+        records.push(record);
+    }
+    Ok(LogScan {
+        records,
+        valid_len: reader.valid_len(),
+        zero_tail: reader.zero_tail().await?,
+    })
+}
+
+/// Reads a log front to back, `chunk_size` bytes at a time, and yields its intact
+/// records. A record larger than a chunk is read across several.
+pub struct LogReader<'a> {
+    backend: &'a dyn StorageBackend,
+    size: u64,
+    chunk_size: usize,
+    // Where the next read starts
+    read_pos: u64,
+    // Bytes read but not yet consumed start at `consumed`
+    pending: Vec<u8>,
+    consumed: usize,
+    valid_len: u64,
+    done: bool,
+}
+
+impl<'a> LogReader<'a> {
+    pub async fn open(backend: &'a dyn StorageBackend, chunk_size: usize) -> Result<Self, DiskError> {
+        Ok(Self {
+            backend,
+            size: backend.size().await?,
+            chunk_size: chunk_size.max(LOG_FRAME_HEADER_SIZE),
+            read_pos: 0,
+            pending: Vec::new(),
+            consumed: 0,
+            valid_len: 0,
+            done: false,
+        })
+    }
+
+    /// The next intact record, or None at the end of the log or at a torn tail.
+    pub async fn next_record(&mut self) -> Result<Option<Vec<u8>>, DiskError> {
+        while !self.done {
+            match decode_frame(&self.pending[self.consumed..]) {
+                Decoded::Frame(len) => {
+                    let start = self.consumed + LOG_FRAME_HEADER_SIZE;
+                    let record = self.pending[start..start + len].to_vec();
+                    self.consumed = start + len;
+                    self.valid_len += (LOG_FRAME_HEADER_SIZE + len) as u64;
+                    return Ok(Some(record));
+                }
+                Decoded::Incomplete if self.read_pos < self.size => self.read_chunk().await?,
+                Decoded::Incomplete | Decoded::Invalid => self.done = true,
+            }
+        }
+        Ok(None)
+    }
+
+    /// Bytes of intact records returned so far.
+    pub fn valid_len(&self) -> u64 {
+        self.valid_len
+    }
+
+    /// Once next_record() has returned None: whether everything past valid_len() is
+    /// zero, i.e. preallocated space rather than a torn record. Reads the rest of the log.
+    pub async fn zero_tail(&mut self) -> Result<bool, DiskError> {
+        loop {
+            if self.pending[self.consumed..].iter().any(|&b| b != 0) {
+                return Ok(false);
+            }
+            self.consumed = self.pending.len();
+            if self.read_pos == self.size {
+                return Ok(true);
+            }
+            self.read_chunk().await?;
+        }
+    }
+
+    async fn read_chunk(&mut self) -> Result<(), DiskError> {
+        self.pending.drain(..self.consumed);
+        self.consumed = 0;
+        let len = (self.size - self.read_pos).min(self.chunk_size as u64) as usize;
+        let start = self.pending.len();
+        self.pending.resize(start + len, 0);
+        self.backend.read_at(self.read_pos, &mut self.pending[start..]).await?;
+        self.read_pos += len as u64;
+        Ok(())
+    }
+}
+
+fn frame_crc(len: &[u8], payload: &[u8]) -> u32 {
+    let mut crc = Crc32::new();
+    crc.update(len);
+    crc.update(payload);
+    crc.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::storage::storage_backend::MemoryBackend;
+
+    #[test]
+    fn test_round_trip() {
+        let mut log = encode_frame(b"first");
+        log.extend(encode_frame(b""));
+        log.extend(encode_frame(b"third"));
+
+        let scan = decode_frames(&log);
+        assert_eq!(scan.records, vec![b"first".to_vec(), Vec::new(), b"third".to_vec()]);
+        assert_eq!(scan.valid_len, log.len() as u64);
+        assert!(scan.zero_tail);
+
+        // Preallocated space after the records is not a torn tail
+        let valid_len = log.len() as u64;
+        log.resize(log.len() + 4096, 0);
+        let scan = decode_frames(&log);
+        assert_eq!((scan.records.len(), scan.valid_len, scan.zero_tail), (3, valid_len, true));
+    }
+
+    #[test]
+    fn test_stops_at_torn_tail() {
+        let mut log = encode_frame(b"kept");
+        let intact = log.len() as u64;
+        let second = encode_frame(b"torn record");
+
+        // Cut short, in the header and in the payload
+        for cut in [3, LOG_FRAME_HEADER_SIZE + 4] {
+            let mut torn = log.clone();
+            torn.extend_from_slice(&second[..cut]);
+            let scan = decode_frames(&torn);
+            assert_eq!(scan.records, vec![b"kept".to_vec()]);
+            assert_eq!(scan.valid_len, intact);
+            assert!(!scan.zero_tail);
+        }
+
+        // Complete length but garbage payload, followed by an intact frame
+        let mut corrupt = second.clone();
+        corrupt[LOG_FRAME_HEADER_SIZE] ^= 0xFF;
+        log.extend(corrupt);
+        log.extend(encode_frame(b"unreachable"));
+        let scan = decode_frames(&log);
+        assert_eq!(scan.records.len(), 1);
+        assert_eq!(scan.valid_len, intact);
+    }
+
+    #[tokio::test]
+    async fn test_reader_crosses_chunk_boundaries() {
+        let backend = MemoryBackend::new();
+        let records: Vec<Vec<u8>> = (0..40usize).map(|i| vec![i as u8; i * 3]).collect();
+        let mut log: Vec<u8> = records.iter().flat_map(|r| encode_frame(r)).collect();
+        let valid_len = log.len() as u64;
+        log.resize(log.len() + 100, 0);
+        backend.write_at(0, &log).await.unwrap();
+
+        // Chunks smaller than most records, and than a header
+        for chunk_size in [1, 7, 64, 1 << 20] {
+            let mut reader = LogReader::open(&backend, chunk_size).await.unwrap();
+            let mut read = Vec::new();
+            while let Some(record) = reader.next_record().await.unwrap() {
+                read.push(record);
+            }
+            assert_eq!(read, records);
+            assert_eq!(reader.valid_len(), valid_len);
+            assert!(reader.zero_tail().await.unwrap());
+        }
+
+        backend.write_at(valid_len + 50, &[1]).await.unwrap();
+        let scan = scan_log(&backend).await.unwrap();
+        assert_eq!((scan.records.len(), scan.valid_len, scan.zero_tail), (40, valid_len, false));
+    }
+}
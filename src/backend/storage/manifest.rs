@@ -0,0 +1,176 @@
+// src/storage/manifest.rs
+
+//! Small metadata files that are replaced whole and atomically.
+//!
+//! store() never writes the file in place. It writes the new contents to `<name>.tmp`
+//! next to it, syncs that, renames it over the file and syncs the directory. A crash
+//! at any point leaves either the old contents or the new ones, never a mix:
+//! - before the rename, the file is untouched and the temp file is junk, which the next
+//!   load() deletes;
+//! - the rename replaces the directory entry in one step, and the temp file's data was
+//!   durable before it happened;
+//! - the directory sync makes the rename itself survive a power loss.
+//!
+//! Stores to one Manifest are serialized; two Manifests must not share a path.
+
+use std::{
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use tokio::sync::Mutex;
+
+use crate::backend::storage::platform;
+use crate::common::errors::DiskError;
+
+// Steps of store(), for crash injection in tests
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    // Half the new contents are in the temp file
+    TempTorn,
+    TempWritten,
+    TempSynced,
+    Renamed,
+}
+
+pub struct Manifest {
+    path: PathBuf,
+    temp_path: PathBuf,
+    store_lock: Mutex<()>,
+    // store() stops right after this step, as if the process died there
+    #[cfg(test)]
+    crash_after: Option<Step>,
+}
+
+impl Manifest {
+    pub fn new(path: &Path) -> Self {
+        let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+        temp_name.push(".tmp");
+        Self {
+            path: path.to_path_buf(),
+            temp_path: path.with_file_name(temp_name),
+            store_lock: Mutex::new(()),
+            #[cfg(test)]
+            crash_after: None,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The current contents, or None if nothing was ever stored. Deletes what an
+    /// interrupted store() left behind.
+    pub async fn load(&self) -> Result<Option<Vec<u8>>, DiskError> {
+        let _store = self.store_lock.lock().await;
+        match tokio::fs::remove_file(&self.temp_path).await {
+            Ok(()) => log::warn!("removed {} left by an interrupted update", self.temp_path.display()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(DiskError::IoError(e)),
+        }
+        match tokio::fs::read(&self.path).await {
+            Ok(contents) => Ok(Some(contents)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(DiskError::IoError(e)),
+        }
+    }
+
+    /// Replace the contents with `contents`, durably and atomically.
+    pub async fn store(&self, contents: &[u8]) -> Result<(), DiskError> {
+        let _store = self.store_lock.lock().await;
+        let (path, temp_path) = (self.path.clone(), self.temp_path.clone());
+        let contents = contents.to_vec();
+        let crash_after = self.crash_after();
+        tokio::task::spawn_blocking(move || replace(&path, &temp_path, &contents, crash_after))
+            .await
+            .map_err(|e| DiskError::IoError(io::Error::other(e)))?
+            .map_err(DiskError::IoError)
+    }
+
+    #[cfg(test)]
+    fn crash_after(&self) -> Option<Step> {
+        self.crash_after
+    }
+
+    #[cfg(not(test))]
+    fn crash_after(&self) -> Option<Step> {
+        None
+    }
+}
+
+fn replace(path: &Path, temp_path: &Path, contents: &[u8], crash_after: Option<Step>) -> io::Result<()> {
+    let crashed = |step| crash_after == Some(step);
+
+    let mut temp = platform::open_options()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(temp_path)?;
+    if crashed(Step::TempTorn) {
+        return temp.write_all(&contents[..contents.len() / 2]);
+    }
+    temp.write_all(contents)?;
+    if crashed(Step::TempWritten) {
+        return Ok(());
+    }
+    platform::full_sync(&temp)?;
+    drop(temp);
+    if crashed(Step::TempSynced) {
+        return Ok(());
+    }
+
+    std::fs::rename(temp_path, path)?;
+    if crashed(Step::Renamed) {
+        return Ok(());
+    }
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    platform::sync_dir(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_store_replaces_contents() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = Manifest::new(&dir.path().join("MANIFEST"));
+        assert_eq!(manifest.load().await.unwrap(), None);
+
+        manifest.store(b"first").await.unwrap();
+        manifest.store(b"second").await.unwrap();
+        assert_eq!(manifest.load().await.unwrap().as_deref(), Some(&b"second"[..]));
+        let names: Vec<_> = std::fs::read_dir(dir.path()).unwrap().map(|e| e.unwrap().file_name()).collect();
+        assert_eq!(names, ["MANIFEST"]);
+    }
+
+    #[tokio::test]
+    async fn test_crash_at_any_step_leaves_old_or_new() {
+        for (step, survives) in [
+            (Step::TempTorn, "old"),
+            (Step::TempWritten, "old"),
+            (Step::TempSynced, "old"),
+            (Step::Renamed, "new"),
+        ] {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("MANIFEST");
+            Manifest::new(&path).store(b"old").await.unwrap();
+
+            let mut crashing = Manifest::new(&path);
+            crashing.crash_after = Some(step);
+            crashing.store(b"new").await.unwrap();
+
+            // What the restarted process sees
+            let manifest = Manifest::new(&path);
+            let contents = manifest.load().await.unwrap().unwrap();
+            assert_eq!(contents, survives.as_bytes(), "crash after {:?}", step);
+            assert!(!dir.path().join("MANIFEST.tmp").exists(), "crash after {:?}", step);
+
+            manifest.store(b"next").await.unwrap();
+            assert_eq!(manifest.load().await.unwrap().as_deref(), Some(&b"next"[..]));
+        }
+    }
+}
@@ -1,3 +1,22 @@
+pub mod commit_pipeline;
 pub mod disk_manager;
 pub mod disk_scheduler;
-pub mod page_guard;
\ No newline at end of file
+pub mod double_write;
+pub mod free_space;
+pub mod indexed_db_backend;
+pub mod io_stats;
+pub mod io_tuner;
+pub mod log_frame;
+#[cfg(feature = "native")]
+pub mod manifest;
+#[cfg(feature = "object-store")]
+pub mod object_store_backend;
+pub mod page_checksums;
+pub mod page_directory;
+pub mod page_guard;
+pub mod page_repair;
+#[cfg(feature = "native")]
+pub mod platform;
+pub mod redo;
+pub mod storage_backend;
+pub mod tiering;
\ No newline at end of file
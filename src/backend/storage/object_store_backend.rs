@@ -0,0 +1,183 @@
+// src/storage/object_store_backend.rs
+
+//! StorageBackend over an object store (S3, GCS, Azure, or anything else implementing
+//! object_store::ObjectStore), behind the `object-store` feature.
+//!
+//! Object stores cannot write into the middle of an object, so this backend suits data
+//! that is written once and then read, such as SSTables and backups, rather than the
+//! live page file. Reads of an untouched object are ranged GETs. The first write loads
+//! the whole object into memory, later writes change that copy, and sync() uploads it
+//! with a single PUT; until then the store still holds the previous version.
+
+use std::sync::Arc;
+
+use object_store::{ObjectStore, PutPayload, path::Path as ObjectPath};
+use tokio::sync::Mutex;
+
+use crate::backend::storage::storage_backend::{BackendFuture, StorageBackend};
+use crate::common::errors::DiskError;
+
+pub struct ObjectStoreBackend {
+    store: Arc<dyn ObjectStore>,
+    path: ObjectPath,
+    // Whole object, loaded by the first write and uploaded by sync()
+    staged: Mutex<Option<Staged>>,
+}
+
+struct Staged {
+    bytes: Vec<u8>,
+    dirty: bool,
+}
+
+impl ObjectStoreBackend {
+    /// Back onto the object at `path` in `store`. A missing object reads as empty and is
+    /// created by the first sync() after a write.
+    pub fn new(store: Arc<dyn ObjectStore>, path: impl Into<ObjectPath>) -> Self {
+        Self {
+            store,
+            path: path.into(),
+            staged: Mutex::new(None),
+        }
+    }
+
+    pub fn path(&self) -> &ObjectPath {
+        &self.path
+    }
+
+    async fn fetch(&self) -> Result<Vec<u8>, DiskError> {
+        match self.store.get(&self.path).await {
+            Ok(result) => Ok(result.bytes().await.map_err(store_error)?.to_vec()),
+            Err(object_store::Error::NotFound { .. }) => Ok(Vec::new()),
+            Err(e) => Err(store_error(e)),
+        }
+    }
+
+    /// Apply `change` to the staged copy, loading it first if needed.
+    async fn modify(&self, change: impl FnOnce(&mut Vec<u8>)) -> Result<(), DiskError> {
+        let mut staged = self.staged.lock().await;
+        if staged.is_none() {
+            *staged = Some(Staged {
+                bytes: self.fetch().await?,
+                dirty: false,
+            });
+        }
+        let staged = staged.as_mut().expect("staged copy loaded above");
+        change(&mut staged.bytes);
+        staged.dirty = true;
+        Ok(())
+    }
+}
+
+impl StorageBackend for ObjectStoreBackend {
+    fn read_at<'a>(&'a self, offset: u64, buf: &'a mut [u8]) -> BackendFuture<'a, ()> {
+        Box::pin(async move {
+            let end = offset + buf.len() as u64;
+            if let Some(staged) = self.staged.lock().await.as_ref() {
+                let bytes = staged.bytes.get(offset as usize..end as usize).ok_or_else(past_end)?;
+                buf.copy_from_slice(bytes);
+                return Ok(());
+            }
+            if buf.is_empty() {
+                return Ok(());
+            }
+            let bytes = match self.store.get_range(&self.path, offset..end).await {
+                Ok(bytes) => bytes,
+                Err(object_store::Error::NotFound { .. }) => return Err(past_end()),
+                Err(e) => return Err(store_error(e)),
+            };
+            if bytes.len() != buf.len() {
+                return Err(past_end());
+            }
+            buf.copy_from_slice(&bytes);
+            Ok(())
+        })
+    }
+
+    fn write_at<'a>(&'a self, offset: u64, data: &'a [u8]) -> BackendFuture<'a, ()> {
+        Box::pin(self.modify(move |bytes| {
+            let start = offset as usize;
+            let end = start + data.len();
+            if end > bytes.len() {
+                bytes.resize(end, 0);
+            }
+            bytes[start..end].copy_from_slice(data);
+        }))
+    }
+
+    fn append<'a>(&'a self, data: &'a [u8]) -> BackendFuture<'a, ()> {
+        Box::pin(self.modify(move |bytes| bytes.extend_from_slice(data)))
+    }
+
+    /// Upload the staged copy if it has changed since the last sync.
+    fn sync(&self) -> BackendFuture<'_, ()> {
+        Box::pin(async move {
+            let mut staged = self.staged.lock().await;
+            let Some(staged) = staged.as_mut().filter(|staged| staged.dirty) else {
+                return Ok(());
+            };
+            let payload = PutPayload::from(staged.bytes.clone());
+            self.store.put(&self.path, payload).await.map_err(store_error)?;
+            staged.dirty = false;
+            Ok(())
+        })
+    }
+
+    fn size(&self) -> BackendFuture<'_, u64> {
+        Box::pin(async move {
+            if let Some(staged) = self.staged.lock().await.as_ref() {
+                return Ok(staged.bytes.len() as u64);
+            }
+            match self.store.head(&self.path).await {
+                Ok(meta) => Ok(meta.size),
+                Err(object_store::Error::NotFound { .. }) => Ok(0),
+                Err(e) => Err(store_error(e)),
+            }
+        })
+    }
+
+    fn set_len(&self, len: u64) -> BackendFuture<'_, ()> {
+        Box::pin(self.modify(move |bytes| bytes.resize(len as usize, 0)))
+    }
+}
+
+fn store_error(e: object_store::Error) -> DiskError {
+    DiskError::IoError(std::io::Error::other(e))
+}
+
+fn past_end() -> DiskError {
+    DiskError::IoError(std::io::Error::new(
+        std::io::ErrorKind::UnexpectedEof,
+        "read past end of object",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use object_store::memory::InMemory;
+
+    #[tokio::test]
+    async fn test_writes_reach_the_store_on_sync() {
+        let store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let backend = ObjectStoreBackend::new(Arc::clone(&store), "segments/000001.sst");
+        assert_eq!(backend.size().await.unwrap(), 0);
+
+        backend.set_len(8).await.unwrap();
+        backend.write_at(4, b"abcd").await.unwrap();
+        backend.append(b"ef").await.unwrap();
+        let mut buf = [0u8; 6];
+        backend.read_at(4, &mut buf).await.unwrap();
+        assert_eq!(&buf, b"abcdef");
+
+        // Nothing is uploaded before sync
+        let reader = ObjectStoreBackend::new(Arc::clone(&store), "segments/000001.sst");
+        assert_eq!(reader.size().await.unwrap(), 0);
+        backend.sync().await.unwrap();
+        assert_eq!(reader.size().await.unwrap(), 10);
+
+        let mut buf = [0u8; 4];
+        reader.read_at(4, &mut buf).await.unwrap();
+        assert_eq!(&buf, b"abcd");
+        assert!(reader.read_at(8, &mut buf).await.is_err());
+    }
+}
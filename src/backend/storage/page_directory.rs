@@ -0,0 +1,339 @@
+// src/storage/page_directory.rs
+
+//! Durable page directory: which offset each page lives at.
+//!
+//! Every allocation or free appends one small delta record instead of rewriting the
+//! whole directory, so allocation stays cheap under write-heavy workloads. Once the
+//! deltas outnumber the live pages by enough, compact() writes the current mappings as a
+//! snapshot and later deltas are appended after it.
+//!
+//! The directory lives in two backends used in turn. Each one starts with a header
+//! record carrying a generation number; on open, the one with the newer intact header
+//! wins and its records are replayed. A snapshot goes into the idle backend with its
+//! header written last, so a crash during compaction leaves the old generation in
+//! charge. Records are framed with log_frame, so a torn last delta is dropped on open.
+//!
+//! Record payloads (little endian):
+//! | 0: u8 | generation: u64 |                 header
+//! | 1: u8 | page_id: i32 | offset: u64 |     page mapped at offset
+//! | 2: u8 | page_id: i32 |                  page freed
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use tokio::sync::Mutex;
+
+use crate::backend::storage::disk_manager::DiskManager;
+use crate::backend::storage::log_frame::{encode_frame, scan_log};
+use crate::backend::storage::storage_backend::{DurabilityMode, StorageBackend};
+use crate::common::{errors::DiskError, types::PageId};
+
+const TAG_HEADER: u8 = 0;
+const TAG_MAP: u8 = 1;
+const TAG_UNMAP: u8 = 2;
+
+/// Deltas tolerated before compaction, at the least; above that, two per live page.
+const MIN_DELTAS_BEFORE_COMPACTION: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DirectoryDelta {
+    Map { page_id: PageId, offset: u64 },
+    Unmap { page_id: PageId },
+}
+
+impl DirectoryDelta {
+    fn encode(&self) -> Vec<u8> {
+        let mut payload = Vec::with_capacity(13);
+        match *self {
+            DirectoryDelta::Map { page_id, offset } => {
+                payload.push(TAG_MAP);
+                payload.extend_from_slice(&page_id.to_le_bytes());
+                payload.extend_from_slice(&offset.to_le_bytes());
+            }
+            DirectoryDelta::Unmap { page_id } => {
+                payload.push(TAG_UNMAP);
+                payload.extend_from_slice(&page_id.to_le_bytes());
+            }
+        }
+        encode_frame(&payload)
+    }
+
+    fn decode(payload: &[u8]) -> Option<Self> {
+        let page_id = PageId::from_le_bytes(payload.get(1..5)?.try_into().ok()?);
+        match *payload.first()? {
+            TAG_MAP => Some(DirectoryDelta::Map {
+                page_id,
+                offset: u64::from_le_bytes(payload.get(5..13)?.try_into().ok()?),
+            }),
+            TAG_UNMAP => Some(DirectoryDelta::Unmap { page_id }),
+            _ => None,
+        }
+    }
+}
+
+fn encode_header(generation: u64) -> Vec<u8> {
+    let mut payload = vec![TAG_HEADER];
+    payload.extend_from_slice(&generation.to_le_bytes());
+    encode_frame(&payload)
+}
+
+fn decode_header(payload: &[u8]) -> Option<u64> {
+    match payload.split_first()? {
+        (&TAG_HEADER, generation) => Some(u64::from_le_bytes(generation.try_into().ok()?)),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DirectoryStats {
+    pub generation: u64,
+    /// Deltas appended since the last snapshot.
+    pub deltas: usize,
+    /// Bytes in the backend currently appended to.
+    pub bytes: u64,
+    pub compactions: u64,
+}
+
+struct DirectoryState {
+    // Index into `backends` of the one being appended to
+    active: usize,
+    generation: u64,
+    end: u64,
+    deltas: usize,
+    compactions: u64,
+}
+
+pub struct PageDirectory {
+    backends: [Arc<dyn StorageBackend>; 2],
+    state: Mutex<DirectoryState>,
+}
+
+impl PageDirectory {
+    /// Open the directory kept in `first` and `second`, returning it along with the
+    /// mappings it holds. Both start out empty for a new database.
+    pub async fn open(
+        first: Arc<dyn StorageBackend>,
+        second: Arc<dyn StorageBackend>,
+    ) -> Result<(Self, HashMap<PageId, u64>), DiskError> {
+        let backends = [first, second];
+        let mut newest: Option<(usize, u64, Vec<Vec<u8>>, u64)> = None;
+        for (index, backend) in backends.iter().enumerate() {
+            let scan = scan_log(backend.as_ref()).await?;
+            let Some(generation) = scan.records.first().and_then(|header| decode_header(header)) else {
+                continue;
+            };
+            if newest.as_ref().is_none_or(|(_, newest_gen, _, _)| generation > *newest_gen) {
+                newest = Some((index, generation, scan.records, scan.valid_len));
+            }
+        }
+
+        let mut pages = HashMap::new();
+        let state = match newest {
+            Some((active, generation, records, valid_len)) => {
+                for record in &records[1..] {
+                    match DirectoryDelta::decode(record) {
+                        Some(DirectoryDelta::Map { page_id, offset }) => {
+                            pages.insert(page_id, offset);
+                        }
+                        Some(DirectoryDelta::Unmap { page_id }) => {
+                            pages.remove(&page_id);
+                        }
+                        None => return Err(corrupt_record()),
+                    }
+                }
+                // Drop a torn last delta so appends continue from intact records
+                if backends[active].size().await? != valid_len {
+                    backends[active].set_len(valid_len).await?;
+                    backends[active].sync().await?;
+                }
+                DirectoryState {
+                    active,
+                    generation,
+                    end: valid_len,
+                    deltas: records.len() - 1,
+                    compactions: 0,
+                }
+            }
+            None => {
+                let header = encode_header(1);
+                backends[0].set_len(0).await?;
+                backends[0].write_at(0, &header).await?;
+                backends[0].sync().await?;
+                DirectoryState {
+                    active: 0,
+                    generation: 1,
+                    end: header.len() as u64,
+                    deltas: 0,
+                    compactions: 0,
+                }
+            }
+        };
+
+        Ok((
+            Self {
+                backends,
+                state: Mutex::new(state),
+            },
+            pages,
+        ))
+    }
+
+    /// Append `deltas` with one write and sync them per `durability`. `pages` must be
+    /// the mappings with the deltas already applied; it is snapshotted instead if enough
+    /// deltas have piled up.
+    pub async fn append(
+        &self,
+        deltas: &[DirectoryDelta],
+        pages: &HashMap<PageId, u64>,
+        durability: DurabilityMode,
+    ) -> Result<(), DiskError> {
+        let mut state = self.state.lock().await;
+        if state.deltas + deltas.len() > MIN_DELTAS_BEFORE_COMPACTION.max(2 * pages.len()) {
+            return self.write_snapshot(&mut state, pages).await;
+        }
+
+        let bytes: Vec<u8> = deltas.iter().flat_map(DirectoryDelta::encode).collect();
+        let backend = self.backends[state.active].as_ref();
+        backend.write_at(state.end, &bytes).await?;
+        DiskManager::sync_backend_as(durability, backend, state.end, bytes.len() as u64).await?;
+        state.end += bytes.len() as u64;
+        state.deltas += deltas.len();
+        Ok(())
+    }
+
+    /// Replace the accumulated deltas with a snapshot of `pages`.
+    pub async fn compact(&self, pages: &HashMap<PageId, u64>) -> Result<(), DiskError> {
+        let mut state = self.state.lock().await;
+        self.write_snapshot(&mut state, pages).await
+    }
+
+    pub async fn stats(&self) -> DirectoryStats {
+        let state = self.state.lock().await;
+        DirectoryStats {
+            generation: state.generation,
+            deltas: state.deltas,
+            bytes: state.end,
+            compactions: state.compactions,
+        }
+    }
+
+    async fn write_snapshot(
+        &self,
+        state: &mut DirectoryState,
+        pages: &HashMap<PageId, u64>,
+    ) -> Result<(), DiskError> {
+        let generation = state.generation + 1;
+        let header = encode_header(generation);
+        let body: Vec<u8> = pages
+            .iter()
+            .flat_map(|(&page_id, &offset)| DirectoryDelta::Map { page_id, offset }.encode())
+            .collect();
+
+        // The body must be durable before the header that makes this generation the newest
+        let target = self.backends[1 - state.active].as_ref();
+        target.set_len(0).await?;
+        target.write_at(0, &vec![0u8; header.len()]).await?;
+        target.write_at(header.len() as u64, &body).await?;
+        target.sync().await?;
+        target.write_at(0, &header).await?;
+        target.sync().await?;
+
+        // The old generation is superseded; empty it so it does not hold on to space
+        let old = self.backends[state.active].as_ref();
+        old.set_len(0).await?;
+        old.sync().await?;
+
+        state.active = 1 - state.active;
+        state.generation = generation;
+        state.end = (header.len() + body.len()) as u64;
+        state.deltas = 0;
+        state.compactions += 1;
+        Ok(())
+    }
+}
+
+fn corrupt_record() -> DiskError {
+    DiskError::IoError(std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        "unrecognized page directory record",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::storage::storage_backend::MemoryBackend;
+
+    fn backends() -> (Arc<dyn StorageBackend>, Arc<dyn StorageBackend>) {
+        (Arc::new(MemoryBackend::new()), Arc::new(MemoryBackend::new()))
+    }
+
+    #[tokio::test]
+    async fn test_deltas_replay_and_compact() {
+        let (first, second) = backends();
+        let (dir, pages) = PageDirectory::open(Arc::clone(&first), Arc::clone(&second)).await.unwrap();
+        assert!(pages.is_empty());
+
+        let mut pages = HashMap::new();
+        for page_id in 0..10 {
+            let offset = page_id as u64 * 4096;
+            pages.insert(page_id, offset);
+            dir.append(&[DirectoryDelta::Map { page_id, offset }], &pages, DurabilityMode::None)
+                .await
+                .unwrap();
+        }
+        pages.remove(&3);
+        dir.append(&[DirectoryDelta::Unmap { page_id: 3 }], &pages, DurabilityMode::None)
+            .await
+            .unwrap();
+        assert_eq!(dir.stats().await.deltas, 11);
+
+        let (_, reopened) = PageDirectory::open(Arc::clone(&first), Arc::clone(&second)).await.unwrap();
+        assert_eq!(reopened, pages);
+
+        dir.compact(&pages).await.unwrap();
+        let stats = dir.stats().await;
+        assert_eq!((stats.generation, stats.deltas, stats.compactions), (2, 0, 1));
+        assert_eq!(first.size().await.unwrap(), 0);
+
+        pages.insert(3, 9 * 4096 + 4096);
+        dir.append(&[DirectoryDelta::Map { page_id: 3, offset: pages[&3] }], &pages, DurabilityMode::None)
+            .await
+            .unwrap();
+        let (reopened_dir, reopened) = PageDirectory::open(first, second).await.unwrap();
+        assert_eq!(reopened, pages);
+        assert_eq!(reopened_dir.stats().await.generation, 2);
+    }
+
+    #[tokio::test]
+    async fn test_torn_snapshot_keeps_old_generation() {
+        let (first, second) = backends();
+        let (dir, _) = PageDirectory::open(Arc::clone(&first), Arc::clone(&second)).await.unwrap();
+        let pages = HashMap::from([(7, 0)]);
+        dir.append(&[DirectoryDelta::Map { page_id: 7, offset: 0 }], &pages, DurabilityMode::None)
+            .await
+            .unwrap();
+
+        // A snapshot whose body made it to disk but whose header did not
+        let body = DirectoryDelta::Map { page_id: 8, offset: 4096 }.encode();
+        second.write_at(encode_header(2).len() as u64, &body).await.unwrap();
+
+        let (dir, reopened) = PageDirectory::open(first, second).await.unwrap();
+        assert_eq!(reopened, pages);
+        assert_eq!(dir.stats().await.generation, 1);
+    }
+
+    #[tokio::test]
+    async fn test_compacts_when_deltas_pile_up() {
+        let (first, second) = backends();
+        let (dir, _) = PageDirectory::open(first, second).await.unwrap();
+        let pages = HashMap::from([(1, 0)]);
+        for _ in 0..=MIN_DELTAS_BEFORE_COMPACTION {
+            dir.append(&[DirectoryDelta::Map { page_id: 1, offset: 0 }], &pages, DurabilityMode::None)
+                .await
+                .unwrap();
+        }
+        let stats = dir.stats().await;
+        assert_eq!((stats.compactions, stats.deltas), (1, 0));
+    }
+}
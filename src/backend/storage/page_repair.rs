@@ -0,0 +1,39 @@
+// src/storage/page_repair.rs
+
+//! Page repair from a second copy of the database.
+//!
+//! When a page read fails because the backend reports the page corrupt, DiskManager asks
+//! its PageSource (see DiskManager::with_page_repair) for a copy. A replica, or a
+//! database restored from the latest backup, can serve as the source. A clean copy is
+//! written back over the damaged one and handed to the reader, so a bad sector costs a
+//! log line instead of a query that fails on every retry.
+//!
+//! A page counts as corrupt when reading it fails with ErrorCode::Corruption: it does not
+//! match its checksum (see DiskManager::with_page_checksums), the read came up short on
+//! a truncated file, or a backend that verifies what it stores reported InvalidData.
+//!
+//! The source's copy is whatever it has, which may be older than the page that was lost.
+//! That is the trade repair makes for availability; pick a source whose lag is acceptable.
+
+use crate::backend::storage::disk_manager::DiskManager;
+use crate::backend::storage::storage_backend::BackendFuture;
+use crate::common::{errors::DiskError, types::PageId};
+
+/// Where DiskManager fetches clean copies of corrupt pages from.
+pub trait PageSource: Send + Sync {
+    /// Fill `buf` with the source's copy of `page_id`. Ok(false) if it has none.
+    fn fetch_page<'a>(&'a self, page_id: PageId, buf: &'a mut [u8]) -> BackendFuture<'a, bool>;
+}
+
+/// Another database, e.g. a replica or one restored from a backup (opened read-only).
+impl PageSource for DiskManager {
+    fn fetch_page<'a>(&'a self, page_id: PageId, buf: &'a mut [u8]) -> BackendFuture<'a, bool> {
+        Box::pin(async move {
+            match self.read_page(page_id, buf).await {
+                Ok(()) => Ok(true),
+                Err(DiskError::PageNotFound(_)) => Ok(false),
+                Err(e) => Err(e),
+            }
+        })
+    }
+}
@@ -0,0 +1,227 @@
+// src/storage/platform.rs
+
+//! Platform specifics behind FileBackend's durability guarantees.
+//!
+//! What each operation maps to:
+//!
+//! | operation      | Linux           | macOS / iOS                  | Windows                   |
+//! |----------------|-----------------|------------------------------|---------------------------|
+//! | full_sync      | fsync           | fcntl(F_FULLFSYNC)           | FlushFileBuffers          |
+//! | data_sync      | fdatasync       | fcntl(F_FULLFSYNC)           | FlushFileBuffers          |
+//! | range_sync     | sync_file_range | fcntl(F_FULLFSYNC)           | FlushFileBuffers          |
+//! | sync_dir       | fsync on the dir| fcntl(F_FULLFSYNC) on the dir| nothing, NTFS journals it |
+//! | preallocate    | fallocate       | set_len                      | set_len                   |
+//! | try_lock       | flock           | flock                        | LockFileEx                |
+//! | available      | fstatvfs        | fstatvfs                     | unknown                   |
+//!
+//! On macOS a plain fsync only hands the data to the drive, which may keep it in its
+//! volatile cache across a power loss; F_FULLFSYNC also flushes that cache. So does
+//! FlushFileBuffers on Windows. Some filesystems (network mounts, FAT) reject
+//! F_FULLFSYNC, and there fsync is the best on offer, the same fallback SQLite makes.
+//!
+//! Every function is synchronous; FileBackend calls them on the blocking pool.
+
+use std::{fs::File, io, path::Path};
+
+/// OpenOptions for the handles FileBackend opens. FileBackend opens a handle per
+/// operation and they overlap, so on Windows each must share read, write and delete
+/// access or the next open fails with a sharing violation. That is std's default today;
+/// it is spelled out so the guarantee does not rest on a default.
+pub fn open_options() -> std::fs::OpenOptions {
+    #[allow(unused_mut)]
+    let mut options = std::fs::OpenOptions::new();
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::OpenOptionsExt;
+
+        const FILE_SHARE_READ: u32 = 0x1;
+        const FILE_SHARE_WRITE: u32 = 0x2;
+        const FILE_SHARE_DELETE: u32 = 0x4;
+        options.share_mode(FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE);
+    }
+    options
+}
+
+/// Make the file's data and metadata durable, through the drive's write cache.
+#[cfg(target_vendor = "apple")]
+pub fn full_sync(file: &File) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    // SAFETY: the fd is owned by `file`, which outlives the call.
+    if unsafe { libc::fcntl(file.as_raw_fd(), libc::F_FULLFSYNC) } == 0 {
+        return Ok(());
+    }
+    // SAFETY: as above.
+    if unsafe { libc::fsync(file.as_raw_fd()) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Make the file's data and metadata durable, through the drive's write cache.
+/// std already maps this to fsync on Linux and FlushFileBuffers on Windows.
+#[cfg(not(target_vendor = "apple"))]
+pub fn full_sync(file: &File) -> io::Result<()> {
+    file.sync_all()
+}
+
+/// Make the file's data durable, skipping metadata not needed to read it back. macOS has
+/// no fdatasync that reaches the platter, so it gets a full sync.
+#[cfg(target_vendor = "apple")]
+pub fn data_sync(file: &File) -> io::Result<()> {
+    full_sync(file)
+}
+
+#[cfg(not(target_vendor = "apple"))]
+pub fn data_sync(file: &File) -> io::Result<()> {
+    file.sync_data()
+}
+
+/// Flush only `offset..offset + len` (`len == 0` means to the end), where the platform
+/// can; elsewhere the whole file's data.
+#[cfg(target_os = "linux")]
+pub fn range_sync(file: &File, offset: u64, len: u64) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let flags = libc::SYNC_FILE_RANGE_WAIT_BEFORE
+        | libc::SYNC_FILE_RANGE_WRITE
+        | libc::SYNC_FILE_RANGE_WAIT_AFTER;
+    // SAFETY: the fd is owned by `file`, which outlives the call.
+    let ret = unsafe { libc::sync_file_range(file.as_raw_fd(), offset as i64, len as i64, flags) };
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn range_sync(file: &File, _offset: u64, _len: u64) -> io::Result<()> {
+    data_sync(file)
+}
+
+/// Make the directory entries in `dir` durable, so a file created (or renamed) there
+/// is still there after a crash. Windows cannot open a directory for flushing; NTFS
+/// commits directory changes through its own journal.
+#[cfg(unix)]
+pub fn sync_dir(dir: &Path) -> io::Result<()> {
+    full_sync(&File::open(dir)?)
+}
+
+#[cfg(not(unix))]
+pub fn sync_dir(_dir: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Reserve disk blocks so the file is at least `len` bytes long. Never shrinks.
+#[cfg(target_os = "linux")]
+pub fn preallocate(file: &File, len: u64) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    // SAFETY: the fd is owned by `file`, which outlives the call.
+    let ret = unsafe { libc::fallocate(file.as_raw_fd(), 0, 0, len as libc::off_t) };
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn preallocate(file: &File, len: u64) -> io::Result<()> {
+    if file.metadata()?.len() < len {
+        file.set_len(len)?;
+    }
+    Ok(())
+}
+
+/// Take an advisory lock on `file` without waiting: exclusive, or shared with other
+/// shared holders. Ok(false) means another handle holds a conflicting lock. The lock is
+/// released when the handle is closed.
+pub fn try_lock(file: &File, exclusive: bool) -> io::Result<bool> {
+    let result = if exclusive {
+        file.try_lock()
+    } else {
+        file.try_lock_shared()
+    };
+    match result {
+        Ok(()) => Ok(true),
+        Err(std::fs::TryLockError::WouldBlock) => Ok(false),
+        Err(std::fs::TryLockError::Error(e)) => Err(e),
+    }
+}
+
+/// Bytes free on the volume holding `file`, or None where that is unknown.
+#[cfg(unix)]
+pub fn available_space(file: &File) -> io::Result<Option<u64>> {
+    use std::os::fd::AsRawFd;
+
+    let mut stats = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: the fd is owned by `file`, which outlives the call, and fstatvfs fills in
+    // `stats` when it succeeds.
+    let ret = unsafe { libc::fstatvfs(file.as_raw_fd(), stats.as_mut_ptr()) };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: initialized by the successful call above.
+    let stats = unsafe { stats.assume_init() };
+    // Blocks available to unprivileged users, not the root-reserved ones. The field
+    // types are narrower than u64 on some targets.
+    #[allow(clippy::unnecessary_cast)]
+    let available = stats.f_bavail as u64 * stats.f_frsize as u64;
+    Ok(Some(available))
+}
+
+#[cfg(not(unix))]
+pub fn available_space(_file: &File) -> io::Result<Option<u64>> {
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    // These run against whatever platform the tests are built for, so each target in CI
+    // checks its own implementation.
+
+    #[test]
+    fn test_syncs_succeed_on_this_platform() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sync.db");
+        let mut file = open_options().read(true).write(true).create(true).truncate(false).open(&path).unwrap();
+        file.write_all(&[7u8; 8192]).unwrap();
+
+        full_sync(&file).unwrap();
+        data_sync(&file).unwrap();
+        range_sync(&file, 4096, 4096).unwrap();
+        range_sync(&file, 0, 0).unwrap();
+        sync_dir(dir.path()).unwrap();
+
+        preallocate(&file, 16384).unwrap();
+        preallocate(&file, 4096).unwrap();
+        assert_eq!(file.metadata().unwrap().len(), 16384);
+        if let Some(available) = available_space(&file).unwrap() {
+            assert!(available > 0);
+        }
+    }
+
+    #[test]
+    fn test_lock_excludes_other_handles() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lock.db");
+        let open = || open_options().read(true).write(true).create(true).truncate(false).open(&path).unwrap();
+
+        let (first, second) = (open(), open());
+        assert!(try_lock(&first, true).unwrap());
+        assert!(!try_lock(&second, true).unwrap());
+        assert!(!try_lock(&second, false).unwrap());
+        // Closing the holder releases the lock
+        drop(first);
+        assert!(try_lock(&second, false).unwrap());
+        let third = open();
+        assert!(try_lock(&third, false).unwrap());
+        assert!(!try_lock(&open(), true).unwrap());
+    }
+}
@@ -0,0 +1,476 @@
+// src/storage/storage_backend.rs
+
+//! StorageBackend module
+//! Abstracts the byte-level I/O that DiskManager performs on its data and log files,
+//! so the engine is not tied to tokio::fs.
+//!
+//! - FileBackend (feature `native`, on by default): a regular file on the local filesystem
+//! - MemoryBackend: a growable in-memory buffer, for tests and targets without a
+//!   filesystem
+//! - IndexedDbBackend: blocks in a browser's IndexedDB, for wasm32
+//! - ObjectStoreBackend (feature `object-store`): an object in S3 or another object
+//!   store, for write-once data such as SSTables and backups
+
+use std::{future::Future, pin::Pin};
+#[cfg(feature = "native")]
+use std::path::{Path, PathBuf};
+
+use tokio::sync::RwLock;
+#[cfg(feature = "native")]
+use tokio::{
+    fs::OpenOptions,
+    io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt},
+};
+
+#[cfg(feature = "native")]
+use crate::backend::storage::platform;
+use crate::common::errors::DiskError;
+
+/// How hard DiskManager pushes writes to stable storage after each page or log write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DurabilityMode {
+    /// fsync: data and all metadata are durable once the write returns. Slowest, and the default.
+    #[default]
+    SyncAll,
+    /// fdatasync: data and the metadata needed to read it back (e.g. file size) are durable,
+    /// but timestamps may be lost on a crash.
+    DataSync,
+    /// sync_file_range on Linux: only the written range is flushed to the device. File size
+    /// changes and the drive's volatile cache are not covered, so a power loss can still
+    /// lose the write. Falls back to DataSync on other platforms.
+    RangeSync,
+    /// No syncing at all; the OS writes back whenever it likes. A crash can lose any write.
+    None,
+}
+
+impl DurabilityMode {
+    /// Name used in config files.
+    pub fn name(self) -> &'static str {
+        match self {
+            DurabilityMode::SyncAll => "sync_all",
+            DurabilityMode::DataSync => "data_sync",
+            DurabilityMode::RangeSync => "range_sync",
+            DurabilityMode::None => "none",
+        }
+    }
+}
+
+impl std::str::FromStr for DurabilityMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [
+            DurabilityMode::SyncAll,
+            DurabilityMode::DataSync,
+            DurabilityMode::RangeSync,
+            DurabilityMode::None,
+        ]
+        .into_iter()
+        .find(|mode| mode.name() == s)
+        .ok_or_else(|| format!("unknown durability mode {:?}, expected sync_all, data_sync, range_sync or none", s))
+    }
+}
+
+/// Boxed future returned by StorageBackend methods, keeping the trait object safe.
+pub type BackendFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, DiskError>> + Send + 'a>>;
+
+/// Positional byte storage used by DiskManager for both the db file and the log.
+pub trait StorageBackend: Send + Sync {
+    /// Fill `buf` with the bytes at `offset`. Reading past the end is an error.
+    fn read_at<'a>(&'a self, offset: u64, buf: &'a mut [u8]) -> BackendFuture<'a, ()>;
+
+    /// Write `data` at `offset`, growing the storage if needed.
+    fn write_at<'a>(&'a self, offset: u64, data: &'a [u8]) -> BackendFuture<'a, ()>;
+
+    /// Append `data` at the current end of the storage.
+    fn append<'a>(&'a self, data: &'a [u8]) -> BackendFuture<'a, ()>;
+
+    /// Write `bufs` back to back starting at `offset`, in one vectored write where the
+    /// backend supports it.
+    fn write_vectored_at(&self, offset: u64, bufs: Vec<Vec<u8>>) -> BackendFuture<'_, ()> {
+        Box::pin(async move { self.write_at(offset, &bufs.concat()).await })
+    }
+
+    /// Reserve space so the storage is at least `len` bytes long, zero-filled. Never shrinks.
+    fn preallocate(&self, len: u64) -> BackendFuture<'_, ()> {
+        Box::pin(async move {
+            if self.size().await? < len {
+                self.set_len(len).await?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Make all previous writes and metadata durable.
+    fn sync(&self) -> BackendFuture<'_, ()>;
+
+    /// Make all previous writes durable, skipping metadata not needed to read them back.
+    fn sync_data(&self) -> BackendFuture<'_, ()> {
+        self.sync()
+    }
+
+    /// Flush only the bytes in `offset..offset + len` (`len == 0` means to the end).
+    fn sync_range(&self, _offset: u64, _len: u64) -> BackendFuture<'_, ()> {
+        self.sync_data()
+    }
+
+    /// Current size in bytes.
+    fn size(&self) -> BackendFuture<'_, u64>;
+
+    /// Truncate or extend (with zeroes) to exactly `len` bytes.
+    fn set_len(&self, len: u64) -> BackendFuture<'_, ()>;
+
+    /// Bytes still free on the volume holding this backend, or None if that is unknown
+    /// or does not apply.
+    fn available_space(&self) -> BackendFuture<'_, Option<u64>> {
+        Box::pin(async { Ok(None) })
+    }
+}
+
+/// Backend over a file on the local filesystem.
+/// A handle is opened per operation, matching how DiskManager always did its I/O. Syncs,
+/// locking and the other platform specifics go through the platform module.
+#[cfg(feature = "native")]
+pub struct FileBackend {
+    path: PathBuf,
+    // Handle holding the lock taken by lock_exclusive(), released on drop
+    lock: Option<std::fs::File>,
+}
+
+// tokio OpenOptions with the platform's share modes
+#[cfg(feature = "native")]
+fn open_options() -> OpenOptions {
+    OpenOptions::from(platform::open_options())
+}
+
+// Run a blocking file operation on the blocking pool
+#[cfg(feature = "native")]
+async fn blocking<T: Send + 'static>(
+    f: impl FnOnce() -> std::io::Result<T> + Send + 'static,
+) -> Result<T, DiskError> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| DiskError::IoError(std::io::Error::other(e)))?
+        .map_err(DiskError::IoError)
+}
+
+#[cfg(feature = "native")]
+impl FileBackend {
+    /// Open the file at `path`, creating it if it does not exist. A newly created file's
+    /// directory entry is synced, so the file itself survives a crash.
+    pub async fn open(path: &Path) -> Result<Self, DiskError> {
+        let existed = tokio::fs::try_exists(path).await.map_err(DiskError::IoError)?;
+        open_options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .await
+            .map_err(DiskError::IoError)?;
+        if !existed {
+            let dir = match path.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+                _ => PathBuf::from("."),
+            };
+            blocking(move || platform::sync_dir(&dir)).await?;
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            lock: None,
+        })
+    }
+
+    /// Open an existing file without write access. Fails if it does not exist.
+    pub async fn open_read_only(path: &Path) -> Result<Self, DiskError> {
+        open_options().read(true).open(path).await.map_err(DiskError::IoError)?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            lock: None,
+        })
+    }
+
+    /// Take an exclusive lock on the file for as long as this backend lives, so no other
+    /// process (or other backend in this one) can write it at the same time. Fails with
+    /// DatabaseLocked if someone else holds it.
+    pub async fn lock_exclusive(mut self) -> Result<Self, DiskError> {
+        let path = self.path.clone();
+        let (file, locked) = blocking(move || {
+            let file = platform::open_options().read(true).write(true).open(&path)?;
+            let locked = platform::try_lock(&file, true)?;
+            Ok((file, locked))
+        })
+        .await?;
+        if !locked {
+            return Err(DiskError::DatabaseLocked(self.path));
+        }
+        self.lock = Some(file);
+        Ok(self)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[cfg(feature = "native")]
+impl StorageBackend for FileBackend {
+    fn read_at<'a>(&'a self, offset: u64, buf: &'a mut [u8]) -> BackendFuture<'a, ()> {
+        Box::pin(async move {
+            let mut file = open_options().read(true).open(&self.path).await.map_err(DiskError::IoError)?;
+            file.seek(std::io::SeekFrom::Start(offset))
+                .await
+                .map_err(DiskError::IoError)?;
+            file.read_exact(buf).await.map_err(DiskError::IoError)?;
+            Ok(())
+        })
+    }
+
+    fn write_at<'a>(&'a self, offset: u64, data: &'a [u8]) -> BackendFuture<'a, ()> {
+        Box::pin(async move {
+            let mut file = open_options()
+                .write(true)
+                .open(&self.path)
+                .await
+                .map_err(DiskError::IoError)?;
+            file.seek(std::io::SeekFrom::Start(offset))
+                .await
+                .map_err(DiskError::IoError)?;
+            file.write_all(data).await.map_err(DiskError::IoError)?;
+            // tokio finishes the write in the background; wait for it before dropping the handle
+            file.flush().await.map_err(DiskError::IoError)?;
+            Ok(())
+        })
+    }
+
+    fn append<'a>(&'a self, data: &'a [u8]) -> BackendFuture<'a, ()> {
+        Box::pin(async move {
+            let mut file = open_options()
+                .append(true)
+                .open(&self.path)
+                .await
+                .map_err(DiskError::IoError)?;
+            file.write_all(data).await.map_err(DiskError::IoError)?;
+            // tokio finishes the write in the background; wait for it before dropping the handle
+            file.flush().await.map_err(DiskError::IoError)?;
+            Ok(())
+        })
+    }
+
+    fn write_vectored_at(&self, offset: u64, bufs: Vec<Vec<u8>>) -> BackendFuture<'_, ()> {
+        let path = self.path.clone();
+        Box::pin(blocking(move || {
+            let mut file = platform::open_options().write(true).open(&path)?;
+            std::io::Seek::seek(&mut file, std::io::SeekFrom::Start(offset))?;
+            write_all_vectored(&mut file, &bufs)
+        }))
+    }
+
+    fn preallocate(&self, len: u64) -> BackendFuture<'_, ()> {
+        let path = self.path.clone();
+        Box::pin(blocking(move || {
+            let file = platform::open_options().write(true).open(&path)?;
+            platform::preallocate(&file, len)
+        }))
+    }
+
+    fn sync(&self) -> BackendFuture<'_, ()> {
+        let path = self.path.clone();
+        Box::pin(blocking(move || {
+            let file = platform::open_options().write(true).open(&path)?;
+            platform::full_sync(&file)
+        }))
+    }
+
+    fn sync_data(&self) -> BackendFuture<'_, ()> {
+        let path = self.path.clone();
+        Box::pin(blocking(move || {
+            let file = platform::open_options().write(true).open(&path)?;
+            platform::data_sync(&file)
+        }))
+    }
+
+    fn sync_range(&self, offset: u64, len: u64) -> BackendFuture<'_, ()> {
+        let path = self.path.clone();
+        Box::pin(blocking(move || {
+            let file = platform::open_options().write(true).open(&path)?;
+            platform::range_sync(&file, offset, len)
+        }))
+    }
+
+    fn size(&self) -> BackendFuture<'_, u64> {
+        Box::pin(async move {
+            let metadata = tokio::fs::metadata(&self.path)
+                .await
+                .map_err(DiskError::IoError)?;
+            Ok(metadata.len())
+        })
+    }
+
+    fn set_len(&self, len: u64) -> BackendFuture<'_, ()> {
+        Box::pin(async move {
+            let file = open_options()
+                .write(true)
+                .open(&self.path)
+                .await
+                .map_err(DiskError::IoError)?;
+            file.set_len(len).await.map_err(DiskError::IoError)
+        })
+    }
+
+    fn available_space(&self) -> BackendFuture<'_, Option<u64>> {
+        let path = self.path.clone();
+        Box::pin(blocking(move || {
+            let file = platform::open_options().read(true).open(&path)?;
+            platform::available_space(&file)
+        }))
+    }
+}
+
+// std's write_all_vectored is not stable yet
+#[cfg(feature = "native")]
+fn write_all_vectored(file: &mut std::fs::File, bufs: &[Vec<u8>]) -> std::io::Result<()> {
+    use std::io::{IoSlice, Write};
+
+    let mut owned: Vec<IoSlice<'_>> = bufs.iter().map(|buf| IoSlice::new(buf)).collect();
+    let mut slices = &mut owned[..];
+    while !slices.is_empty() {
+        match file.write_vectored(slices) {
+            Ok(0) => return Err(std::io::ErrorKind::WriteZero.into()),
+            Ok(n) => IoSlice::advance_slices(&mut slices, n),
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+/// Backend over an in-memory buffer. Nothing survives the process, and sync is a no-op.
+#[derive(Default)]
+pub struct MemoryBackend {
+    bytes: RwLock<Vec<u8>>,
+    // What available_space() reports, for exercising low-space handling
+    available: std::sync::Mutex<Option<u64>>,
+}
+
+impl MemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make available_space() report `bytes`, as if the buffer lived on a volume with
+    /// that much room left. It does not change as the buffer grows.
+    pub fn set_available_space(&self, bytes: Option<u64>) {
+        *self.available.lock().unwrap_or_else(|p| p.into_inner()) = bytes;
+    }
+}
+
+impl StorageBackend for MemoryBackend {
+    fn read_at<'a>(&'a self, offset: u64, buf: &'a mut [u8]) -> BackendFuture<'a, ()> {
+        Box::pin(async move {
+            let bytes = self.bytes.read().await;
+            let start = offset as usize;
+            let end = start + buf.len();
+            if end > bytes.len() {
+                return Err(DiskError::IoError(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "read past end of in-memory storage",
+                )));
+            }
+            buf.copy_from_slice(&bytes[start..end]);
+            Ok(())
+        })
+    }
+
+    fn write_at<'a>(&'a self, offset: u64, data: &'a [u8]) -> BackendFuture<'a, ()> {
+        Box::pin(async move {
+            let mut bytes = self.bytes.write().await;
+            let start = offset as usize;
+            let end = start + data.len();
+            if end > bytes.len() {
+                bytes.resize(end, 0);
+            }
+            bytes[start..end].copy_from_slice(data);
+            Ok(())
+        })
+    }
+
+    fn append<'a>(&'a self, data: &'a [u8]) -> BackendFuture<'a, ()> {
+        Box::pin(async move {
+            self.bytes.write().await.extend_from_slice(data);
+            Ok(())
+        })
+    }
+
+    fn sync(&self) -> BackendFuture<'_, ()> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn size(&self) -> BackendFuture<'_, u64> {
+        Box::pin(async move { Ok(self.bytes.read().await.len() as u64) })
+    }
+
+    fn set_len(&self, len: u64) -> BackendFuture<'_, ()> {
+        Box::pin(async move {
+            self.bytes.write().await.resize(len as usize, 0);
+            Ok(())
+        })
+    }
+
+    fn available_space(&self) -> BackendFuture<'_, Option<u64>> {
+        let available = *self.available.lock().unwrap_or_else(|p| p.into_inner());
+        Box::pin(async move { Ok(available) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "native")]
+    use tempfile::tempdir;
+
+    async fn exercise(backend: &dyn StorageBackend) {
+        backend.set_len(8).await.unwrap();
+        backend.write_at(4, b"abcd").await.unwrap();
+        backend.append(b"xy").await.unwrap();
+        backend.write_vectored_at(10, vec![b"12".to_vec(), Vec::new(), b"345".to_vec()]).await.unwrap();
+        backend.set_len(10).await.unwrap();
+        backend.sync().await.unwrap();
+        backend.sync_data().await.unwrap();
+        backend.sync_range(4, 4).await.unwrap();
+
+        assert_eq!(backend.size().await.unwrap(), 10);
+        let mut buf = [0u8; 6];
+        backend.read_at(4, &mut buf).await.unwrap();
+        assert_eq!(&buf, b"abcdxy");
+
+        let mut past_end = [0u8; 4];
+        assert!(backend.read_at(8, &mut past_end).await.is_err());
+
+        backend.write_vectored_at(12, vec![b"ab".to_vec(), b"cd".to_vec()]).await.unwrap();
+        backend.preallocate(32).await.unwrap();
+        backend.preallocate(8).await.unwrap();
+        assert_eq!(backend.size().await.unwrap(), 32);
+        let mut buf = [0xFFu8; 8];
+        backend.read_at(10, &mut buf).await.unwrap();
+        assert_eq!(&buf, b"\0\0abcd\0\0");
+    }
+
+    #[cfg(feature = "native")]
+    #[tokio::test]
+    async fn test_file_backend() {
+        let dir = tempdir().unwrap();
+        let backend = FileBackend::open(&dir.path().join("backend.db")).await.unwrap();
+        exercise(&backend).await;
+        if cfg!(target_os = "linux") {
+            assert!(backend.available_space().await.unwrap().is_some_and(|bytes| bytes > 0));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_memory_backend() {
+        exercise(&MemoryBackend::new()).await;
+    }
+}
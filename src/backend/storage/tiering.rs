@@ -0,0 +1,171 @@
+// src/storage/tiering.rs
+
+//! Cold storage tier.
+//!
+//! DiskManager keeps pages in its main ("hot") backend. With a cold tier attached,
+//! DiskManager::migrate_cold() moves pages that have not been read or written for a
+//! while into a second, slower and cheaper backend (an HDD path, an object store) and
+//! frees their hot slots, which compact() can then give back to the filesystem. Reading
+//! or writing a cold page promotes it back transparently.
+//!
+//! Recency is tracked at the disk level, so a page the buffer pool keeps serving from
+//! memory looks cold here; demoting it costs nothing until it is evicted and read again.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex as SyncMutex},
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+use tokio::sync::{Mutex, MutexGuard};
+
+use crate::backend::storage::disk_manager::GRIMOIRE_PAGE_SIZE;
+use crate::backend::storage::storage_backend::StorageBackend;
+use crate::common::{clock::Clock, types::PageId};
+
+/// When a page counts as cold, and how much one migration run may move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TieringPolicy {
+    /// Pages not read or written for this long are demoted.
+    pub cold_after: Duration,
+    /// Upper bound on pages demoted per run, to spread migration traffic out.
+    pub max_pages_per_run: usize,
+}
+
+impl Default for TieringPolicy {
+    fn default() -> Self {
+        Self {
+            cold_after: Duration::from_secs(24 * 60 * 60),
+            max_pages_per_run: 1024,
+        }
+    }
+}
+
+/// Where pages live and how much has moved between the tiers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct TierStats {
+    pub hot_pages: usize,
+    pub cold_pages: usize,
+    pub pages_demoted: u64,
+    pub pages_promoted: u64,
+    pub bytes_demoted: u64,
+    pub bytes_promoted: u64,
+}
+
+pub(crate) struct ColdPages {
+    pub(crate) offsets: HashMap<PageId, u64>,
+    free_slots: Vec<u64>,
+    next_offset: u64,
+}
+
+impl ColdPages {
+    /// Slot for a page being demoted.
+    pub(crate) fn allocate(&mut self) -> u64 {
+        self.free_slots.pop().unwrap_or_else(|| {
+            let offset = self.next_offset;
+            self.next_offset += GRIMOIRE_PAGE_SIZE as u64;
+            offset
+        })
+    }
+
+    /// Give back a slot from allocate() that ended up unused.
+    pub(crate) fn release(&mut self, offset: u64) {
+        self.free_slots.push(offset);
+    }
+
+    /// Drop `page_id` from the tier. Returns whether it was there.
+    pub(crate) fn remove(&mut self, page_id: PageId) -> bool {
+        match self.offsets.remove(&page_id) {
+            Some(offset) => {
+                self.release(offset);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+pub(crate) struct ColdTier {
+    pub(crate) backend: Arc<dyn StorageBackend>,
+    // Also serializes promotions, so a page is never promoted twice
+    pages: Mutex<ColdPages>,
+    last_access: SyncMutex<HashMap<PageId, Instant>>,
+    // Pages with no recorded access count as accessed when the tier was attached
+    attached_at: Instant,
+    clock: Arc<dyn Clock>,
+    stats: SyncMutex<TierStats>,
+}
+
+impl ColdTier {
+    /// Attach `backend` as an empty cold tier; anything already in it is overwritten.
+    pub(crate) fn new(backend: Arc<dyn StorageBackend>, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            backend,
+            pages: Mutex::new(ColdPages {
+                offsets: HashMap::new(),
+                free_slots: Vec::new(),
+                next_offset: 0,
+            }),
+            last_access: SyncMutex::new(HashMap::new()),
+            attached_at: clock.now(),
+            clock,
+            stats: SyncMutex::new(TierStats::default()),
+        }
+    }
+
+    pub(crate) async fn lock_pages(&self) -> MutexGuard<'_, ColdPages> {
+        self.pages.lock().await
+    }
+
+    pub(crate) fn touch(&self, page_id: PageId) {
+        self.lock_last_access().insert(page_id, self.clock.now());
+    }
+
+    pub(crate) fn forget(&self, page_id: PageId) {
+        self.lock_last_access().remove(&page_id);
+    }
+
+    /// Of `candidates`, the ones idle for at least `cold_after`, least recently used first.
+    pub(crate) fn coldest(
+        &self,
+        candidates: impl Iterator<Item = PageId>,
+        policy: &TieringPolicy,
+    ) -> Vec<PageId> {
+        let now = self.clock.now();
+        let last_access = self.lock_last_access();
+        let mut cold: Vec<(Instant, PageId)> = candidates
+            .map(|page_id| (last_access.get(&page_id).copied().unwrap_or(self.attached_at), page_id))
+            .filter(|&(at, _)| now.duration_since(at) >= policy.cold_after)
+            .collect();
+        cold.sort_unstable();
+        cold.into_iter()
+            .take(policy.max_pages_per_run)
+            .map(|(_, page_id)| page_id)
+            .collect()
+    }
+
+    pub(crate) fn record_demoted(&self) {
+        let mut stats = self.lock_stats();
+        stats.pages_demoted += 1;
+        stats.bytes_demoted += GRIMOIRE_PAGE_SIZE as u64;
+    }
+
+    pub(crate) fn record_promoted(&self) {
+        let mut stats = self.lock_stats();
+        stats.pages_promoted += 1;
+        stats.bytes_promoted += GRIMOIRE_PAGE_SIZE as u64;
+    }
+
+    pub(crate) fn stats(&self) -> TierStats {
+        *self.lock_stats()
+    }
+
+    fn lock_last_access(&self) -> std::sync::MutexGuard<'_, HashMap<PageId, Instant>> {
+        self.last_access.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn lock_stats(&self) -> std::sync::MutexGuard<'_, TierStats> {
+        self.stats.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
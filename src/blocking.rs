@@ -0,0 +1,90 @@
+//! Blocking facade over the async storage engine.
+//!
+//! Embedders that don't run tokio (CLI tools, sync services) can use these
+//! wrappers instead of the async API. Each handle owns a small current-thread
+//! runtime and blocks on it for every call.
+//!
+//! Calling these methods from inside an async context panics, the same way
+//! `Runtime::block_on` does; use the async API there instead.
+
+use std::{path::Path, sync::Arc};
+
+use tokio::runtime::{Builder, Runtime};
+
+use crate::backend::storage::disk_manager::DiskManager;
+use crate::common::{errors::DiskError, types::PageId};
+
+/// Synchronous handle to a DiskManager.
+pub struct BlockingDiskManager {
+    runtime: Runtime,
+    manager: Arc<DiskManager>,
+}
+
+impl BlockingDiskManager {
+    /// Open (or create) the database file at `db_file`.
+    pub fn open(db_file: &Path) -> Result<Self, DiskError> {
+        let runtime = Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(DiskError::IoError)?;
+        let manager = runtime.block_on(DiskManager::new(db_file))?;
+
+        Ok(Self {
+            runtime,
+            manager: Arc::new(manager),
+        })
+    }
+
+    /// Write a page, blocking until it is durable.
+    pub fn write_page(&self, page_id: PageId, page_data: &[u8]) -> Result<(), DiskError> {
+        self.runtime.block_on(self.manager.write_page(page_id, page_data))
+    }
+
+    /// Read a page into `page_data`.
+    pub fn read_page(&self, page_id: PageId, page_data: &mut [u8]) -> Result<(), DiskError> {
+        self.runtime.block_on(self.manager.read_page(page_id, page_data))
+    }
+
+    /// Delete a page and release its slot for reuse.
+    pub fn delete_page(&self, page_id: PageId) -> Result<(), DiskError> {
+        self.runtime.block_on(self.manager.delete_page(page_id))
+    }
+
+    /// Append to the log file, blocking until it is durable.
+    pub fn write_log(&self, log_data: &[u8]) -> Result<(), DiskError> {
+        self.runtime.block_on(self.manager.write_log(log_data))
+    }
+
+    /// Every intact record in the log, oldest first.
+    pub fn read_log(&self) -> Result<Vec<Vec<u8>>, DiskError> {
+        self.runtime.block_on(self.manager.read_log())
+    }
+
+    /// Shared handle to the underlying async manager, for mixing sync and async callers.
+    pub fn manager(&self) -> Arc<DiskManager> {
+        Arc::clone(&self.manager)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::storage::disk_manager::GRIMOIRE_PAGE_SIZE;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_blocking_write_read_delete() {
+        let dir = tempdir().unwrap();
+        let dm = BlockingDiskManager::open(&dir.path().join("blocking.db")).unwrap();
+
+        let page_data = vec![7u8; GRIMOIRE_PAGE_SIZE];
+        dm.write_page(3, &page_data).unwrap();
+
+        let mut buf = vec![0u8; GRIMOIRE_PAGE_SIZE];
+        dm.read_page(3, &mut buf).unwrap();
+        assert_eq!(buf, page_data);
+
+        dm.delete_page(3).unwrap();
+        assert!(matches!(dm.read_page(3, &mut buf), Err(DiskError::PageNotFound(3))));
+    }
+}
@@ -0,0 +1,123 @@
+//! Admission control for queries and transactions.
+//! At most `max_running` holders of an AdmissionPermit execute at once. Up to `max_queued`
+//! more wait in FIFO order for a slot; anything beyond that is rejected right away with
+//! AdmissionError::Busy, so overload sheds work instead of piling it onto the buffer pool
+//! and disk.
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::common::errors::AdmissionError;
+
+#[derive(Debug)]
+pub struct AdmissionController {
+    max_running: usize,
+    max_queued: usize,
+    slots: Arc<Semaphore>,
+    queued: AtomicUsize,
+}
+
+/// Held for the lifetime of a query/transaction; frees its slot on drop.
+#[derive(Debug)]
+pub struct AdmissionPermit {
+    _permit: OwnedSemaphorePermit,
+}
+
+impl AdmissionController {
+    pub fn new(max_running: usize, max_queued: usize) -> Self {
+        Self {
+            max_running,
+            max_queued,
+            slots: Arc::new(Semaphore::new(max_running)),
+            queued: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn running(&self) -> usize {
+        self.max_running - self.slots.available_permits()
+    }
+
+    pub fn queued(&self) -> usize {
+        self.queued.load(Ordering::SeqCst)
+    }
+
+    /// Take a slot if one is free, without queueing.
+    pub fn try_admit(&self) -> Result<AdmissionPermit, AdmissionError> {
+        match Arc::clone(&self.slots).try_acquire_owned() {
+            Ok(permit) => Ok(AdmissionPermit { _permit: permit }),
+            Err(_) => Err(self.busy()),
+        }
+    }
+
+    /// Take a slot, waiting in the queue if all are taken. Fails with Busy when the
+    /// queue is full too.
+    pub async fn admit(&self) -> Result<AdmissionPermit, AdmissionError> {
+        if let Ok(permit) = self.try_admit() {
+            return Ok(permit);
+        }
+        let reserved = self
+            .queued
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |queued| {
+                (queued < self.max_queued).then_some(queued + 1)
+            });
+        if reserved.is_err() {
+            return Err(self.busy());
+        }
+        // Leave the queue even if the caller gives up waiting
+        let _queued = QueueSlot(&self.queued);
+        let permit = Arc::clone(&self.slots)
+            .acquire_owned()
+            .await
+            .expect("admission semaphore is never closed");
+        Ok(AdmissionPermit { _permit: permit })
+    }
+
+    fn busy(&self) -> AdmissionError {
+        AdmissionError::Busy {
+            running: self.running(),
+            queued: self.queued(),
+        }
+    }
+}
+
+struct QueueSlot<'a>(&'a AtomicUsize);
+
+impl Drop for QueueSlot<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_admission_queues_then_rejects() {
+        let gate = Arc::new(AdmissionController::new(1, 1));
+        let first = gate.admit().await.unwrap();
+        assert!(gate.try_admit().is_err());
+
+        let waiter = {
+            let gate = Arc::clone(&gate);
+            tokio::spawn(async move { gate.admit().await.map(|_| ()) })
+        };
+        while gate.queued() == 0 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        assert_eq!(
+            gate.admit().await.unwrap_err(),
+            AdmissionError::Busy { running: 1, queued: 1 }
+        );
+
+        drop(first);
+        waiter.await.unwrap().unwrap();
+        assert_eq!(gate.running(), 0);
+        assert_eq!(gate.queued(), 0);
+    }
+}
@@ -0,0 +1,114 @@
+//! Free list of fixed-size byte buffers.
+//! Paths that need a page-sized scratch buffer for one I/O (promoting a cold page,
+//! staging a double-write slot) take one from a BufferRecycler and give it back when
+//! done, instead of allocating a fresh Vec every time. stats() shows how often a
+//! buffer was actually allocated versus reused.
+
+use std::sync::{
+    Mutex,
+    atomic::{AtomicU64, Ordering},
+};
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct BufferStats {
+    /// Buffers allocated because none was idle.
+    pub allocated: u64,
+    /// Buffers handed out again after being given back.
+    pub reused: u64,
+    /// Buffers waiting to be reused.
+    pub idle: usize,
+}
+
+impl BufferStats {
+    /// Totals over both recyclers.
+    pub fn combine(self, other: BufferStats) -> BufferStats {
+        BufferStats {
+            allocated: self.allocated + other.allocated,
+            reused: self.reused + other.reused,
+            idle: self.idle + other.idle,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct BufferRecycler {
+    len: usize,
+    max_idle: usize,
+    idle: Mutex<Vec<Vec<u8>>>,
+    allocated: AtomicU64,
+    reused: AtomicU64,
+}
+
+impl BufferRecycler {
+    /// Recycle buffers of `len` bytes, keeping at most `max_idle` of them around.
+    pub fn new(len: usize, max_idle: usize) -> Self {
+        Self {
+            len,
+            max_idle,
+            idle: Mutex::new(Vec::new()),
+            allocated: AtomicU64::new(0),
+            reused: AtomicU64::new(0),
+        }
+    }
+
+    /// A buffer of `len` bytes. A reused one still holds whatever its last user left in it.
+    pub fn take(&self) -> Vec<u8> {
+        match self.lock_idle().pop() {
+            Some(buf) => {
+                self.reused.fetch_add(1, Ordering::Relaxed);
+                buf
+            }
+            None => {
+                self.allocated.fetch_add(1, Ordering::Relaxed);
+                vec![0u8; self.len]
+            }
+        }
+    }
+
+    /// Return a buffer from take(). It is dropped if enough are idle already.
+    pub fn give_back(&self, buf: Vec<u8>) {
+        debug_assert_eq!(buf.len(), self.len, "buffer given back to the wrong recycler");
+        let mut idle = self.lock_idle();
+        if buf.len() == self.len && idle.len() < self.max_idle {
+            idle.push(buf);
+        }
+    }
+
+    pub fn stats(&self) -> BufferStats {
+        BufferStats {
+            allocated: self.allocated.load(Ordering::Relaxed),
+            reused: self.reused.load(Ordering::Relaxed),
+            idle: self.lock_idle().len(),
+        }
+    }
+
+    fn lock_idle(&self) -> std::sync::MutexGuard<'_, Vec<Vec<u8>>> {
+        self.idle.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buffers_are_reused_up_to_max_idle() {
+        let recycler = BufferRecycler::new(16, 1);
+        let a = recycler.take();
+        let b = recycler.take();
+        assert_eq!(a.len(), 16);
+        recycler.give_back(a);
+        recycler.give_back(b); // one idle buffer is enough; this one is dropped
+        let _c = recycler.take();
+        assert_eq!(
+            recycler.stats(),
+            BufferStats {
+                allocated: 2,
+                reused: 1,
+                idle: 0,
+            }
+        );
+    }
+}
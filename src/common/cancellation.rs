@@ -0,0 +1,35 @@
+//! Cancellation tokens for long-running work.
+//! A caller hands a CancellationToken down to the scheduler/buffer pool; cancelling it
+//! (directly or through a statement timeout) makes pending waits return
+//! DiskError::Cancelled instead of running to completion.
+
+use std::time::Duration;
+
+pub use tokio_util::sync::CancellationToken;
+
+/// A token that cancels itself after `timeout`, for statement-level timeouts.
+/// Must be called from within a tokio runtime.
+pub fn statement_timeout(timeout: Duration) -> CancellationToken {
+    let token = CancellationToken::new();
+    let timer = token.clone();
+    tokio::spawn(async move {
+        tokio::select! {
+            _ = tokio::time::sleep(timeout) => timer.cancel(),
+            _ = timer.cancelled() => {}
+        }
+    });
+    token
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_statement_timeout_fires() {
+        let token = statement_timeout(Duration::from_millis(10));
+        assert!(!token.is_cancelled());
+        token.cancelled().await;
+        assert!(token.is_cancelled());
+    }
+}
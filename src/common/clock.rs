@@ -0,0 +1,120 @@
+//! Injectable time source.
+//! Code that reads the time or sleeps takes an `Arc<dyn Clock>` instead of calling
+//! Instant::now() or tokio::time::sleep directly, so tests (or a simulation harness) can
+//! swap in a ManualClock and decide exactly when time passes. SystemClock is the
+//! default everywhere. Randomness is injected the same way through rand::RngCore.
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use tokio::sync::watch;
+
+pub type Sleep = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+
+pub trait Clock: Send + Sync {
+    /// Monotonic time, for measuring durations.
+    fn now(&self) -> Instant;
+
+    /// Wall-clock time since the unix epoch, for timestamps that outlive the process.
+    fn unix_time(&self) -> Duration;
+
+    /// Resolves once `duration` has passed on this clock.
+    fn sleep(&self, duration: Duration) -> Sleep;
+}
+
+/// The real clock: std time and tokio timers.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn unix_time(&self) -> Duration {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default()
+    }
+
+    fn sleep(&self, duration: Duration) -> Sleep {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+pub fn system_clock() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
+
+/// A clock that only moves when advance() is called. Sleeps resolve as soon as the
+/// clock has been advanced past their deadline.
+pub struct ManualClock {
+    start: Instant,
+    unix_start: Duration,
+    elapsed: watch::Sender<Duration>,
+}
+
+impl ManualClock {
+    /// A clock reading `unix_start` since the epoch.
+    pub fn new(unix_start: Duration) -> Self {
+        Self {
+            start: Instant::now(),
+            unix_start,
+            elapsed: watch::Sender::new(Duration::ZERO),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.elapsed.send_modify(|elapsed| *elapsed += duration);
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        *self.elapsed.borrow()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+
+    fn unix_time(&self) -> Duration {
+        self.unix_start + self.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) -> Sleep {
+        let deadline = self.elapsed() + duration;
+        let mut elapsed = self.elapsed.subscribe();
+        Box::pin(async move {
+            while *elapsed.borrow_and_update() < deadline {
+                if elapsed.changed().await.is_err() {
+                    // The clock is gone and will never reach the deadline
+                    std::future::pending::<()>().await;
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_manual_clock_sleeps_until_advanced() {
+        let clock = Arc::new(ManualClock::new(Duration::from_secs(1_700_000_000)));
+        let before = clock.now();
+        let sleep = tokio::spawn(clock.sleep(Duration::from_secs(10)));
+
+        clock.advance(Duration::from_secs(9));
+        tokio::task::yield_now().await;
+        assert!(!sleep.is_finished());
+
+        clock.advance(Duration::from_secs(1));
+        sleep.await.unwrap();
+        assert_eq!(clock.now() - before, Duration::from_secs(10));
+        assert_eq!(clock.unix_time(), Duration::from_secs(1_700_000_010));
+    }
+}
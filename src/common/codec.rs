@@ -0,0 +1,380 @@
+//! Encodings for typed keys and values.
+//! Keys are encoded so that comparing the encoded bytes gives the same order as
+//! comparing the keys themselves, which is what an ordered index over raw bytes needs:
+//!
+//! - unsigned integers: big endian
+//! - signed integers: big endian with the sign bit flipped
+//! - strings and byte strings: 0x00 escaped as 0x00 0xFF, terminated by 0x00 0x01, so
+//!   a prefix sorts before its extensions
+//! - tuples: components concatenated (each component is self-delimiting)
+//! - Option (NULLS FIRST): 0x00 for None, 0x01 followed by the key for Some;
+//!   NullsLast swaps the markers
+//! - DescendingKey: the wrapped key's bytes inverted, which reverses their order
+//!
+//! So a composite index key like (tenant_id, created_at DESC NULLS LAST) is the tuple
+//! `(u64, DescendingKey<Option<i64>>)`, and its encoded bytes compare like the columns do.
+//!
+//! Values only need to round-trip, so they use serde with postcard (the `postcard`
+//! feature, on by default).
+
+use crate::common::types::BinaryKey;
+
+/// A key with an order-preserving byte encoding.
+pub trait OrderedKey: Sized {
+    fn encode_key(&self, out: &mut Vec<u8>);
+
+    /// Decode one key from the front of `input`, advancing it. None if malformed.
+    fn decode_key(input: &mut &[u8]) -> Option<Self>;
+
+    fn to_key_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.encode_key(&mut out);
+        out
+    }
+
+    /// Decode a key that must span all of `bytes`.
+    fn from_key_bytes(mut bytes: &[u8]) -> Option<Self> {
+        let key = Self::decode_key(&mut bytes)?;
+        bytes.is_empty().then_some(key)
+    }
+}
+
+fn take<'a>(input: &mut &'a [u8], n: usize) -> Option<&'a [u8]> {
+    if input.len() < n {
+        return None;
+    }
+    let (head, rest) = input.split_at(n);
+    *input = rest;
+    Some(head)
+}
+
+macro_rules! unsigned_key {
+    ($($t:ty),*) => {$(
+        impl OrderedKey for $t {
+            fn encode_key(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_be_bytes());
+            }
+
+            fn decode_key(input: &mut &[u8]) -> Option<Self> {
+                let bytes = take(input, size_of::<$t>())?;
+                Some(<$t>::from_be_bytes(bytes.try_into().ok()?))
+            }
+        }
+    )*};
+}
+
+macro_rules! signed_key {
+    ($($t:ty => $u:ty),*) => {$(
+        impl OrderedKey for $t {
+            fn encode_key(&self, out: &mut Vec<u8>) {
+                let flipped = (*self as $u) ^ (1 << (<$u>::BITS - 1));
+                out.extend_from_slice(&flipped.to_be_bytes());
+            }
+
+            fn decode_key(input: &mut &[u8]) -> Option<Self> {
+                let flipped = <$u>::decode_key(input)?;
+                Some((flipped ^ (1 << (<$u>::BITS - 1))) as $t)
+            }
+        }
+    )*};
+}
+
+unsigned_key!(u8, u16, u32, u64, u128);
+signed_key!(i8 => u8, i16 => u16, i32 => u32, i64 => u64, i128 => u128);
+
+impl OrderedKey for bool {
+    fn encode_key(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8);
+    }
+
+    fn decode_key(input: &mut &[u8]) -> Option<Self> {
+        match take(input, 1)? {
+            [0] => Some(false),
+            [1] => Some(true),
+            _ => None,
+        }
+    }
+}
+
+impl OrderedKey for BinaryKey {
+    fn encode_key(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self);
+    }
+
+    fn decode_key(input: &mut &[u8]) -> Option<Self> {
+        take(input, 16)?.try_into().ok()
+    }
+}
+
+impl OrderedKey for Vec<u8> {
+    fn encode_key(&self, out: &mut Vec<u8>) {
+        for &b in self {
+            out.push(b);
+            if b == 0 {
+                out.push(0xFF);
+            }
+        }
+        out.extend_from_slice(&[0, 1]);
+    }
+
+    fn decode_key(input: &mut &[u8]) -> Option<Self> {
+        let mut bytes = Vec::new();
+        loop {
+            match take(input, 1)?[0] {
+                0 => match take(input, 1)?[0] {
+                    0xFF => bytes.push(0),
+                    1 => return Some(bytes),
+                    _ => return None,
+                },
+                b => bytes.push(b),
+            }
+        }
+    }
+}
+
+impl OrderedKey for String {
+    fn encode_key(&self, out: &mut Vec<u8>) {
+        // Same bytes as Vec<u8>; UTF-8 byte order matches char order
+        self.as_bytes().to_vec().encode_key(out);
+    }
+
+    fn decode_key(input: &mut &[u8]) -> Option<Self> {
+        String::from_utf8(Vec::<u8>::decode_key(input)?).ok()
+    }
+}
+
+impl<A: OrderedKey, B: OrderedKey> OrderedKey for (A, B) {
+    fn encode_key(&self, out: &mut Vec<u8>) {
+        self.0.encode_key(out);
+        self.1.encode_key(out);
+    }
+
+    fn decode_key(input: &mut &[u8]) -> Option<Self> {
+        Some((A::decode_key(input)?, B::decode_key(input)?))
+    }
+}
+
+impl<A: OrderedKey, B: OrderedKey, C: OrderedKey> OrderedKey for (A, B, C) {
+    fn encode_key(&self, out: &mut Vec<u8>) {
+        self.0.encode_key(out);
+        self.1.encode_key(out);
+        self.2.encode_key(out);
+    }
+
+    fn decode_key(input: &mut &[u8]) -> Option<Self> {
+        Some((A::decode_key(input)?, B::decode_key(input)?, C::decode_key(input)?))
+    }
+}
+
+impl<A: OrderedKey, B: OrderedKey, C: OrderedKey, D: OrderedKey> OrderedKey for (A, B, C, D) {
+    fn encode_key(&self, out: &mut Vec<u8>) {
+        self.0.encode_key(out);
+        self.1.encode_key(out);
+        self.2.encode_key(out);
+        self.3.encode_key(out);
+    }
+
+    fn decode_key(input: &mut &[u8]) -> Option<Self> {
+        Some((A::decode_key(input)?, B::decode_key(input)?, C::decode_key(input)?, D::decode_key(input)?))
+    }
+}
+
+/// A nullable column, NULLS FIRST: None sorts before every Some.
+impl<K: OrderedKey> OrderedKey for Option<K> {
+    fn encode_key(&self, out: &mut Vec<u8>) {
+        match self {
+            None => out.push(0),
+            Some(key) => {
+                out.push(1);
+                key.encode_key(out);
+            }
+        }
+    }
+
+    fn decode_key(input: &mut &[u8]) -> Option<Self> {
+        match take(input, 1)? {
+            [0] => Some(None),
+            [1] => Some(Some(K::decode_key(input)?)),
+            _ => None,
+        }
+    }
+}
+
+/// A nullable column, NULLS LAST: None sorts after every Some.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NullsLast<K>(pub Option<K>);
+
+impl<K: Ord> PartialOrd for NullsLast<K> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord> Ord for NullsLast<K> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        match (&self.0, &other.0) {
+            (Some(a), Some(b)) => a.cmp(b),
+            (a, b) => a.is_none().cmp(&b.is_none()),
+        }
+    }
+}
+
+impl<K: OrderedKey> OrderedKey for NullsLast<K> {
+    fn encode_key(&self, out: &mut Vec<u8>) {
+        match &self.0 {
+            Some(key) => {
+                out.push(0);
+                key.encode_key(out);
+            }
+            None => out.push(1),
+        }
+    }
+
+    fn decode_key(input: &mut &[u8]) -> Option<Self> {
+        match take(input, 1)? {
+            [0] => Some(NullsLast(Some(K::decode_key(input)?))),
+            [1] => Some(NullsLast(None)),
+            _ => None,
+        }
+    }
+}
+
+/// A DESC column: sorts in the reverse order of the wrapped key.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DescendingKey<K>(pub K);
+
+impl<K: Ord> PartialOrd for DescendingKey<K> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord> Ord for DescendingKey<K> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        other.0.cmp(&self.0)
+    }
+}
+
+impl<K: OrderedKey> OrderedKey for DescendingKey<K> {
+    fn encode_key(&self, out: &mut Vec<u8>) {
+        // Every encoding is self-delimiting, so no inverted key is a prefix of another
+        let start = out.len();
+        self.0.encode_key(out);
+        for b in &mut out[start..] {
+            *b = !*b;
+        }
+    }
+
+    fn decode_key(input: &mut &[u8]) -> Option<Self> {
+        let inverted: Vec<u8> = input.iter().map(|b| !b).collect();
+        let mut rest = inverted.as_slice();
+        let key = K::decode_key(&mut rest)?;
+        *input = &input[inverted.len() - rest.len()..];
+        Some(DescendingKey(key))
+    }
+}
+
+/// Serialize a value for storage.
+#[cfg(feature = "postcard")]
+pub fn encode_value<V: serde::Serialize>(value: &V) -> Result<Vec<u8>, postcard::Error> {
+    postcard::to_allocvec(value)
+}
+
+/// Deserialize a value written by encode_value.
+#[cfg(feature = "postcard")]
+pub fn decode_value<V: serde::de::DeserializeOwned>(bytes: &[u8]) -> Result<V, postcard::Error> {
+    postcard::from_bytes(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    fn assert_order_preserved<K: OrderedKey + Ord + Clone + std::fmt::Debug>(mut keys: Vec<K>) {
+        keys.sort();
+        let encoded: Vec<Vec<u8>> = keys.iter().map(|k| k.to_key_bytes()).collect();
+        for pair in encoded.windows(2) {
+            assert!(pair[0] <= pair[1]);
+        }
+        for (key, bytes) in keys.iter().zip(&encoded) {
+            assert_eq!(K::from_key_bytes(bytes).as_ref(), Some(key));
+        }
+    }
+
+    #[test]
+    fn test_integer_keys_keep_order() {
+        let mut rng = rand::rng();
+        assert_order_preserved((0..500).map(|_| rng.random::<i64>()).chain([i64::MIN, -1, 0, i64::MAX]).collect());
+        assert_order_preserved((0..500).map(|_| rng.random::<i32>()).collect());
+        assert_order_preserved((0..500).map(|_| rng.random::<u64>()).collect());
+    }
+
+    #[test]
+    fn test_string_and_tuple_keys_keep_order() {
+        let strings: Vec<String> = ["", "a", "a\0", "a\0b", "ab", "b", "\0", "zz", "é"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_order_preserved(strings.clone());
+
+        let tuples: Vec<(String, i32)> = strings
+            .iter()
+            .flat_map(|s| [(s.clone(), -5), (s.clone(), 0), (s.clone(), i32::MAX)])
+            .collect();
+        assert_order_preserved(tuples);
+        assert_eq!(String::from_key_bytes(&[b'a', 0, 0xFF]), None);
+        assert_eq!(String::from_key_bytes(&[b'a', 0, 1, 0]), None);
+    }
+
+    #[test]
+    fn test_composite_keys_with_desc_and_nulls() {
+        let mut rng = rand::rng();
+        let created = |rng: &mut rand::rngs::ThreadRng| (rng.random_bool(0.8)).then(|| rng.random_range(-50i64..50));
+
+        // (tenant_id, created_at DESC NULLS LAST) and every other ordering combination
+        assert_order_preserved(
+            (0..300)
+                .map(|_| (rng.random_range(0u64..4), DescendingKey(created(&mut rng))))
+                .collect(),
+        );
+        assert_order_preserved((0..300).map(|_| NullsLast(created(&mut rng))).collect());
+        assert_order_preserved((0..300).map(|_| DescendingKey(NullsLast(created(&mut rng)))).collect());
+
+        // Descending strings still sort by prefix, reversed, and stay self-delimiting
+        let names: Vec<(DescendingKey<String>, Option<i32>)> = ["", "a", "a\0", "ab", "b"]
+            .iter()
+            .flat_map(|s| [(DescendingKey(s.to_string()), None), (DescendingKey(s.to_string()), Some(-1))])
+            .collect();
+        assert_order_preserved(names);
+        assert_order_preserved(
+            (0..300)
+                .map(|_| {
+                    let len = rng.random_range(0..4);
+                    let bytes: Vec<u8> = (0..len).map(|_| rng.random_range(0..3)).collect();
+                    (DescendingKey(bytes), rng.random_range(0u8..3), NullsLast(created(&mut rng)), true)
+                })
+                .collect(),
+        );
+    }
+
+    #[cfg(feature = "postcard")]
+    #[test]
+    fn test_value_roundtrip() {
+        #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+        struct Account {
+            owner: String,
+            balance: i64,
+            tags: Vec<String>,
+        }
+
+        let account = Account {
+            owner: "ada".to_string(),
+            balance: -42,
+            tags: vec!["vip".to_string()],
+        };
+        let bytes = encode_value(&account).unwrap();
+        assert_eq!(decode_value::<Account>(&bytes).unwrap(), account);
+        assert!(decode_value::<Account>(&bytes[..2]).is_err());
+    }
+}
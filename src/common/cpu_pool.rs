@@ -0,0 +1,112 @@
+//! Pool for CPU-bound work such as checksumming, compression and encryption.
+//! Running those inline would stall the async runtime's worker threads and every I/O
+//! task queued behind them, so they run on tokio's blocking threads instead, at most
+//! `workers` at a time. The limit can change at runtime.
+
+use std::sync::Arc;
+
+use tokio::sync::{Mutex, Semaphore};
+
+use crate::common::errors::DiskError;
+
+#[derive(Debug)]
+pub struct CpuPool {
+    permits: Arc<Semaphore>,
+    workers: Mutex<usize>,
+}
+
+impl Default for CpuPool {
+    fn default() -> Self {
+        Self::new(default_workers())
+    }
+}
+
+/// One worker per available core.
+pub fn default_workers() -> usize {
+    std::thread::available_parallelism().map_or(4, |n| n.get())
+}
+
+impl CpuPool {
+    pub fn new(workers: usize) -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(workers)),
+            workers: Mutex::new(workers),
+        }
+    }
+
+    pub async fn workers(&self) -> usize {
+        *self.workers.lock().await
+    }
+
+    /// Change how many jobs may run at once. Shrinking waits for enough running jobs to finish.
+    pub async fn set_workers(&self, workers: usize) {
+        let mut current = self.workers.lock().await;
+        if workers > *current {
+            self.permits.add_permits(workers - *current);
+        } else if workers < *current {
+            let excess = u32::try_from(*current - workers).expect("CPU workers fit in u32");
+            // Closed only on shutdown, when there is nothing left to limit
+            if let Ok(permits) = self.permits.acquire_many(excess).await {
+                permits.forget();
+            }
+        }
+        *current = workers;
+    }
+
+    /// Run `job` on a blocking thread once a worker is free. A panic in `job` is resumed
+    /// here; if the runtime shuts down first the job fails with Cancelled.
+    /// Without the `native` feature there are no blocking threads, and `job` runs inline.
+    pub async fn run<T: Send + 'static>(
+        &self,
+        job: impl FnOnce() -> T + Send + 'static,
+    ) -> Result<T, DiskError> {
+        let _permit = self.permits.acquire().await?;
+        #[cfg(not(feature = "native"))]
+        return Ok(job());
+        #[cfg(feature = "native")]
+        match tokio::task::spawn_blocking(job).await {
+            Ok(value) => Ok(value),
+            Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+            Err(_) => Err(DiskError::Cancelled),
+        }
+    }
+}
+
+// Exercises the blocking threads
+#[cfg(all(test, feature = "native"))]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_runs_at_most_workers_jobs_at_once() {
+        let pool = Arc::new(CpuPool::new(2));
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+
+        let jobs: Vec<_> = (0..8)
+            .map(|i| {
+                let (pool, running, peak) = (Arc::clone(&pool), Arc::clone(&running), Arc::clone(&peak));
+                tokio::spawn(async move {
+                    pool.run(move || {
+                        let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                        peak.fetch_max(now, Ordering::SeqCst);
+                        std::thread::sleep(std::time::Duration::from_millis(5));
+                        running.fetch_sub(1, Ordering::SeqCst);
+                        i * 2
+                    })
+                    .await
+                })
+            })
+            .collect();
+        let mut results = Vec::new();
+        for job in jobs {
+            results.push(job.await.unwrap().unwrap());
+        }
+        assert_eq!(results, (0..8).map(|i| i * 2).collect::<Vec<_>>());
+        assert!(peak.load(Ordering::SeqCst) <= 2);
+
+        pool.set_workers(1).await;
+        assert_eq!(pool.workers().await, 1);
+    }
+}
@@ -1,8 +1,407 @@
 use std::fmt;
 use std::error::Error;
 
+use crate::common::types::PageId;
+
+/// Stable classification of an error, for applications that branch on what went wrong
+/// rather than on the message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    /// A page or database that does not exist.
+    NotFound,
+    AlreadyExists,
+    /// The operation clashes with other users of the same object (e.g. open handles).
+    Conflict,
+    /// Resources are temporarily taken; retrying later may succeed.
+    Busy,
+    Timeout,
+    Cancelled,
+    /// Stored data failed validation.
+    Corruption,
+    /// A write to a database opened read-only.
+    ReadOnly,
+    /// A malformed name, setting or request.
+    InvalidArgument,
+    /// A memory or other budget was exceeded.
+    ResourceExhausted,
+    Io,
+}
+
+impl ErrorCode {
+    pub fn name(self) -> &'static str {
+        match self {
+            ErrorCode::NotFound => "not_found",
+            ErrorCode::AlreadyExists => "already_exists",
+            ErrorCode::Conflict => "conflict",
+            ErrorCode::Busy => "busy",
+            ErrorCode::Timeout => "timeout",
+            ErrorCode::Cancelled => "cancelled",
+            ErrorCode::Corruption => "corruption",
+            ErrorCode::ReadOnly => "read_only",
+            ErrorCode::InvalidArgument => "invalid_argument",
+            ErrorCode::ResourceExhausted => "resource_exhausted",
+            ErrorCode::Io => "io",
+        }
+    }
+
+    /// Whether the same request may succeed if simply tried again later.
+    pub fn is_retryable(self) -> bool {
+        matches!(self, ErrorCode::Busy | ErrorCode::Timeout | ErrorCode::Conflict)
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// What the engine was working on when an error happened.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ErrorContext {
+    pub database: Option<String>,
+    pub page_id: Option<PageId>,
+    pub table: Option<String>,
+    pub txn_id: Option<u64>,
+}
+
+/// Error returned by the public API (Instance, Database, maintenance): an ErrorCode to
+/// branch on, the context it happened in, and the underlying error as its source.
+#[derive(Debug)]
+pub struct GrimoireError {
+    code: ErrorCode,
+    context: ErrorContext,
+    source: Box<dyn Error + Send + Sync>,
+}
+
+impl GrimoireError {
+    pub fn new(code: ErrorCode, source: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+        Self {
+            code,
+            context: ErrorContext::default(),
+            source: source.into(),
+        }
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        self.code
+    }
+
+    pub fn context(&self) -> &ErrorContext {
+        &self.context
+    }
+
+    /// The underlying DiskError, if that is what this wraps.
+    pub fn disk_error(&self) -> Option<&DiskError> {
+        self.source.downcast_ref()
+    }
+
+    pub fn with_database(mut self, name: &str) -> Self {
+        self.context.database = Some(name.to_string());
+        self
+    }
+
+    pub fn with_page(mut self, page_id: PageId) -> Self {
+        self.context.page_id = Some(page_id);
+        self
+    }
+
+    pub fn with_table(mut self, table: &str) -> Self {
+        self.context.table = Some(table.to_string());
+        self
+    }
+
+    pub fn with_txn(mut self, txn_id: u64) -> Self {
+        self.context.txn_id = Some(txn_id);
+        self
+    }
+}
+
+impl fmt::Display for GrimoireError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)?;
+        let ErrorContext { database, page_id, table, txn_id } = &self.context;
+        let mut parts = Vec::new();
+        if let Some(database) = database {
+            parts.push(format!("database {}", database));
+        }
+        if let Some(table) = table {
+            parts.push(format!("table {}", table));
+        }
+        // Skip a page the source's own message already names
+        if let Some(page_id) = page_id.filter(|&id| self.disk_error().and_then(DiskError::page_id) != Some(id)) {
+            parts.push(format!("page {}", page_id));
+        }
+        if let Some(txn_id) = txn_id {
+            parts.push(format!("txn {}", txn_id));
+        }
+        if !parts.is_empty() {
+            write!(f, " ({})", parts.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for GrimoireError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
+
+/// Attach context to a failing result on its way out of the public API.
+pub trait ResultExt<T> {
+    fn with_database(self, name: &str) -> Result<T, GrimoireError>;
+    fn with_page(self, page_id: PageId) -> Result<T, GrimoireError>;
+}
+
+impl<T, E: Into<GrimoireError>> ResultExt<T> for Result<T, E> {
+    fn with_database(self, name: &str) -> Result<T, GrimoireError> {
+        self.map_err(|e| e.into().with_database(name))
+    }
+
+    fn with_page(self, page_id: PageId) -> Result<T, GrimoireError> {
+        self.map_err(|e| e.into().with_page(page_id))
+    }
+}
+
+impl From<DiskError> for GrimoireError {
+    fn from(e: DiskError) -> Self {
+        let page_id = e.page_id();
+        let mut error = GrimoireError::new(e.code(), e);
+        error.context.page_id = page_id;
+        error
+    }
+}
+
+impl From<MemoryError> for GrimoireError {
+    fn from(e: MemoryError) -> Self {
+        GrimoireError::new(ErrorCode::ResourceExhausted, e)
+    }
+}
+
+impl From<AdmissionError> for GrimoireError {
+    fn from(e: AdmissionError) -> Self {
+        GrimoireError::new(ErrorCode::Busy, e)
+    }
+}
+
+impl From<ConfigError> for GrimoireError {
+    fn from(e: ConfigError) -> Self {
+        let code = match e {
+            ConfigError::Io { .. } => ErrorCode::Io,
+            ConfigError::Invalid { .. } | ConfigError::Parse { .. } => ErrorCode::InvalidArgument,
+        };
+        GrimoireError::new(code, e)
+    }
+}
+
 #[derive(Debug)]
 pub enum DiskError {
     IoError(std::io::Error),
     PageNotFound(i32),
+    NoFreeFrame,
+    Cancelled,
+    /// Opened with create_if_missing(false) and the database file does not exist.
+    DatabaseNotFound(std::path::PathBuf),
+    /// Opened with error_if_exists(true) and the database file already exists.
+    AlreadyExists(std::path::PathBuf),
+    /// A write was attempted on a database opened read-only.
+    ReadOnly,
+    /// Database names are limited to ASCII letters, digits, `_` and `-`.
+    InvalidDatabaseName(String),
+    /// The database cannot be dropped while handles to it are still alive.
+    DatabaseInUse(String),
+    /// The write would leave less free space on the volume than the configured reserve
+    /// (see DiskManager::with_min_free_space). Writes resume once space is freed.
+    DiskFull { available: u64, required: u64 },
+    /// Another process, or another handle in this one, has the database open for writing.
+    DatabaseLocked(std::path::PathBuf),
+    /// The page read back does not match the checksum recorded when it was written.
+    ChecksumMismatch { page_id: i32, expected: u32, actual: u32 },
+    /// The page read as corrupt in salvage mode and is quarantined until rewritten.
+    PageQuarantined(i32),
+    /// A write backlog is past its stop threshold (see WriteController); retry once it drains.
+    WriteStalled(crate::common::write_stall::StallReason),
+    /// The hash index with this directory page needs a split its directory has no room
+    /// left for (see hash_index::MAX_GLOBAL_DEPTH).
+    IndexFull(i32),
+}
+
+impl DiskError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            DiskError::IoError(e) => match e.kind() {
+                std::io::ErrorKind::InvalidData | std::io::ErrorKind::UnexpectedEof => ErrorCode::Corruption,
+                std::io::ErrorKind::TimedOut => ErrorCode::Timeout,
+                _ => ErrorCode::Io,
+            },
+            DiskError::PageNotFound(_) | DiskError::DatabaseNotFound(_) => ErrorCode::NotFound,
+            // Every frame is pinned; unpinning frees one up
+            DiskError::NoFreeFrame => ErrorCode::Busy,
+            DiskError::Cancelled => ErrorCode::Cancelled,
+            DiskError::AlreadyExists(_) => ErrorCode::AlreadyExists,
+            DiskError::ReadOnly => ErrorCode::ReadOnly,
+            DiskError::InvalidDatabaseName(_) => ErrorCode::InvalidArgument,
+            DiskError::DatabaseInUse(_) => ErrorCode::Conflict,
+            DiskError::DiskFull { .. } => ErrorCode::ResourceExhausted,
+            DiskError::DatabaseLocked(_) => ErrorCode::Conflict,
+            DiskError::ChecksumMismatch { .. } | DiskError::PageQuarantined(_) => ErrorCode::Corruption,
+            DiskError::WriteStalled(_) => ErrorCode::Busy,
+            DiskError::IndexFull(_) => ErrorCode::ResourceExhausted,
+        }
+    }
+
+    /// The page this error is about, for the variants whose message names one.
+    pub fn page_id(&self) -> Option<i32> {
+        match self {
+            DiskError::PageNotFound(page_id)
+            | DiskError::ChecksumMismatch { page_id, .. }
+            | DiskError::PageQuarantined(page_id) => Some(*page_id),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for DiskError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiskError::IoError(e) => write!(f, "I/O error: {}", e),
+            DiskError::PageNotFound(page_id) => write!(f, "page {} not found", page_id),
+            DiskError::NoFreeFrame => write!(f, "no free or evictable frame in the buffer pool"),
+            DiskError::Cancelled => write!(f, "operation cancelled"),
+            DiskError::DatabaseNotFound(path) => write!(f, "database {} does not exist", path.display()),
+            DiskError::AlreadyExists(path) => write!(f, "database {} already exists", path.display()),
+            DiskError::ReadOnly => write!(f, "database is open read-only"),
+            DiskError::InvalidDatabaseName(name) => write!(f, "invalid database name {:?}", name),
+            DiskError::DatabaseInUse(name) => write!(f, "database {} is still in use", name),
+            DiskError::DiskFull { available, required } => {
+                write!(f, "disk space low: {} bytes free, {} needed", available, required)
+            }
+            DiskError::DatabaseLocked(path) => write!(f, "database {} is locked by another writer", path.display()),
+            DiskError::ChecksumMismatch { page_id, expected, actual } => write!(
+                f,
+                "page {} failed its checksum: expected {:08x}, found {:08x}",
+                page_id, expected, actual
+            ),
+            DiskError::PageQuarantined(page_id) => write!(f, "page {} is quarantined as corrupt", page_id),
+            DiskError::WriteStalled(reason) => write!(f, "writes stopped: {}", reason),
+            DiskError::IndexFull(page_id) => {
+                write!(f, "hash index {} is full: its directory is at its maximum depth", page_id)
+            }
+        }
+    }
+}
+
+/// Only a closed semaphore fails to hand out permits, and they are closed on shutdown.
+impl From<tokio::sync::AcquireError> for DiskError {
+    fn from(_: tokio::sync::AcquireError) -> Self {
+        DiskError::Cancelled
+    }
+}
+
+impl Error for DiskError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DiskError::IoError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Raised when a memory reservation cannot be granted. Operators that can spill treat
+/// this as the signal to spill; the rest fail the query with it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MemoryError {
+    /// The query would go over its own budget.
+    QueryLimitExceeded { requested: usize, used: usize, limit: usize },
+    /// The engine-wide budget is used up by all queries together.
+    GlobalLimitExceeded { requested: usize, used: usize, limit: usize },
+}
+
+impl fmt::Display for MemoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MemoryError::QueryLimitExceeded { requested, used, limit } => write!(
+                f,
+                "query memory limit exceeded: requested {} bytes with {} of {} in use",
+                requested, used, limit
+            ),
+            MemoryError::GlobalLimitExceeded { requested, used, limit } => write!(
+                f,
+                "global memory limit exceeded: requested {} bytes with {} of {} in use",
+                requested, used, limit
+            ),
+        }
+    }
+}
+
+impl Error for MemoryError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AdmissionError {
+    /// All execution slots are taken and the wait queue is full (or waiting was not allowed).
+    Busy { running: usize, queued: usize },
+}
+
+impl fmt::Display for AdmissionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AdmissionError::Busy { running, queued } => write!(
+                f,
+                "server busy: {} queries running, {} queued",
+                running, queued
+            ),
+        }
+    }
+}
+
+impl Error for AdmissionError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    /// A setting has a value outside its allowed range.
+    Invalid { field: &'static str, reason: String },
+    /// The config file could not be read.
+    Io { path: String, message: String },
+    /// The config file is not valid TOML or does not match the expected layout.
+    Parse { path: String, message: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Invalid { field, reason } => write!(f, "invalid config `{}`: {}", field, reason),
+            ConfigError::Io { path, message } => write!(f, "cannot read config {}: {}", path, message),
+            ConfigError::Parse { path, message } => write!(f, "cannot parse config {}: {}", path, message),
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes_and_context() {
+        let e = GrimoireError::from(DiskError::PageNotFound(7)).with_database("sales");
+        assert_eq!(e.code(), ErrorCode::NotFound);
+        assert_eq!(e.context().page_id, Some(7));
+        assert_eq!(e.to_string(), "page 7 not found (database sales)");
+        assert!(matches!(e.disk_error(), Some(DiskError::PageNotFound(7))));
+        // A different page than the one the source names is still shown
+        let e = GrimoireError::from(DiskError::PageQuarantined(7)).with_page(9);
+        assert_eq!(e.to_string(), "page 7 is quarantined as corrupt (page 9)");
+
+        let io = std::io::Error::new(std::io::ErrorKind::InvalidData, "bad checksum");
+        let e = Err::<(), _>(DiskError::IoError(io)).with_page(3).unwrap_err().with_txn(42);
+        assert_eq!(e.code(), ErrorCode::Corruption);
+        assert_eq!(e.to_string(), "I/O error: bad checksum (page 3, txn 42)");
+        assert!(e.source().is_some());
+
+        let e = GrimoireError::from(AdmissionError::Busy { running: 1, queued: 0 });
+        assert!(e.code().is_retryable());
+        assert!(!GrimoireError::from(DiskError::ReadOnly).code().is_retryable());
+    }
 }
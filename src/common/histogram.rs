@@ -0,0 +1,144 @@
+//! Latency histograms with percentiles.
+//! Buckets are log-linear in the style of HdrHistogram: exact below 16µs, then every power
+//! of two is split into 8 equal buckets, so a reported percentile is within 12.5% of the
+//! true value. Counts are atomics, so recording is a couple of atomic adds and never
+//! blocks the I/O path it measures.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use serde::Serialize;
+
+// Values below 2^EXACT_BITS µs get a bucket each
+const EXACT_BITS: u32 = 4;
+// Buckets per power of two above that
+const SUB_BUCKETS: u64 = 8;
+// Longer latencies (about 19 hours) are recorded as this
+const MAX_MICROS: u64 = (1 << 36) - 1;
+const NUM_BUCKETS: usize = (1 << EXACT_BITS) + (36 - EXACT_BITS as usize) * SUB_BUCKETS as usize;
+
+/// Percentiles of a histogram at one point in time, in microseconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct LatencySummary {
+    pub count: u64,
+    pub mean_us: f64,
+    pub p50_us: u64,
+    pub p95_us: u64,
+    pub p99_us: u64,
+    pub max_us: u64,
+}
+
+pub struct LatencyHistogram {
+    buckets: Box<[AtomicU64]>,
+    count: AtomicU64,
+    sum_us: AtomicU64,
+    max_us: AtomicU64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LatencyHistogram {
+    pub fn new() -> Self {
+        Self {
+            buckets: (0..NUM_BUCKETS).map(|_| AtomicU64::new(0)).collect(),
+            count: AtomicU64::new(0),
+            sum_us: AtomicU64::new(0),
+            max_us: AtomicU64::new(0),
+        }
+    }
+
+    pub fn record(&self, latency: Duration) {
+        let micros = (latency.as_micros() as u64).min(MAX_MICROS);
+        self.buckets[bucket_of(micros)].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_us.fetch_add(micros, Ordering::Relaxed);
+        self.max_us.fetch_max(micros, Ordering::Relaxed);
+    }
+
+    pub fn summary(&self) -> LatencySummary {
+        let counts: Vec<u64> = self.buckets.iter().map(|b| b.load(Ordering::Relaxed)).collect();
+        let count: u64 = counts.iter().sum();
+        if count == 0 {
+            return LatencySummary::default();
+        }
+        let max_us = self.max_us.load(Ordering::Relaxed);
+        let percentile = |q: f64| {
+            let rank = ((q * count as f64).ceil() as u64).max(1);
+            let mut seen = 0;
+            for (bucket, &n) in counts.iter().enumerate() {
+                seen += n;
+                if seen >= rank {
+                    return bucket_upper(bucket).min(max_us);
+                }
+            }
+            max_us
+        };
+        LatencySummary {
+            count,
+            mean_us: self.sum_us.load(Ordering::Relaxed) as f64 / count as f64,
+            p50_us: percentile(0.50),
+            p95_us: percentile(0.95),
+            p99_us: percentile(0.99),
+            max_us,
+        }
+    }
+}
+
+fn bucket_of(micros: u64) -> usize {
+    if micros < 1 << EXACT_BITS {
+        return micros as usize;
+    }
+    let msb = 63 - micros.leading_zeros();
+    let shift = msb - SUB_BUCKETS.trailing_zeros();
+    let sub = (micros >> shift) - SUB_BUCKETS;
+    (1 << EXACT_BITS) + ((msb - EXACT_BITS) as u64 * SUB_BUCKETS + sub) as usize
+}
+
+/// Largest value that falls into `bucket`.
+fn bucket_upper(bucket: usize) -> u64 {
+    if bucket < 1 << EXACT_BITS {
+        return bucket as u64;
+    }
+    let above = (bucket - (1 << EXACT_BITS)) as u64;
+    let msb = EXACT_BITS as u64 + above / SUB_BUCKETS;
+    let shift = msb - SUB_BUCKETS.trailing_zeros() as u64;
+    let sub = above % SUB_BUCKETS + SUB_BUCKETS;
+    ((sub + 1) << shift) - 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles_within_bucket_precision() {
+        let histogram = LatencyHistogram::new();
+        assert_eq!(histogram.summary(), LatencySummary::default());
+        for micros in 1..=10_000 {
+            histogram.record(Duration::from_micros(micros));
+        }
+        let summary = histogram.summary();
+        assert_eq!((summary.count, summary.max_us), (10_000, 10_000));
+        assert!((summary.mean_us - 5000.5).abs() < 1e-9);
+        for (reported, actual) in [(summary.p50_us, 5000), (summary.p95_us, 9500), (summary.p99_us, 9900)] {
+            assert!(reported >= actual && reported <= actual + actual / 8, "{} for {}", reported, actual);
+        }
+    }
+
+    #[test]
+    fn test_buckets_cover_the_range() {
+        let mut last = None;
+        for micros in (0..5000).chain([MAX_MICROS - 1, MAX_MICROS]) {
+            let bucket = bucket_of(micros);
+            assert!(micros <= bucket_upper(bucket));
+            assert!(bucket == 0 || micros > bucket_upper(bucket - 1));
+            assert!(last.is_none_or(|last| bucket >= last));
+            last = Some(bucket);
+        }
+        assert_eq!(bucket_of(MAX_MICROS), NUM_BUCKETS - 1);
+    }
+}
@@ -0,0 +1,254 @@
+//! Key generation helpers.
+//! Random UUIDv4 keys scatter inserts across the whole key space, which touches a
+//! different leaf on nearly every insert. ULIDs put a millisecond timestamp in the high
+//! 48 bits, so keys generated close together sort close together and inserts stay on the
+//! right edge of an ordered index. Both are 16-byte BinaryKeys that compare bytewise.
+
+use std::{
+    fmt,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use rand::{Rng, RngCore};
+
+use crate::common::{
+    clock::{Clock, SystemClock, system_clock},
+    types::BinaryKey,
+};
+
+// Crockford base32, as used by the ULID spec
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+const RANDOM_BITS: u32 = 80;
+
+/// Universally unique lexicographically sortable identifier: 48-bit unix milliseconds
+/// followed by 80 random bits, big endian.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Ulid(u128);
+
+impl Ulid {
+    /// A fresh ULID for the current time. Not monotonic within a millisecond; use
+    /// UlidGenerator when keys must strictly increase.
+    pub fn new() -> Self {
+        Self::with_clock(&SystemClock)
+    }
+
+    /// A fresh ULID for the current time on `clock`.
+    pub fn with_clock(clock: &dyn Clock) -> Self {
+        Self::from_parts(unix_ms(clock), rand::rng().random())
+    }
+
+    pub fn from_parts(timestamp_ms: u64, random: u128) -> Self {
+        let timestamp = (timestamp_ms as u128 & 0xFFFF_FFFF_FFFF) << RANDOM_BITS;
+        Ulid(timestamp | (random & random_mask()))
+    }
+
+    pub fn from_bytes(bytes: BinaryKey) -> Self {
+        Ulid(u128::from_be_bytes(bytes))
+    }
+
+    /// Big-endian bytes, so byte order matches ULID order.
+    pub fn to_bytes(self) -> BinaryKey {
+        self.0.to_be_bytes()
+    }
+
+    pub fn timestamp_ms(self) -> u64 {
+        (self.0 >> RANDOM_BITS) as u64
+    }
+}
+
+impl Default for Ulid {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for Ulid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = [0u8; 26];
+        for (i, c) in out.iter_mut().enumerate() {
+            let shift = 5 * (25 - i);
+            *c = ALPHABET[((self.0 >> shift) & 0x1F) as usize];
+        }
+        f.write_str(std::str::from_utf8(&out).expect("alphabet is ASCII"))
+    }
+}
+
+impl FromStr for Ulid {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() != 26 {
+            return Err(format!("ULID must be 26 characters, got {}", s.len()));
+        }
+        // The first character only carries 3 bits
+        if !matches!(s.as_bytes()[0], b'0'..=b'7') {
+            return Err(format!("ULID {:?} overflows 128 bits", s));
+        }
+        let mut value = 0u128;
+        for c in s.bytes() {
+            let digit = ALPHABET
+                .iter()
+                .position(|&a| a == c.to_ascii_uppercase())
+                .ok_or_else(|| format!("invalid ULID character {:?}", c as char))?;
+            value = (value << 5) | digit as u128;
+        }
+        Ok(Ulid(value))
+    }
+}
+
+/// Hands out strictly increasing ULIDs. Within one millisecond the random part is
+/// incremented instead of redrawn, so insert order and key order agree.
+pub struct UlidGenerator {
+    last: Mutex<Option<Ulid>>,
+    clock: Arc<dyn Clock>,
+    // None draws from the thread-local generator
+    rng: Option<Mutex<Box<dyn RngCore + Send>>>,
+}
+
+impl Default for UlidGenerator {
+    fn default() -> Self {
+        Self {
+            last: Mutex::new(None),
+            clock: system_clock(),
+            rng: None,
+        }
+    }
+}
+
+impl UlidGenerator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take timestamps from `clock` and random bits from `rng`; with a ManualClock and a
+    /// seeded rng the sequence is the same on every run.
+    pub fn with_sources(clock: Arc<dyn Clock>, rng: Box<dyn RngCore + Send>) -> Self {
+        Self {
+            last: Mutex::new(None),
+            clock,
+            rng: Some(Mutex::new(rng)),
+        }
+    }
+
+    pub fn next(&self) -> Ulid {
+        let mut last = self.last.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let random = match &self.rng {
+            Some(rng) => rng.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).random(),
+            None => rand::rng().random(),
+        };
+        let fresh = Ulid::from_parts(unix_ms(self.clock.as_ref()), random);
+        let ulid = match *last {
+            // Same (or earlier, if the clock stepped back) millisecond: bump the previous id
+            Some(prev) if fresh.timestamp_ms() <= prev.timestamp_ms() => {
+                if prev.0 & random_mask() == random_mask() {
+                    // Random part exhausted; borrow from the next millisecond
+                    Ulid::from_parts(prev.timestamp_ms() + 1, 0)
+                } else {
+                    Ulid(prev.0 + 1)
+                }
+            }
+            _ => fresh,
+        };
+        *last = Some(ulid);
+        ulid
+    }
+}
+
+/// Random (version 4, RFC 9562) UUID.
+pub fn uuid_v4() -> BinaryKey {
+    let mut bytes: BinaryKey = rand::rng().random();
+    bytes[6] = (bytes[6] & 0x0F) | 0x40;
+    bytes[8] = (bytes[8] & 0x3F) | 0x80;
+    bytes
+}
+
+/// Time-ordered (version 7, RFC 9562) UUID: 48-bit unix milliseconds, then random bits.
+/// Sorts like a ULID while staying a valid UUID.
+pub fn uuid_v7() -> BinaryKey {
+    uuid_v7_with_clock(&SystemClock)
+}
+
+/// Version 7 UUID for the current time on `clock`.
+pub fn uuid_v7_with_clock(clock: &dyn Clock) -> BinaryKey {
+    let mut bytes: BinaryKey = rand::rng().random();
+    bytes[..6].copy_from_slice(&unix_ms(clock).to_be_bytes()[2..]);
+    bytes[6] = (bytes[6] & 0x0F) | 0x70;
+    bytes[8] = (bytes[8] & 0x3F) | 0x80;
+    bytes
+}
+
+/// Hyphenated lowercase form, e.g. `0190f3c2-7b1e-7c3a-9f00-5d2a4b6c8e10`.
+pub fn format_uuid(key: &BinaryKey) -> String {
+    let hex: String = key.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}-{}-{}-{}-{}", &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32])
+}
+
+fn random_mask() -> u128 {
+    (1u128 << RANDOM_BITS) - 1
+}
+
+fn unix_ms(clock: &dyn Clock) -> u64 {
+    clock.unix_time().as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ulid_roundtrip_and_order() {
+        let ulid = Ulid::from_parts(1_700_000_000_000, 0x1234_5678_9ABC_DEF0_1234);
+        assert_eq!(ulid.timestamp_ms(), 1_700_000_000_000);
+        assert_eq!(ulid.to_string().parse::<Ulid>().unwrap(), ulid);
+        assert_eq!(Ulid::from_bytes(ulid.to_bytes()), ulid);
+
+        let later = Ulid::from_parts(1_700_000_000_001, 0);
+        assert!(later > ulid);
+        assert!(later.to_bytes() > ulid.to_bytes());
+        assert!(later.to_string() > ulid.to_string());
+
+        assert!("8ZZZZZZZZZZZZZZZZZZZZZZZZZ".parse::<Ulid>().is_err());
+        assert!("0000000000000000000000000U".parse::<Ulid>().is_err());
+    }
+
+    #[test]
+    fn test_ulid_generator_is_monotonic() {
+        let generator = UlidGenerator::new();
+        let ids: Vec<BinaryKey> = (0..1000).map(|_| generator.next().to_bytes()).collect();
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn test_uuid_versions() {
+        let v4 = uuid_v4();
+        assert_eq!(v4[6] >> 4, 4);
+        assert_eq!(v4[8] >> 6, 0b10);
+
+        let v7 = uuid_v7();
+        assert_eq!(v7[6] >> 4, 7);
+        let clock = crate::common::clock::ManualClock::new(std::time::Duration::from_millis(0x0123_4567_89AB));
+        assert_eq!(&uuid_v7_with_clock(&clock)[..6], &[0x01, 0x23, 0x45, 0x67, 0x89, 0xAB]);
+        assert_eq!(Ulid::with_clock(&clock).timestamp_ms(), 0x0123_4567_89AB);
+        let text = format_uuid(&v7);
+        assert_eq!(text.len(), 36);
+        assert_eq!(&text[14..15], "7");
+    }
+
+    #[test]
+    fn test_ulid_generator_with_fixed_sources_is_reproducible() {
+        use crate::common::clock::ManualClock;
+        use rand::{SeedableRng, rngs::StdRng};
+
+        let generate = || {
+            let clock = Arc::new(ManualClock::new(std::time::Duration::from_millis(1_700_000_000_000)));
+            let ids = UlidGenerator::with_sources(clock.clone(), Box::new(StdRng::seed_from_u64(7)));
+            let first = ids.next();
+            clock.advance(std::time::Duration::from_millis(1));
+            (first, ids.next())
+        };
+        let (first, second) = generate();
+        assert_eq!(generate(), (first, second));
+        assert_eq!((first.timestamp_ms(), second.timestamp_ms()), (1_700_000_000_000, 1_700_000_000_001));
+    }
+}
@@ -0,0 +1,198 @@
+//! Memory budgets for query operators.
+//! A MemoryManager owns the engine-wide budget. Each query registers a QueryMemory with
+//! its own limit, and each memory-hungry operator (hash table, sort buffer) holds a
+//! MemoryReservation that it grows before allocating. A failed try_grow is the spill
+//! trigger: the operator writes what it has to temp storage, shrinks, and retries, or
+//! fails the query with the MemoryError if it cannot spill.
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use crate::common::errors::MemoryError;
+
+/// Engine-wide memory budget shared by all queries.
+#[derive(Debug)]
+pub struct MemoryManager {
+    limit: usize,
+    used: AtomicUsize,
+}
+
+impl MemoryManager {
+    pub fn new(limit: usize) -> Arc<Self> {
+        Arc::new(Self {
+            limit,
+            used: AtomicUsize::new(0),
+        })
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Bytes currently reserved by all queries.
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::SeqCst)
+    }
+
+    /// Register a query that may use at most `query_limit` bytes (capped by the global limit).
+    pub fn register_query(self: &Arc<Self>, query_limit: usize) -> Arc<QueryMemory> {
+        Arc::new(QueryMemory {
+            manager: Arc::clone(self),
+            limit: query_limit.min(self.limit),
+            used: AtomicUsize::new(0),
+        })
+    }
+
+    fn try_grow(&self, bytes: usize) -> Result<(), MemoryError> {
+        self.used
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |used| {
+                used.checked_add(bytes).filter(|total| *total <= self.limit)
+            })
+            .map(|_| ())
+            .map_err(|used| MemoryError::GlobalLimitExceeded {
+                requested: bytes,
+                used,
+                limit: self.limit,
+            })
+    }
+
+    fn shrink(&self, bytes: usize) {
+        self.used.fetch_sub(bytes, Ordering::SeqCst);
+    }
+}
+
+/// Memory budget of a single query, carved out of the global one.
+#[derive(Debug)]
+pub struct QueryMemory {
+    manager: Arc<MemoryManager>,
+    limit: usize,
+    used: AtomicUsize,
+}
+
+impl QueryMemory {
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Bytes currently reserved by this query's operators.
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::SeqCst)
+    }
+
+    /// New, empty reservation for one operator. `name` shows up in logs.
+    pub fn reservation(self: &Arc<Self>, name: &str) -> MemoryReservation {
+        MemoryReservation {
+            query: Arc::clone(self),
+            name: name.to_string(),
+            size: 0,
+        }
+    }
+
+    fn try_grow(&self, bytes: usize) -> Result<(), MemoryError> {
+        self.used
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |used| {
+                used.checked_add(bytes).filter(|total| *total <= self.limit)
+            })
+            .map_err(|used| MemoryError::QueryLimitExceeded {
+                requested: bytes,
+                used,
+                limit: self.limit,
+            })?;
+        if let Err(e) = self.manager.try_grow(bytes) {
+            self.used.fetch_sub(bytes, Ordering::SeqCst);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    fn shrink(&self, bytes: usize) {
+        self.used.fetch_sub(bytes, Ordering::SeqCst);
+        self.manager.shrink(bytes);
+    }
+}
+
+/// Bytes held by one operator. Everything still held is returned on drop.
+#[derive(Debug)]
+pub struct MemoryReservation {
+    query: Arc<QueryMemory>,
+    name: String,
+    size: usize,
+}
+
+impl MemoryReservation {
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Reserve `bytes` more. On error nothing is reserved and the caller should spill.
+    pub fn try_grow(&mut self, bytes: usize) -> Result<(), MemoryError> {
+        self.query.try_grow(bytes).inspect_err(|e| {
+            log::debug!("{}: {}", self.name, e);
+        })?;
+        self.size += bytes;
+        Ok(())
+    }
+
+    /// Give back up to `bytes`, e.g. after spilling part of the operator's state.
+    pub fn shrink(&mut self, bytes: usize) {
+        let bytes = bytes.min(self.size);
+        self.query.shrink(bytes);
+        self.size -= bytes;
+    }
+
+    /// Give back everything, returning how much was held.
+    pub fn free(&mut self) -> usize {
+        let size = self.size;
+        self.shrink(size);
+        size
+    }
+}
+
+impl Drop for MemoryReservation {
+    fn drop(&mut self) {
+        self.free();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_limit_triggers_spill() {
+        let manager = MemoryManager::new(1000);
+        let query = manager.register_query(100);
+        let mut sort = query.reservation("sort");
+
+        sort.try_grow(80).unwrap();
+        assert!(matches!(sort.try_grow(40), Err(MemoryError::QueryLimitExceeded { .. })));
+        assert_eq!(sort.size(), 80);
+
+        // Spill half, then the request fits
+        sort.shrink(40);
+        sort.try_grow(40).unwrap();
+        assert_eq!(query.used(), 80);
+        assert_eq!(manager.used(), 80);
+    }
+
+    #[test]
+    fn test_global_limit_shared_between_queries() {
+        let manager = MemoryManager::new(100);
+        let q1 = manager.register_query(100);
+        let q2 = manager.register_query(100);
+        let mut hash = q1.reservation("hash join");
+        let mut sort = q2.reservation("sort");
+
+        hash.try_grow(70).unwrap();
+        assert!(matches!(sort.try_grow(40), Err(MemoryError::GlobalLimitExceeded { .. })));
+        assert_eq!(q2.used(), 0);
+
+        drop(hash);
+        assert_eq!(manager.used(), 0);
+        sort.try_grow(40).unwrap();
+        assert_eq!(sort.free(), 40);
+        assert_eq!(manager.used(), 0);
+    }
+}
@@ -1,2 +1,19 @@
 pub mod types;
-pub mod errors;
\ No newline at end of file
+pub mod errors;
+pub mod checksum;
+pub mod cancellation;
+pub mod memory;
+pub mod admission;
+pub mod write_stall;
+pub mod keys;
+pub mod codec;
+pub mod slow_log;
+pub mod histogram;
+pub mod progress;
+pub mod cpu_pool;
+pub mod buffer_recycler;
+pub mod clock;
+pub mod options;
+pub mod supervisor;
+pub mod task_manager;
+pub(crate) mod sync;
@@ -0,0 +1,69 @@
+//! Per-call options for reads and writes.
+//! Defaults give the ordinary behaviour, so callers only spell out what they change, and
+//! new options can be added without touching the signatures that take them.
+
+use crate::backend::storage::storage_backend::DurabilityMode;
+
+/// How a read treats the buffer pool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadOptions {
+    /// Keep pages this read loads in the buffer pool like any other. Turn it off for
+    /// one-off scans (ETL, analytics): their pages then become the next eviction victims
+    /// and do not push the regular working set out of the pool.
+    pub fill_cache: bool,
+}
+
+impl Default for ReadOptions {
+    fn default() -> Self {
+        Self { fill_cache: true }
+    }
+}
+
+/// How a write is made durable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteOptions {
+    /// Sync this write with the given mode instead of the DiskManager's own.
+    pub durability: Option<DurabilityMode>,
+    /// Wait for the write to reach stable storage before returning. Without it the write
+    /// is left to the OS, as with DurabilityMode::None, and a crash can lose it.
+    pub sync: bool,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        Self {
+            durability: None,
+            sync: true,
+        }
+    }
+}
+
+impl WriteOptions {
+    /// The mode a write with these options is synced with, given the manager's `default`.
+    pub fn effective_durability(&self, default: DurabilityMode) -> DurabilityMode {
+        if self.sync {
+            self.durability.unwrap_or(default)
+        } else {
+            DurabilityMode::None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_effective_durability() {
+        let options = WriteOptions::default();
+        assert_eq!(options.effective_durability(DurabilityMode::DataSync), DurabilityMode::DataSync);
+
+        let options = WriteOptions {
+            durability: Some(DurabilityMode::RangeSync),
+            ..WriteOptions::default()
+        };
+        assert_eq!(options.effective_durability(DurabilityMode::SyncAll), DurabilityMode::RangeSync);
+        let options = WriteOptions { sync: false, ..options };
+        assert_eq!(options.effective_durability(DurabilityMode::SyncAll), DurabilityMode::None);
+    }
+}
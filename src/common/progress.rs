@@ -0,0 +1,114 @@
+//! Progress reporting for long-running operations.
+//! The operation owns a ProgressTracker and advances it as it goes; any number of
+//! observers watch it through subscribe() and can derive an ETA from what they see.
+
+use std::time::{Duration, Instant};
+
+use tokio::sync::watch;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// Units of work finished so far (pages, for the maintenance tasks).
+    pub done: u64,
+    /// Units of work the operation expects to do in total.
+    pub total: u64,
+    pub elapsed: Duration,
+}
+
+impl Progress {
+    /// Time left at the average rate so far, once there is a rate to go by.
+    pub fn eta(&self) -> Option<Duration> {
+        if self.done == 0 {
+            return None;
+        }
+        let remaining = self.total.saturating_sub(self.done);
+        Some(self.elapsed.mul_f64(remaining as f64 / self.done as f64))
+    }
+
+    pub fn fraction(&self) -> f64 {
+        if self.total == 0 { 1.0 } else { self.done as f64 / self.total as f64 }
+    }
+}
+
+#[derive(Debug)]
+pub struct ProgressTracker {
+    tx: watch::Sender<Progress>,
+    started: Instant,
+}
+
+impl Default for ProgressTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProgressTracker {
+    pub fn new() -> Self {
+        let (tx, _) = watch::channel(Progress {
+            done: 0,
+            total: 0,
+            elapsed: Duration::ZERO,
+        });
+        Self {
+            tx,
+            started: Instant::now(),
+        }
+    }
+
+    /// Receiver that sees every update.
+    pub fn subscribe(&self) -> watch::Receiver<Progress> {
+        self.tx.subscribe()
+    }
+
+    pub fn current(&self) -> Progress {
+        *self.tx.borrow()
+    }
+
+    /// Announce how much work there is.
+    pub fn set_total(&self, total: u64) {
+        let elapsed = self.started.elapsed();
+        self.tx.send_modify(|progress| {
+            progress.total = total;
+            progress.elapsed = elapsed;
+        });
+    }
+
+    /// Record `units` more units of work as finished.
+    pub fn advance(&self, units: u64) {
+        let elapsed = self.started.elapsed();
+        self.tx.send_modify(|progress| {
+            progress.done += units;
+            progress.elapsed = elapsed;
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eta_from_average_rate() {
+        let progress = Progress {
+            done: 25,
+            total: 100,
+            elapsed: Duration::from_secs(10),
+        };
+        assert_eq!(progress.eta(), Some(Duration::from_secs(30)));
+        assert_eq!(progress.fraction(), 0.25);
+        assert_eq!(Progress { done: 0, ..progress }.eta(), None);
+    }
+
+    #[test]
+    fn test_subscribers_see_updates() {
+        let tracker = ProgressTracker::new();
+        let mut rx = tracker.subscribe();
+        tracker.set_total(4);
+        tracker.advance(1);
+        tracker.advance(2);
+        assert!(rx.has_changed().unwrap());
+        let progress = *rx.borrow_and_update();
+        assert_eq!((progress.done, progress.total), (3, 4));
+        assert_eq!(tracker.current(), progress);
+    }
+}
@@ -0,0 +1,187 @@
+//! Slow operation log.
+//! Page I/O, WAL fsyncs and queries that take longer than their threshold are written as
+//! one human-readable line each to a dedicated diagnostics file (or to the regular log
+//! when no file is set), with enough context to find the culprit:
+//!
+//! ```text
+//! [1760590000.123] slow page write: page 42 took 153 ms (threshold 100 ms)
+//! [1760590001.456] slow wal fsync: 4120 bytes took 212 ms (threshold 50 ms)
+//! [1760590002.789] slow query: 1840 ms (threshold 1000 ms): SELECT ... | scan 1700 ms, sort 120 ms
+//! ```
+//!
+//! Each threshold can be changed at runtime; None turns that kind of entry off.
+
+use std::{
+    fs::File,
+    io::Write,
+    path::Path,
+    sync::{
+        Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::common::types::PageId;
+
+// Stored in place of a threshold that is turned off
+const DISABLED: u64 = u64::MAX;
+
+/// Per-kind thresholds; None disables that kind.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SlowLogThresholds {
+    pub page_io: Option<Duration>,
+    pub wal_fsync: Option<Duration>,
+    pub query: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageIoKind {
+    Read,
+    Write,
+}
+
+pub struct SlowLog {
+    page_io_ms: AtomicU64,
+    wal_fsync_ms: AtomicU64,
+    query_ms: AtomicU64,
+    // None sends entries to log::warn! instead
+    file: Option<Mutex<File>>,
+}
+
+impl SlowLog {
+    /// A slow log that reports through the regular log.
+    pub fn new(thresholds: SlowLogThresholds) -> Self {
+        let slow_log = Self {
+            page_io_ms: AtomicU64::new(DISABLED),
+            wal_fsync_ms: AtomicU64::new(DISABLED),
+            query_ms: AtomicU64::new(DISABLED),
+            file: None,
+        };
+        slow_log.set_thresholds(thresholds);
+        slow_log
+    }
+
+    /// A slow log that appends to the file at `path`, creating it if needed.
+    pub fn to_file(thresholds: SlowLogThresholds, path: &Path) -> std::io::Result<Self> {
+        let file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            file: Some(Mutex::new(file)),
+            ..Self::new(thresholds)
+        })
+    }
+
+    pub fn thresholds(&self) -> SlowLogThresholds {
+        SlowLogThresholds {
+            page_io: load(&self.page_io_ms),
+            wal_fsync: load(&self.wal_fsync_ms),
+            query: load(&self.query_ms),
+        }
+    }
+
+    pub fn set_thresholds(&self, thresholds: SlowLogThresholds) {
+        store(&self.page_io_ms, thresholds.page_io);
+        store(&self.wal_fsync_ms, thresholds.wal_fsync);
+        store(&self.query_ms, thresholds.query);
+    }
+
+    /// Report a page read or write that took `elapsed`, if that is over the threshold.
+    pub fn page_io(&self, kind: PageIoKind, page_id: PageId, elapsed: Duration) {
+        let kind = match kind {
+            PageIoKind::Read => "read",
+            PageIoKind::Write => "write",
+        };
+        self.report(&self.page_io_ms, elapsed, |threshold| {
+            format!("slow page {}: page {} took {}", kind, page_id, threshold)
+        });
+    }
+
+    /// Report a log sync of `bytes` appended bytes that took `elapsed`.
+    pub fn wal_fsync(&self, bytes: usize, elapsed: Duration) {
+        self.report(&self.wal_fsync_ms, elapsed, |threshold| {
+            format!("slow wal fsync: {} bytes took {}", bytes, threshold)
+        });
+    }
+
+    /// Report a query that took `elapsed`, with the time spent in each of its operators.
+    pub fn query(&self, statement: &str, elapsed: Duration, operators: &[(&str, Duration)]) {
+        self.report(&self.query_ms, elapsed, |threshold| {
+            let mut line = format!("slow query: {}: {}", threshold, statement.trim());
+            for (i, (name, time)) in operators.iter().enumerate() {
+                line.push_str(if i == 0 { " | " } else { ", " });
+                line.push_str(&format!("{} {} ms", name, time.as_millis()));
+            }
+            line
+        });
+    }
+
+    // `describe` gets the "N ms (threshold M ms)" part and returns the whole entry
+    fn report(&self, threshold_ms: &AtomicU64, elapsed: Duration, describe: impl FnOnce(String) -> String) {
+        let threshold = threshold_ms.load(Ordering::Relaxed);
+        if threshold == DISABLED || elapsed <= Duration::from_millis(threshold) {
+            return;
+        }
+        let entry = describe(format!("{} ms (threshold {} ms)", elapsed.as_millis(), threshold));
+        let Some(file) = &self.file else {
+            log::warn!("{}", entry);
+            return;
+        };
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let line = format!("[{}.{:03}] {}\n", now.as_secs(), now.subsec_millis(), entry);
+        let mut file = file.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Err(e) = file.write_all(line.as_bytes()) {
+            log::warn!("cannot write slow log entry ({}): {}", e, entry);
+        }
+    }
+}
+
+fn load(threshold_ms: &AtomicU64) -> Option<Duration> {
+    match threshold_ms.load(Ordering::Relaxed) {
+        DISABLED => None,
+        ms => Some(Duration::from_millis(ms)),
+    }
+}
+
+fn store(threshold_ms: &AtomicU64, threshold: Option<Duration>) {
+    let ms = threshold.map_or(DISABLED, |t| (t.as_millis() as u64).min(DISABLED - 1));
+    threshold_ms.store(ms, Ordering::Relaxed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_entries_over_threshold_are_written() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("slow.log");
+        let slow_log = SlowLog::to_file(
+            SlowLogThresholds {
+                page_io: Some(Duration::from_millis(10)),
+                wal_fsync: None,
+                query: Some(Duration::from_millis(100)),
+            },
+            &path,
+        )
+        .unwrap();
+
+        slow_log.page_io(PageIoKind::Read, 7, Duration::from_millis(5));
+        slow_log.page_io(PageIoKind::Write, 8, Duration::from_millis(25));
+        slow_log.wal_fsync(4096, Duration::from_secs(5));
+        slow_log.query(
+            "SELECT 1 ",
+            Duration::from_millis(150),
+            &[("scan", Duration::from_millis(120)), ("sort", Duration::from_millis(30))],
+        );
+        slow_log.set_thresholds(SlowLogThresholds::default());
+        slow_log.page_io(PageIoKind::Write, 9, Duration::from_secs(1));
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with("] slow page write: page 8 took 25 ms (threshold 10 ms)"));
+        assert!(lines[1].ends_with(
+            "] slow query: 150 ms (threshold 100 ms): SELECT 1 | scan 120 ms, sort 30 ms"
+        ));
+    }
+}
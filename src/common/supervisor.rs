@@ -0,0 +1,82 @@
+//! Restarts background workers that die from a panic.
+//! A panic inside a plain tokio::spawn'ed loop ends the task quietly and the work it did
+//! (flushing, scheduling) simply stops. spawn_supervised() runs the worker as a child
+//! task instead, logs the panic and starts a fresh one after `restart_delay`.
+
+use std::{any::Any, future::Future, time::Duration};
+
+use tokio::task::{AbortHandle, JoinHandle};
+
+/// Run the worker `make()` returns, making a new one whenever it panics. The supervisor
+/// ends when a worker returns normally; aborting the returned handle also stops the
+/// current worker.
+pub fn spawn_supervised<F, Fut>(name: &'static str, restart_delay: Duration, mut make: F) -> JoinHandle<()>
+where
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    tokio::spawn(async move {
+        loop {
+            let worker = tokio::spawn(make());
+            let _abort_worker = AbortOnDrop(worker.abort_handle());
+            match worker.await {
+                Ok(()) => break,
+                Err(e) if e.is_panic() => {
+                    log::error!(
+                        "background worker {} panicked: {}; restarting in {:?}",
+                        name,
+                        panic_message(e.into_panic().as_ref()),
+                        restart_delay
+                    );
+                    tokio::time::sleep(restart_delay).await;
+                }
+                // The runtime is shutting down
+                Err(_) => break,
+            }
+        }
+    })
+}
+
+/// Best-effort text of a panic payload.
+pub fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "non-string panic payload"
+    }
+}
+
+struct AbortOnDrop(AbortHandle);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    #[tokio::test]
+    async fn test_panicking_worker_is_restarted() {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&runs);
+        let supervisor = spawn_supervised("flaky", Duration::from_millis(1), move || {
+            let counter = Arc::clone(&counter);
+            async move {
+                if counter.fetch_add(1, Ordering::SeqCst) < 2 {
+                    panic!("worker crashed");
+                }
+            }
+        });
+        supervisor.await.unwrap();
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+    }
+}
@@ -0,0 +1,16 @@
+//! std::sync, or loom's model of it when built with `--cfg grimoire_loom`.
+//! The buffer pool's frame table goes through these types, so loom can explore every
+//! interleaving of pin, unpin and eviction in the tests marked `loom`. Run them with
+//!
+//! ```text
+//! RUSTFLAGS="--cfg grimoire_loom" cargo test --release --lib loom
+//! ```
+//!
+//! The cfg is not plain `loom` because tokio reads that one too and drops its fs and
+//! signal support under it. Everything else still uses std directly. Under loom the other
+//! tests are not meant to run: loom's primitives panic when touched outside loom::model.
+
+#[cfg(grimoire_loom)]
+pub(crate) use loom::sync::{Mutex, MutexGuard};
+#[cfg(not(grimoire_loom))]
+pub(crate) use std::sync::{Mutex, MutexGuard};
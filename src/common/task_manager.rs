@@ -0,0 +1,305 @@
+//! Background task manager.
+//!
+//! The engine's periodic work (checkpoints, and in time flushing, compaction, GC and
+//! WAL archiving) runs as tasks owned by a TaskManager instead of detached spawns. Each
+//! task is a factory for a worker future. The manager watches it and, when a worker
+//! returns an error or panics, logs it and starts a new one after an exponential backoff.
+//! status() reports every task's state, restart count and last failure, and Instance
+//! includes it in its status report.
+//!
+//! Workers get a CancellationToken and should return Ok(()) soon after it is cancelled;
+//! shutdown() cancels every task and waits for them, dropping the manager aborts them.
+
+use std::{
+    error::Error,
+    future::Future,
+    sync::{Arc, Mutex as SyncMutex},
+    time::Duration,
+};
+
+use serde::Serialize;
+use tokio::task::JoinHandle;
+
+use crate::common::{
+    cancellation::CancellationToken,
+    clock::{Clock, system_clock},
+    supervisor::panic_message,
+};
+
+/// What a worker returns; an error counts as a crash and is retried.
+pub type TaskResult = Result<(), Box<dyn Error + Send + Sync>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskState {
+    Running,
+    /// The worker crashed and a new one starts once the backoff has passed.
+    Backoff,
+    /// The worker returned Ok, or the task was shut down.
+    Finished,
+    /// The worker crashed more often than RestartPolicy::max_restarts allows.
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TaskStatus {
+    pub name: String,
+    pub state: TaskState,
+    pub restarts: u32,
+    pub last_error: Option<String>,
+}
+
+/// How a crashed task is restarted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RestartPolicy {
+    /// Wait before the first restart; it doubles with every crash in a row.
+    pub initial_backoff: Duration,
+    /// Longest wait. A worker that stays up this long resets the backoff.
+    pub max_backoff: Duration,
+    /// Give up after this many restarts; None keeps restarting.
+    pub max_restarts: Option<u32>,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(30),
+            max_restarts: None,
+        }
+    }
+}
+
+struct Task {
+    status: Arc<SyncMutex<TaskStatus>>,
+    // Taken by shutdown()
+    handle: Option<JoinHandle<()>>,
+}
+
+pub struct TaskManager {
+    tasks: SyncMutex<Vec<Task>>,
+    shutdown: CancellationToken,
+    // Times worker uptime and restart backoffs
+    clock: Arc<dyn Clock>,
+}
+
+impl Default for TaskManager {
+    fn default() -> Self {
+        Self {
+            tasks: SyncMutex::new(Vec::new()),
+            shutdown: CancellationToken::new(),
+            clock: system_clock(),
+        }
+    }
+}
+
+impl TaskManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Time backoffs with `clock` instead of the system clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Run the worker `make` builds under supervision. Must be called from within a
+    /// tokio runtime.
+    pub fn spawn<F, Fut>(&self, name: impl Into<String>, policy: RestartPolicy, mut make: F)
+    where
+        F: FnMut(CancellationToken) -> Fut + Send + 'static,
+        Fut: Future<Output = TaskResult> + Send + 'static,
+    {
+        let name = name.into();
+        let status = Arc::new(SyncMutex::new(TaskStatus {
+            name: name.clone(),
+            state: TaskState::Running,
+            restarts: 0,
+            last_error: None,
+        }));
+        let cancel = self.shutdown.child_token();
+        let health = Arc::clone(&status);
+        let clock = Arc::clone(&self.clock);
+        let handle = tokio::spawn(async move {
+            let mut crashes_in_a_row = 0;
+            loop {
+                update(&health, |status| status.state = TaskState::Running);
+                let started = clock.now();
+                let error = match tokio::spawn(make(cancel.clone())).await {
+                    Ok(Ok(())) => None,
+                    Ok(Err(e)) => Some(e.to_string()),
+                    Err(e) if e.is_panic() => Some(format!("panicked: {}", panic_message(e.into_panic().as_ref()))),
+                    Err(_) => None,
+                };
+                let Some(error) = error.filter(|_| !cancel.is_cancelled()) else {
+                    update(&health, |status| status.state = TaskState::Finished);
+                    return;
+                };
+
+                let restarts = update(&health, |status| {
+                    status.last_error = Some(error.clone());
+                    status.restarts
+                });
+                if policy.max_restarts.is_some_and(|max| restarts >= max) {
+                    log::error!("background task {} failed, giving up after {} restarts: {}", name, restarts, error);
+                    update(&health, |status| status.state = TaskState::Failed);
+                    return;
+                }
+                if clock.now().duration_since(started) >= policy.max_backoff {
+                    crashes_in_a_row = 0;
+                }
+                let backoff = policy
+                    .initial_backoff
+                    .saturating_mul(1 << crashes_in_a_row.min(16))
+                    .min(policy.max_backoff);
+                crashes_in_a_row += 1;
+                log::warn!("background task {} failed: {}; restarting in {:?}", name, error, backoff);
+                update(&health, |status| {
+                    status.state = TaskState::Backoff;
+                    status.restarts += 1;
+                });
+                tokio::select! {
+                    _ = clock.sleep(backoff) => {}
+                    _ = cancel.cancelled() => {
+                        update(&health, |status| status.state = TaskState::Finished);
+                        return;
+                    }
+                }
+            }
+        });
+
+        let mut tasks = self.lock_tasks();
+        tasks.retain(|task| task.handle.as_ref().is_some_and(|handle| !handle.is_finished()));
+        tasks.push(Task {
+            status,
+            handle: Some(handle),
+        });
+    }
+
+    /// Every task that is still running or restarting, plus those that finished or
+    /// failed since the last spawn().
+    pub fn status(&self) -> Vec<TaskStatus> {
+        self.lock_tasks()
+            .iter()
+            .map(|task| task.status.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone())
+            .collect()
+    }
+
+    /// Cancel every task and wait for its worker to return.
+    pub async fn shutdown(&self) {
+        self.shutdown.cancel();
+        let handles: Vec<JoinHandle<()>> = self
+            .lock_tasks()
+            .iter_mut()
+            .filter_map(|task| task.handle.take())
+            .collect();
+        for handle in handles {
+            let _ = handle.await;
+        }
+    }
+
+    fn lock_tasks(&self) -> std::sync::MutexGuard<'_, Vec<Task>> {
+        self.tasks.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Drop for TaskManager {
+    fn drop(&mut self) {
+        self.shutdown.cancel();
+        for handle in self.lock_tasks().iter().filter_map(|task| task.handle.as_ref()) {
+            handle.abort();
+        }
+    }
+}
+
+fn update<T>(status: &SyncMutex<TaskStatus>, change: impl FnOnce(&mut TaskStatus) -> T) -> T {
+    change(&mut status.lock().unwrap_or_else(|poisoned| poisoned.into_inner()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[tokio::test]
+    async fn test_crashed_task_restarts_with_backoff() {
+        let tasks = TaskManager::new();
+        let runs = Arc::new(AtomicU32::new(0));
+        let counter = Arc::clone(&runs);
+        let policy = RestartPolicy {
+            initial_backoff: Duration::from_millis(1),
+            ..RestartPolicy::default()
+        };
+        tasks.spawn("flaky", policy, move |cancel| {
+            let run = counter.fetch_add(1, Ordering::SeqCst);
+            async move {
+                match run {
+                    0 => Err("disk full".into()),
+                    1 => panic!("worker crashed"),
+                    _ => {
+                        cancel.cancelled().await;
+                        Ok(())
+                    }
+                }
+            }
+        });
+
+        while runs.load(Ordering::SeqCst) < 3 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        let status = &tasks.status()[0];
+        assert_eq!((status.state, status.restarts), (TaskState::Running, 2));
+        assert_eq!(status.last_error.as_deref(), Some("panicked: worker crashed"));
+
+        tasks.shutdown().await;
+        assert_eq!(tasks.status()[0].state, TaskState::Finished);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_restarts() {
+        let tasks = TaskManager::new();
+        let policy = RestartPolicy {
+            initial_backoff: Duration::from_millis(1),
+            max_restarts: Some(2),
+            ..RestartPolicy::default()
+        };
+        tasks.spawn("broken", policy, |_| async { Err("no such file".into()) });
+        while tasks.status()[0].state != TaskState::Failed {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        assert_eq!(tasks.status()[0].restarts, 2);
+    }
+
+    #[tokio::test]
+    async fn test_backoff_follows_injected_clock() {
+        use crate::common::clock::ManualClock;
+
+        let clock = Arc::new(ManualClock::new(Duration::ZERO));
+        let tasks = TaskManager::new().with_clock(clock.clone());
+        let runs = Arc::new(AtomicU32::new(0));
+        let counter = Arc::clone(&runs);
+        let policy = RestartPolicy {
+            initial_backoff: Duration::from_secs(60),
+            max_backoff: Duration::from_secs(3600),
+            max_restarts: None,
+        };
+        tasks.spawn("flaky", policy, move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async { Err("disk full".into()) }
+        });
+
+        // However long we wait in real time, no restart happens until the clock moves
+        while tasks.status()[0].state != TaskState::Backoff {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+        clock.advance(Duration::from_secs(60));
+        while runs.load(Ordering::SeqCst) < 2 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        tasks.shutdown().await;
+    }
+}
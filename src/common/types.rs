@@ -1,2 +1,8 @@
 pub type FrameId = usize;
 pub type PageId = i32;
+
+/// 16-byte binary key, e.g. a ULID or UUID. Compares bytewise.
+pub type BinaryKey = [u8; 16];
+
+/// A table or index: the unit that owns extents of the page file.
+pub type SegmentId = u32;
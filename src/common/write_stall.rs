@@ -0,0 +1,270 @@
+//! Write stalls: flow control for incoming writes.
+//! Writes can arrive faster than the engine retires them: dirty pages pile up faster
+//! than checkpoints write them back, or committed records faster than the applier gets
+//! through them. Rather than letting memory grow until something gives, WriteController
+//! holds writes back in proportion to how far past a slowdown threshold the worst
+//! signal is, up to `max_delay` just short of the stop threshold. At the stop threshold
+//! writes are rejected with DiskError::WriteStalled (ErrorCode::Busy) until the backlog
+//! drains.
+//!
+//! The signals are reported by whoever can see them (Instance samples the dirty ratio,
+//! CommitPipeline reports its apply backlog); admit() only reads the resulting state, so
+//! it costs a lock and no I/O.
+
+use std::{
+    fmt,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use serde::Serialize;
+
+use crate::common::{
+    clock::{Clock, system_clock},
+    errors::DiskError,
+};
+
+/// Thresholds at which writes are slowed down and stopped.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WriteStallConfig {
+    /// Fraction of buffer pool frames holding dirty pages at which writes slow down.
+    pub slowdown_dirty_ratio: f64,
+    pub stop_dirty_ratio: f64,
+    /// Committed records not applied yet at which commits slow down.
+    pub slowdown_apply_backlog: u64,
+    pub stop_apply_backlog: u64,
+    /// Delay of a write just short of a stop threshold.
+    pub max_delay: Duration,
+}
+
+impl Default for WriteStallConfig {
+    fn default() -> Self {
+        Self {
+            slowdown_dirty_ratio: 0.75,
+            stop_dirty_ratio: 0.95,
+            slowdown_apply_backlog: 256,
+            stop_apply_backlog: 1000,
+            max_delay: Duration::from_millis(50),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StallReason {
+    DirtyPages,
+    ApplyBacklog,
+}
+
+impl fmt::Display for StallReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StallReason::DirtyPages => write!(f, "too many dirty pages"),
+            StallReason::ApplyBacklog => write!(f, "too many committed records waiting to be applied"),
+        }
+    }
+}
+
+/// Where writes stand, and how often they have been held back.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct WriteStallStatus {
+    /// The signal holding writes back, if any.
+    pub reason: Option<StallReason>,
+    /// Delay each write currently gets.
+    pub delay_us: u64,
+    /// Writes are being rejected.
+    pub stopped: bool,
+    pub dirty_ratio: f64,
+    pub apply_backlog: u64,
+    pub delayed_writes: u64,
+    pub rejected_writes: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Signals {
+    dirty_ratio: f64,
+    apply_backlog: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Stall {
+    reason: Option<StallReason>,
+    delay: Duration,
+    stopped: bool,
+}
+
+pub struct WriteController {
+    config: WriteStallConfig,
+    state: Mutex<(Signals, Stall)>,
+    delayed: AtomicU64,
+    rejected: AtomicU64,
+    clock: Arc<dyn Clock>,
+}
+
+impl WriteController {
+    pub fn new(config: WriteStallConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new((Signals::default(), Stall::default())),
+            delayed: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+            clock: system_clock(),
+        }
+    }
+
+    /// Time delays with `clock` instead of the system clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn config(&self) -> &WriteStallConfig {
+        &self.config
+    }
+
+    /// Report the buffer pool's current share of dirty frames.
+    pub fn set_dirty_ratio(&self, dirty_ratio: f64) {
+        self.update(|signals| signals.dirty_ratio = dirty_ratio);
+    }
+
+    /// Report how many committed records are waiting to be applied.
+    pub fn set_apply_backlog(&self, apply_backlog: u64) {
+        self.update(|signals| signals.apply_backlog = apply_backlog);
+    }
+
+    fn update(&self, change: impl FnOnce(&mut Signals)) {
+        let mut state = self.state.lock().unwrap_or_else(|p| p.into_inner());
+        change(&mut state.0);
+        let stall = self.assess(&state.0);
+        if stall.reason != state.1.reason || stall.stopped != state.1.stopped {
+            match stall.reason {
+                Some(reason) if stall.stopped => log::warn!("writes stopped: {}", reason),
+                Some(reason) => log::info!("writes slowed down: {}", reason),
+                None => log::info!("write stall cleared"),
+            }
+        }
+        state.1 = stall;
+    }
+
+    // The worst signal decides. Past its slowdown threshold a write is delayed in
+    // proportion to the distance to the stop threshold.
+    fn assess(&self, signals: &Signals) -> Stall {
+        let config = &self.config;
+        let severity = |value: f64, slowdown: f64, stop: f64| {
+            if value >= stop {
+                Some(1.0)
+            } else if value >= slowdown {
+                Some((value - slowdown) / (stop - slowdown))
+            } else {
+                None
+            }
+        };
+        let worst = [
+            (
+                StallReason::DirtyPages,
+                severity(signals.dirty_ratio, config.slowdown_dirty_ratio, config.stop_dirty_ratio),
+            ),
+            (
+                StallReason::ApplyBacklog,
+                severity(
+                    signals.apply_backlog as f64,
+                    config.slowdown_apply_backlog as f64,
+                    config.stop_apply_backlog as f64,
+                ),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(reason, severity)| Some((reason, severity?)))
+        .max_by(|a, b| a.1.total_cmp(&b.1));
+
+        match worst {
+            None => Stall::default(),
+            Some((reason, severity)) if severity >= 1.0 => Stall {
+                reason: Some(reason),
+                delay: Duration::ZERO,
+                stopped: true,
+            },
+            Some((reason, severity)) => Stall {
+                reason: Some(reason),
+                delay: config.max_delay.mul_f64(severity),
+                stopped: false,
+            },
+        }
+    }
+
+    /// Let a write through: right away when nothing is backed up, after a delay when
+    /// something is, and not at all (WriteStalled) past a stop threshold.
+    pub async fn admit(&self) -> Result<(), DiskError> {
+        let stall = self.state.lock().unwrap_or_else(|p| p.into_inner()).1;
+        match stall.reason {
+            None => Ok(()),
+            Some(reason) if stall.stopped => {
+                self.rejected.fetch_add(1, Ordering::Relaxed);
+                Err(DiskError::WriteStalled(reason))
+            }
+            Some(_) => {
+                self.delayed.fetch_add(1, Ordering::Relaxed);
+                self.clock.sleep(stall.delay).await;
+                Ok(())
+            }
+        }
+    }
+
+    pub fn status(&self) -> WriteStallStatus {
+        let (signals, stall) = *self.state.lock().unwrap_or_else(|p| p.into_inner());
+        WriteStallStatus {
+            reason: stall.reason,
+            delay_us: stall.delay.as_micros() as u64,
+            stopped: stall.stopped,
+            dirty_ratio: signals.dirty_ratio,
+            apply_backlog: signals.apply_backlog,
+            delayed_writes: self.delayed.load(Ordering::Relaxed),
+            rejected_writes: self.rejected.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::clock::ManualClock;
+
+    #[tokio::test]
+    async fn test_writes_slow_down_then_stop() {
+        let clock = Arc::new(ManualClock::new(Duration::ZERO));
+        let controller = Arc::new(WriteController::new(WriteStallConfig::default()).with_clock(clock.clone()));
+        controller.admit().await.unwrap();
+
+        // Halfway from slowdown to stop: half the maximum delay
+        controller.set_dirty_ratio(0.85);
+        let status = controller.status();
+        assert_eq!((status.reason, status.delay_us, status.stopped), (Some(StallReason::DirtyPages), 25_000, false));
+        let write = tokio::spawn({
+            let controller = Arc::clone(&controller);
+            async move { controller.admit().await }
+        });
+        while controller.status().delayed_writes == 0 {
+            tokio::task::yield_now().await;
+        }
+        assert!(!write.is_finished());
+        clock.advance(Duration::from_millis(25));
+        write.await.unwrap().unwrap();
+
+        // The backlog is worse off, so it is the one reported
+        controller.set_apply_backlog(1000);
+        assert!(matches!(
+            controller.admit().await,
+            Err(DiskError::WriteStalled(StallReason::ApplyBacklog))
+        ));
+        assert_eq!(DiskError::WriteStalled(StallReason::ApplyBacklog).code(), crate::common::errors::ErrorCode::Busy);
+
+        controller.set_apply_backlog(0);
+        controller.set_dirty_ratio(0.1);
+        controller.admit().await.unwrap();
+        let status = controller.status();
+        assert_eq!((status.reason, status.delayed_writes, status.rejected_writes), (None, 1, 1));
+    }
+}
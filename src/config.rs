@@ -0,0 +1,569 @@
+// src/config.rs
+
+//! Engine configuration
+//!
+//! Config gathers the settings that used to be hard-coded constants. load() reads them
+//! from a TOML file; anything the file leaves out keeps its Config::default() value:
+//!
+//! ```toml
+//! [storage]
+//! path = "grimoire.db"
+//! page_size = 4096
+//! durability = "sync_all"     # sync_all | data_sync | range_sync | none
+//! log_preallocate = 4194304   # bytes the log grows by at a time, 0 to grow per write
+//! data_preallocate = 67108864 # bytes the data file grows by at least, 0 to only double it
+//! min_free_space = 1073741824 # bytes to keep free on the volume, 0 to not check
+//!
+//! [buffer_pool]
+//! frames = 64
+//!
+//! [server]
+//! listen_addr = "127.0.0.1:7433"
+//! max_concurrent_queries = 16
+//! max_queued_queries = 64
+//!
+//! [tuning]
+//! flush_interval_ms = 1000
+//! compaction_rate_limit = 8388608  # bytes/s, omit for unlimited
+//! io_concurrency = 10
+//! checkpoint_interval_ms = 300000
+//! cpu_workers = 8             # checksumming threads, defaults to one per core
+//!
+//! [diagnostics]
+//! slow_log_path = "grimoire-slow.log"  # omit to report through the regular log
+//! slow_io_ms = 100                     # omit any threshold to turn it off
+//! slow_fsync_ms = 50
+//! slow_query_ms = 1000
+//! ```
+//!
+//! LiveConfig holds the current Config for a running engine: set() validates a new one,
+//! logs every changed field and publishes it to subscribers, which apply it to their
+//! component (e.g. the DiskManager's I/O concurrency). Only the [tuning] settings and
+//! the slow log thresholds can change at runtime; the rest need a restart.
+
+use std::{
+    fmt::Debug,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use serde::Deserialize;
+use tokio::sync::watch;
+
+use crate::backend::storage::disk_manager::{DEFAULT_IO_CONCURRENCY, DiskManager, GRIMOIRE_PAGE_SIZE};
+use crate::backend::storage::storage_backend::DurabilityMode;
+use crate::common::cpu_pool;
+use crate::common::errors::ConfigError;
+use crate::common::slow_log::SlowLogThresholds;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// Database file; the log and double-write files live next to it.
+    pub db_path: PathBuf,
+    /// Page size in bytes. Pages are fixed-size in this build, so it must be GRIMOIRE_PAGE_SIZE.
+    pub page_size: usize,
+    /// How page and log writes are synced.
+    pub durability: DurabilityMode,
+    /// Bytes the log file is grown by at a time (see DiskManager::with_log_preallocation).
+    pub log_preallocation: u64,
+    /// Bytes the data file is grown by at least (see DiskManager::with_data_preallocation).
+    pub data_preallocation: u64,
+    /// Free bytes to keep on the volume before rejecting writes with DiskFull
+    /// (see DiskManager::with_min_free_space); 0 turns the check off.
+    pub min_free_space: u64,
+    /// Frames in the buffer pool.
+    pub buffer_pool_frames: usize,
+    pub server: ServerConfig,
+    /// How often dirty pages are flushed in the background.
+    pub flush_interval: Duration,
+    /// Bytes per second compaction may write; None means unlimited.
+    pub compaction_rate_limit: Option<u64>,
+    /// Page/log I/O operations allowed in flight at once.
+    pub io_concurrency: usize,
+    /// How often a checkpoint is taken.
+    pub checkpoint_interval: Duration,
+    /// Checksumming and other CPU-heavy jobs allowed to run at once (see CpuPool).
+    pub cpu_workers: usize,
+    /// File slow operations are appended to; None reports them through the regular log.
+    pub slow_log_path: Option<PathBuf>,
+    /// When a page I/O, WAL fsync or query counts as slow (see SlowLog).
+    pub slow_log: SlowLogThresholds,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerConfig {
+    pub listen_addr: String,
+    /// Queries/transactions executing at once (see AdmissionController).
+    pub max_concurrent_queries: usize,
+    /// Queries waiting for a slot before new ones are rejected as busy.
+    pub max_queued_queries: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            db_path: PathBuf::from("grimoire.db"),
+            page_size: GRIMOIRE_PAGE_SIZE,
+            durability: DurabilityMode::default(),
+            log_preallocation: 0,
+            data_preallocation: 0,
+            min_free_space: 0,
+            buffer_pool_frames: 64,
+            server: ServerConfig::default(),
+            flush_interval: Duration::from_secs(1),
+            compaction_rate_limit: None,
+            io_concurrency: DEFAULT_IO_CONCURRENCY,
+            checkpoint_interval: Duration::from_secs(300),
+            cpu_workers: cpu_pool::default_workers(),
+            slow_log_path: None,
+            slow_log: SlowLogThresholds::default(),
+        }
+    }
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            listen_addr: "127.0.0.1:7433".to_string(),
+            max_concurrent_queries: 16,
+            max_queued_queries: 64,
+        }
+    }
+}
+
+impl Config {
+    /// Parse a TOML document, starting from the defaults. `origin` names the source in errors.
+    pub fn from_toml(text: &str, origin: &str) -> Result<Self, ConfigError> {
+        let file: ConfigFile = toml::from_str(text).map_err(|e| ConfigError::Parse {
+            path: origin.to_string(),
+            message: e.to_string(),
+        })?;
+        let config = file.into_config();
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.db_path.as_os_str().is_empty() {
+            return Err(invalid("storage.path", "must not be empty"));
+        }
+        if self.page_size != GRIMOIRE_PAGE_SIZE {
+            return Err(invalid(
+                "storage.page_size",
+                format!("is {} but this build only supports {}", self.page_size, GRIMOIRE_PAGE_SIZE),
+            ));
+        }
+        if self.buffer_pool_frames == 0 {
+            return Err(invalid("buffer_pool.frames", "must be greater than zero"));
+        }
+        if let Err(e) = self.server.listen_addr.parse::<SocketAddr>() {
+            return Err(invalid(
+                "server.listen_addr",
+                format!("{:?} is not an ip:port address ({})", self.server.listen_addr, e),
+            ));
+        }
+        if self.server.max_concurrent_queries == 0 {
+            return Err(invalid("server.max_concurrent_queries", "must be greater than zero"));
+        }
+        if self.flush_interval.is_zero() {
+            return Err(invalid("tuning.flush_interval_ms", "must be greater than zero"));
+        }
+        if self.compaction_rate_limit == Some(0) {
+            return Err(invalid(
+                "tuning.compaction_rate_limit",
+                "must be greater than zero (omit it for unlimited)",
+            ));
+        }
+        if self.io_concurrency == 0 || self.io_concurrency > u32::MAX as usize {
+            return Err(invalid("tuning.io_concurrency", format!("must be between 1 and {}", u32::MAX)));
+        }
+        if self.cpu_workers == 0 || self.cpu_workers > u32::MAX as usize {
+            return Err(invalid("tuning.cpu_workers", format!("must be between 1 and {}", u32::MAX)));
+        }
+        if self.checkpoint_interval < self.flush_interval {
+            return Err(invalid(
+                "tuning.checkpoint_interval_ms",
+                "must not be shorter than flush_interval_ms",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Human-readable `field: old -> new` lines for every setting that differs.
+    pub fn diff(&self, new: &Config) -> Vec<String> {
+        let mut changes = Vec::new();
+        let mut check = |field: &str, old: &dyn Debug, new: &dyn Debug| {
+            let (old, new) = (format!("{:?}", old), format!("{:?}", new));
+            if old != new {
+                changes.push(format!("{}: {} -> {}", field, old, new));
+            }
+        };
+        check("storage.path", &self.db_path, &new.db_path);
+        check("storage.page_size", &self.page_size, &new.page_size);
+        check("storage.durability", &self.durability, &new.durability);
+        check("storage.log_preallocate", &self.log_preallocation, &new.log_preallocation);
+        check("storage.data_preallocate", &self.data_preallocation, &new.data_preallocation);
+        check("storage.min_free_space", &self.min_free_space, &new.min_free_space);
+        check("buffer_pool.frames", &self.buffer_pool_frames, &new.buffer_pool_frames);
+        check("server", &self.server, &new.server);
+        check("tuning.flush_interval", &self.flush_interval, &new.flush_interval);
+        check("tuning.compaction_rate_limit", &self.compaction_rate_limit, &new.compaction_rate_limit);
+        check("tuning.io_concurrency", &self.io_concurrency, &new.io_concurrency);
+        check("tuning.checkpoint_interval", &self.checkpoint_interval, &new.checkpoint_interval);
+        check("tuning.cpu_workers", &self.cpu_workers, &new.cpu_workers);
+        check("diagnostics.slow_log_path", &self.slow_log_path, &new.slow_log_path);
+        check("diagnostics.slow_log", &self.slow_log, &new.slow_log);
+        changes
+    }
+
+    /// Copy of `new` with every setting that needs a restart taken from `self`.
+    fn with_runtime_settings_of(&self, new: Config) -> Config {
+        Config {
+            db_path: self.db_path.clone(),
+            page_size: self.page_size,
+            durability: self.durability,
+            log_preallocation: self.log_preallocation,
+            data_preallocation: self.data_preallocation,
+            min_free_space: self.min_free_space,
+            buffer_pool_frames: self.buffer_pool_frames,
+            server: self.server.clone(),
+            slow_log_path: self.slow_log_path.clone(),
+            ..new
+        }
+    }
+}
+
+/// Read and validate the TOML config at `path`.
+pub fn load(path: &Path) -> Result<Config, ConfigError> {
+    let text = std::fs::read_to_string(path).map_err(|e| ConfigError::Io {
+        path: path.display().to_string(),
+        message: e.to_string(),
+    })?;
+    Config::from_toml(&text, &path.display().to_string())
+}
+
+fn invalid(field: &'static str, reason: impl Into<String>) -> ConfigError {
+    ConfigError::Invalid { field, reason: reason.into() }
+}
+
+// On-disk layout; every field is optional and falls back to Config::default()
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    storage: StorageSection,
+    buffer_pool: BufferPoolSection,
+    server: ServerSection,
+    tuning: TuningSection,
+    diagnostics: DiagnosticsSection,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct StorageSection {
+    path: Option<PathBuf>,
+    page_size: Option<usize>,
+    durability: Option<DurabilityName>,
+    log_preallocate: Option<u64>,
+    data_preallocate: Option<u64>,
+    min_free_space: Option<u64>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct BufferPoolSection {
+    frames: Option<usize>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct ServerSection {
+    listen_addr: Option<String>,
+    max_concurrent_queries: Option<usize>,
+    max_queued_queries: Option<usize>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct TuningSection {
+    flush_interval_ms: Option<u64>,
+    compaction_rate_limit: Option<u64>,
+    io_concurrency: Option<usize>,
+    checkpoint_interval_ms: Option<u64>,
+    cpu_workers: Option<usize>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct DiagnosticsSection {
+    slow_log_path: Option<PathBuf>,
+    slow_io_ms: Option<u64>,
+    slow_fsync_ms: Option<u64>,
+    slow_query_ms: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(try_from = "String")]
+struct DurabilityName(DurabilityMode);
+
+impl TryFrom<String> for DurabilityName {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        name.parse().map(DurabilityName)
+    }
+}
+
+impl ConfigFile {
+    fn into_config(self) -> Config {
+        let defaults = Config::default();
+        let server = ServerConfig {
+            listen_addr: self.server.listen_addr.unwrap_or(defaults.server.listen_addr),
+            max_concurrent_queries: self
+                .server
+                .max_concurrent_queries
+                .unwrap_or(defaults.server.max_concurrent_queries),
+            max_queued_queries: self
+                .server
+                .max_queued_queries
+                .unwrap_or(defaults.server.max_queued_queries),
+        };
+        Config {
+            db_path: self.storage.path.unwrap_or(defaults.db_path),
+            page_size: self.storage.page_size.unwrap_or(defaults.page_size),
+            durability: self.storage.durability.map_or(defaults.durability, |d| d.0),
+            log_preallocation: self.storage.log_preallocate.unwrap_or(defaults.log_preallocation),
+            data_preallocation: self.storage.data_preallocate.unwrap_or(defaults.data_preallocation),
+            min_free_space: self.storage.min_free_space.unwrap_or(defaults.min_free_space),
+            buffer_pool_frames: self.buffer_pool.frames.unwrap_or(defaults.buffer_pool_frames),
+            server,
+            flush_interval: self
+                .tuning
+                .flush_interval_ms
+                .map_or(defaults.flush_interval, Duration::from_millis),
+            compaction_rate_limit: self.tuning.compaction_rate_limit.or(defaults.compaction_rate_limit),
+            io_concurrency: self.tuning.io_concurrency.unwrap_or(defaults.io_concurrency),
+            checkpoint_interval: self
+                .tuning
+                .checkpoint_interval_ms
+                .map_or(defaults.checkpoint_interval, Duration::from_millis),
+            cpu_workers: self.tuning.cpu_workers.unwrap_or(defaults.cpu_workers),
+            slow_log_path: self.diagnostics.slow_log_path.or(defaults.slow_log_path),
+            slow_log: SlowLogThresholds {
+                page_io: self.diagnostics.slow_io_ms.map(Duration::from_millis),
+                wal_fsync: self.diagnostics.slow_fsync_ms.map(Duration::from_millis),
+                query: self.diagnostics.slow_query_ms.map(Duration::from_millis),
+            },
+        }
+    }
+}
+
+/// The current Config of a running engine, updatable at runtime.
+pub struct LiveConfig {
+    current: RwLock<Arc<Config>>,
+    tx: watch::Sender<Arc<Config>>,
+}
+
+impl LiveConfig {
+    pub fn new(config: Config) -> Result<Self, ConfigError> {
+        config.validate()?;
+        let config = Arc::new(config);
+        let (tx, _) = watch::channel(Arc::clone(&config));
+        Ok(Self {
+            current: RwLock::new(config),
+            tx,
+        })
+    }
+
+    pub fn get(&self) -> Arc<Config> {
+        Arc::clone(&self.current.read().unwrap_or_else(|poisoned| poisoned.into_inner()))
+    }
+
+    /// Replace the configuration. An invalid config is rejected and the old one stays.
+    pub fn set(&self, config: Config) -> Result<(), ConfigError> {
+        if let Err(e) = config.validate() {
+            log::warn!("rejected config change: {}", e);
+            return Err(e);
+        }
+        let mut current = self.current.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let changes = current.diff(&config);
+        if changes.is_empty() {
+            return Ok(());
+        }
+        for change in &changes {
+            log::info!("config changed: {}", change);
+        }
+        *current = Arc::new(config);
+        self.tx.send_replace(Arc::clone(&current));
+        Ok(())
+    }
+
+    /// Re-read `path` and apply its runtime settings. Settings that need a restart keep
+    /// their current value, with a warning if the file changed them.
+    pub fn reload(&self, path: &Path) -> Result<(), ConfigError> {
+        let loaded = load(path).inspect_err(|e| log::warn!("config reload failed: {}", e))?;
+        let current = self.get();
+        let applied = current.with_runtime_settings_of(loaded.clone());
+        for ignored in applied.diff(&loaded) {
+            log::warn!("config reload: {} needs a restart, ignored", ignored);
+        }
+        self.set(applied)
+    }
+
+    /// Reload `path` every time the process receives SIGHUP.
+    #[cfg(all(unix, feature = "native"))]
+    pub fn reload_on_sighup(self: Arc<Self>, path: PathBuf) -> std::io::Result<tokio::task::JoinHandle<()>> {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        let mut hangups = signal(SignalKind::hangup())?;
+        Ok(tokio::spawn(async move {
+            while hangups.recv().await.is_some() {
+                log::info!("SIGHUP received, reloading {}", path.display());
+                // Failures are logged by reload and leave the running config untouched
+                let _ = self.reload(&path);
+            }
+        }))
+    }
+
+    /// Receiver that sees every accepted change.
+    pub fn subscribe(&self) -> watch::Receiver<Arc<Config>> {
+        self.tx.subscribe()
+    }
+
+    /// Keep `disk_manager`'s tunables in line with this config until it is dropped.
+    pub fn apply_to(&self, disk_manager: Arc<DiskManager>) -> tokio::task::JoinHandle<()> {
+        let mut rx = self.subscribe();
+        tokio::spawn(async move {
+            loop {
+                let (io_concurrency, cpu_workers, slow_log) = {
+                    let config = rx.borrow_and_update();
+                    (config.io_concurrency, config.cpu_workers, config.slow_log)
+                };
+                if let Some(log) = disk_manager.slow_log() {
+                    log.set_thresholds(slow_log);
+                }
+                disk_manager.set_io_concurrency(io_concurrency).await;
+                disk_manager.cpu_pool().set_workers(cpu_workers).await;
+                if rx.changed().await.is_err() {
+                    break;
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_config_rejected() {
+        let live = LiveConfig::new(Config::default()).unwrap();
+        let bad = Config {
+            io_concurrency: 0,
+            ..Config::default()
+        };
+        assert!(matches!(
+            live.set(bad),
+            Err(ConfigError::Invalid { field: "tuning.io_concurrency", .. })
+        ));
+        assert_eq!(*live.get(), Config::default());
+    }
+
+    #[tokio::test]
+    async fn test_set_config_reaches_disk_manager() {
+        let live = LiveConfig::new(Config::default()).unwrap();
+        let dm = Arc::new(DiskManager::in_memory().await.unwrap());
+        let _applier = live.apply_to(Arc::clone(&dm));
+
+        let mut rx = live.subscribe();
+        live.set(Config {
+            io_concurrency: 3,
+            ..Config::default()
+        })
+        .unwrap();
+        rx.changed().await.unwrap();
+        assert_eq!(rx.borrow().io_concurrency, 3);
+
+        while dm.io_concurrency().await != 3 {
+            tokio::task::yield_now().await;
+        }
+        dm.set_io_concurrency(12).await;
+        assert_eq!(dm.io_concurrency().await, 12);
+    }
+
+    #[test]
+    fn test_load_toml() {
+        assert_eq!(Config::from_toml("", "empty").unwrap(), Config::default());
+
+        let config = Config::from_toml(
+            r#"
+            [storage]
+            path = "/var/lib/grimoire/main.db"
+            durability = "data_sync"
+            log_preallocate = 1048576
+            min_free_space = 536870912
+
+            [buffer_pool]
+            frames = 256
+
+            [tuning]
+            io_concurrency = 4
+            cpu_workers = 3
+
+            [diagnostics]
+            slow_io_ms = 100
+            slow_query_ms = 1000
+            "#,
+            "test",
+        )
+        .unwrap();
+        assert_eq!(config.db_path, PathBuf::from("/var/lib/grimoire/main.db"));
+        assert_eq!(config.durability, DurabilityMode::DataSync);
+        assert_eq!(config.log_preallocation, 1 << 20);
+        assert_eq!((config.data_preallocation, config.min_free_space), (0, 512 << 20));
+        assert_eq!(config.buffer_pool_frames, 256);
+        assert_eq!(config.io_concurrency, 4);
+        assert_eq!(config.cpu_workers, 3);
+        assert_eq!(config.server, ServerConfig::default());
+        assert_eq!(config.slow_log, SlowLogThresholds {
+            page_io: Some(Duration::from_millis(100)),
+            wal_fsync: None,
+            query: Some(Duration::from_secs(1)),
+        });
+    }
+
+    #[test]
+    fn test_load_toml_errors() {
+        let err = Config::from_toml("[storage]\ndurability = \"fast\"\n", "bad.toml").unwrap_err();
+        assert!(matches!(&err, ConfigError::Parse { message, .. } if message.contains("unknown durability mode")));
+
+        let err = Config::from_toml("[storage]\npage_szie = 4096\n", "bad.toml").unwrap_err();
+        assert!(matches!(&err, ConfigError::Parse { message, .. } if message.contains("page_szie")));
+
+        let err = Config::from_toml("[storage]\npage_size = 8192\n", "bad.toml").unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid { field: "storage.page_size", .. }));
+
+        let err = Config::from_toml("[server]\nlisten_addr = \"localhost\"\n", "bad.toml").unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid { field: "server.listen_addr", .. }));
+
+        assert!(matches!(load(Path::new("/nonexistent/grimoire.toml")), Err(ConfigError::Io { .. })));
+    }
+
+    #[test]
+    fn test_reload_keeps_restart_only_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("grimoire.toml");
+        std::fs::write(&path, "[buffer_pool]\nframes = 8\n[tuning]\nio_concurrency = 2\n").unwrap();
+
+        let live = LiveConfig::new(Config::default()).unwrap();
+        live.reload(&path).unwrap();
+        assert_eq!(live.get().io_concurrency, 2);
+        assert_eq!(live.get().buffer_pool_frames, Config::default().buffer_pool_frames);
+
+        std::fs::write(&path, "[tuning]\nio_concurrency = 0\n").unwrap();
+        assert!(live.reload(&path).is_err());
+        assert_eq!(live.get().io_concurrency, 2);
+    }
+}
@@ -0,0 +1,714 @@
+// src/instance.rs
+
+//! Instance
+//!
+//! One engine instance serving several named databases out of a data directory:
+//!
+//! ```text
+//! <data_dir>/
+//!     <name>/
+//!         data.db       pages
+//!         data.log      log
+//!         pages.0       page directory (which slot of data.db holds each page),
+//!         pages.1         written to in turn
+//!         pool_frames   buffer pool quota, in frames
+//! ```
+//!
+//! pool_frames is a Manifest, so a crash while it is written leaves the old quota or the
+//! new one. A new database's directory is synced into the data directory before its
+//! files are created.
+//!
+//! Every database gets its own DiskManager and a BufferPoolManager sized by its quota,
+//! so a busy database cannot evict another one's pages. Databases are opened lazily on
+//! first use and stay open until dropped.
+//!
+//! Background work runs on the instance's TaskManager. With with_checkpoint_interval()
+//! every open database gets a "checkpoint:<name>" task that writes its dirty pages back
+//! periodically and ends once the database is dropped. With with_io_tuning() every open
+//! database also gets an "iotune:<name>" task that adjusts its I/O concurrency and
+//! checkpoint flush batch to the latencies it observes (see IoTuner). With
+//! with_write_stalls() a "stall:<name>" task reports the share of dirty buffer pool
+//! frames to the database's WriteController, whose admit() write entry points call
+//! before starting a write (CommitPipeline::with_write_controller does so for commits).
+//!
+//! status() reports on every open database and background task in a serde-serializable
+//! form, e.g. for a dashboard that polls it as JSON. InstanceStatus::to_prometheus()
+//! renders the same report in the Prometheus text format, for a /metrics endpoint.
+//!
+//! Errors come back as GrimoireError, naming the database they happened in.
+
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    path::{Path, PathBuf},
+    sync::{Arc, Weak},
+    time::Duration,
+};
+
+use serde::Serialize;
+use tokio::sync::Mutex;
+
+use crate::backend::buffer::buffer_pool_manager::{BufferPoolManager, BufferPoolStats};
+use crate::backend::storage::disk_manager::{DiskLatencyStats, DiskManager, OpenOptions};
+use crate::backend::storage::io_tuner::{IoTuner, IoTunerConfig, IoTuning};
+use crate::backend::storage::storage_backend::FileBackend;
+use crate::backend::storage::{manifest::Manifest, platform};
+use crate::common::errors::{DiskError, GrimoireError, ResultExt};
+use crate::common::histogram::LatencySummary;
+use crate::common::task_manager::{RestartPolicy, TaskManager, TaskStatus};
+use crate::common::write_stall::{WriteController, WriteStallConfig, WriteStallStatus};
+
+const DATA_FILE: &str = "data.db";
+const QUOTA_FILE: &str = "pool_frames";
+const DIRECTORY_FILES: [&str; 2] = ["pages.0", "pages.1"];
+
+/// An open named database.
+pub struct Database {
+    name: String,
+    disk_manager: Arc<DiskManager>,
+    buffer_pool: Arc<BufferPoolManager>,
+    write_controller: Arc<WriteController>,
+}
+
+impl Database {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn disk_manager(&self) -> &Arc<DiskManager> {
+        &self.disk_manager
+    }
+
+    pub fn buffer_pool(&self) -> &Arc<BufferPoolManager> {
+        &self.buffer_pool
+    }
+
+    /// Flow control for writes to this database (see Instance::with_write_stalls).
+    pub fn write_controller(&self) -> &Arc<WriteController> {
+        &self.write_controller
+    }
+
+    pub async fn status(&self) -> Result<DatabaseStatus, GrimoireError> {
+        let buffer_pool = self.buffer_pool.stats().await;
+        Ok(DatabaseStatus {
+            name: self.name.clone(),
+            db_size: self.disk_manager.db_size().await.with_database(&self.name)?,
+            wal_size: self.disk_manager.log_size().await.with_database(&self.name)?,
+            hit_rate: buffer_pool.hit_rate(),
+            buffer_pool,
+            io_in_flight: self.disk_manager.io_in_flight().await,
+            io_concurrency: self.disk_manager.io_concurrency().await,
+            latency: self.disk_manager.latency_stats(),
+            available_space: self.disk_manager.available_space().await.with_database(&self.name)?,
+            low_disk_space: self.disk_manager.is_low_on_space(),
+            repaired_pages: self.disk_manager.get_num_repairs().await,
+            quarantined_pages: self.disk_manager.quarantined_pages().len(),
+            write_stall: self.write_controller.status(),
+            read_only: self.disk_manager.is_read_only(),
+        })
+    }
+}
+
+/// Health report for one open database.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DatabaseStatus {
+    pub name: String,
+    /// Bytes in the page file, including preallocated space.
+    pub db_size: u64,
+    pub wal_size: u64,
+    pub buffer_pool: BufferPoolStats,
+    pub hit_rate: f64,
+    /// I/O operations running against the io_concurrency limit.
+    pub io_in_flight: usize,
+    pub io_concurrency: usize,
+    pub latency: DiskLatencyStats,
+    /// Free bytes on the data file's volume, where the platform reports it.
+    pub available_space: Option<u64>,
+    /// Writes are being rejected with DiskFull (see DiskManager::with_min_free_space).
+    pub low_disk_space: bool,
+    /// Corrupt pages replaced from the repair source (see DiskManager::with_page_repair).
+    pub repaired_pages: u64,
+    /// Pages salvage mode gave up on as corrupt (see DiskManager::with_salvage).
+    pub quarantined_pages: usize,
+    pub write_stall: WriteStallStatus,
+    pub read_only: bool,
+}
+
+/// Health report for an instance: every database it currently has open, and its
+/// background tasks.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct InstanceStatus {
+    pub data_dir: PathBuf,
+    pub databases: Vec<DatabaseStatus>,
+    pub tasks: Vec<TaskStatus>,
+}
+
+impl InstanceStatus {
+    /// The per-database figures in the Prometheus text exposition format, labelled with
+    /// the database name. Latencies are exported as summaries in seconds.
+    pub fn to_prometheus(&self) -> String {
+        type Gauge = fn(&DatabaseStatus) -> f64;
+        let gauges: [(&str, &str, &str, Gauge); 11] = [
+            ("grimoire_db_size_bytes", "gauge", "Bytes in the page file.", |db| db.db_size as f64),
+            ("grimoire_wal_size_bytes", "gauge", "Bytes in the log.", |db| db.wal_size as f64),
+            ("grimoire_buffer_pool_resident_pages", "gauge", "Pages in the buffer pool.", |db| {
+                db.buffer_pool.resident_pages as f64
+            }),
+            ("grimoire_buffer_pool_dirty_pages", "gauge", "Dirty pages in the buffer pool.", |db| {
+                db.buffer_pool.dirty_pages as f64
+            }),
+            ("grimoire_buffer_pool_hits_total", "counter", "Page requests served from the pool.", |db| {
+                db.buffer_pool.hits as f64
+            }),
+            ("grimoire_buffer_pool_misses_total", "counter", "Page requests read from disk.", |db| {
+                db.buffer_pool.misses as f64
+            }),
+            ("grimoire_repaired_pages_total", "counter", "Corrupt pages repaired from a copy.", |db| {
+                db.repaired_pages as f64
+            }),
+            ("grimoire_quarantined_pages", "gauge", "Corrupt pages quarantined in salvage mode.", |db| {
+                db.quarantined_pages as f64
+            }),
+            ("grimoire_write_stall_delay_seconds", "gauge", "Delay each write currently gets.", |db| {
+                db.write_stall.delay_us as f64 / 1e6
+            }),
+            ("grimoire_delayed_writes_total", "counter", "Writes held back by a write stall.", |db| {
+                db.write_stall.delayed_writes as f64
+            }),
+            ("grimoire_rejected_writes_total", "counter", "Writes rejected by a write stall.", |db| {
+                db.write_stall.rejected_writes as f64
+            }),
+        ];
+        type Latency = fn(&DiskLatencyStats) -> &LatencySummary;
+        let latencies: [(&str, &str, Latency); 4] = [
+            ("grimoire_page_read_seconds", "Page reads from disk.", |l| &l.page_read),
+            ("grimoire_page_write_seconds", "Page writes to disk.", |l| &l.page_write),
+            ("grimoire_wal_fsync_seconds", "Log syncs.", |l| &l.wal_fsync),
+            ("grimoire_io_queue_wait_seconds", "Waits for an I/O permit.", |l| &l.io_queue_wait),
+        ];
+
+        // Database names are plain ASCII words, so labels need no escaping
+        let mut out = String::new();
+        for (name, kind, help, value) in gauges {
+            let _ = writeln!(out, "# HELP {} {}\n# TYPE {} {}", name, help, name, kind);
+            for db in &self.databases {
+                let _ = writeln!(out, "{}{{database=\"{}\"}} {}", name, db.name, value(db));
+            }
+        }
+        for (name, help, summary) in latencies {
+            let _ = writeln!(out, "# HELP {} {}\n# TYPE {} summary", name, help, name);
+            for db in &self.databases {
+                let summary = summary(&db.latency);
+                for (quantile, micros) in [("0.5", summary.p50_us), ("0.95", summary.p95_us), ("0.99", summary.p99_us)] {
+                    let _ = writeln!(
+                        out,
+                        "{}{{database=\"{}\",quantile=\"{}\"}} {}",
+                        name,
+                        db.name,
+                        quantile,
+                        micros as f64 / 1e6
+                    );
+                }
+                let sum = summary.mean_us * summary.count as f64 / 1e6;
+                let _ = writeln!(out, "{}_sum{{database=\"{}\"}} {}", name, db.name, sum);
+                let _ = writeln!(out, "{}_count{{database=\"{}\"}} {}", name, db.name, summary.count);
+            }
+        }
+        out
+    }
+}
+
+pub struct Instance {
+    data_dir: PathBuf,
+    default_frames: usize,
+    open: Mutex<HashMap<String, Arc<Database>>>,
+    tasks: TaskManager,
+    // Checkpoint every open database this often, if set
+    checkpoint_interval: Option<Duration>,
+    // Retune every open database's I/O this often within these bounds, if set
+    io_tuning: Option<(IoTunerConfig, Duration)>,
+    // Thresholds for every database's WriteController, and how often its dirty ratio is
+    // sampled, if set
+    write_stalls: Option<(WriteStallConfig, Duration)>,
+}
+
+impl Instance {
+    /// Use `data_dir` (created if missing). Databases created without an explicit quota
+    /// get `default_frames` buffer pool frames.
+    pub async fn open(data_dir: &Path, default_frames: usize) -> Result<Self, GrimoireError> {
+        tokio::fs::create_dir_all(data_dir).await.map_err(DiskError::IoError)?;
+        Ok(Self {
+            data_dir: data_dir.to_path_buf(),
+            default_frames,
+            open: Mutex::new(HashMap::new()),
+            tasks: TaskManager::new(),
+            checkpoint_interval: None,
+            io_tuning: None,
+            write_stalls: None,
+        })
+    }
+
+    /// Checkpoint every database this often in the background, from the moment it is
+    /// created or opened.
+    pub fn with_checkpoint_interval(mut self, interval: Duration) -> Self {
+        self.checkpoint_interval = Some(interval);
+        self
+    }
+
+    /// Adjust every database's I/O concurrency and checkpoint flush batch every
+    /// `interval`, within the bounds of `config`, instead of keeping them fixed.
+    pub fn with_io_tuning(mut self, config: IoTunerConfig, interval: Duration) -> Self {
+        self.io_tuning = Some((config, interval));
+        self
+    }
+
+    /// Hold writes back as dirty pages pile up, sampling every database's buffer pool
+    /// every `interval`, with the thresholds in `config`.
+    pub fn with_write_stalls(mut self, config: WriteStallConfig, interval: Duration) -> Self {
+        self.write_stalls = Some((config, interval));
+        self
+    }
+
+    /// The instance's background tasks; embedders can run their own there too.
+    pub fn tasks(&self) -> &TaskManager {
+        &self.tasks
+    }
+
+    /// Create a new database with a buffer pool of `frames` frames (or the default).
+    pub async fn create_database(
+        &self,
+        name: &str,
+        frames: Option<usize>,
+    ) -> Result<Arc<Database>, GrimoireError> {
+        self.create(name, frames).await.with_database(name)
+    }
+
+    async fn create(&self, name: &str, frames: Option<usize>) -> Result<Arc<Database>, DiskError> {
+        let dir = self.database_dir(name)?;
+        let mut open = self.open.lock().await;
+        if tokio::fs::try_exists(dir.join(DATA_FILE)).await.map_err(DiskError::IoError)? {
+            return Err(DiskError::AlreadyExists(dir));
+        }
+
+        let frames = frames.unwrap_or(self.default_frames);
+        tokio::fs::create_dir_all(&dir).await.map_err(DiskError::IoError)?;
+        let data_dir = self.data_dir.clone();
+        tokio::task::spawn_blocking(move || platform::sync_dir(&data_dir))
+            .await
+            .map_err(|e| DiskError::IoError(std::io::Error::other(e)))?
+            .map_err(DiskError::IoError)?;
+        Manifest::new(&dir.join(QUOTA_FILE)).store(frames.to_string().as_bytes()).await?;
+        let disk_manager = OpenOptions::new().error_if_exists(true).open(&dir.join(DATA_FILE)).await?;
+        let disk_manager = with_page_directory(disk_manager, &dir).await?;
+
+        let database = self.assemble(name, disk_manager, frames);
+        open.insert(name.to_string(), Arc::clone(&database));
+        log::info!("created database {} with {} buffer pool frames", name, frames);
+        Ok(database)
+    }
+
+    /// Handle to an existing database, opening it if needed.
+    pub async fn database(&self, name: &str) -> Result<Arc<Database>, GrimoireError> {
+        self.get_or_open(name).await.with_database(name)
+    }
+
+    async fn get_or_open(&self, name: &str) -> Result<Arc<Database>, DiskError> {
+        let dir = self.database_dir(name)?;
+        let mut open = self.open.lock().await;
+        if let Some(database) = open.get(name) {
+            return Ok(Arc::clone(database));
+        }
+
+        let disk_manager = OpenOptions::new()
+            .create_if_missing(false)
+            .open(&dir.join(DATA_FILE))
+            .await
+            .map_err(|e| match e {
+                DiskError::DatabaseNotFound(_) => DiskError::DatabaseNotFound(dir.clone()),
+                e => e,
+            })?;
+        let disk_manager = with_page_directory(disk_manager, &dir).await?;
+        let frames = match Manifest::new(&dir.join(QUOTA_FILE)).load().await {
+            Ok(Some(quota)) => String::from_utf8_lossy(&quota).trim().parse().unwrap_or(self.default_frames),
+            _ => self.default_frames,
+        };
+
+        let database = self.assemble(name, disk_manager, frames);
+        open.insert(name.to_string(), Arc::clone(&database));
+        Ok(database)
+    }
+
+    /// Delete a database and all of its files. Fails with ErrorCode::Conflict while any
+    /// handle returned by create_database()/database() is still alive.
+    pub async fn drop_database(&self, name: &str) -> Result<(), GrimoireError> {
+        self.remove(name).await.with_database(name)
+    }
+
+    async fn remove(&self, name: &str) -> Result<(), DiskError> {
+        let dir = self.database_dir(name)?;
+        let mut open = self.open.lock().await;
+        if let Some(database) = open.get(name) {
+            if Arc::strong_count(database) > 1 {
+                return Err(DiskError::DatabaseInUse(name.to_string()));
+            }
+            open.remove(name);
+        }
+        if !tokio::fs::try_exists(dir.join(DATA_FILE)).await.map_err(DiskError::IoError)? {
+            return Err(DiskError::DatabaseNotFound(dir));
+        }
+        tokio::fs::remove_dir_all(&dir).await.map_err(DiskError::IoError)?;
+        log::info!("dropped database {}", name);
+        Ok(())
+    }
+
+    /// Names of all databases in the data directory, sorted.
+    pub async fn list_databases(&self) -> Result<Vec<String>, GrimoireError> {
+        let mut names = Vec::new();
+        let mut entries = tokio::fs::read_dir(&self.data_dir).await.map_err(DiskError::IoError)?;
+        while let Some(entry) = entries.next_entry().await.map_err(DiskError::IoError)? {
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
+            if valid_name(&name) && entry.path().join(DATA_FILE).is_file() {
+                names.push(name);
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    /// Status of every open database, sorted by name.
+    pub async fn status(&self) -> Result<InstanceStatus, GrimoireError> {
+        let mut open: Vec<Arc<Database>> = self.open.lock().await.values().cloned().collect();
+        open.sort_by(|a, b| a.name.cmp(&b.name));
+        let mut databases = Vec::with_capacity(open.len());
+        for database in open {
+            databases.push(database.status().await?);
+        }
+        Ok(InstanceStatus {
+            data_dir: self.data_dir.clone(),
+            databases,
+            tasks: self.tasks.status(),
+        })
+    }
+
+    fn database_dir(&self, name: &str) -> Result<PathBuf, DiskError> {
+        if !valid_name(name) {
+            return Err(DiskError::InvalidDatabaseName(name.to_string()));
+        }
+        Ok(self.data_dir.join(name))
+    }
+
+    fn assemble(&self, name: &str, disk_manager: DiskManager, frames: usize) -> Arc<Database> {
+        let disk_manager = Arc::new(disk_manager);
+        let buffer_pool = Arc::new(BufferPoolManager::new(frames, Arc::clone(&disk_manager)));
+        let config = self.write_stalls.map(|(config, _)| config).unwrap_or_default();
+        let database = Arc::new(Database {
+            name: name.to_string(),
+            disk_manager,
+            buffer_pool,
+            write_controller: Arc::new(WriteController::new(config)),
+        });
+        if let Some(interval) = self.checkpoint_interval {
+            self.spawn_checkpointer(&database, interval);
+        }
+        if let Some((config, interval)) = self.io_tuning {
+            self.spawn_io_tuner(&database, config, interval);
+        }
+        if let Some((_, interval)) = self.write_stalls {
+            self.spawn_stall_monitor(&database, interval);
+        }
+        database
+    }
+
+    fn spawn_checkpointer(&self, database: &Arc<Database>, interval: Duration) {
+        let name = format!("checkpoint:{}", database.name());
+        let database: Weak<Database> = Arc::downgrade(database);
+        self.tasks.spawn(
+            name,
+            RestartPolicy::default(),
+            move |cancel| {
+                let database = database.clone();
+                async move {
+                    let mut ticks = tokio::time::interval(interval);
+                    ticks.tick().await; // the first tick fires immediately
+                    loop {
+                        tokio::select! {
+                            _ = ticks.tick() => {}
+                            _ = cancel.cancelled() => return Ok(()),
+                        }
+                        let Some(database) = database.upgrade() else {
+                            return Ok(());
+                        };
+                        database.checkpoint().await?;
+                    }
+                }
+            },
+        );
+    }
+
+    fn spawn_io_tuner(&self, database: &Arc<Database>, config: IoTunerConfig, interval: Duration) {
+        let name = format!("iotune:{}", database.name());
+        let database: Weak<Database> = Arc::downgrade(database);
+        self.tasks.spawn(
+            name,
+            RestartPolicy::default(),
+            move |cancel| {
+                let database = database.clone();
+                async move {
+                    let mut tuner = match database.upgrade() {
+                        Some(database) => IoTuner::new(
+                            config,
+                            IoTuning {
+                                io_concurrency: database.disk_manager.io_concurrency().await,
+                                flush_batch: database.buffer_pool.flush_batch(),
+                            },
+                            database.disk_manager.latency_stats(),
+                        ),
+                        None => return Ok(()),
+                    };
+                    let mut ticks = tokio::time::interval(interval);
+                    ticks.tick().await; // the first tick fires immediately
+                    loop {
+                        tokio::select! {
+                            _ = ticks.tick() => {}
+                            _ = cancel.cancelled() => return Ok(()),
+                        }
+                        let Some(database) = database.upgrade() else {
+                            return Ok(());
+                        };
+                        let tuning = tuner.observe(&database.disk_manager.latency_stats());
+                        database.disk_manager.set_io_concurrency(tuning.io_concurrency).await;
+                        database.buffer_pool.set_flush_batch(tuning.flush_batch);
+                    }
+                }
+            },
+        );
+    }
+
+    fn spawn_stall_monitor(&self, database: &Arc<Database>, interval: Duration) {
+        let name = format!("stall:{}", database.name());
+        let database: Weak<Database> = Arc::downgrade(database);
+        self.tasks.spawn(
+            name,
+            RestartPolicy::default(),
+            move |cancel| {
+                let database = database.clone();
+                async move {
+                    let mut ticks = tokio::time::interval(interval);
+                    loop {
+                        tokio::select! {
+                            _ = ticks.tick() => {}
+                            _ = cancel.cancelled() => return Ok(()),
+                        }
+                        let Some(database) = database.upgrade() else {
+                            return Ok(());
+                        };
+                        let stats = database.buffer_pool.stats().await;
+                        let dirty_ratio = stats.dirty_pages as f64 / stats.num_frames.max(1) as f64;
+                        database.write_controller.set_dirty_ratio(dirty_ratio);
+                    }
+                }
+            },
+        );
+    }
+}
+
+// Names become directory names, so keep them free of separators and dots
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-')
+}
+
+// Keep the page map on disk, so a reopened database finds its pages again and its
+// buffer pool does not hand out their ids as new ones
+async fn with_page_directory(disk_manager: DiskManager, dir: &Path) -> Result<DiskManager, DiskError> {
+    let first = FileBackend::open(&dir.join(DIRECTORY_FILES[0])).await?;
+    let second = FileBackend::open(&dir.join(DIRECTORY_FILES[1])).await?;
+    disk_manager.with_page_directory(Arc::new(first), Arc::new(second)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::errors::ErrorCode;
+    use crate::common::task_manager::TaskState;
+    use crate::common::write_stall::StallReason;
+
+    #[tokio::test]
+    async fn test_create_list_drop() {
+        let dir = tempfile::tempdir().unwrap();
+        let instance = Instance::open(dir.path(), 8).await.unwrap();
+
+        let sales = instance.create_database("sales", Some(4)).await.unwrap();
+        instance.create_database("hr", None).await.unwrap();
+        assert_eq!(sales.buffer_pool().size(), 4);
+        assert_eq!(instance.list_databases().await.unwrap(), vec!["hr", "sales"]);
+
+        let err = instance.create_database("sales", None).await.err().unwrap();
+        assert_eq!(err.code(), ErrorCode::AlreadyExists);
+        assert_eq!(err.context().database.as_deref(), Some("sales"));
+        let err = instance.create_database("../etc", None).await.err().unwrap();
+        assert!(matches!(err.disk_error(), Some(DiskError::InvalidDatabaseName(_))));
+
+        let err = instance.drop_database("sales").await.unwrap_err();
+        assert_eq!(err.code(), ErrorCode::Conflict);
+        drop(sales);
+        instance.drop_database("sales").await.unwrap();
+        assert_eq!(instance.list_databases().await.unwrap(), vec!["hr"]);
+        let err = instance.database("sales").await.err().unwrap();
+        assert_eq!(err.code(), ErrorCode::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_reopen_keeps_quota() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let instance = Instance::open(dir.path(), 8).await.unwrap();
+            instance.create_database("metrics", Some(3)).await.unwrap();
+        }
+        let instance = Instance::open(dir.path(), 8).await.unwrap();
+        let metrics = instance.database("metrics").await.unwrap();
+        assert_eq!(metrics.buffer_pool().size(), 3);
+        assert!(Arc::ptr_eq(&metrics, &instance.database("metrics").await.unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_reopened_database_allocates_past_its_pages() {
+        let dir = tempfile::tempdir().unwrap();
+        let written = {
+            let instance = Instance::open(dir.path(), 8).await.unwrap();
+            let sales = instance.create_database("sales", None).await.unwrap();
+            let pages: Vec<_> = (0..3).map(|_| sales.buffer_pool().new_page()).collect();
+            for &page_id in &pages {
+                sales.buffer_pool().write_page(page_id).await.unwrap().data_mut()[0] = page_id as u8 + 1;
+            }
+            sales.buffer_pool().checkpoint().await.unwrap();
+            pages
+        };
+
+        let instance = Instance::open(dir.path(), 8).await.unwrap();
+        let sales = instance.database("sales").await.unwrap();
+        let fresh = sales.buffer_pool().new_page();
+        assert!(!written.contains(&fresh));
+        sales.buffer_pool().write_page(fresh).await.unwrap().data_mut()[0] = 0xFF;
+        sales.buffer_pool().checkpoint().await.unwrap();
+        for &page_id in &written {
+            assert_eq!(sales.buffer_pool().read_page(page_id).await.unwrap().data()[0], page_id as u8 + 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_status_reports_open_databases() {
+        let dir = tempfile::tempdir().unwrap();
+        let instance = Instance::open(dir.path(), 8).await.unwrap();
+        let sales = instance.create_database("sales", Some(4)).await.unwrap();
+        instance.create_database("hr", None).await.unwrap();
+
+        let page_id = sales.buffer_pool().new_page();
+        drop(sales.buffer_pool().write_page(page_id).await.unwrap());
+        drop(sales.buffer_pool().read_page(page_id).await.unwrap());
+        sales.disk_manager().write_log(b"record").await.unwrap();
+
+        let status = instance.status().await.unwrap();
+        assert_eq!(status.data_dir, dir.path());
+        let names: Vec<&str> = status.databases.iter().map(|db| db.name.as_str()).collect();
+        assert_eq!(names, vec!["hr", "sales"]);
+
+        let sales = &status.databases[1];
+        assert!(sales.db_size > 0);
+        assert!(sales.wal_size > 0);
+        assert_eq!(sales.buffer_pool.num_frames, 4);
+        assert_eq!(sales.buffer_pool.resident_pages, 1);
+        assert_eq!(sales.hit_rate, 0.5);
+        assert_eq!(sales.io_in_flight, 0);
+        assert_eq!(sales.latency.wal_fsync.count, 1);
+        assert_eq!(sales.available_space.is_some(), cfg!(unix));
+        assert!(!sales.low_disk_space);
+        assert_eq!(status.databases[0].wal_size, 0);
+
+        let metrics = status.to_prometheus();
+        assert!(metrics.contains("# TYPE grimoire_wal_fsync_seconds summary\n"));
+        assert!(metrics.contains("grimoire_buffer_pool_hits_total{database=\"sales\"} 1\n"));
+        assert!(metrics.contains("grimoire_wal_fsync_seconds_count{database=\"sales\"} 1\n"));
+        assert!(metrics.contains("grimoire_page_read_seconds{database=\"hr\",quantile=\"0.99\"} 0\n"));
+    }
+
+    #[tokio::test]
+    async fn test_io_tuning_task_applies_bounds() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = IoTunerConfig {
+            min_io_concurrency: 3,
+            max_io_concurrency: 3,
+            min_flush_batch: 2,
+            max_flush_batch: 2,
+            ..IoTunerConfig::default()
+        };
+        let instance = Instance::open(dir.path(), 8)
+            .await
+            .unwrap()
+            .with_io_tuning(config, Duration::from_millis(5));
+        let sales = instance.create_database("sales", None).await.unwrap();
+
+        while sales.disk_manager().io_concurrency().await != 3 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        assert_eq!(sales.buffer_pool().flush_batch(), 2);
+        let tasks = instance.status().await.unwrap().tasks;
+        assert_eq!(tasks[0].name, "iotune:sales");
+        instance.tasks().shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_write_stall_follows_dirty_pages() {
+        let dir = tempfile::tempdir().unwrap();
+        let instance = Instance::open(dir.path(), 4)
+            .await
+            .unwrap()
+            .with_write_stalls(WriteStallConfig::default(), Duration::from_millis(5));
+        let sales = instance.create_database("sales", None).await.unwrap();
+        for _ in 0..4 {
+            let page_id = sales.buffer_pool().new_page();
+            sales.buffer_pool().write_page(page_id).await.unwrap().data_mut()[0] = 1;
+        }
+
+        while !sales.write_controller().status().stopped {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        let err = sales.write_controller().admit().await.unwrap_err();
+        assert!(matches!(err, DiskError::WriteStalled(StallReason::DirtyPages)));
+
+        sales.checkpoint().await.unwrap();
+        while sales.write_controller().status().reason.is_some() {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        let status = instance.status().await.unwrap();
+        assert_eq!(status.databases[0].write_stall.rejected_writes, 1);
+        assert!(status.to_prometheus().contains("grimoire_rejected_writes_total{database=\"sales\"} 1\n"));
+        instance.tasks().shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_background_checkpoint_task() {
+        let dir = tempfile::tempdir().unwrap();
+        let instance = Instance::open(dir.path(), 8)
+            .await
+            .unwrap()
+            .with_checkpoint_interval(Duration::from_millis(5));
+        let sales = instance.create_database("sales", None).await.unwrap();
+        let page_id = sales.buffer_pool().new_page();
+        sales.buffer_pool().write_page(page_id).await.unwrap().data_mut()[0] = 1;
+
+        while sales.buffer_pool().stats().await.dirty_pages > 0 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        let tasks = instance.status().await.unwrap().tasks;
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].name, "checkpoint:sales");
+        assert_eq!(tasks[0].state, TaskState::Running);
+
+        instance.tasks().shutdown().await;
+        assert_eq!(instance.tasks().status()[0].state, TaskState::Finished);
+    }
+}
@@ -1,5 +1,14 @@
 pub mod common;   // exposes common to crate
+pub mod config;   // engine tunables, changeable at runtime
+#[cfg(feature = "native")]
+pub mod instance; // named databases under one data directory
+#[cfg(feature = "native")]
+pub mod maintenance; // on-demand checkpoint and compaction
+#[cfg(feature = "native")]
+pub mod blocking; // sync facade for non-tokio embedders
+pub mod skiplist; // in-memory ordered index (memtable)
 pub mod backend {
     pub mod buffer;
+    pub mod index;
     pub mod storage;
 }
@@ -1,3 +1,116 @@
-fn main(){
-    println!("Hello, world!");
-}
\ No newline at end of file
+//! Command line entry point.
+//!
+//! Subcommands:
+//!   simulate <trace> [--frames N,N,..] [--policies arc,lru-2,clock]
+//!       Replay a page-access trace through replacement policies and print hit rates.
+//!   maintain <data_dir> <database> <checkpoint|compact>
+//!       Run a maintenance task on one database, printing its progress as it goes and what
+//!       it did at the end. Ctrl-C stops it between pages.
+
+use std::{path::Path, process::ExitCode, time::Duration};
+
+use sqlite_rust::backend::buffer::replacer_sim::{self, Policy};
+use sqlite_rust::common::{cancellation::CancellationToken, progress::ProgressTracker};
+use sqlite_rust::instance::Instance;
+use sqlite_rust::maintenance::MaintenanceTask;
+
+const USAGE: &str = "usage: sqlite-rust simulate <trace> [--frames 64,256,1024] [--policies arc,lru-2,clock]
+       sqlite-rust maintain <data_dir> <database> <checkpoint|compact>";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("simulate") => simulate(&args[1..]),
+        Some("maintain") => maintain(&args[1..]),
+        _ => Err(USAGE.to_string()),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn simulate(args: &[String]) -> Result<(), String> {
+    let mut path = None;
+    let mut sizes = vec![64, 256, 1024];
+    let mut policies = vec![Policy::Arc, Policy::LruK(2), Policy::Clock];
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--frames" => {
+                let list = args.next().ok_or(USAGE)?;
+                sizes = parse_list(list)?;
+            }
+            "--policies" => {
+                let list = args.next().ok_or(USAGE)?;
+                policies = parse_list(list)?;
+            }
+            _ if path.is_none() => path = Some(arg),
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+    let path = path.ok_or(USAGE)?;
+
+    let bytes = std::fs::read(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+    let trace = replacer_sim::parse_trace(&bytes)?;
+    println!("{} accesses from {}", trace.len(), path);
+    for result in replacer_sim::compare(&policies, &sizes, &trace) {
+        println!("{}", result);
+    }
+    Ok(())
+}
+
+fn maintain(args: &[String]) -> Result<(), String> {
+    let [data_dir, name, task] = args else {
+        return Err(USAGE.to_string());
+    };
+    let task: MaintenanceTask = task.parse()?;
+
+    let runtime = tokio::runtime::Runtime::new().map_err(|e| format!("cannot start runtime: {}", e))?;
+    let report = runtime
+        .block_on(async {
+            let instance = Instance::open(Path::new(data_dir), 64).await?;
+            let database = instance.database(name).await?;
+
+            // Ctrl-C stops the task cleanly instead of killing it mid-page
+            let cancel = CancellationToken::new();
+            let on_interrupt = cancel.clone();
+            tokio::spawn(async move {
+                if tokio::signal::ctrl_c().await.is_ok() {
+                    on_interrupt.cancel();
+                }
+            });
+
+            let progress = ProgressTracker::new();
+            let mut updates = progress.subscribe();
+            let printer = tokio::spawn(async move {
+                while updates.changed().await.is_ok() {
+                    let progress = *updates.borrow_and_update();
+                    let eta = progress.eta().map_or("?".to_string(), |eta| format!("{} s", eta.as_secs()));
+                    eprintln!("{}/{} pages, eta {}", progress.done, progress.total, eta);
+                    tokio::time::sleep(Duration::from_millis(500)).await;
+                }
+            });
+
+            let report = database.run_maintenance_with(task, &cancel, &progress).await;
+            drop(progress);
+            let _ = printer.await;
+            report
+        })
+        .map_err(|e| e.to_string())?;
+    println!("{}", report);
+    Ok(())
+}
+
+fn parse_list<T: std::str::FromStr>(list: &str) -> Result<Vec<T>, String>
+where
+    T::Err: std::fmt::Display,
+{
+    list.split(',')
+        .map(|item| item.trim().parse().map_err(|e| format!("{:?}: {}", item, e)))
+        .collect()
+}
@@ -0,0 +1,216 @@
+// src/maintenance.rs
+
+//! Maintenance
+//!
+//! Operations an operator runs on demand, e.g. from cron during off-peak hours, rather
+//! than ones the engine schedules itself:
+//!
+//! - `checkpoint` writes every dirty buffer pool page back to disk.
+//! - `compact` moves pages into the slots of deleted ones and shrinks the page file.
+//!
+//! Each returns a MaintenanceReport with how many pages it touched and how long it took.
+//! run_maintenance_with() also reports progress while the task runs and stops it early
+//! when its CancellationToken is cancelled, failing with ErrorCode::Cancelled.
+
+use std::{
+    fmt,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use crate::common::{
+    cancellation::CancellationToken,
+    errors::{GrimoireError, ResultExt},
+    progress::ProgressTracker,
+};
+use crate::instance::Database;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaintenanceTask {
+    Checkpoint,
+    Compact,
+}
+
+impl MaintenanceTask {
+    pub fn name(self) -> &'static str {
+        match self {
+            MaintenanceTask::Checkpoint => "checkpoint",
+            MaintenanceTask::Compact => "compact",
+        }
+    }
+}
+
+impl FromStr for MaintenanceTask {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "checkpoint" => Ok(MaintenanceTask::Checkpoint),
+            "compact" => Ok(MaintenanceTask::Compact),
+            _ => Err(format!("unknown maintenance task {:?}, expected checkpoint or compact", s)),
+        }
+    }
+}
+
+/// Outcome of one maintenance run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaintenanceReport {
+    pub task: MaintenanceTask,
+    pub database: String,
+    /// Pages written (checkpoint) or moved (compact).
+    pub pages: usize,
+    pub duration: Duration,
+}
+
+impl fmt::Display for MaintenanceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} of {}: {} pages in {} ms",
+            self.task.name(),
+            self.database,
+            self.pages,
+            self.duration.as_millis()
+        )
+    }
+}
+
+impl Database {
+    /// Write every dirty page back to disk.
+    pub async fn checkpoint(&self) -> Result<MaintenanceReport, GrimoireError> {
+        self.run_maintenance(MaintenanceTask::Checkpoint).await
+    }
+
+    /// Reclaim the space of deleted pages.
+    pub async fn compact(&self) -> Result<MaintenanceReport, GrimoireError> {
+        self.run_maintenance(MaintenanceTask::Compact).await
+    }
+
+    pub async fn run_maintenance(&self, task: MaintenanceTask) -> Result<MaintenanceReport, GrimoireError> {
+        self.run_maintenance_with(task, &CancellationToken::new(), &ProgressTracker::new())
+            .await
+    }
+
+    /// Run `task`, advancing `progress` page by page. Cancelling `cancel` stops the task
+    /// between pages with ErrorCode::Cancelled and leaves the database consistent.
+    pub async fn run_maintenance_with(
+        &self,
+        task: MaintenanceTask,
+        cancel: &CancellationToken,
+        progress: &ProgressTracker,
+    ) -> Result<MaintenanceReport, GrimoireError> {
+        let started = Instant::now();
+        let result = match task {
+            MaintenanceTask::Checkpoint => self.buffer_pool().checkpoint_with(cancel, progress).await,
+            MaintenanceTask::Compact => self.disk_manager().compact_with(cancel, progress).await,
+        };
+        let pages = result.with_database(self.name()).inspect_err(|e| {
+            log::warn!("{} stopped after {:?}: {}", task.name(), started.elapsed(), e);
+        })?;
+        let report = MaintenanceReport {
+            task,
+            database: self.name().to_string(),
+            pages,
+            duration: started.elapsed(),
+        };
+        log::info!("{}", report);
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::storage::io_stats::IoSource;
+    use crate::common::errors::ErrorCode;
+    use crate::instance::Instance;
+
+    #[tokio::test]
+    async fn test_checkpoint_and_compact() {
+        let dir = tempfile::tempdir().unwrap();
+        let instance = Instance::open(dir.path(), 4).await.unwrap();
+        let db = instance.create_database("ops", None).await.unwrap();
+        let pool = db.buffer_pool();
+
+        let pages: Vec<_> = (0..3).map(|_| pool.new_page()).collect();
+        for &page_id in &pages {
+            pool.write_page(page_id).await.unwrap().data_mut()[0] = 7;
+        }
+        let report = db.checkpoint().await.unwrap();
+        assert_eq!((report.task, report.pages), (MaintenanceTask::Checkpoint, 3));
+        assert_eq!(db.checkpoint().await.unwrap().pages, 0);
+        let io = db.disk_manager().io_breakdown().await;
+        assert_eq!(io.bytes_written(IoSource::Checkpoint), 3 * 4096);
+
+        // Pages written in order sit at increasing offsets, so the last one fills the hole
+        let dm = db.disk_manager();
+        for page_id in 100..103 {
+            dm.write_page(page_id, &[page_id as u8; 4096]).await.unwrap();
+        }
+        pool.delete_page(100).await.unwrap();
+        let report = db.compact().await.unwrap();
+        assert_eq!((report.task, report.pages), (MaintenanceTask::Compact, 1));
+        let mut page_data = vec![0u8; 4096];
+        dm.read_page(102, &mut page_data).await.unwrap();
+        assert_eq!(page_data[0], 102);
+        dm.read_page(pages[2], &mut page_data).await.unwrap();
+        assert_eq!(page_data[0], 7);
+    }
+
+    #[tokio::test]
+    async fn test_progress_and_cancellation() {
+        let dir = tempfile::tempdir().unwrap();
+        let instance = Instance::open(dir.path(), 8).await.unwrap();
+        let db = instance.create_database("ops", None).await.unwrap();
+        let pool = db.buffer_pool();
+        let pages: Vec<_> = (0..4).map(|_| pool.new_page()).collect();
+        for &page_id in &pages {
+            pool.write_page(page_id).await.unwrap().data_mut()[0] = 1;
+        }
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let progress = ProgressTracker::new();
+        let err = db
+            .run_maintenance_with(MaintenanceTask::Checkpoint, &cancel, &progress)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::Cancelled);
+        assert_eq!(err.context().database.as_deref(), Some("ops"));
+        assert_eq!((progress.current().done, progress.current().total), (0, 4));
+        assert_eq!(pool.stats().await.dirty_pages, 4);
+
+        let progress = ProgressTracker::new();
+        let rx = progress.subscribe();
+        db.run_maintenance_with(MaintenanceTask::Checkpoint, &CancellationToken::new(), &progress)
+            .await
+            .unwrap();
+        assert_eq!((rx.borrow().done, rx.borrow().total), (4, 4));
+
+        // A cancelled compaction leaves every page readable and can be rerun
+        let dm = db.disk_manager();
+        for page_id in 100..104 {
+            dm.write_page(page_id, &[page_id as u8; 4096]).await.unwrap();
+        }
+        dm.delete_page(100).await.unwrap();
+        dm.delete_page(101).await.unwrap();
+        let err = db
+            .run_maintenance_with(MaintenanceTask::Compact, &cancel, &ProgressTracker::new())
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::Cancelled);
+        assert!(db.compact().await.unwrap().pages > 0);
+        let mut page_data = vec![0u8; 4096];
+        for page_id in 102..104 {
+            dm.read_page(page_id, &mut page_data).await.unwrap();
+            assert_eq!(page_data[0], page_id as u8);
+        }
+    }
+
+    #[test]
+    fn test_parse_task() {
+        assert_eq!("compact".parse(), Ok(MaintenanceTask::Compact));
+        assert_eq!("checkpoint".parse(), Ok(MaintenanceTask::Checkpoint));
+        assert!("vacuum".parse::<MaintenanceTask>().is_err());
+    }
+}
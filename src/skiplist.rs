@@ -1,53 +1,210 @@
-use rand::Rng;
-use std::cell::RefCell;
-use std::rc::Rc;
+use bytes::Bytes;
+use rand::{Rng, RngCore, SeedableRng, rngs::StdRng};
+use std::cmp::Ordering;
+use std::fmt::Debug;
+use std::sync::{Arc, RwLock};
 
 const MAX_LEVEL: usize = 8;
 
-type Link = Option<Rc<RefCell<Node>>>;
+//ordering used by a SkipList, so keys can sort in a domain-specific way
+//(case-insensitive, descending, composite) without wrapping them in a newtype
+pub trait Comparator<K> {
+    fn compare(&self, a: &K, b: &K) -> Ordering;
+
+    //optional fingerprint stored inline in each node so searches can skip most full key
+    //comparisons: whenever two keys' prefixes differ they must order like the keys do
+    //(equal prefixes say nothing). None, the default, turns it off
+    fn prefix(&self, _key: &K) -> Option<u64> {
+        None
+    }
+}
+
+//the key's own Ord, the default
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NaturalOrder;
+
+impl<K: Ord> Comparator<K> for NaturalOrder {
+    fn compare(&self, a: &K, b: &K) -> Ordering {
+        a.cmp(b)
+    }
+}
+
+//byte-string order, like NaturalOrder on Vec<u8>, but with the first 8 bytes kept in
+//each node as a prefix, so long keys are usually told apart without touching them
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Bytewise;
+
+impl<K: AsRef<[u8]>> Comparator<K> for Bytewise {
+    fn compare(&self, a: &K, b: &K) -> Ordering {
+        a.as_ref().cmp(b.as_ref())
+    }
+
+    fn prefix(&self, key: &K) -> Option<u64> {
+        Some(byte_prefix(key.as_ref().iter().copied()))
+    }
+}
+
+//reverses another comparator
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Descending<C>(pub C);
+
+impl<K, C: Comparator<K>> Comparator<K> for Descending<C> {
+    fn compare(&self, a: &K, b: &K) -> Ordering {
+        self.0.compare(b, a)
+    }
+
+    fn prefix(&self, key: &K) -> Option<u64> {
+        self.0.prefix(key).map(|prefix| !prefix)
+    }
+}
+
+//orders strings ignoring ASCII case; keys equal under it are treated as the same key
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CaseInsensitive;
+
+impl<K: AsRef<str>> Comparator<K> for CaseInsensitive {
+    fn compare(&self, a: &K, b: &K) -> Ordering {
+        let a = a.as_ref().bytes().map(|c| c.to_ascii_lowercase());
+        let b = b.as_ref().bytes().map(|c| c.to_ascii_lowercase());
+        a.cmp(b)
+    }
+
+    fn prefix(&self, key: &K) -> Option<u64> {
+        Some(byte_prefix(key.as_ref().bytes().map(|c| c.to_ascii_lowercase())))
+    }
+}
+
+//first 8 bytes, big endian, zero padded: a zero-padded short key sorts no later than
+//any key it is a prefix of, so differing prefixes always order like the bytes
+fn byte_prefix(bytes: impl Iterator<Item = u8>) -> u64 {
+    let mut prefix = [0u8; 8];
+    for (dst, src) in prefix.iter_mut().zip(bytes) {
+        *dst = src;
+    }
+    u64::from_be_bytes(prefix)
+}
+
+//any fn(&K, &K) -> Ordering works as a comparator too
+impl<K, F: Fn(&K, &K) -> Ordering> Comparator<K> for F {
+    fn compare(&self, a: &K, b: &K) -> Ordering {
+        self(a, b)
+    }
+}
+
+//nodes live in one arena (a Vec) and link to each other by index; the head is always
+//index 0, so 0 doubles as the null link. Nodes are never removed, so an index stays
+//valid for the life of the list and the whole structure is plain data that can be
+//written out as-is when the memtable is flushed. Indexes are handed out in insertion
+//order, which also makes them versions: the nodes a snapshot sees are exactly those
+//with an index below the arena length when it was taken
+type Link = u32;
+const NIL: Link = 0;
+const HEAD: Link = 0;
+
 //Node struct of a skip list
 #[derive(Debug)]
-struct Node {
-    id: i32,
-    #[allow(dead_code)]
-    payload: String,
+struct Node<K> {
+    id: Option<K>, // None only for the head
+    prefix: u64, // the comparator's prefix of id, 0 when it has none
+    payload: Bytes, // shared, so reads hand out the same buffer instead of copying
     fwd: [Link; MAX_LEVEL], // fixed array for skip list levels
+    back: Link, // previous node on level 0, the head (NIL) for the first
 }
 //implemedntation of {Node}/
-impl Node {
+impl<K> Node<K> {
     //function to create a new node
-    fn new(id: i32, payload: &str) -> Self {
+    fn new(id: Option<K>, prefix: u64, payload: Bytes) -> Self {
         Node {
             id,
-            payload: payload.to_string(),
-            fwd: Default::default(), // all None
+            prefix,
+            payload,
+            fwd: [NIL; MAX_LEVEL],
+            back: NIL,
         }
     }
-    fn get_lvlsize(&self)->usize{
-        return MAX_LEVEL;
+
+    fn key(&self) -> &K {
+        self.id.as_ref().expect("head node has no key")
     }
 }
-//SkipList struct
-struct SkipList {
-    head: Rc<RefCell<Node>>,
+//SkipList struct, ordered by the comparator C
+pub struct SkipList<K = i32, C = NaturalOrder> {
+    nodes: Vec<Node<K>>, // arena, head first
+    tail: Link, // node with the largest key, NIL when empty
+    level_tails: [Link; MAX_LEVEL], // last node on each level, the head while a level is empty
+    appends: usize, // inserts that went in after the tail without a search
     p: i32,
-    lvl_count: [usize; MAX_LEVEL]
+    lvl_count: [usize; MAX_LEVEL],
+    cmp: C,
+    rng: Box<dyn RngCore + Send + Sync>, // draws node levels; swap in a seeded one for reproducible layouts
 }
-//implementation of SkipList
-impl SkipList {
+
+impl<K: Ord + Clone> SkipList<K, NaturalOrder> {
     //function to createa new head with prob(p) as main distibutor
-    fn new(p: i32) -> Self {
+    pub fn new(p: i32) -> Self {
+        Self::with_comparator(p, NaturalOrder)
+    }
+}
+
+//implementation of SkipList
+impl<K: Clone, C: Comparator<K>> SkipList<K, C> {
+    //function to create a list ordered by cmp instead of the key's Ord
+    pub fn with_comparator(p: i32, cmp: C) -> Self {
         SkipList {
-            head: Rc::new(RefCell::new(Node::new(-1, ""))),
+            nodes: vec![Node::new(None, 0, Bytes::new())],
+            tail: NIL,
+            level_tails: [HEAD; MAX_LEVEL],
+            appends: 0,
             p,
             lvl_count: [0; MAX_LEVEL],
+            cmp,
+            rng: Box::new(StdRng::from_rng(&mut rand::rng())),
+        }
+    }
+
+    //draw node levels from rng instead of a randomly seeded one
+    pub fn with_rng(mut self, rng: impl RngCore + Send + Sync + 'static) -> Self {
+        self.rng = Box::new(rng);
+        self
+    }
+
+    //number of keys in the list
+    pub fn len(&self) -> usize {
+        self.nodes.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    //how many inserts took the append path: keys larger than every key before them, as
+    //time series and sequence numbers arrive
+    pub fn appends(&self) -> usize {
+        self.appends
+    }
+
+    //grows with every insert; pass to cursor_at() to see the list as it is now
+    pub fn version(&self) -> u32 {
+        self.nodes.len() as u32
+    }
+
+    fn node(&self, link: Link) -> &Node<K> {
+        &self.nodes[link as usize]
+    }
+
+    //true if the key at link sorts before id; id_prefix is the comparator's prefix of id
+    fn node_less(&self, link: Link, id: &K, id_prefix: Option<u64>) -> bool {
+        let node = self.node(link);
+        match id_prefix {
+            Some(prefix) if node.prefix != prefix => node.prefix < prefix,
+            _ => self.cmp.compare(node.key(), id) == Ordering::Less,
         }
     }
+
     //function to generate random level for node insertion
-    fn gen_random_level(&self) -> usize {
+    fn gen_random_level(&mut self) -> usize {
         let mut lvl = 0;
-        let mut rng = rand::rng();
-        while rng.random_range(0..100) < self.p && lvl < MAX_LEVEL - 1 {
+        while self.rng.random_range(0..100) < self.p && lvl < MAX_LEVEL - 1 {
             lvl += 1;
         }
         lvl
@@ -59,77 +216,674 @@ impl SkipList {
     //function to insert a new node in the skip list
     //has id key and payload as value
     //loop through the key and arr and while loop through the levels to find empty forward pointer
-    fn insert(&mut self, id: i32, payload: &str) {
-        let lvl = self.gen_random_level();
-        let new_node = Rc::new(RefCell::new(Node::new(id, payload)));
+    pub fn insert(&mut self, id: K, payload: &str) {
+        self.insert_bytes(id, Bytes::copy_from_slice(payload.as_bytes()));
+    }
 
-        for i in (0..=lvl).rev() {
-            let mut current = Rc::clone(&self.head);
-            loop {
-                // clone the next pointer first
-                let next_opt = current.borrow().fwd[i].as_ref().map(Rc::clone);
+    //function to insert a payload that is already a Bytes, without copying it
+    pub fn insert_bytes(&mut self, id: K, payload: Bytes) {
+        let lvl = self.gen_random_level();
+        let id_prefix = self.cmp.prefix(&id);
 
-                match next_opt {
-                    Some(next) if next.borrow().id < id => {
-                        current = next; // safe: borrow already dropped
+        // last node before id on every level the new node joins. A key past the tail goes
+        // after the last node of every level, so in-order inserts skip the search; any
+        // other key, including one equal to the tail's (it goes in front), falls back to it
+        let appending = self.tail == NIL || self.cmp.compare(self.node(self.tail).key(), &id) == Ordering::Less;
+        let mut prev = [HEAD; MAX_LEVEL];
+        if appending {
+            prev = self.level_tails;
+            self.appends += 1;
+        } else {
+            // descend from the top level even when the new node is short, or a level-0
+            // insert would walk level 0 from the head
+            let mut current = HEAD;
+            for i in (0..MAX_LEVEL).rev() {
+                loop {
+                    let next = self.node(current).fwd[i];
+                    if next != NIL && self.node_less(next, &id, id_prefix) {
+                        current = next; // keep moving right
+                    } else {
+                        break;
                     }
-                    _ => break,
                 }
+                prev[i] = current;
             }
+        }
 
-            // insert new_node between current and current.fwd[i]
-            new_node.borrow_mut().fwd[i] = current.borrow_mut().fwd[i].take();
-            current.borrow_mut().fwd[i] = Some(Rc::clone(&new_node));
+        let link = Link::try_from(self.nodes.len()).expect("skip list holds at most u32::MAX nodes");
+        let mut new_node = Node::new(Some(id), id_prefix.unwrap_or(0), payload);
+        for (i, &before) in prev.iter().enumerate().take(lvl + 1) {
+            new_node.fwd[i] = self.node(before).fwd[i];
+            self.nodes[before as usize].fwd[i] = link;
             self.lvl_count[i] += 1;
+            if new_node.fwd[i] == NIL {
+                self.level_tails[i] = link;
+            }
+        }
+        new_node.back = prev[0];
+        match new_node.fwd[0] {
+            NIL => self.tail = link,
+            next => self.nodes[next as usize].back = link,
         }
+        self.nodes.push(new_node);
     }
-    //function to search
-fn search(&self, id: i32) -> Option<String> {
-    let mut current = Rc::clone(&self.head);
 
-    // Start from the highest possible level down to 0
-    for i in (0..MAX_LEVEL).rev() {
-        loop {
-            let next_opt = current.borrow().fwd[i].as_ref().map(Rc::clone);
+    //function to find the last node with key < id (the head if there is none)
+    fn find_less_than(&self, id: &K) -> Link {
+        let id_prefix = self.cmp.prefix(id);
+        let mut current = HEAD;
 
-            match next_opt {
-                Some(next) if next.borrow().id < id => {
+        // Start from the highest possible level down to 0
+        for i in (0..MAX_LEVEL).rev() {
+            loop {
+                let next = self.node(current).fwd[i];
+                if next != NIL && self.node_less(next, id, id_prefix) {
                     current = next; // keep moving right
+                } else {
+                    break; // drop down one level
                 }
-                _ => break, // drop down one level
             }
         }
+        current
+    }
+
+    //function to search, copying the payload into a String
+    pub fn search(&self, id: &K) -> Option<String> {
+        self.get(id).map(|payload| String::from_utf8_lossy(&payload).into_owned())
     }
 
-    // After descending, move to the candidate node
-    if let Some(next) = current.borrow().fwd[0].as_ref().map(Rc::clone) {
-        if next.borrow().id == id {
-            return Some(next.borrow().payload.clone());
+    //function to search without copying: the returned Bytes shares the node's buffer
+    pub fn get(&self, id: &K) -> Option<Bytes> {
+        // After descending, move to the candidate node
+        let next = self.node(self.find_less_than(id)).fwd[0];
+        if next != NIL && self.cmp.compare(self.node(next).key(), id) == Ordering::Equal {
+            return Some(self.node(next).payload.clone());
         }
+
+        None
     }
 
-    None
-}
+    //function to open a cursor, initially not positioned on any node
+    pub fn cursor(&self) -> Cursor<'_, K, C> {
+        self.cursor_at(Link::MAX)
+    }
 
-    fn print_list(&self) {
+    //a cursor that skips every key inserted at or after version
+    pub fn cursor_at(&self, version: u32) -> Cursor<'_, K, C> {
+        Cursor {
+            list: self,
+            current: NIL,
+            version,
+        }
+    }
+
+    //first node from link on, following level 0, that is older than version
+    fn visible_from(&self, mut link: Link, version: u32) -> Link {
+        while link != NIL && link >= version {
+            link = self.node(link).fwd[0];
+        }
+        link
+    }
+
+    //same, walking back
+    fn visible_before(&self, mut link: Link, version: u32) -> Link {
+        while link != NIL && link >= version {
+            link = self.node(link).back;
+        }
+        link
+    }
+
+    fn same_key(&self, a: Link, b: Link) -> bool {
+        self.cmp.compare(self.node(a).key(), self.node(b).key()) == Ordering::Equal
+    }
+
+    //first visible node after link with a different key. A re-inserted key goes in front
+    //of its older entries, so skipping equal keys skips the versions the newest shadows
+    fn visible_after(&self, link: Link, version: u32) -> Link {
+        let mut next = self.visible_from(self.node(link).fwd[0], version);
+        if link != HEAD {
+            while next != NIL && self.same_key(next, link) {
+                next = self.visible_from(self.node(next).fwd[0], version);
+            }
+        }
+        next
+    }
+
+    //the newest visible version of the key at link, the frontmost of its visible entries
+    fn newest_visible(&self, mut link: Link, version: u32) -> Link {
+        while link != NIL {
+            let before = self.visible_before(self.node(link).back, version);
+            if before == NIL || !self.same_key(before, link) {
+                break;
+            }
+            link = before;
+        }
+        link
+    }
+
+    pub fn print_list(&self)
+    where
+        K: Debug,
+    {
         for i in (0..MAX_LEVEL).rev() {
-            let mut node_opt = self.head.borrow().fwd[i].as_ref().map(Rc::clone);
+            let mut link = self.node(HEAD).fwd[i];
             print!("Level {}: ", i);
-            while let Some(node) = node_opt {
-                print!("{} -> ", node.borrow().id);
-                node_opt = node.borrow().fwd[i].as_ref().map(Rc::clone);
+            while link != NIL {
+                print!("{:?} -> ", self.node(link).key());
+                link = self.node(link).fwd[i];
             }
             println!("None");
         }
     }
 }
 
-fn main() {
-    let mut sl = SkipList::new(50);
-    sl.insert(10, "ten");
-    sl.insert(5, "five");
-    sl.insert(20, "twenty");
-    sl.insert(15, "fifteen");
-    sl.print_list();
-    println!("{:?}", sl.search(5))
+//Cursor over a SkipList, used for custom traversal (merge joins, pagination)
+//a cursor is either positioned on a node or invalid (past either end)
+pub struct Cursor<'a, K = i32, C = NaturalOrder> {
+    list: &'a SkipList<K, C>,
+    current: Link,
+    version: u32, // nodes at or past this index are invisible
+}
+
+impl<K: Clone, C: Comparator<K>> Cursor<'_, K, C> {
+    //true if the cursor is positioned on a node
+    pub fn valid(&self) -> bool {
+        self.current != NIL
+    }
+
+    //key of the current node
+    pub fn key(&self) -> Option<K> {
+        self.current_node().map(|node| node.key().clone())
+    }
+
+    //payload of the current node
+    pub fn value(&self) -> Option<String> {
+        self.value_bytes()
+            .map(|payload| String::from_utf8_lossy(&payload).into_owned())
+    }
+
+    //payload of the current node, sharing the node's buffer
+    pub fn value_bytes(&self) -> Option<Bytes> {
+        self.current_node().map(|node| node.payload.clone())
+    }
+
+    //position on the first node with key >= id
+    pub fn seek(&mut self, id: &K) {
+        let before = self.list.find_less_than(id);
+        self.current = self.list.visible_from(self.list.node(before).fwd[0], self.version);
+    }
+
+    //position on the last node with key <= id
+    pub fn seek_for_prev(&mut self, id: &K) {
+        self.seek(id);
+        match self.key() {
+            Some(key) if self.list.cmp.compare(&key, id) == Ordering::Equal => {}
+            Some(_) => self.prev(),
+            None => self.seek_to_last(),
+        }
+    }
+
+    //position on the smallest key
+    pub fn seek_to_first(&mut self) {
+        self.current = self.list.visible_from(self.list.node(HEAD).fwd[0], self.version);
+    }
+
+    //position on the largest key
+    pub fn seek_to_last(&mut self) {
+        let last = self.list.visible_before(self.list.tail, self.version);
+        self.current = self.list.newest_visible(last, self.version);
+    }
+
+    //move to the next larger key
+    pub fn next(&mut self) {
+        if self.valid() {
+            self.current = self.list.visible_after(self.current, self.version);
+        }
+    }
+
+    //move to the next smaller key, following the node's level-0 back link
+    pub fn prev(&mut self) {
+        if self.valid() {
+            let before = self.list.visible_before(self.list.node(self.current).back, self.version);
+            self.current = self.list.newest_visible(before, self.version);
+        }
+    }
+
+    fn current_node(&self) -> Option<&Node<K>> {
+        self.valid().then(|| self.list.node(self.current))
+    }
+}
+
+//a skip list one writer and any number of snapshot readers can share, e.g. a memtable
+//being flushed while it still takes writes. A snapshot iterator holds the read lock only
+//for one step at a time, so a long scan never blocks inserts
+pub struct SharedSkipList<K = i32, C = NaturalOrder> {
+    list: Arc<RwLock<SkipList<K, C>>>,
+}
+
+impl<K, C> Clone for SharedSkipList<K, C> {
+    fn clone(&self) -> Self {
+        SharedSkipList {
+            list: Arc::clone(&self.list),
+        }
+    }
+}
+
+impl<K: Clone, C: Comparator<K>> SharedSkipList<K, C> {
+    pub fn new(list: SkipList<K, C>) -> Self {
+        SharedSkipList {
+            list: Arc::new(RwLock::new(list)),
+        }
+    }
+
+    pub fn insert_bytes(&self, id: K, payload: Bytes) {
+        lock_write(&self.list).insert_bytes(id, payload);
+    }
+
+    pub fn get(&self, id: &K) -> Option<Bytes> {
+        lock_read(&self.list).get(id)
+    }
+
+    pub fn len(&self) -> usize {
+        lock_read(&self.list).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    //iterate the keys present right now, in order; later inserts are never seen
+    pub fn snapshot(&self) -> Snapshot<K, C> {
+        Snapshot {
+            list: Arc::clone(&self.list),
+            version: lock_read(&self.list).version(),
+            last: HEAD,
+            done: false,
+        }
+    }
+}
+
+//point-in-time view of a SharedSkipList, yielding (key, payload) in order
+pub struct Snapshot<K = i32, C = NaturalOrder> {
+    list: Arc<RwLock<SkipList<K, C>>>,
+    version: u32,
+    last: Link, // last node yielded, the head before the first
+    done: bool,
+}
+
+impl<K: Clone, C: Comparator<K>> Snapshot<K, C> {
+    //the payload id had when the snapshot was taken
+    pub fn get(&self, id: &K) -> Option<Bytes> {
+        let list = lock_read(&self.list);
+        let mut cursor = list.cursor_at(self.version);
+        cursor.seek(id);
+        match cursor.key() {
+            Some(key) if list.cmp.compare(&key, id) == Ordering::Equal => cursor.value_bytes(),
+            _ => None,
+        }
+    }
+}
+
+impl<K: Clone, C: Comparator<K>> Iterator for Snapshot<K, C> {
+    type Item = (K, Bytes);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let list = lock_read(&self.list);
+        let next = list.visible_after(self.last, self.version);
+        if next == NIL {
+            self.done = true;
+            return None;
+        }
+        self.last = next;
+        let node = list.node(next);
+        Some((node.key().clone(), node.payload.clone()))
+    }
+}
+
+fn lock_read<T>(lock: &RwLock<T>) -> std::sync::RwLockReadGuard<'_, T> {
+    lock.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn lock_write<T>(lock: &RwLock<T>) -> std::sync::RwLockWriteGuard<'_, T> {
+    lock.write().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_list() -> SkipList {
+        let mut sl = SkipList::new(50);
+        sl.insert(10, "ten");
+        sl.insert(5, "five");
+        sl.insert(20, "twenty");
+        sl.insert(15, "fifteen");
+        sl
+    }
+
+    #[test]
+    fn test_insert_and_search() {
+        let sl = make_list();
+        assert_eq!(sl.search(&5), Some("five".to_string()));
+        assert_eq!(sl.search(&20), Some("twenty".to_string()));
+        assert_eq!(sl.search(&7), None);
+    }
+
+    #[test]
+    fn test_cursor_forward_and_backward() {
+        let sl = make_list();
+        let mut cursor = sl.cursor();
+        assert!(!cursor.valid());
+
+        cursor.seek_to_first();
+        let mut keys = vec![];
+        while cursor.valid() {
+            keys.push(cursor.key().unwrap());
+            cursor.next();
+        }
+        assert_eq!(keys, vec![5, 10, 15, 20]);
+
+        cursor.seek_to_last();
+        let mut keys = vec![];
+        while cursor.valid() {
+            keys.push(cursor.key().unwrap());
+            cursor.prev();
+        }
+        assert_eq!(keys, vec![20, 15, 10, 5]);
+    }
+
+    #[test]
+    fn test_cursor_seek() {
+        let sl = make_list();
+        let mut cursor = sl.cursor();
+
+        cursor.seek(&10);
+        assert_eq!(cursor.key(), Some(10));
+        assert_eq!(cursor.value(), Some("ten".to_string()));
+
+        cursor.seek(&11);
+        assert_eq!(cursor.key(), Some(15));
+        cursor.seek(&21);
+        assert!(!cursor.valid());
+
+        cursor.seek_for_prev(&15);
+        assert_eq!(cursor.key(), Some(15));
+        cursor.seek_for_prev(&14);
+        assert_eq!(cursor.key(), Some(10));
+        cursor.seek_for_prev(&4);
+        assert!(!cursor.valid());
+    }
+
+    #[test]
+    fn test_get_shares_payload() {
+        let mut sl = SkipList::new(50);
+        let big = Bytes::from(vec![7u8; 64 * 1024]);
+        sl.insert_bytes(1, big.clone());
+
+        let read = sl.get(&1).unwrap();
+        assert_eq!(read.as_ptr(), big.as_ptr());
+        let mut cursor = sl.cursor();
+        cursor.seek(&1);
+        assert_eq!(cursor.value_bytes().unwrap().as_ptr(), big.as_ptr());
+        assert_eq!(sl.get(&2), None);
+    }
+
+    #[test]
+    fn test_custom_comparators() {
+        let mut desc = SkipList::with_comparator(50, Descending(NaturalOrder));
+        for id in [3, 1, 2] {
+            desc.insert(id, "");
+        }
+        let mut cursor = desc.cursor();
+        cursor.seek_to_first();
+        let mut keys = vec![];
+        while let Some(key) = cursor.key() {
+            keys.push(key);
+            cursor.next();
+        }
+        assert_eq!(keys, vec![3, 2, 1]);
+
+        let mut names = SkipList::with_comparator(50, CaseInsensitive);
+        names.insert("Bob".to_string(), "b");
+        names.insert("alice".to_string(), "a");
+        assert_eq!(names.search(&"BOB".to_string()), Some("b".to_string()));
+        let mut cursor = names.cursor();
+        cursor.seek_to_first();
+        assert_eq!(cursor.key(), Some("alice".to_string()));
+
+        // composite key: name ascending, then score descending
+        let mut scores = SkipList::with_comparator(50, |a: &(String, i32), b: &(String, i32)| {
+            a.0.cmp(&b.0).then(b.1.cmp(&a.1))
+        });
+        scores.insert(("x".to_string(), 1), "low");
+        scores.insert(("x".to_string(), 9), "high");
+        let mut cursor = scores.cursor();
+        cursor.seek(&("x".to_string(), i32::MAX));
+        assert_eq!(cursor.value(), Some("high".to_string()));
+    }
+
+    #[test]
+    fn test_seeded_rng_gives_same_levels() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        let build = || {
+            let mut sl = SkipList::new(50).with_rng(StdRng::seed_from_u64(42));
+            for id in 0..200 {
+                sl.insert(id, "");
+            }
+            sl.lvl_count
+        };
+        let levels = build();
+        assert_eq!(build(), levels);
+        assert!(levels[1] > 0);
+    }
+
+    #[test]
+    fn test_arena_links_and_send() {
+        fn assert_send<T: Send>(_: &T) {}
+
+        let mut sl = SkipList::new(50);
+        assert!(sl.is_empty());
+        let mut cursor = sl.cursor();
+        cursor.seek_to_last();
+        assert!(!cursor.valid());
+
+        for id in (0..100).rev() {
+            sl.insert(id * 2, "");
+        }
+        assert_eq!(sl.len(), 100);
+        assert_send(&sl);
+
+        // Every level is a sorted chain of arena indexes, ending at the null link
+        for level in 0..MAX_LEVEL {
+            let mut link = sl.node(HEAD).fwd[level];
+            let mut keys = vec![];
+            while link != NIL {
+                keys.push(*sl.node(link).key());
+                link = sl.node(link).fwd[level];
+            }
+            assert!(keys.windows(2).all(|pair| pair[0] < pair[1]));
+            assert_eq!(keys.len(), sl.lvl_count[level]);
+        }
+
+        let mut cursor = sl.cursor();
+        cursor.seek_for_prev(&7);
+        assert_eq!(cursor.key(), Some(6));
+        cursor.seek_to_last();
+        assert_eq!(cursor.key(), Some(198));
+    }
+
+    #[test]
+    fn test_prefixes_agree_with_full_comparison() {
+        let keys: Vec<Vec<u8>> = vec![
+            b"".to_vec(),
+            b"a".to_vec(),
+            b"a\0".to_vec(),
+            b"a\x01".to_vec(),
+            b"user/0001/profile".to_vec(),
+            b"user/0001/settings".to_vec(), // same first 8 bytes as the one above
+            b"user/0002".to_vec(),
+            vec![0xFF; 32],
+        ];
+        let mut asc = SkipList::with_comparator(50, Bytewise);
+        let mut desc = SkipList::with_comparator(50, Descending(Bytewise));
+        for key in keys.iter().rev() {
+            asc.insert(key.clone(), "");
+            desc.insert(key.clone(), "");
+        }
+
+        fn collect<C: Comparator<Vec<u8>>>(list: &SkipList<Vec<u8>, C>) -> Vec<Vec<u8>> {
+            let mut cursor = list.cursor();
+            cursor.seek_to_first();
+            let mut found = vec![];
+            while let Some(key) = cursor.key() {
+                found.push(key);
+                cursor.next();
+            }
+            found
+        }
+        let mut sorted = keys.clone();
+        sorted.sort();
+        assert_eq!(collect(&asc), sorted);
+        sorted.reverse();
+        assert_eq!(collect(&desc), sorted);
+        for key in &keys {
+            assert!(asc.get(key).is_some() && desc.get(key).is_some());
+        }
+        assert_eq!(asc.get(&b"user/0001".to_vec()), None);
+
+        let mut names = SkipList::with_comparator(50, CaseInsensitive);
+        names.insert("Zed".to_string(), "z");
+        names.insert("alpha".to_string(), "a");
+        assert_eq!(names.search(&"ZED".to_string()), Some("z".to_string()));
+    }
+
+    #[test]
+    fn test_back_links_mirror_forward_links() {
+        let mut sl = SkipList::new(50);
+        for id in [40, 10, 30, 20, 50, 30] {
+            sl.insert(id, &id.to_string());
+        }
+
+        let mut forward = vec![];
+        let mut cursor = sl.cursor();
+        cursor.seek_to_first();
+        while let Some(key) = cursor.key() {
+            forward.push(key);
+            cursor.next();
+        }
+        let mut backward = vec![];
+        cursor.seek_to_last();
+        while let Some(key) = cursor.key() {
+            backward.push(key);
+            cursor.prev();
+        }
+        backward.reverse();
+        // the re-inserted 30 shadows its older entry
+        assert_eq!(forward, vec![10, 20, 30, 40, 50]);
+        assert_eq!(backward, forward);
+
+        cursor.seek_for_prev(&100);
+        assert_eq!(cursor.key(), Some(50));
+        cursor.seek_for_prev(&35);
+        assert_eq!(cursor.key(), Some(30));
+        cursor.prev();
+        assert_eq!(cursor.key(), Some(20));
+    }
+
+    #[test]
+    fn test_in_order_inserts_append() {
+        let mut sl = SkipList::new(50);
+        for id in 0..1000 {
+            sl.insert(id * 10, "");
+        }
+        assert_eq!(sl.appends(), 1000);
+
+        // out of order and a repeat of the last key search; in order resumes appending
+        sl.insert(55, "");
+        sl.insert(9990, "newer");
+        sl.insert(10_000, "");
+        assert_eq!(sl.appends(), 1001);
+        assert_eq!(sl.search(&9990), Some("newer".to_string()));
+
+        // every level is still a sorted chain, and ends where level_tails says
+        for level in 0..MAX_LEVEL {
+            let mut link = sl.node(HEAD).fwd[level];
+            let mut last = HEAD;
+            let mut keys = vec![];
+            while link != NIL {
+                keys.push(*sl.node(link).key());
+                last = link;
+                link = sl.node(link).fwd[level];
+            }
+            assert!(keys.windows(2).all(|pair| pair[0] <= pair[1]));
+            assert_eq!(sl.level_tails[level], last);
+        }
+        let mut cursor = sl.cursor();
+        cursor.seek_for_prev(&60);
+        assert_eq!(cursor.key(), Some(60));
+        cursor.prev();
+        assert_eq!(cursor.key(), Some(55));
+    }
+
+    #[test]
+    fn test_snapshot_ignores_concurrent_inserts() {
+        let shared = SharedSkipList::new(SkipList::new(50));
+        for id in [10, 30, 50] {
+            shared.insert_bytes(id, Bytes::from(id.to_string()));
+        }
+
+        let mut snapshot = shared.snapshot();
+        let before = shared.snapshot();
+        assert_eq!(snapshot.next().map(|(key, _)| key), Some(10));
+        let writer = {
+            let shared = shared.clone();
+            std::thread::spawn(move || {
+                for id in [5, 20, 40, 60, 30] {
+                    shared.insert_bytes(id, Bytes::from("new"));
+                }
+            })
+        };
+        writer.join().unwrap();
+
+        let rest: Vec<i32> = snapshot.by_ref().map(|(key, _)| key).collect();
+        assert_eq!(rest, vec![30, 50]);
+        assert_eq!(snapshot.next(), None);
+        // 30 was re-inserted: the scan sees its newest version only
+        let now: Vec<(i32, Bytes)> = shared.snapshot().collect();
+        assert_eq!(now.iter().map(|(key, _)| *key).collect::<Vec<_>>(), vec![5, 10, 20, 30, 40, 50, 60]);
+        assert_eq!(now[3].1, Bytes::from("new"));
+
+        let old = shared.snapshot();
+        shared.insert_bytes(70, Bytes::from("later"));
+        assert_eq!(old.get(&70), None);
+        // a re-inserted key goes in front of its older entries, so each view sees its newest
+        assert_eq!(old.get(&30), Some(Bytes::from("new")));
+        assert_eq!(before.get(&30), Some(Bytes::from("30")));
+        assert_eq!(shared.get(&70), Some(Bytes::from("later")));
+
+        let list = shared.list.read().unwrap();
+        let mut cursor = list.cursor_at(4);
+        cursor.seek_to_last();
+        assert_eq!(cursor.key(), Some(50));
+        cursor.prev();
+        assert_eq!(cursor.key(), Some(30));
+        cursor.seek_for_prev(&45);
+        assert_eq!(cursor.key(), Some(30));
+        assert_eq!(cursor.value_bytes(), Some(Bytes::from("30")));
+
+        // stepping either way over the re-inserted 30 lands on its newest version, once
+        let mut cursor = list.cursor();
+        cursor.seek(&40);
+        cursor.prev();
+        assert_eq!(cursor.value_bytes(), Some(Bytes::from("new")));
+        cursor.prev();
+        assert_eq!(cursor.key(), Some(20));
+        cursor.next();
+        cursor.next();
+        assert_eq!(cursor.key(), Some(40));
+    }
 }